@@ -0,0 +1,98 @@
+//! Peak memory used to deserialize a ~50MB json array received in chunks,
+//! when the body is loaded in memory first compared to when it is
+//! deserialized as it is received by `Body::take_as::<Json<T>>`.
+//!
+//! Run with `cargo bench -p saphir --features json --bench json_body`
+
+use futures::stream;
+use saphir::{body::RawBody, prelude::*, test::TestServer};
+use serde_derive::Deserialize;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const ITEMS: usize = 1_400_000;
+const ITEMS_PER_CHUNK: usize = 2_000;
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Item {
+    id: u64,
+    name: String,
+}
+
+fn chunk(start: usize) -> Bytes {
+    let mut chunk = String::new();
+    for id in start..(start + ITEMS_PER_CHUNK).min(ITEMS) {
+        chunk.push(if id == 0 { '[' } else { ',' });
+        chunk.push_str(&format!(r#"{{"id":{},"name":"item-{}"}}"#, id, id));
+    }
+    if start + ITEMS_PER_CHUNK >= ITEMS {
+        chunk.push(']');
+    }
+    Bytes::from(chunk)
+}
+
+fn chunked_body() -> hyper::Body {
+    hyper::Body::wrap_stream(stream::iter(
+        (0..ITEMS).step_by(ITEMS_PER_CHUNK).map(|start| Ok::<_, std::io::Error>(chunk(start))),
+    ))
+}
+
+async fn buffered(mut req: Request) -> (u16, String) {
+    let body = RawBody::from_request(&mut req).await.expect("valid body");
+    let items: Vec<Item> = serde_json::from_slice(&body).expect("valid json");
+    (200, items.len().to_string())
+}
+
+async fn streamed(mut req: Request) -> (u16, String) {
+    let items = req.body_mut().take_as::<Json<Vec<Item>>>().await.expect("valid json");
+    (200, items.len().to_string())
+}
+
+async fn measure(client: &saphir::test::TestClient, path: &str) -> usize {
+    let baseline = ALLOCATED.load(Ordering::SeqCst);
+    PEAK.store(baseline, Ordering::SeqCst);
+    let res = client.post(path).body(chunked_body()).send().await.expect("response");
+    assert_eq!(res.text().expect("text body"), ITEMS.to_string());
+    PEAK.load(Ordering::SeqCst) - baseline
+}
+
+#[tokio::main]
+async fn main() {
+    let size: usize = (0..ITEMS).step_by(ITEMS_PER_CHUNK).map(|start| chunk(start).len()).sum();
+    let client =
+        TestServer::new(Server::builder().configure_router(|r| r.route("/buffered", Method::POST, buffered).route("/streamed", Method::POST, streamed)))
+            .expect("test server")
+            .client();
+
+    let mb = |bytes: usize| bytes as f64 / (1024.0 * 1024.0);
+    println!("json body of {:.1}MB, {} items", mb(size), ITEMS);
+    println!("loaded then deserialized : {:.1}MB peak", mb(measure(&client, "/buffered").await));
+    println!("deserialized as received : {:.1}MB peak", mb(measure(&client, "/streamed").await));
+}
@@ -6,6 +6,7 @@ use futures::{
     FutureExt,
 };
 use saphir::prelude::*;
+#[cfg(any(feature = "json", feature = "form"))]
 use serde_derive::{Deserialize, Serialize};
 #[cfg(feature = "operation")]
 use tokio::sync::RwLock;
@@ -117,6 +118,7 @@ fn magic_handler(controller: &MagicController, _: Request) -> Ready<(u16, String
     futures::future::ready((200, controller.label.clone()))
 }
 
+#[cfg(any(feature = "json", feature = "form"))]
 #[derive(Serialize, Deserialize)]
 struct User {
     username: String,
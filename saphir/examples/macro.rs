@@ -46,6 +46,12 @@ impl UserController {
         (200, user)
     }
 
+    #[post("/any")]
+    #[validator(exclude("user"))]
+    async fn user_any(&self, user: AnyBody<User>) -> (u16, Json<User>) {
+        (200, Json(user.into_inner()))
+    }
+
     #[cookies]
     #[post("/sync")]
     #[validator(exclude("req"))]
@@ -61,6 +67,11 @@ impl UserController {
         (200, "Yo".to_string())
     }
 
+    #[get("/connection")]
+    async fn connection(&self, info: ConnectionInfo) -> (u16, String) {
+        (200, format!("peer: {:?}, tls: {}", info.peer_addr(), info.is_tls()))
+    }
+
     #[post("/multi")]
     async fn multipart(&self, mul: Multipart) -> (u16, String) {
         let mut multipart_image_count = 0;
@@ -74,6 +85,13 @@ impl UserController {
         (200, format!("Multipart form data image saved on disk: {}", multipart_image_count))
     }
 
+    #[put("/upload")]
+    async fn upload(&self, file: TempFile) -> Result<(u16, String), SaphirError> {
+        let size = file.size();
+        file.persist("/tmp/upload.bin").await?;
+        Ok((201, format!("Uploaded {} bytes", size)))
+    }
+
     #[get("/file")]
     async fn file(&self, _req: Request<Body<Vec<u8>>>) -> (u16, Option<File>) {
         match File::open("/path/to/file").await {
@@ -0,0 +1,290 @@
+//! Access logs.
+//!
+//! The [`AccessLogMiddleware`] logs a line for every request handled by the
+//! stack, through the `log` facade, once its response is produced. The line
+//! is written in one of the [`AccessLogFormat`]s:
+//! - `Common` : `127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] "GET /users/1
+//!   HTTP/1.1" 200 2326`
+//! - `Combined` : the common format followed by the `Referer` and
+//!   `User-Agent` of the request
+//! - `Json` : a json object with the method, path, route template, status,
+//!   bytes, peer, duration, user agent and referer of the request, and its
+//!   operation id with the `operation` feature
+//!
+//! The route template, e.g. `/users/{id}`, lets the requests of an endpoint be
+//! grouped regardless of their parameters. Paths which shouldn't be logged,
+//! like health checks, are excluded when applying the middleware:
+//!
+//! ```rust
+//! use saphir::{
+//!     access_log::{AccessLogFormat, AccessLogMiddleware},
+//!     prelude::*,
+//! };
+//!
+//! let access_log = AccessLogMiddleware::new().format(AccessLogFormat::Json);
+//!
+//! let server = Server::builder()
+//!     .configure_middlewares(|m| m.apply(access_log, vec!["/**"], vec!["/health"]))
+//!     .build();
+//! ```
+//!
+//! The middleware turns errors returned by the rest of the chain into their
+//! response so that their status is logged; it should therefore be the first
+//! middleware applied.
+
+use crate::{
+    error::SaphirError,
+    http_context::HttpContext,
+    middleware::{Middleware, MiddlewareChain},
+};
+use futures::future::{BoxFuture, FutureExt};
+use http::{header, HeaderMap, StatusCode};
+use hyper::body::HttpBody;
+use log::Level;
+use std::{fmt::Write, net::SocketAddr, time::Duration};
+use time::{format_description::well_known::Rfc3339, macros::format_description, OffsetDateTime};
+
+/// Format of the access log lines
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AccessLogFormat {
+    /// The Common Log Format
+    #[default]
+    Common,
+    /// The Combined Log Format, adding the referer and user agent to the
+    /// common one
+    Combined,
+    /// One json object per line
+    Json,
+}
+
+/// Middleware logging every request and the status of its response
+pub struct AccessLogMiddleware {
+    format: AccessLogFormat,
+    level: Level,
+}
+
+impl AccessLogMiddleware {
+    /// Create a middleware logging in the common format, at the `Info` level
+    pub fn new() -> Self {
+        AccessLogMiddleware {
+            format: AccessLogFormat::Common,
+            level: Level::Info,
+        }
+    }
+
+    /// Set the format of the log lines
+    pub fn format(mut self, format: AccessLogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set the level the lines are logged at
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    async fn next_inner(&self, ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+        if !log_enabled!(self.level) {
+            return chain.next(ctx).await;
+        }
+
+        let mut entry = ctx.state.request().map(|req| AccessLogEntry {
+            method: req.method().to_string(),
+            path: req.uri().path_and_query().map(|p| p.as_str()).unwrap_or("/").to_string(),
+            version: format!("{:?}", req.version()),
+            peer: req.peer_addr().copied(),
+            user_agent: header_value(req.headers(), header::USER_AGENT),
+            referer: header_value(req.headers(), header::REFERER),
+            ..Default::default()
+        });
+
+        let ctx = chain.next_with_response(ctx).await?;
+
+        if let Some(entry) = entry.as_mut() {
+            entry.route = ctx.metadata.route_template.as_deref().map(str::to_string);
+            entry.status = ctx.state.response().map(|r| r.status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            entry.bytes = ctx.state.response().and_then(|r| {
+                header_value(r.headers(), header::CONTENT_LENGTH)
+                    .and_then(|len| len.parse().ok())
+                    .or_else(|| r.body().size_hint().exact())
+            });
+            entry.duration = ctx.elapsed();
+            #[cfg(feature = "operation")]
+            {
+                entry.operation_id = Some(ctx.operation_id.to_string());
+            }
+
+            log!(self.level, "{}", entry.format(self.format, OffsetDateTime::now_utc()));
+        }
+
+        Ok(ctx)
+    }
+}
+
+impl Default for AccessLogMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Middleware for AccessLogMiddleware {
+    fn next(&'static self, ctx: HttpContext, chain: &'static dyn MiddlewareChain) -> BoxFuture<'static, Result<HttpContext, SaphirError>> {
+        self.next_inner(ctx, chain).boxed()
+    }
+}
+
+#[derive(Default)]
+struct AccessLogEntry {
+    method: String,
+    path: String,
+    version: String,
+    route: Option<String>,
+    status: StatusCode,
+    bytes: Option<u64>,
+    peer: Option<SocketAddr>,
+    duration: Duration,
+    user_agent: Option<String>,
+    referer: Option<String>,
+    operation_id: Option<String>,
+}
+
+impl AccessLogEntry {
+    fn format(&self, format: AccessLogFormat, now: OffsetDateTime) -> String {
+        match format {
+            AccessLogFormat::Common => self.common(now),
+            AccessLogFormat::Combined => format!(
+                "{} \"{}\" \"{}\"",
+                self.common(now),
+                self.referer.as_deref().map_or_else(|| "-".to_string(), escape_json),
+                self.user_agent.as_deref().map_or_else(|| "-".to_string(), escape_json)
+            ),
+            AccessLogFormat::Json => self.json(now),
+        }
+    }
+
+    fn common(&self, now: OffsetDateTime) -> String {
+        let time = now
+            .format(format_description!("[day]/[month repr:short]/[year]:[hour]:[minute]:[second] +0000"))
+            .unwrap_or_default();
+        format!(
+            "{} - - [{}] \"{} {} {}\" {} {}",
+            self.peer.map_or_else(|| "-".to_string(), |peer| peer.ip().to_string()),
+            time,
+            self.method,
+            self.path,
+            self.version,
+            self.status.as_u16(),
+            self.bytes.map_or_else(|| "-".to_string(), |b| b.to_string())
+        )
+    }
+
+    fn json(&self, now: OffsetDateTime) -> String {
+        let mut out = String::from("{");
+        let _ = write!(out, "\"time\":\"{}\"", now.format(&Rfc3339).unwrap_or_default());
+        let peer = self.peer.map(|peer| peer.to_string());
+        let strings = [
+            ("method", Some(&self.method)),
+            ("path", Some(&self.path)),
+            ("version", Some(&self.version)),
+            ("route", self.route.as_ref()),
+            ("peer", peer.as_ref()),
+            ("user_agent", self.user_agent.as_ref()),
+            ("referer", self.referer.as_ref()),
+            ("operation_id", self.operation_id.as_ref()),
+        ];
+        for (key, value) in strings {
+            match value {
+                Some(value) => {
+                    let _ = write!(out, ",\"{}\":\"{}\"", key, escape_json(value));
+                }
+                None if key == "operation_id" => {}
+                None => {
+                    let _ = write!(out, ",\"{}\":null", key);
+                }
+            }
+        }
+        let _ = write!(out, ",\"status\":{}", self.status.as_u16());
+        match self.bytes {
+            Some(bytes) => {
+                let _ = write!(out, ",\"bytes\":{}", bytes);
+            }
+            None => out.push_str(",\"bytes\":null"),
+        }
+        let _ = write!(out, ",\"duration_ms\":{:.3}}}", self.duration.as_secs_f64() * 1000.0);
+        out
+    }
+}
+
+fn header_value(headers: &HeaderMap, name: header::HeaderName) -> Option<String> {
+    headers.get(name).and_then(|h| h.to_str().ok()).map(str::to_string)
+}
+
+fn escape_json(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    fn entry() -> AccessLogEntry {
+        AccessLogEntry {
+            method: "GET".to_string(),
+            path: "/users/1?full=true".to_string(),
+            version: "HTTP/1.1".to_string(),
+            route: Some("/users/{id}".to_string()),
+            status: StatusCode::OK,
+            bytes: Some(2326),
+            peer: Some("[::1]:54321".parse().unwrap()),
+            duration: Duration::from_micros(1500),
+            user_agent: Some("curl/8.0 \"test\"".to_string()),
+            referer: None,
+            operation_id: None,
+        }
+    }
+
+    #[test]
+    fn entries_are_formatted() {
+        let now = datetime!(2000-10-10 13:55:36 UTC);
+        let entry = entry();
+
+        assert_eq!(
+            entry.format(AccessLogFormat::Common, now),
+            r#"::1 - - [10/Oct/2000:13:55:36 +0000] "GET /users/1?full=true HTTP/1.1" 200 2326"#
+        );
+        assert_eq!(
+            entry.format(AccessLogFormat::Combined, now),
+            r#"::1 - - [10/Oct/2000:13:55:36 +0000] "GET /users/1?full=true HTTP/1.1" 200 2326 "-" "curl/8.0 \"test\"""#
+        );
+        assert_eq!(
+            entry.format(AccessLogFormat::Json, now),
+            r#"{"time":"2000-10-10T13:55:36Z","method":"GET","path":"/users/1?full=true","version":"HTTP/1.1","route":"/users/{id}","peer":"[::1]:54321","user_agent":"curl/8.0 \"test\"","referer":null,"status":200,"bytes":2326,"duration_ms":1.500}"#
+        );
+
+        let entry = AccessLogEntry {
+            peer: None,
+            bytes: None,
+            ..entry
+        };
+        assert_eq!(
+            entry.format(AccessLogFormat::Common, now),
+            r#"- - - [10/Oct/2000:13:55:36 +0000] "GET /users/1?full=true HTTP/1.1" 200 -"#
+        );
+    }
+}
@@ -0,0 +1,257 @@
+//! HTTP basic authentication.
+//!
+//! The [`BasicAuth`] guard reads the credentials of the `Authorization: Basic`
+//! header and checks them with a [`Verifier`]. Requests without valid
+//! credentials are answered with `401 Unauthorized` and a `WWW-Authenticate`
+//! header asking the client to authenticate. Once verified, the username is
+//! inserted in the request extensions as a [`BasicAuthUser`].
+//!
+//! ```rust
+//! use saphir::{
+//!     basic_auth::{BasicAuth, BasicAuthUser, Users},
+//!     prelude::*,
+//! };
+//!
+//! async fn admin(req: Request) -> (u16, String) {
+//!     let user = req.extensions().get::<BasicAuthUser>().map(|u| u.to_string()).unwrap_or_default();
+//!     (200, format!("hello {}", user))
+//! }
+//!
+//! let users = Users::new().user("admin", "correct horse battery staple");
+//! let server = Server::builder()
+//!     .configure_router(|r| r.route_with_guards("/admin", Method::GET, admin, |g| g.apply(BasicAuth::new(users).realm("admin"))))
+//!     .build();
+//! ```
+//!
+//! Credentials can also be verified by a closure, or asynchronously by
+//! implementing [`Verifier`], e.g. to look them up in a database. Passwords
+//! should then be compared with [`constant_time_eq`] so that the time taken
+//! to reject them doesn't leak how much of them was right.
+
+use crate::{guard::Guard, http_context::HttpContext, request::Request, responder::Responder, response::Builder};
+use base64::engine::Engine;
+use futures::future::{ready, BoxFuture, FutureExt};
+use http::{header, HeaderValue, StatusCode};
+use std::{
+    fmt::{Display, Formatter},
+    ops::Deref,
+};
+
+/// Realm sent to the clients when none is configured
+pub const DEFAULT_REALM: &str = "Restricted";
+
+/// Compare two byte strings in a time which only depends on their length
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    // black_box keeps the compiler from short-circuiting the fold
+    a.iter().zip(b).fold(0u8, |acc, (a, b)| std::hint::black_box(acc | (a ^ b))) == 0
+}
+
+/// Verification of the credentials sent by a client
+pub trait Verifier: Send + Sync {
+    fn verify<'a>(&'a self, username: &'a str, password: &'a str) -> BoxFuture<'a, bool>;
+}
+
+impl<F> Verifier for F
+where
+    F: Fn(&str, &str) -> bool + Send + Sync,
+{
+    fn verify<'a>(&'a self, username: &'a str, password: &'a str) -> BoxFuture<'a, bool> {
+        ready((self)(username, password)).boxed()
+    }
+}
+
+/// A fixed set of users and their password
+#[derive(Default)]
+pub struct Users {
+    users: Vec<(String, String)>,
+}
+
+impl Users {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn user(mut self, username: &str, password: &str) -> Self {
+        self.users.push((username.to_string(), password.to_string()));
+        self
+    }
+}
+
+impl Verifier for Users {
+    fn verify<'a>(&'a self, username: &'a str, password: &'a str) -> BoxFuture<'a, bool> {
+        // Every user is compared so that the time taken doesn't tell whether a
+        // username exists
+        let valid = self.users.iter().fold(false, |valid, (u, p)| {
+            let user_matches = constant_time_eq(u.as_bytes(), username.as_bytes());
+            let password_matches = constant_time_eq(p.as_bytes(), password.as_bytes());
+            valid | (user_matches & password_matches)
+        });
+        ready(valid).boxed()
+    }
+}
+
+/// Username of a request authenticated by the [`BasicAuth`] guard
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BasicAuthUser(String);
+
+impl BasicAuthUser {
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl Deref for BasicAuthUser {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Display for BasicAuthUser {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Guard authenticating the requests with the basic authentication scheme
+pub struct BasicAuth {
+    verifier: Box<dyn Verifier>,
+    challenge: HeaderValue,
+}
+
+impl BasicAuth {
+    pub fn new<V: Verifier + 'static>(verifier: V) -> Self {
+        BasicAuth {
+            verifier: Box::new(verifier),
+            challenge: challenge(DEFAULT_REALM),
+        }
+    }
+
+    /// Set the realm sent in the `WWW-Authenticate` header
+    ///
+    /// # Panics
+    /// If the realm contains characters which can't be sent in a header
+    pub fn realm(mut self, realm: &str) -> Self {
+        self.challenge = challenge(realm);
+        self
+    }
+}
+
+fn challenge(realm: &str) -> HeaderValue {
+    let realm = realm.replace('\\', "\\\\").replace('"', "\\\"");
+    HeaderValue::from_str(&format!("Basic realm=\"{}\", charset=\"UTF-8\"", realm)).expect("Invalid basic auth realm")
+}
+
+/// Username and password of a `Basic` authorization header
+fn credentials(req: &Request) -> Option<(String, String)> {
+    let value = req.headers().get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, encoded) = value.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+    let decoded = base64::engine::general_purpose::STANDARD.decode(encoded.trim()).ok()?;
+    let (username, password) = String::from_utf8(decoded).ok()?.split_once(':').map(|(u, p)| (u.to_string(), p.to_string()))?;
+    Some((username, password))
+}
+
+impl Guard for BasicAuth {
+    type Future = BoxFuture<'static, Result<Request, Self::Responder>>;
+    type Responder = Unauthorized;
+
+    fn validate(&'static self, mut req: Request) -> Self::Future {
+        async move {
+            let unauthorized = || Unauthorized {
+                challenge: self.challenge.clone(),
+            };
+            let (username, password) = credentials(&req).ok_or_else(unauthorized)?;
+            if !self.verifier.verify(&username, &password).await {
+                return Err(unauthorized());
+            }
+            req.extensions_mut().insert(BasicAuthUser(username));
+            Ok(req)
+        }
+        .boxed()
+    }
+}
+
+/// Responder of the requests without valid credentials, `401 Unauthorized`
+/// with a `WWW-Authenticate` header
+#[derive(Debug)]
+pub struct Unauthorized {
+    challenge: HeaderValue,
+}
+
+impl Responder for Unauthorized {
+    fn respond_with_builder(self, builder: Builder, _ctx: &HttpContext) -> Builder {
+        builder.status(StatusCode::UNAUTHORIZED).header(header::WWW_AUTHENTICATE, self.challenge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{server::Server, test::TestServer};
+    use http::Method;
+
+    async fn whoami(req: Request) -> (u16, String) {
+        (200, req.extensions().get::<BasicAuthUser>().map(|u| u.to_string()).unwrap_or_default())
+    }
+
+    fn basic(credentials: &str) -> String {
+        format!("Basic {}", base64::engine::general_purpose::STANDARD.encode(credentials))
+    }
+
+    #[test]
+    fn compares_in_constant_time() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret!"));
+        assert!(constant_time_eq(b"", b""));
+    }
+
+    #[tokio::test]
+    async fn authenticates_requests() {
+        let server = TestServer::new(Server::builder().configure_router(|r| {
+            r.route_with_guards("/users", Method::GET, whoami, |g| {
+                g.apply(BasicAuth::new(Users::new().user("admin", "p:ss").user("richer", "hunter2")).realm("the \"admin\" zone"))
+            })
+            .route_with_guards("/closure", Method::GET, whoami, |g| {
+                g.apply(BasicAuth::new(|user: &str, password: &str| user == password))
+            })
+        }))
+        .unwrap();
+        let client = server.client();
+
+        let res = client.get("/users").header(header::AUTHORIZATION, basic("admin:p:ss")).send().await.unwrap();
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.text().unwrap(), "admin");
+        let res = client
+            .get("/users")
+            .header(header::AUTHORIZATION, basic("richer:hunter2"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.text().unwrap(), "richer");
+
+        let res = client.get("/users").header(header::AUTHORIZATION, basic("richer:p:ss")).send().await.unwrap();
+        assert_eq!(res.status(), 401);
+        assert_eq!(
+            res.header(header::WWW_AUTHENTICATE),
+            Some(r#"Basic realm="the \"admin\" zone", charset="UTF-8""#)
+        );
+        let res = client.get("/users").header(header::AUTHORIZATION, "Bearer token").send().await.unwrap();
+        assert_eq!(res.status(), 401);
+        let res = client.get("/users").header(header::AUTHORIZATION, "Basic not-base64").send().await.unwrap();
+        assert_eq!(res.status(), 401);
+        assert_eq!(client.get("/users").send().await.unwrap().status(), 401);
+
+        let res = client.get("/closure").header(header::AUTHORIZATION, basic("same:same")).send().await.unwrap();
+        assert_eq!(res.text().unwrap(), "same");
+        let res = client.get("/closure").header(header::AUTHORIZATION, basic("same:other")).send().await.unwrap();
+        assert_eq!(res.header(header::WWW_AUTHENTICATE), Some(r#"Basic realm="Restricted", charset="UTF-8""#));
+    }
+}
@@ -6,51 +6,84 @@ use futures::{
     Future, StreamExt,
 };
 use http::HeaderMap;
-use hyper::body::{Body as RawBody, Buf, HttpBody, SizeHint};
+use hyper::body::{Body as HyperBody, Buf, HttpBody, SizeHint};
 use std::pin::Pin;
 
 pub use hyper::body::Bytes;
 
+#[cfg(feature = "json5")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json5")))]
+pub use self::json5::Json5;
+#[cfg(any(feature = "json", feature = "form"))]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "json", feature = "form"))))]
+pub use any::AnyBody;
+#[cfg(feature = "cbor")]
+#[cfg_attr(docsrs, doc(cfg(feature = "cbor")))]
+pub use cbor::Cbor;
+#[cfg(all(feature = "cbor", feature = "json"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "cbor", feature = "json"))))]
+pub use cbor::Negotiated;
 #[cfg(feature = "form")]
 #[cfg_attr(docsrs, doc(cfg(feature = "form")))]
 pub use form::Form;
 #[cfg(feature = "json")]
 #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
-pub use json::Json;
+pub use json::{Json, JsonLines};
+#[cfg(feature = "msgpack")]
+#[cfg_attr(docsrs, doc(cfg(feature = "msgpack")))]
+pub use msgpack::MsgPack;
+#[cfg(feature = "temp-file")]
+pub(crate) use raw::set_temp_dir;
+pub use raw::RawBody;
+#[cfg(feature = "temp-file")]
+#[cfg_attr(docsrs, doc(cfg(feature = "temp-file")))]
+pub use raw::TempFile;
 use std::ops::DerefMut;
 
 #[doc(hidden)]
 pub(crate) static mut REQUEST_BODY_BYTES_LIMIT: Option<usize> = None;
 
 pub(crate) enum BodyInner {
-    Raw(RawBody),
+    Raw(HyperBody),
     Memory(Bytes),
 }
 
 impl BodyInner {
     pub fn empty() -> Self {
-        BodyInner::Raw(RawBody::empty())
+        BodyInner::Raw(HyperBody::empty())
     }
 
     #[inline]
-    pub(crate) fn from_raw(raw: RawBody) -> Self {
+    pub(crate) fn from_raw(raw: HyperBody) -> Self {
         BodyInner::Raw(raw)
     }
 
     #[inline]
-    pub(crate) fn into_raw(self) -> RawBody {
+    pub(crate) fn into_raw(self) -> HyperBody {
         match self {
             BodyInner::Raw(r) => r,
-            BodyInner::Memory(b) => RawBody::from(b),
+            BodyInner::Memory(b) => HyperBody::from(b),
         }
     }
 
+    /// Load the whole body in memory, failing with
+    /// `SaphirError::PayloadTooLarge` once it exceeds the request body limit of
+    /// the listener, whether it has a `Content-Length` or is chunked
     pub async fn load(self) -> Result<Bytes, SaphirError> {
-        unsafe {
-            if let Some(0) = REQUEST_BODY_BYTES_LIMIT {
-                return Ok(Bytes::new());
+        // # SAFETY #
+        // The limit is only written once, before the server starts.
+        self.load_limited(unsafe { REQUEST_BODY_BYTES_LIMIT }).await
+    }
+
+    pub(crate) async fn load_limited(self, limit: Option<usize>) -> Result<Bytes, SaphirError> {
+        let check = |len: usize| {
+            if limit.is_some_and(|l| len > l) {
+                Err(SaphirError::PayloadTooLarge)
+            } else {
+                Ok(())
             }
-        }
+        };
+
         match self {
             BodyInner::Raw(mut r) => {
                 let first = if let Some(buf) = r.next().await.transpose().map_err(SaphirError::from)? {
@@ -58,12 +91,7 @@ impl BodyInner {
                 } else {
                     return Ok(Bytes::new());
                 };
-
-                unsafe {
-                    if REQUEST_BODY_BYTES_LIMIT.as_ref().filter(|p| first.len() >= **p).is_some() {
-                        return Ok(first);
-                    }
-                }
+                check(first.len())?;
 
                 let second = if let Some(buf) = r.next().await.transpose().map_err(SaphirError::from)? {
                     buf
@@ -72,23 +100,14 @@ impl BodyInner {
                 };
 
                 let cap = first.remaining() + second.remaining() + r.size_hint().lower() as usize;
-                let mut vec = Vec::with_capacity(cap);
+                let mut vec = Vec::with_capacity(limit.map_or(cap, |l| cap.min(l)));
                 vec.extend_from_slice(first.as_ref());
                 vec.extend_from_slice(second.as_ref());
-
-                unsafe {
-                    if REQUEST_BODY_BYTES_LIMIT.as_ref().filter(|p| vec.len() >= **p).is_some() {
-                        return Ok(vec.into());
-                    }
-                }
+                check(vec.len())?;
 
                 while let Some(buf) = r.next().await.transpose().map_err(SaphirError::from)? {
                     vec.extend_from_slice(buf.as_ref());
-                    unsafe {
-                        if REQUEST_BODY_BYTES_LIMIT.as_ref().filter(|p| vec.len() >= **p).is_some() {
-                            break;
-                        }
-                    }
+                    check(vec.len())?;
                 }
 
                 Ok(vec.into())
@@ -100,7 +119,7 @@ impl BodyInner {
 
 impl<T> From<T> for BodyInner
 where
-    T: Into<RawBody>,
+    T: Into<HyperBody>,
 {
     fn from(b: T) -> Self {
         BodyInner::Raw(b.into())
@@ -111,8 +130,8 @@ pub struct Body<T = Bytes>
 where
     T: FromBytes,
 {
-    inner: Option<BodyInner>,
-    fut: Option<Pin<Box<dyn Future<Output = Result<(T::Out, Bytes), SaphirError>> + Send + Sync + 'static>>>,
+    pub(crate) inner: Option<BodyInner>,
+    fut: Option<Pin<Box<dyn Future<Output = Result<(T::Out, Option<Bytes>), SaphirError>> + Send + Sync + 'static>>>,
 }
 
 impl Body<Bytes> {
@@ -129,23 +148,42 @@ where
     T: FromBytes,
 {
     #[inline]
-    pub(crate) async fn generate(inner: BodyInner) -> Result<(T::Out, Bytes), SaphirError> {
-        T::from_bytes(inner.load().await?)
+    pub(crate) async fn generate(inner: BodyInner) -> Result<(T::Out, Option<Bytes>), SaphirError> {
+        T::from_body(Body { inner: Some(inner), fut: None }).await
     }
 
+    /// Wrap a hyper body, without reading it
     #[inline]
-    pub(crate) fn from_raw(raw: RawBody) -> Self {
+    pub fn from_raw(raw: HyperBody) -> Self {
         Body {
             inner: Some(BodyInner::from_raw(raw)),
             fut: None,
         }
     }
 
+    /// Unwrap the hyper body, without reading it unless it was already
+    /// loaded in memory, e.g. to stream it to another server. An empty body is
+    /// returned if it was taken.
+    ///
+    /// The request body limit of the listener is only enforced when the body
+    /// is loaded by saphir, so it doesn't apply to the returned body.
     #[inline]
-    pub(crate) fn into_raw(self) -> RawBody {
+    pub fn into_raw(self) -> HyperBody {
         self.inner.unwrap_or_else(BodyInner::empty).into_raw()
     }
 
+    /// Load the whole body in memory and return its bytes, keeping them as
+    /// the body so that it can still be taken or awaited afterward.
+    ///
+    /// This lets a guard inspect the payload of a request, e.g. to verify a
+    /// webhook signature, and hand it over untouched to the handler.
+    pub async fn buffer(&mut self) -> Result<Bytes, SaphirError> {
+        let bytes = self.inner.take().ok_or(SaphirError::BodyAlreadyTaken)?.load().await?;
+        self.inner = Some(BodyInner::Memory(bytes.clone()));
+        self.fut = None;
+        Ok(bytes)
+    }
+
     /// Performing `take` will give your a owned version of the body, leaving a
     /// empty one behind
     #[inline]
@@ -167,6 +205,17 @@ where
     }
 }
 
+/// Body already in memory, e.g. to replace the body of a request with
+/// [`Request::replace_body`](crate::request::Request::replace_body)
+impl<T: FromBytes> From<Bytes> for Body<T> {
+    fn from(bytes: Bytes) -> Self {
+        Body {
+            inner: Some(BodyInner::Memory(bytes)),
+            fut: None,
+        }
+    }
+}
+
 impl<T: FromBytes> Default for Body<T> {
     fn default() -> Self {
         Body { inner: None, fut: None }
@@ -178,6 +227,24 @@ pub trait FromBytes {
     fn from_bytes(bytes: Bytes) -> Result<(Self::Out, Bytes), SaphirError>
     where
         Self: Sized;
+
+    /// Produce the value from a body which is not loaded yet, along with the
+    /// bytes kept as the body afterward.
+    ///
+    /// By default, the whole body is loaded in memory and passed to
+    /// `from_bytes`. Implementors can override this to consume the body as a
+    /// stream instead, returning `None` as the bytes: the body then counts as
+    /// taken, and reading it again fails with
+    /// [`SaphirError::BodyAlreadyTaken`].
+    fn from_body(body: Body) -> Pin<Box<dyn Future<Output = Result<(Self::Out, Option<Bytes>), SaphirError>> + Send + Sync + 'static>>
+    where
+        Self: Sized + 'static,
+    {
+        Box::pin(async move {
+            let (out, bytes) = Self::from_bytes(body.inner.unwrap_or_else(BodyInner::empty).load().await?)?;
+            Ok((out, Some(bytes)))
+        })
+    }
 }
 
 impl<T: 'static + Unpin> Future for Body<T>
@@ -190,7 +257,7 @@ where
         if let Some(fut) = self.fut.as_mut() {
             match fut.as_mut().poll(cx) {
                 Poll::Ready(res) => Poll::Ready(res.map(|(out, b)| {
-                    self.inner = Some(BodyInner::Memory(b));
+                    self.inner = b.map(BodyInner::Memory);
                     out
                 })),
                 Poll::Pending => Poll::Pending,
@@ -206,7 +273,7 @@ where
                 .poll(cx)
             {
                 Poll::Ready(res) => Poll::Ready(res.map(|(out, b)| {
-                    self.inner = Some(BodyInner::Memory(b));
+                    self.inner = b.map(BodyInner::Memory);
                     out
                 })),
                 Poll::Pending => Poll::Pending,
@@ -258,14 +325,39 @@ impl FromBytes for Vec<u8> {
 #[cfg(feature = "json")]
 #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
 pub mod json {
-    use crate::{body::FromBytes, error::SaphirError};
-    use hyper::body::Bytes;
+    use crate::{
+        body::{raw::request_chunks, Body, BodyInner, FromBytes},
+        error::SaphirError,
+        request::{FromRequest, Request},
+    };
+    use futures::{
+        future::BoxFuture,
+        task::{Context, Poll},
+        Future, Stream, StreamExt,
+    };
+    use hyper::body::{Buf, Bytes, HttpBody};
     use serde::Deserialize;
     use std::{
         borrow::{Borrow, BorrowMut},
+        io::Read,
         ops::{Deref, DerefMut},
+        pin::Pin,
     };
-
+    use tokio::sync::{mpsc, Semaphore, SemaphorePermit};
+
+    /// A json body.
+    ///
+    /// Bodies which fail to deserialize result in a
+    /// [`SaphirError::SerdeJsonPath`]. It is answered with `400 Bad Request`
+    /// and a json body giving the `path` and json `pointer` of the invalid
+    /// value, e.g. `items[3].price` and `/items/3/price`, along with a generic
+    /// `message`. The details of the error are only logged, not sent to the
+    /// client.
+    ///
+    /// Streamed bodies are deserialized on a blocking thread, so `T` must be
+    /// `Send + 'static` to be read as a body; types borrowing from their
+    /// input can still be read with `serde_json::from_slice` on the loaded
+    /// bytes.
     pub struct Json<T>(pub T);
 
     impl<T> Json<T> {
@@ -319,7 +411,7 @@ pub mod json {
 
     impl<T> FromBytes for Json<T>
     where
-        T: for<'a> Deserialize<'a>,
+        T: for<'a> Deserialize<'a> + Send + 'static,
     {
         type Out = T;
 
@@ -328,7 +420,406 @@ pub mod json {
         where
             Self: Sized,
         {
-            Ok((serde_json::from_slice(bytes.as_ref())?, bytes))
+            Ok((from_slice(bytes.as_ref())?, bytes))
+        }
+
+        /// Bodies larger than [`STREAMING_THRESHOLD`], or of unknown length,
+        /// are deserialized while they are received instead of being loaded
+        /// in memory first, as long as fewer than [`MAX_STREAMING_PARSES`]
+        /// are already. Such bodies are consumed and not kept afterward.
+        fn from_body(body: Body) -> Pin<Box<dyn Future<Output = Result<(Self::Out, Option<Bytes>), SaphirError>> + Send + Sync + 'static>>
+        where
+            Self: Sized + 'static,
+        {
+            match body.inner {
+                Some(BodyInner::Raw(raw)) if HttpBody::size_hint(&raw).upper().is_none_or(|len| len > STREAMING_THRESHOLD as u64) => {
+                    match STREAMING_PARSES.try_acquire() {
+                        Ok(permit) => Box::pin(async move { Ok((from_stream(request_chunks(BodyInner::Raw(raw)), permit).await?, None)) }),
+                        Err(_) => Box::pin(async move {
+                            let (out, bytes) = Self::from_bytes(BodyInner::Raw(raw).load().await?)?;
+                            Ok((out, Some(bytes)))
+                        }),
+                    }
+                }
+                inner => Box::pin(async move {
+                    let (out, bytes) = Self::from_bytes(inner.unwrap_or_else(BodyInner::empty).load().await?)?;
+                    Ok((out, Some(bytes)))
+                }),
+            }
+        }
+    }
+
+    /// Deserialize json, keeping track of the path of the value which failed
+    /// to deserialize, e.g. `items[3].price`
+    pub(crate) fn from_slice<T: for<'a> Deserialize<'a>>(bytes: &[u8]) -> Result<T, SaphirError> {
+        deserialize(serde_json::Deserializer::from_slice(bytes))
+    }
+
+    fn deserialize<'de, R: serde_json::de::Read<'de>, T: Deserialize<'de>>(mut de: serde_json::Deserializer<R>) -> Result<T, SaphirError> {
+        let value = serde_path_to_error::deserialize(&mut de)?;
+        de.end()?;
+        Ok(value)
+    }
+
+    /// Size of a json body from which it gets deserialized while being
+    /// received
+    pub const STREAMING_THRESHOLD: usize = 256 * 1024;
+
+    /// Most json bodies deserialized while they are received at once. Each
+    /// one holds a thread of the blocking pool until its body is received, so
+    /// the next ones are loaded in memory first instead, sparing the pool from
+    /// slow clients.
+    pub const MAX_STREAMING_PARSES: usize = 16;
+
+    static STREAMING_PARSES: Semaphore = Semaphore::const_new(MAX_STREAMING_PARSES);
+
+    /// Number of received chunks waiting to be deserialized before the body
+    /// stops being read
+    const STREAMING_BUFFERED_CHUNKS: usize = 8;
+
+    /// Deserialize the chunks of a body as they are received. serde_json only
+    /// reads synchronously, so the deserialization happens on a blocking task
+    /// fed through a channel, enforcing the request body limit on the way.
+    async fn from_stream<T>(chunks: impl Stream<Item = Result<Bytes, SaphirError>> + Send, permit: SemaphorePermit<'static>) -> Result<T, SaphirError>
+    where
+        T: for<'a> Deserialize<'a> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel(STREAMING_BUFFERED_CHUNKS);
+        let parse = tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            deserialize::<_, T>(serde_json::Deserializer::from_reader(ChunkReader { rx, chunk: Bytes::new() }))
+        });
+
+        let mut chunks = Box::pin(chunks);
+        let mut body_error = None;
+        while let Some(chunk) = chunks.next().await {
+            match chunk {
+                Ok(chunk) => {
+                    // The receiver is gone once deserialization failed
+                    if tx.send(chunk).await.is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    body_error = Some(e);
+                    break;
+                }
+            }
+        }
+        drop(tx);
+
+        let parsed = parse
+            .await
+            .map_err(|e| SaphirError::Other(format!("Json deserialization task failed: {}", e)))?;
+        match body_error {
+            Some(e) => Err(e),
+            None => Ok(parsed?),
+        }
+    }
+
+    struct ChunkReader {
+        rx: mpsc::Receiver<Bytes>,
+        chunk: Bytes,
+    }
+
+    impl Read for ChunkReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            while self.chunk.is_empty() {
+                match self.rx.blocking_recv() {
+                    Some(chunk) => self.chunk = chunk,
+                    None => return Ok(0),
+                }
+            }
+            let len = buf.len().min(self.chunk.len());
+            buf[..len].copy_from_slice(&self.chunk[..len]);
+            self.chunk.advance(len);
+            Ok(len)
+        }
+    }
+
+    /// Newline delimited json (NDJSON), one json value per line.
+    ///
+    /// As a responder, `JsonLines<S>` streams the items of `S`, each serialized
+    /// on its own line, with the `application/x-ndjson` content type. An item
+    /// which fails to serialize ends the response abruptly, so the client sees
+    /// it as incomplete, and the error is logged.
+    ///
+    /// As an extractor, `JsonLines<JsonLinesStream<T>>` parses the lines of the
+    /// request body into `T` while it is received. Blank lines are skipped.
+    /// ```rust
+    /// # use saphir::{prelude::*, body::json::JsonLinesStream};
+    /// # use futures::{stream, StreamExt};
+    /// # use serde_derive::{Deserialize, Serialize};
+    /// #[derive(Serialize, Deserialize)]
+    /// struct User {
+    ///     name: String,
+    /// }
+    ///
+    /// async fn export(_req: Request) -> JsonLines<impl futures::Stream<Item = User> + Send> {
+    ///     JsonLines(stream::iter(vec!["alice", "bob"]).map(|name| User { name: name.to_string() }))
+    /// }
+    ///
+    /// async fn import(mut req: Request) -> Result<String, SaphirError> {
+    ///     let JsonLines(mut users) = JsonLines::<JsonLinesStream<User>>::from_request(&mut req).await?;
+    ///     let mut names = Vec::new();
+    ///     while let Some(user) = users.next().await {
+    ///         names.push(user?.name);
+    ///     }
+    ///     Ok(names.join(","))
+    /// }
+    /// ```
+    pub struct JsonLines<S>(pub S);
+
+    impl<S> JsonLines<S> {
+        pub fn into_inner(self) -> S {
+            self.0
+        }
+    }
+
+    impl<S> Deref for JsonLines<S> {
+        type Target = S;
+
+        fn deref(&self) -> &Self::Target {
+            &self.0
+        }
+    }
+
+    impl<S> DerefMut for JsonLines<S> {
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            &mut self.0
+        }
+    }
+
+    /// Stream of the values parsed from a newline delimited json body
+    pub struct JsonLinesStream<T> {
+        inner: Pin<Box<dyn Stream<Item = Result<T, SaphirError>> + Send>>,
+    }
+
+    impl<T> Stream for JsonLinesStream<T> {
+        type Item = Result<T, SaphirError>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            self.inner.as_mut().poll_next(cx)
+        }
+    }
+
+    impl<T> FromRequest for JsonLines<JsonLinesStream<T>>
+    where
+        T: for<'a> Deserialize<'a> + Send + 'static,
+    {
+        type Err = SaphirError;
+        type Fut = BoxFuture<'static, Result<Self, Self::Err>>;
+
+        fn from_request(req: &mut Request) -> Self::Fut {
+            let inner = req.body_mut().take().inner;
+            Box::pin(async move {
+                let inner = inner.ok_or(SaphirError::BodyAlreadyTaken)?;
+                Ok(JsonLines(JsonLinesStream {
+                    inner: Box::pin(parse_lines(request_chunks(inner))),
+                }))
+            })
+        }
+    }
+
+    fn parse_lines<T>(chunks: impl Stream<Item = Result<Bytes, SaphirError>> + Send + 'static) -> impl Stream<Item = Result<T, SaphirError>> + Send
+    where
+        T: for<'a> Deserialize<'a> + Send + 'static,
+    {
+        async_stream::try_stream! {
+            let mut chunks = Box::pin(chunks);
+            let mut buf = Vec::new();
+            loop {
+                let chunk = chunks.next().await.transpose()?;
+                if let Some(chunk) = &chunk {
+                    buf.extend_from_slice(chunk);
+                }
+
+                let mut start = 0;
+                while let Some(len) = buf[start..].iter().position(|b| *b == b'\n') {
+                    let line = &buf[start..start + len];
+                    start += len + 1;
+                    if !line.iter().all(u8::is_ascii_whitespace) {
+                        yield from_slice::<T>(line)?;
+                    }
+                }
+                buf.drain(..start);
+
+                if chunk.is_none() {
+                    // The last line doesn't need to be terminated
+                    if !buf.iter().all(u8::is_ascii_whitespace) {
+                        yield from_slice::<T>(&buf)?;
+                    }
+                    break;
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::body::raw::limited_chunks;
+
+        #[derive(serde_derive::Deserialize, Debug, PartialEq)]
+        struct Dto {
+            name: String,
+            age: u8,
+        }
+
+        fn chunked(body: &'static str) -> Body {
+            Body::<Bytes>::from_raw(hyper::Body::wrap_stream(futures::stream::iter(
+                body.as_bytes().chunks(4).map(|c| Ok::<_, std::io::Error>(Bytes::copy_from_slice(c))),
+            )))
+        }
+
+        #[tokio::test]
+        async fn deserializes_while_receiving() {
+            let mut body = chunked(r#"[{"name":"saphir","age":3}, {"name":"hyper","age":7}]"#);
+            let dtos = body.take_as::<Json<Vec<Dto>>>().await.unwrap();
+            assert_eq!(
+                dtos,
+                vec![
+                    Dto {
+                        name: "saphir".to_string(),
+                        age: 3
+                    },
+                    Dto {
+                        name: "hyper".to_string(),
+                        age: 7
+                    }
+                ]
+            );
+
+            let mut body = chunked(r#"{"name":"saphir","age":"#);
+            assert!(matches!(body.take_as::<Json<Dto>>().await, Err(SaphirError::SerdeJsonPath(_))));
+
+            let mut body = Body::<Bytes>::from_raw(hyper::Body::from(r#"{"name":"saphir","age":3}"#));
+            assert_eq!(body.take_as::<Json<Dto>>().await.unwrap().age, 3);
+        }
+
+        #[tokio::test]
+        async fn reports_the_path_of_invalid_values() {
+            use crate::{prelude::*, test::TestServer};
+
+            #[derive(serde_derive::Deserialize)]
+            #[allow(dead_code)]
+            struct Order {
+                items: Vec<Item>,
+            }
+
+            #[derive(serde_derive::Deserialize)]
+            #[allow(dead_code)]
+            struct Item {
+                price: u32,
+            }
+
+            async fn order(mut req: Request) -> Result<u16, SaphirError> {
+                req.body_mut().take_as::<Json<Order>>().await?;
+                Ok(200)
+            }
+
+            let server = TestServer::new(Server::builder().configure_router(|r| r.route("/orders", Method::POST, order))).unwrap();
+            let client = server.client();
+
+            let res = client
+                .post("/orders")
+                .body(r#"{"items":[{"price":1},{"price":2},{"price":3},{"price":"free"}]}"#)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(res.status(), 400);
+            let error: serde_json::Value = res.json().unwrap();
+            assert_eq!(error["path"], "items[3].price");
+            assert_eq!(error["pointer"], "/items/3/price");
+            assert_eq!(error["message"], "Invalid json body");
+
+            let res = client.post("/orders").body(r#"{"items":[]} trailing"#).send().await.unwrap();
+            assert_eq!(res.status(), 400);
+        }
+
+        #[tokio::test]
+        async fn streamed_bodies_are_not_kept() {
+            let mut json = chunked(r#"{"name":"saphir","age":3}"#).take_as::<Json<Dto>>();
+            assert_eq!((&mut json).await.unwrap().age, 3);
+            assert!(matches!(json.take_as::<Bytes>().await, Err(SaphirError::BodyAlreadyTaken)));
+
+            // Without a free streaming slot, the body is loaded and kept
+            let slots = STREAMING_PARSES.acquire_many(MAX_STREAMING_PARSES as u32).await.unwrap();
+            let mut json = chunked(r#"{"name":"saphir","age":3}"#).take_as::<Json<Dto>>();
+            assert_eq!((&mut json).await.unwrap().age, 3);
+            assert_eq!(json.take_as::<Bytes>().await.unwrap(), r#"{"name":"saphir","age":3}"#);
+            drop(slots);
+        }
+
+        #[tokio::test]
+        async fn rejects_streamed_bodies_over_the_limit() {
+            let inner = chunked(r#"{"name":"saphir","age":3}"#).inner.unwrap();
+            let res = from_stream::<Dto>(limited_chunks(inner, Some(8)), STREAMING_PARSES.acquire().await.unwrap()).await;
+            assert!(matches!(res, Err(SaphirError::PayloadTooLarge)));
+        }
+
+        #[tokio::test]
+        async fn parses_json_lines_while_receiving() {
+            let inner = chunked("{\"name\":\"saphir\",\"age\":3}\n\n  \r\n{\"name\":\"hyper\",\"age\":7}")
+                .inner
+                .unwrap();
+            let dtos: Vec<Dto> = parse_lines(request_chunks(inner)).map(Result::unwrap).collect().await;
+            assert_eq!(dtos.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(), vec!["saphir", "hyper"]);
+
+            let inner = chunked("{\"name\":\"saphir\",\"age\":3}\n{\"name\":3}\n{\"name\":\"hyper\",\"age\":7}\n")
+                .inner
+                .unwrap();
+            let results: Vec<Result<Dto, _>> = parse_lines(request_chunks(inner)).collect().await;
+            assert_eq!(results.len(), 2);
+            assert!(results[0].is_ok());
+            assert!(matches!(results[1], Err(SaphirError::SerdeJsonPath(_))));
+        }
+
+        #[tokio::test]
+        async fn streams_json_lines() {
+            use crate::{prelude::*, test::TestServer};
+            use futures::stream;
+
+            #[derive(serde_derive::Serialize)]
+            struct Item(#[serde(serialize_with = "fail_on_zero")] u8);
+
+            fn fail_on_zero<S: serde::Serializer>(n: &u8, s: S) -> Result<S::Ok, S::Error> {
+                match n {
+                    0 => Err(serde::ser::Error::custom("zero")),
+                    n => s.serialize_u8(*n),
+                }
+            }
+
+            async fn export(req: Request) -> JsonLines<impl Stream<Item = Item> + Send> {
+                let items = if req.uri().path() == "/broken" { vec![1, 0, 2] } else { vec![1, 2] };
+                JsonLines(stream::iter(items.into_iter().map(Item)))
+            }
+
+            async fn sum(mut req: Request) -> Result<String, SaphirError> {
+                let JsonLines(mut items) = JsonLines::<JsonLinesStream<u32>>::from_request(&mut req).await?;
+                let mut sum = 0;
+                while let Some(item) = items.next().await {
+                    sum += item?;
+                }
+                Ok(sum.to_string())
+            }
+
+            let server = TestServer::new(Server::builder().configure_router(|r| {
+                r.route("/items", Method::GET, export)
+                    .route("/broken", Method::GET, export)
+                    .route("/sum", Method::POST, sum)
+            }))
+            .unwrap();
+            let client = server.client();
+
+            let res = client.get("/items").send().await.unwrap();
+            assert_eq!(res.header(http::header::CONTENT_TYPE), Some("application/x-ndjson"));
+            assert_eq!(res.text().unwrap(), "1\n2\n");
+            assert!(client.get("/broken").send().await.is_err());
+
+            let res = client.post("/sum").body("1\n2\n3").send().await.unwrap();
+            assert_eq!(res.text().unwrap(), "6");
+            assert_eq!(client.post("/sum").body("1\nnope\n").send().await.unwrap().status(), 400);
         }
     }
 }
@@ -411,6 +902,1095 @@ pub mod form {
     }
 }
 
+#[cfg(feature = "msgpack")]
+#[cfg_attr(docsrs, doc(cfg(feature = "msgpack")))]
+pub mod msgpack {
+    use crate::{body::FromBytes, error::SaphirError};
+    use hyper::body::Bytes;
+    use serde::Deserialize;
+    use std::{
+        borrow::{Borrow, BorrowMut},
+        ops::{Deref, DerefMut},
+    };
+
+    /// Request or response body encoded as MessagePack, `application/msgpack`
+    pub struct MsgPack<T>(pub T);
+
+    impl<T> MsgPack<T> {
+        pub fn into_inner(self) -> T {
+            self.0
+        }
+    }
+
+    impl<T> Deref for MsgPack<T> {
+        type Target = T;
+
+        fn deref(&self) -> &Self::Target {
+            &self.0
+        }
+    }
+
+    impl<T> DerefMut for MsgPack<T> {
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            &mut self.0
+        }
+    }
+
+    impl<T> AsRef<T> for MsgPack<T> {
+        fn as_ref(&self) -> &T {
+            &self.0
+        }
+    }
+
+    impl<T> AsMut<T> for MsgPack<T> {
+        fn as_mut(&mut self) -> &mut T {
+            &mut self.0
+        }
+    }
+
+    impl<T> Borrow<T> for MsgPack<T> {
+        fn borrow(&self) -> &T {
+            &self.0
+        }
+    }
+
+    impl<T> BorrowMut<T> for MsgPack<T> {
+        fn borrow_mut(&mut self) -> &mut T {
+            &mut self.0
+        }
+    }
+
+    impl<T> FromBytes for MsgPack<T>
+    where
+        T: for<'a> Deserialize<'a>,
+    {
+        type Out = T;
+
+        #[inline]
+        fn from_bytes(bytes: Bytes) -> Result<(Self::Out, Bytes), SaphirError>
+        where
+            Self: Sized,
+        {
+            Ok((rmp_serde::from_slice(bytes.as_ref())?, bytes))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::body::Body;
+
+        #[derive(Debug, PartialEq, serde_derive::Deserialize, serde_derive::Serialize)]
+        struct User {
+            name: String,
+            age: u8,
+        }
+
+        #[tokio::test]
+        async fn decodes_msgpack_bodies() {
+            let user = User {
+                name: "richer".to_string(),
+                age: 30,
+            };
+            let mut body = Body::<Bytes>::from_raw(hyper::Body::from(rmp_serde::to_vec_named(&user).unwrap()));
+            assert_eq!(body.take_as::<MsgPack<User>>().await.unwrap(), user);
+
+            let mut body = Body::<Bytes>::from_raw(hyper::Body::from(r#"{"name":"richer"}"#));
+            assert!(matches!(body.take_as::<MsgPack<User>>().await, Err(SaphirError::MsgPackDe(_))));
+        }
+    }
+}
+
+#[cfg(feature = "json5")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json5")))]
+pub mod json5 {
+    use crate::{body::FromBytes, error::SaphirError};
+    use hyper::body::Bytes;
+    use serde::Deserialize;
+    use std::{
+        borrow::{Borrow, BorrowMut},
+        ops::{Deref, DerefMut},
+    };
+
+    /// A lenient json body, allowing comments, trailing commas, unquoted keys
+    /// and the other extensions of [JSON5](https://json5.org), e.g. for
+    /// configuration files written by hand.
+    ///
+    /// Responses are serialized as strict json. Bodies which fail to
+    /// deserialize result in a [`SaphirError::Json5`], answered with `400 Bad
+    /// Request`.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate saphir_macro;
+    /// # use saphir::{prelude::*, test::TestServer};
+    /// # use serde_derive::{Deserialize, Serialize};
+    /// #
+    /// #[derive(Deserialize, Serialize)]
+    /// struct Config {
+    ///     name: String,
+    /// }
+    ///
+    /// struct ConfigController {}
+    ///
+    /// #[controller(name = "config")]
+    /// impl ConfigController {
+    ///     #[put("/")]
+    ///     # #[validator(exclude("config"))]
+    ///     async fn upload(&self, config: Json5<Config>) -> Json5<Config> {
+    ///         config
+    ///     }
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), SaphirError> {
+    /// let client = TestServer::new(Server::builder().configure_router(|r| r.controller(ConfigController {})))?.client();
+    ///
+    /// let res = client.put("/config").body("{ name: 'saphir', /* comment */ }").send().await?;
+    /// assert_eq!(res.text()?, r#"{"name":"saphir"}"#);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub struct Json5<T>(pub T);
+
+    impl<T> Json5<T> {
+        pub fn into_inner(self) -> T {
+            self.0
+        }
+    }
+
+    impl<T> Deref for Json5<T> {
+        type Target = T;
+
+        fn deref(&self) -> &Self::Target {
+            &self.0
+        }
+    }
+
+    impl<T> DerefMut for Json5<T> {
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            &mut self.0
+        }
+    }
+
+    impl<T> AsRef<T> for Json5<T> {
+        fn as_ref(&self) -> &T {
+            &self.0
+        }
+    }
+
+    impl<T> AsMut<T> for Json5<T> {
+        fn as_mut(&mut self) -> &mut T {
+            &mut self.0
+        }
+    }
+
+    impl<T> Borrow<T> for Json5<T> {
+        fn borrow(&self) -> &T {
+            &self.0
+        }
+    }
+
+    impl<T> BorrowMut<T> for Json5<T> {
+        fn borrow_mut(&mut self) -> &mut T {
+            &mut self.0
+        }
+    }
+
+    impl<T> FromBytes for Json5<T>
+    where
+        T: for<'a> Deserialize<'a>,
+    {
+        type Out = T;
+
+        #[inline]
+        fn from_bytes(bytes: Bytes) -> Result<(Self::Out, Bytes), SaphirError>
+        where
+            Self: Sized,
+        {
+            Ok((from_slice(bytes.as_ref())?, bytes))
+        }
+    }
+
+    /// Deepest nesting of arrays and objects accepted. The parser is
+    /// recursive, so deeper documents could overflow the stack.
+    const MAX_DEPTH: usize = 128;
+
+    /// Deserialize lenient json
+    pub(crate) fn from_slice<T: for<'a> Deserialize<'a>>(bytes: &[u8]) -> Result<T, SaphirError> {
+        let text = std::str::from_utf8(bytes).map_err(|e| ::json5::Error::Message {
+            msg: e.to_string(),
+            location: None,
+        })?;
+        if too_deep(text, MAX_DEPTH) {
+            return Err(::json5::Error::Message {
+                msg: format!("nesting deeper than {} levels", MAX_DEPTH),
+                location: None,
+            }
+            .into());
+        }
+        Ok(::json5::from_str(text)?)
+    }
+
+    /// Whether the arrays and objects of `text` are nested deeper than
+    /// `max`, ignoring the brackets within strings and comments
+    fn too_deep(text: &str, max: usize) -> bool {
+        let mut depth = 0usize;
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '[' | '{' => {
+                    depth += 1;
+                    if depth > max {
+                        return true;
+                    }
+                }
+                ']' | '}' => depth = depth.saturating_sub(1),
+                '"' | '\'' => {
+                    while let Some(s) = chars.next() {
+                        match s {
+                            '\\' => {
+                                chars.next();
+                            }
+                            _ if s == c => break,
+                            _ => {}
+                        }
+                    }
+                }
+                '/' if chars.peek() == Some(&'/') => {
+                    for s in chars.by_ref() {
+                        if s == '\n' {
+                            break;
+                        }
+                    }
+                }
+                '/' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    let mut star = false;
+                    for s in chars.by_ref() {
+                        if star && s == '/' {
+                            break;
+                        }
+                        star = s == '*';
+                    }
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::body::Body;
+
+        #[derive(serde_derive::Deserialize, Debug, PartialEq)]
+        struct Config {
+            name: String,
+            ports: Vec<u16>,
+        }
+
+        #[tokio::test]
+        async fn lenient_json_is_parsed() {
+            let config = "{\n  // the service name\n  name: 'saphir',\n  ports: [80, 443,],\n}";
+            let mut body = Body::<Bytes>::from_raw(hyper::Body::from(config));
+            let config = body.take_as::<Json5<Config>>().await.unwrap();
+            assert_eq!(
+                config,
+                Config {
+                    name: "saphir".to_string(),
+                    ports: vec![80, 443]
+                }
+            );
+
+            let mut body = Body::<Bytes>::from_raw(hyper::Body::from("{ name: "));
+            assert!(matches!(body.take_as::<Json5<Config>>().await, Err(SaphirError::Json5(_))));
+        }
+
+        #[test]
+        fn deep_nesting_is_rejected() {
+            let deep = "[".repeat(10_000);
+            assert!(matches!(from_slice::<serde_json::Value>(deep.as_bytes()), Err(SaphirError::Json5(_))));
+
+            let nested = format!("{}{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH));
+            assert!(from_slice::<serde_json::Value>(nested.as_bytes()).is_ok());
+
+            let quoted = format!("{{ a: '{}', /* {} */ b: \"]]\" }}", "[".repeat(1_000), "{".repeat(1_000));
+            assert!(from_slice::<serde_json::Value>(quoted.as_bytes()).is_ok());
+        }
+    }
+}
+
+#[cfg(feature = "cbor")]
+#[cfg_attr(docsrs, doc(cfg(feature = "cbor")))]
+pub mod cbor {
+    #[cfg(feature = "json")]
+    use crate::request::Request;
+    use crate::{body::FromBytes, error::SaphirError};
+    #[cfg(feature = "json")]
+    use http::header;
+    use hyper::body::Bytes;
+    use serde::Deserialize;
+    use std::{
+        borrow::{Borrow, BorrowMut},
+        ops::{Deref, DerefMut},
+    };
+
+    /// Request or response body encoded as CBOR, `application/cbor`
+    pub struct Cbor<T>(pub T);
+
+    impl<T> Cbor<T> {
+        pub fn into_inner(self) -> T {
+            self.0
+        }
+    }
+
+    impl<T> Deref for Cbor<T> {
+        type Target = T;
+
+        fn deref(&self) -> &Self::Target {
+            &self.0
+        }
+    }
+
+    impl<T> DerefMut for Cbor<T> {
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            &mut self.0
+        }
+    }
+
+    impl<T> AsRef<T> for Cbor<T> {
+        fn as_ref(&self) -> &T {
+            &self.0
+        }
+    }
+
+    impl<T> AsMut<T> for Cbor<T> {
+        fn as_mut(&mut self) -> &mut T {
+            &mut self.0
+        }
+    }
+
+    impl<T> Borrow<T> for Cbor<T> {
+        fn borrow(&self) -> &T {
+            &self.0
+        }
+    }
+
+    impl<T> BorrowMut<T> for Cbor<T> {
+        fn borrow_mut(&mut self) -> &mut T {
+            &mut self.0
+        }
+    }
+
+    impl<T> FromBytes for Cbor<T>
+    where
+        T: for<'a> Deserialize<'a>,
+    {
+        type Out = T;
+
+        #[inline]
+        fn from_bytes(bytes: Bytes) -> Result<(Self::Out, Bytes), SaphirError>
+        where
+            Self: Sized,
+        {
+            Ok((ciborium::from_reader(bytes.as_ref())?, bytes))
+        }
+    }
+
+    /// Value serialized as JSON or as CBOR, whichever the `Accept` header of
+    /// the request prefers, so the same type can be returned to both kinds of
+    /// clients. JSON is used when the client has no preference.
+    ///
+    /// ```rust
+    /// # use saphir::prelude::*;
+    /// # #[derive(serde_derive::Serialize)]
+    /// # struct User { name: String }
+    /// async fn user(req: Request) -> Negotiated<User> {
+    ///     Negotiated::new(&req, User { name: "richer".to_string() })
+    /// }
+    /// ```
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "cbor", feature = "json"))))]
+    pub enum Negotiated<T> {
+        Json(T),
+        Cbor(T),
+    }
+
+    #[cfg(feature = "json")]
+    impl<T> Negotiated<T> {
+        pub fn new<B>(req: &Request<B>, value: T) -> Self {
+            let accept = req
+                .headers()
+                .get_all(header::ACCEPT)
+                .iter()
+                .filter_map(|h| h.to_str().ok())
+                .collect::<Vec<_>>()
+                .join(",");
+            if quality(&accept, "application/cbor") > quality(&accept, "application/json") {
+                Negotiated::Cbor(value)
+            } else {
+                Negotiated::Json(value)
+            }
+        }
+
+        pub fn into_inner(self) -> T {
+            match self {
+                Negotiated::Json(value) | Negotiated::Cbor(value) => value,
+            }
+        }
+    }
+
+    /// Quality given to `mime` by the most specific media range of an
+    /// `Accept` header which matches it, 1 when the header is empty
+    #[cfg(feature = "json")]
+    fn quality(accept: &str, mime: &str) -> f32 {
+        let (typ, _) = mime.split_once('/').unwrap_or((mime, ""));
+        let mut best: Option<(u8, f32)> = None;
+        for range in accept.split(',').filter(|r| !r.trim().is_empty()) {
+            let mut parts = range.split(';').map(str::trim);
+            let media = parts.next().unwrap_or_default().to_ascii_lowercase();
+            let specificity = match media.split_once('/') {
+                Some(("*", "*")) => 1,
+                Some((t, "*")) if t == typ => 2,
+                _ if media == mime => 3,
+                _ => continue,
+            };
+            let q = parts
+                .filter_map(|p| p.split_once('='))
+                .find(|(k, _)| k.trim().eq_ignore_ascii_case("q"))
+                .and_then(|(_, v)| v.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if best.is_none_or(|(s, _)| specificity > s) {
+                best = Some((specificity, q));
+            }
+        }
+        match best {
+            Some((_, q)) => q,
+            None if accept.trim().is_empty() => 1.0,
+            None => 0.0,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::body::Body;
+
+        #[derive(Debug, PartialEq, serde_derive::Deserialize, serde_derive::Serialize)]
+        struct User {
+            name: String,
+            age: u8,
+        }
+
+        #[tokio::test]
+        async fn decodes_cbor_bodies() {
+            let user = User {
+                name: "richer".to_string(),
+                age: 30,
+            };
+            let mut bytes = Vec::new();
+            ciborium::into_writer(&user, &mut bytes).unwrap();
+            let mut body = Body::<Bytes>::from_raw(hyper::Body::from(bytes));
+            assert_eq!(body.take_as::<Cbor<User>>().await.unwrap(), user);
+
+            let mut body = Body::<Bytes>::from_raw(hyper::Body::from(r#"{"name":"richer"}"#));
+            assert!(matches!(body.take_as::<Cbor<User>>().await, Err(SaphirError::CborDe(_))));
+        }
+
+        #[cfg(feature = "json")]
+        #[test]
+        fn negotiates_the_format() {
+            let negotiate = |accept: &str| {
+                let req = Request::new(http::Request::builder().header(header::ACCEPT, accept).body(()).unwrap(), None);
+                matches!(Negotiated::new(&req, ()), Negotiated::Cbor(_))
+            };
+            assert!(negotiate("application/cbor"));
+            assert!(negotiate("application/json;q=0.5, application/cbor"));
+            assert!(negotiate("application/cbor, */*;q=0.8"));
+            assert!(!negotiate("application/json, application/cbor"));
+            assert!(!negotiate("application/cbor;q=0.2, application/*"));
+            assert!(!negotiate("*/*"));
+            assert!(!negotiate(""));
+            assert!(!negotiate("text/html"));
+        }
+    }
+}
+
+#[cfg(any(feature = "json", feature = "form"))]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "json", feature = "form"))))]
+pub mod any {
+    use crate::{body::Bytes, error::SaphirError, request::FromRequest, request::Request};
+    use futures::future::BoxFuture;
+    use serde::Deserialize;
+    use std::{
+        borrow::{Borrow, BorrowMut},
+        ops::{Deref, DerefMut},
+    };
+
+    /// Request body deserialized with the codec matching its `Content-Type`.
+    ///
+    /// `application/json` (and `+json` suffixed types) bodies are decoded when
+    /// the `json` feature is enabled, `application/json5` bodies when the
+    /// `json5` feature is enabled, `application/x-www-form-urlencoded` bodies
+    /// when the `form` feature is enabled. Any other content type is rejected
+    /// with a `415 Unsupported Media Type`.
+    pub struct AnyBody<T>(pub T);
+
+    impl<T> AnyBody<T> {
+        pub fn into_inner(self) -> T {
+            self.0
+        }
+    }
+
+    impl<T> Deref for AnyBody<T> {
+        type Target = T;
+
+        fn deref(&self) -> &Self::Target {
+            &self.0
+        }
+    }
+
+    impl<T> DerefMut for AnyBody<T> {
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            &mut self.0
+        }
+    }
+
+    impl<T> AsRef<T> for AnyBody<T> {
+        fn as_ref(&self) -> &T {
+            &self.0
+        }
+    }
+
+    impl<T> AsMut<T> for AnyBody<T> {
+        fn as_mut(&mut self) -> &mut T {
+            &mut self.0
+        }
+    }
+
+    impl<T> Borrow<T> for AnyBody<T> {
+        fn borrow(&self) -> &T {
+            &self.0
+        }
+    }
+
+    impl<T> BorrowMut<T> for AnyBody<T> {
+        fn borrow_mut(&mut self) -> &mut T {
+            &mut self.0
+        }
+    }
+
+    enum Codec {
+        #[cfg(feature = "json")]
+        Json,
+        #[cfg(feature = "json5")]
+        Json5,
+        #[cfg(feature = "form")]
+        Form,
+    }
+
+    impl Codec {
+        fn from_content_type(content_type: &str) -> Option<Self> {
+            let essence = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+            match essence.as_str() {
+                #[cfg(feature = "json")]
+                "application/json" => Some(Codec::Json),
+                #[cfg(feature = "json")]
+                e if e.starts_with("application/") && e.ends_with("+json") => Some(Codec::Json),
+                #[cfg(feature = "json5")]
+                "application/json5" => Some(Codec::Json5),
+                #[cfg(feature = "form")]
+                "application/x-www-form-urlencoded" => Some(Codec::Form),
+                _ => None,
+            }
+        }
+
+        fn decode<T: for<'a> Deserialize<'a>>(&self, bytes: &[u8]) -> Result<T, SaphirError> {
+            match self {
+                #[cfg(feature = "json")]
+                Codec::Json => crate::body::json::from_slice(bytes),
+                #[cfg(feature = "json5")]
+                Codec::Json5 => crate::body::json5::from_slice(bytes),
+                #[cfg(feature = "form")]
+                Codec::Form => Ok(serde_urlencoded::from_bytes(bytes)?),
+            }
+        }
+    }
+
+    impl<T> FromRequest for AnyBody<T>
+    where
+        T: for<'a> Deserialize<'a> + Send + 'static,
+    {
+        type Err = SaphirError;
+        type Fut = BoxFuture<'static, Result<Self, Self::Err>>;
+
+        fn from_request(req: &mut Request) -> Self::Fut {
+            let content_type = req
+                .headers()
+                .get(http::header::CONTENT_TYPE)
+                .and_then(|c_t| c_t.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            let codec = Codec::from_content_type(&content_type);
+            let body = codec.as_ref().map(|_| req.body_mut().take());
+
+            Box::pin(async move {
+                let codec = codec.ok_or(SaphirError::UnsupportedMediaType(content_type))?;
+                let bytes: Bytes = body.expect("body is taken whenever a codec is found").await?;
+                codec.decode(&bytes).map(AnyBody)
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::body::Body;
+
+        #[derive(serde_derive::Deserialize, Debug, PartialEq)]
+        struct Dto {
+            name: String,
+            age: u8,
+        }
+
+        fn request(content_type: &str, body: &'static str) -> Request {
+            let raw = http::Request::builder()
+                .header(http::header::CONTENT_TYPE, content_type)
+                .body(Body::from_raw(hyper::Body::from(body)))
+                .unwrap();
+            Request::new(raw, None)
+        }
+
+        #[tokio::test]
+        async fn decodes_with_matching_codec() {
+            let expected = Dto {
+                name: "saphir".to_string(),
+                age: 3,
+            };
+
+            #[cfg(feature = "json")]
+            {
+                let mut req = request("application/json; charset=utf-8", r#"{"name":"saphir","age":3}"#);
+                assert_eq!(AnyBody::<Dto>::from_request(&mut req).await.unwrap().into_inner(), expected);
+            }
+
+            #[cfg(feature = "json5")]
+            {
+                let mut req = request("application/json5", "{name: 'saphir', age: 3,}");
+                assert_eq!(AnyBody::<Dto>::from_request(&mut req).await.unwrap().into_inner(), expected);
+                let mut req = request("application/json", "{name: 'saphir', age: 3,}");
+                assert!(AnyBody::<Dto>::from_request(&mut req).await.is_err());
+            }
+
+            #[cfg(feature = "form")]
+            {
+                let mut req = request("application/x-www-form-urlencoded", "name=saphir&age=3");
+                assert_eq!(AnyBody::<Dto>::from_request(&mut req).await.unwrap().into_inner(), expected);
+            }
+        }
+
+        #[tokio::test]
+        async fn rejects_unsupported_content_type() {
+            let mut req = request("text/plain", "name=saphir&age=3");
+            match AnyBody::<Dto>::from_request(&mut req).await {
+                Err(SaphirError::UnsupportedMediaType(c_t)) => assert_eq!(c_t, "text/plain"),
+                _ => panic!("expected an unsupported media type error"),
+            }
+            assert!(req.body_mut().take().await.is_ok_and(|b: Bytes| !b.is_empty()));
+        }
+    }
+}
+
+pub mod raw {
+    use crate::{
+        body::{BodyInner, Bytes, REQUEST_BODY_BYTES_LIMIT},
+        error::SaphirError,
+        request::{FromRequest, Request},
+    };
+    use futures::{future::BoxFuture, Stream, StreamExt};
+    use std::ops::Deref;
+
+    /// Whole request body, loaded in memory regardless of its content type.
+    ///
+    /// Bodies larger than the `request_body_max_bytes` limit of the listener
+    /// are rejected with a `413 Payload Too Large`.
+    pub struct RawBody(pub Bytes);
+
+    impl RawBody {
+        pub fn into_inner(self) -> Bytes {
+            self.0
+        }
+    }
+
+    impl Deref for RawBody {
+        type Target = Bytes;
+
+        fn deref(&self) -> &Self::Target {
+            &self.0
+        }
+    }
+
+    impl AsRef<[u8]> for RawBody {
+        fn as_ref(&self) -> &[u8] {
+            &self.0
+        }
+    }
+
+    impl FromRequest for RawBody {
+        type Err = SaphirError;
+        type Fut = BoxFuture<'static, Result<Self, Self::Err>>;
+
+        fn from_request(req: &mut Request) -> Self::Fut {
+            let inner = req.body_mut().take().inner;
+            Box::pin(async move {
+                let inner = inner.ok_or(SaphirError::BodyAlreadyTaken)?;
+                let mut chunks = Box::pin(request_chunks(inner));
+                let mut buf = Vec::new();
+                while let Some(chunk) = chunks.next().await {
+                    buf.extend_from_slice(&chunk?);
+                }
+                Ok(RawBody(buf.into()))
+            })
+        }
+    }
+
+    /// Chunks of a body, failing once the request body limit is exceeded
+    pub(crate) fn request_chunks(inner: BodyInner) -> impl Stream<Item = Result<Bytes, SaphirError>> + Send + Sync {
+        // # SAFETY #
+        // The limit is only written once, before the server starts.
+        limited_chunks(inner, unsafe { REQUEST_BODY_BYTES_LIMIT })
+    }
+
+    pub(crate) fn limited_chunks(inner: BodyInner, limit: Option<usize>) -> impl Stream<Item = Result<Bytes, SaphirError>> + Send + Sync {
+        async_stream::try_stream! {
+            match inner {
+                BodyInner::Memory(bytes) => {
+                    if limit.is_some_and(|l| bytes.len() > l) {
+                        Err(SaphirError::PayloadTooLarge)?;
+                    }
+                    yield bytes;
+                }
+                BodyInner::Raw(mut raw) => {
+                    let mut read = 0;
+                    while let Some(chunk) = raw.next().await {
+                        let chunk = chunk?;
+                        read += chunk.len();
+                        if limit.is_some_and(|l| read > l) {
+                            Err(SaphirError::PayloadTooLarge)?;
+                        }
+                        yield chunk;
+                    }
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "temp-file")]
+    pub(crate) use temp::set_temp_dir;
+    #[cfg(feature = "temp-file")]
+    pub use temp::TempFile;
+
+    #[cfg(feature = "temp-file")]
+    mod temp {
+        use super::request_chunks;
+        use crate::{
+            error::SaphirError,
+            request::{FromRequest, Request},
+        };
+        use futures::{future::BoxFuture, StreamExt};
+        use mime::Mime;
+        use std::{
+            path::{Path, PathBuf},
+            sync::atomic::{AtomicU64, Ordering},
+            time::{SystemTime, UNIX_EPOCH},
+        };
+        use tokio::{
+            io::AsyncWriteExt,
+            sync::{OnceCell, SetError},
+        };
+
+        static TEMP_FILE_COUNT: AtomicU64 = AtomicU64::new(0);
+        static TEMP_DIR: OnceCell<PathBuf> = OnceCell::const_new();
+
+        /// Set the directory of the temporary files, when the server starts.
+        /// The directory is shared by the whole process, so only the first
+        /// one set is used.
+        pub(crate) fn set_temp_dir(dir: PathBuf) {
+            if let Err(SetError::AlreadyInitializedError(dir) | SetError::InitializingError(dir)) = TEMP_DIR.set(dir) {
+                if TEMP_DIR.get() != Some(&dir) {
+                    warn!(
+                        "The temporary files are already created in {}, ignoring the temp dir {}",
+                        TempFile::default_dir().display(),
+                        dir.display()
+                    );
+                }
+            }
+        }
+
+        /// Raw request body streamed to a temporary file, for single file
+        /// uploads which are not sent as multipart forms.
+        ///
+        /// The file is created in the `temp_dir` of the listener, which
+        /// defaults to `std::env::temp_dir()`, or in the directory given to
+        /// [`from_request_in`](TempFile::from_request_in). It is deleted when
+        /// the `TempFile` is dropped, including when the handler returns early
+        /// or with an error, unless it was moved with
+        /// [`persist`](TempFile::persist) or kept with
+        /// [`keep`](TempFile::keep). Bodies larger than the
+        /// `request_body_max_bytes` limit of the listener are rejected with a
+        /// `413 Payload Too Large` and nothing is left on disk.
+        ///
+        /// Files are removed synchronously on drop, so the removal is done even
+        /// while the runtime shuts down. They are however left behind if the
+        /// process is killed or aborts on a panic: their names start with
+        /// `saphir-upload-{pid}-`, so those of a dead process can be swept at
+        /// startup.
+        ///
+        /// ```rust
+        /// use saphir::prelude::*;
+        ///
+        /// async fn upload(mut req: Request) -> Result<(u16, String), SaphirError> {
+        ///     let file = TempFile::from_request(&mut req).await?;
+        ///     let size = file.size();
+        ///     file.persist(std::env::temp_dir().join("upload.bin")).await?;
+        ///     Ok((201, format!("{} bytes uploaded", size)))
+        /// }
+        /// ```
+        #[derive(Debug)]
+        pub struct TempFile {
+            path: PathBuf,
+            content_type: Option<Mime>,
+            size: u64,
+            keep: bool,
+        }
+
+        impl TempFile {
+            /// Path of the temporary file
+            pub fn path(&self) -> &Path {
+                &self.path
+            }
+
+            /// Mime type of the file, as sent in the `Content-Type` header of
+            /// the request
+            pub fn content_type(&self) -> Option<&Mime> {
+                self.content_type.as_ref()
+            }
+
+            /// Size of the file, in bytes
+            pub fn size(&self) -> u64 {
+                self.size
+            }
+
+            /// Move the file to `path`, which will not be deleted afterward
+            pub async fn persist<P: AsRef<Path>>(mut self, path: P) -> Result<PathBuf, SaphirError> {
+                let path = path.as_ref();
+                if tokio::fs::rename(&self.path, path).await.is_err() {
+                    // The temp dir might be on another filesystem
+                    tokio::fs::copy(&self.path, path).await?;
+                    tokio::fs::remove_file(&self.path).await?;
+                }
+                self.keep = true;
+                Ok(path.to_path_buf())
+            }
+
+            /// Keep the file at its temporary location and return its path
+            pub fn keep(mut self) -> PathBuf {
+                self.keep = true;
+                std::mem::take(&mut self.path)
+            }
+
+            /// Stream the request body to a temporary file created in `dir`
+            /// rather than in the `temp_dir` of the listener
+            pub async fn from_request_in<P: AsRef<Path>>(req: &mut Request, dir: P) -> Result<Self, SaphirError> {
+                let dir = dir.as_ref().to_path_buf();
+                Self::load(req, dir).await
+            }
+
+            /// Write `bytes` to a new temporary file, blocking the current
+            /// thread
+            #[cfg(all(feature = "multipart", feature = "form"))]
+            pub(crate) fn from_bytes_blocking(bytes: &[u8], content_type: Option<Mime>) -> std::io::Result<Self> {
+                let path = Self::new_path(&Self::default_dir());
+                let mut temp = TempFile {
+                    path,
+                    content_type,
+                    size: 0,
+                    keep: false,
+                };
+                std::fs::write(&temp.path, bytes)?;
+                temp.size = bytes.len() as u64;
+                Ok(temp)
+            }
+
+            fn load(req: &mut Request, dir: PathBuf) -> impl std::future::Future<Output = Result<Self, SaphirError>> + Send + 'static {
+                let content_type = req
+                    .headers()
+                    .get(http::header::CONTENT_TYPE)
+                    .and_then(|c_t| c_t.to_str().ok())
+                    .and_then(|c_t| c_t.parse().ok());
+                let inner = req.body_mut().take().inner;
+
+                async move {
+                    let inner = inner.ok_or(SaphirError::BodyAlreadyTaken)?;
+                    let path = Self::new_path(&dir);
+                    let mut file = tokio::fs::OpenOptions::new().write(true).create_new(true).open(&path).await?;
+
+                    // From here on, dropping the temp file cleans up whatever was written
+                    let mut temp = TempFile {
+                        path,
+                        content_type,
+                        size: 0,
+                        keep: false,
+                    };
+
+                    let mut chunks = Box::pin(request_chunks(inner));
+                    while let Some(chunk) = chunks.next().await {
+                        let chunk = chunk?;
+                        file.write_all(&chunk).await?;
+                        temp.size += chunk.len() as u64;
+                    }
+                    file.flush().await?;
+
+                    Ok(temp)
+                }
+            }
+
+            fn default_dir() -> PathBuf {
+                TEMP_DIR.get().cloned().unwrap_or_else(std::env::temp_dir)
+            }
+
+            fn new_path(dir: &Path) -> PathBuf {
+                let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or_default();
+                dir.join(format!(
+                    "saphir-upload-{}-{}-{}",
+                    std::process::id(),
+                    TEMP_FILE_COUNT.fetch_add(1, Ordering::Relaxed),
+                    nanos
+                ))
+            }
+        }
+
+        impl Drop for TempFile {
+            fn drop(&mut self) {
+                if !self.keep {
+                    if let Err(e) = std::fs::remove_file(&self.path) {
+                        warn!("Unable to remove temporary file {}: {}", self.path.display(), e);
+                    }
+                }
+            }
+        }
+
+        impl FromRequest for TempFile {
+            type Err = SaphirError;
+            type Fut = BoxFuture<'static, Result<Self, Self::Err>>;
+
+            fn from_request(req: &mut Request) -> Self::Fut {
+                Box::pin(Self::load(req, Self::default_dir()))
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::body::Body;
+
+        fn request(body: &'static str) -> Request {
+            let raw = http::Request::builder()
+                .header(http::header::CONTENT_TYPE, "application/octet-stream")
+                .body(Body::from_raw(hyper::Body::wrap_stream(futures::stream::iter(
+                    body.as_bytes().chunks(4).map(|c| Ok::<_, std::io::Error>(Bytes::copy_from_slice(c))),
+                ))))
+                .unwrap();
+            Request::new(raw, None)
+        }
+
+        #[tokio::test]
+        async fn loads_raw_body() {
+            let mut req = request("some raw content");
+            let body = RawBody::from_request(&mut req).await.unwrap();
+            assert_eq!(body.as_ref(), b"some raw content");
+
+            assert!(matches!(RawBody::from_request(&mut req).await, Err(SaphirError::BodyAlreadyTaken)));
+        }
+
+        #[cfg(feature = "temp-file")]
+        #[tokio::test]
+        async fn streams_body_to_temp_file() {
+            let mut req = request("some raw content");
+            let file = TempFile::from_request(&mut req).await.unwrap();
+            assert_eq!(file.size(), 16);
+            assert_eq!(file.content_type(), Some(&mime::APPLICATION_OCTET_STREAM));
+            assert_eq!(std::fs::read_to_string(file.path()).unwrap(), "some raw content");
+
+            let path = file.path().to_path_buf();
+            drop(file);
+            assert!(!path.exists());
+
+            let mut req = request("kept");
+            let path = TempFile::from_request(&mut req).await.unwrap().keep();
+            assert!(path.exists());
+
+            let dest = std::env::temp_dir().join(format!("saphir-persisted-{}", std::process::id()));
+            let mut req = request("persisted");
+            let file = TempFile::from_request(&mut req).await.unwrap();
+            assert_eq!(file.persist(&dest).await.unwrap(), dest);
+            assert_eq!(std::fs::read_to_string(&dest).unwrap(), "persisted");
+
+            std::fs::remove_file(path).unwrap();
+            std::fs::remove_file(dest).unwrap();
+        }
+
+        #[cfg(feature = "temp-file")]
+        #[tokio::test]
+        async fn creates_temp_file_in_dir() {
+            let dir = std::env::temp_dir().join(format!("saphir-uploads-{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+
+            let mut req = request("in dir");
+            let file = TempFile::from_request_in(&mut req, &dir).await.unwrap();
+            assert_eq!(file.path().parent(), Some(dir.as_path()));
+            assert_eq!(std::fs::read_to_string(file.path()).unwrap(), "in dir");
+
+            drop(file);
+            assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+            std::fs::remove_dir(dir).unwrap();
+        }
+
+        #[tokio::test]
+        async fn loads_chunked_bodies_up_to_the_limit() {
+            let load = |limit| request("0123456789").body_mut().take().inner.unwrap().load_limited(limit);
+            assert_eq!(load(None).await.unwrap(), "0123456789");
+            assert_eq!(load(Some(10)).await.unwrap(), "0123456789");
+            assert!(matches!(load(Some(9)).await, Err(SaphirError::PayloadTooLarge)));
+            assert!(matches!(load(Some(2)).await, Err(SaphirError::PayloadTooLarge)));
+        }
+
+        #[cfg(feature = "json")]
+        #[tokio::test]
+        async fn loads_chunked_json() {
+            let mut req = request(r#"{"name": "chunked", "tags": ["a", "b"]}"#);
+            let json = req.body_mut().take_as::<crate::body::Json<serde_json::Value>>().await.unwrap();
+            assert_eq!(json["name"], "chunked");
+            assert_eq!(json["tags"][1], "b");
+        }
+
+        #[tokio::test]
+        async fn rejects_bodies_over_the_limit() {
+            let raw = request("0123456789").body_mut().take().inner.unwrap();
+            let chunks: Vec<_> = limited_chunks(raw, Some(6)).collect().await;
+            assert_eq!(chunks.len(), 2);
+            assert!(chunks[0].is_ok());
+            assert!(matches!(chunks[1], Err(SaphirError::PayloadTooLarge)));
+
+            let memory = BodyInner::Memory(Bytes::from_static(b"0123456789"));
+            let chunks: Vec<_> = limited_chunks(memory, Some(10)).collect().await;
+            assert!(chunks.iter().all(Result::is_ok));
+        }
+    }
+}
+
 impl<T: FromBytes + Unpin> HttpBody for Body<T> {
     type Data = Bytes;
     type Error = SaphirError;
@@ -524,18 +2104,18 @@ pub trait TransmuteBody {
 #[doc(hidden)]
 impl<T> TransmuteBody for Option<T>
 where
-    T: Into<RawBody>,
+    T: Into<HyperBody>,
 {
     #[inline]
     fn transmute(&mut self) -> Body<Bytes> {
-        Body::from_raw(if let Some(b) = self.take() { b.into() } else { RawBody::empty() })
+        Body::from_raw(if let Some(b) = self.take() { b.into() } else { HyperBody::empty() })
     }
 }
 
 #[allow(clippy::from_over_into)]
-impl<T: FromBytes> Into<RawBody> for Body<T> {
+impl<T: FromBytes> Into<HyperBody> for Body<T> {
     #[inline]
-    fn into(self) -> RawBody {
+    fn into(self) -> HyperBody {
         let Body { inner, .. } = self;
         inner.unwrap_or_else(BodyInner::empty).into_raw()
     }
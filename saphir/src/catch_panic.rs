@@ -0,0 +1,108 @@
+//! Recovery from panicking handlers.
+//!
+//! Without it, a handler which panics drops the connection of the client.
+//! The [`CatchPanicMiddleware`] catches panics raised while the rest of the
+//! chain is polled, including across await points of the handler, and
+//! answers them with a `500 Internal Server Error`. The panic payload is
+//! logged at the error level, along with the operation id with the
+//! `operation` feature. Panics of tasks spawned by the handler are not
+//! caught.
+//!
+//! ```rust
+//! use saphir::{catch_panic::CatchPanicMiddleware, prelude::*};
+//!
+//! let server = Server::builder()
+//!     .configure_middlewares(|m| m.apply(CatchPanicMiddleware::new(), vec!["/**"], None))
+//!     .build();
+//! ```
+//!
+//! The panic hook still runs when a panic is caught, so the default hook
+//! prints the panic to stderr. Applications compiled with `panic = "abort"`
+//! abort regardless of the middleware.
+
+use crate::{
+    error::SaphirError,
+    http_context::HttpContext,
+    middleware::{Middleware, MiddlewareChain},
+};
+use futures::future::{BoxFuture, FutureExt};
+use std::{any::Any, panic::AssertUnwindSafe};
+
+/// Middleware turning panics of the rest of the chain into a
+/// [`SaphirError::Panicked`]
+pub struct CatchPanicMiddleware {}
+
+impl CatchPanicMiddleware {
+    pub fn new() -> Self {
+        CatchPanicMiddleware {}
+    }
+
+    async fn next_inner(&self, ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+        AssertUnwindSafe(chain.next(ctx))
+            .catch_unwind()
+            .await
+            .unwrap_or_else(|payload| Err(SaphirError::Panicked(panic_message(payload.as_ref()))))
+    }
+}
+
+impl Default for CatchPanicMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Middleware for CatchPanicMiddleware {
+    fn next(&'static self, ctx: HttpContext, chain: &'static dyn MiddlewareChain) -> BoxFuture<'static, Result<HttpContext, SaphirError>> {
+        self.next_inner(ctx, chain).boxed()
+    }
+}
+
+/// The message of a panic payload, which is a `&str` or a `String` for panics
+/// raised by `panic!`
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "non-string panic payload".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{request::Request, server::Server, test::TestServer};
+    use http::Method;
+
+    async fn panicking(req: Request) -> &'static str {
+        tokio::task::yield_now().await;
+        if req.uri().query() == Some("panic") {
+            panic!("handler failed");
+        }
+        "ok"
+    }
+
+    #[tokio::test]
+    async fn panics_are_answered_with_500() {
+        let client = TestServer::new(
+            Server::builder()
+                .configure_middlewares(|m| m.apply(CatchPanicMiddleware::new(), vec!["/**"], None))
+                .configure_router(|r| r.route("/", Method::GET, panicking)),
+        )
+        .unwrap()
+        .client();
+
+        let res = client.get("/?panic").send().await.unwrap();
+        assert_eq!(res.status(), 500);
+        assert!(res.text().unwrap().is_empty());
+
+        let res = client.get("/").send().await.unwrap();
+        assert_eq!(res.text().unwrap(), "ok");
+    }
+
+    #[test]
+    fn payloads_are_read() {
+        assert_eq!(panic_message(&"static"), "static");
+        assert_eq!(panic_message(&format!("formatted {}", 1)), "formatted 1");
+        assert_eq!(panic_message(&1u8), "non-string panic payload");
+    }
+}
@@ -0,0 +1,444 @@
+//! Conditional GET for dynamic responses.
+//!
+//! A handler gives its response an [`EntityTag`], computed from the payload
+//! or from a version of the resource, and the [`ConditionalGetMiddleware`]
+//! answers `304 Not Modified` without the body when it matches the
+//! `If-None-Match` header of the request. Handlers which can tell that the
+//! client is up to date before building their payload can answer `304`
+//! themselves with [`is_not_modified`].
+//!
+//! Requests modifying a resource can be made conditional as well, for
+//! optimistic concurrency: the [`Precondition`] extractor reads the
+//! `If-Match` and `If-Unmodified-Since` headers, and
+//! [`Request::check_precondition`] fails with `412 Precondition Failed` when
+//! the client doesn't have the current version of the resource.
+//!
+//! With the `file` feature, `File` and `FileStream` responses already carry an
+//! `ETag` and a `Last-Modified` header, computed from the size and the
+//! modification time of the file, so the middleware applies to them as is.
+//!
+//! ```rust
+//! use saphir::{
+//!     conditional::{ConditionalGetMiddleware, EntityTag},
+//!     prelude::*,
+//! };
+//!
+//! async fn users(_req: Request) -> Builder {
+//!     let body = r#"[{"name":"richer"}]"#;
+//!     Builder::new().etag(&EntityTag::from_content(body.as_bytes())).body(body)
+//! }
+//!
+//! let server = Server::builder()
+//!     .configure_middlewares(|m| m.apply(ConditionalGetMiddleware::new(), vec!["/"], None))
+//!     .configure_router(|r| r.route("/users", Method::GET, users))
+//!     .build();
+//! ```
+
+use crate::request::FromRequest;
+use crate::{
+    body::Body,
+    error::SaphirError,
+    http_context::HttpContext,
+    middleware::{Middleware, MiddlewareChain},
+    request::Request,
+};
+use futures::future::{BoxFuture, FutureExt};
+use http::{header, HeaderMap, Method, StatusCode};
+use std::{
+    fmt::{Display, Formatter},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Entity tag of a representation, as sent in the `ETag` header
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EntityTag {
+    Strong(String),
+    Weak(String),
+}
+
+impl EntityTag {
+    pub fn new(is_weak: bool, tag: &str) -> Self {
+        if is_weak {
+            EntityTag::Weak(tag.to_string())
+        } else {
+            EntityTag::Strong(tag.to_string())
+        }
+    }
+
+    /// Strong tag computed from the content of a payload, identical for
+    /// identical payloads
+    pub fn from_content(content: &[u8]) -> Self {
+        // FNV-1a, stable across builds and server instances
+        let hash = content
+            .iter()
+            .fold(0xcbf29ce484222325u64, |hash, b| (hash ^ *b as u64).wrapping_mul(0x100000001b3));
+        EntityTag::Strong(format!("{:x}-{:016x}", content.len(), hash))
+    }
+
+    pub fn parse(tag: &str) -> Self {
+        let mut is_weak = false;
+        let parsed_tag = {
+            if tag.starts_with("W/") {
+                is_weak = true;
+                tag.trim_start_matches("W/\"").trim_end_matches('\"')
+            } else {
+                tag.trim_start_matches('\"').trim_end_matches('\"')
+            }
+        };
+
+        if is_weak {
+            EntityTag::Weak(parsed_tag.to_string())
+        } else {
+            EntityTag::Strong(parsed_tag.to_string())
+        }
+    }
+
+    pub fn get_tag(&self) -> String {
+        match self {
+            EntityTag::Strong(tag) => format!("\"{}\"", tag),
+            EntityTag::Weak(tag) => format!("W/\"{}\"", tag),
+        }
+    }
+
+    fn is_weak(&self) -> bool {
+        match self {
+            EntityTag::Weak(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Weak comparison, the tags match regardless of their weakness. Used for
+    /// `If-None-Match`.
+    pub fn weak_eq(&self, other: EntityTag) -> bool {
+        self.as_ref() == other.as_ref()
+    }
+
+    /// Strong comparison, the tags match only if both are strong. Used for
+    /// `If-Match` and `If-Range`.
+    pub fn strong_eq(&self, other: EntityTag) -> bool {
+        !self.is_weak() && !other.is_weak() && self.as_ref() == other.as_ref()
+    }
+
+    /// Check if a header listing entity tags, like `If-None-Match`, contains
+    /// this tag according to the weak comparison. `*` matches any tag.
+    pub fn weak_matches_any(&self, tags: &str) -> bool {
+        tags.trim() == "*" || tags.split(',').any(|tag| self.weak_eq(EntityTag::parse(tag.trim())))
+    }
+
+    /// Check if a header listing entity tags, like `If-Match`, contains this
+    /// tag according to the strong comparison. `*` matches any tag.
+    pub fn strong_matches_any(&self, tags: &str) -> bool {
+        tags.trim() == "*" || tags.split(',').any(|tag| self.strong_eq(EntityTag::parse(tag.trim())))
+    }
+}
+
+impl AsRef<str> for EntityTag {
+    fn as_ref(&self) -> &str {
+        match self {
+            EntityTag::Strong(str) => str.as_str(),
+            EntityTag::Weak(str) => str.as_str(),
+        }
+    }
+}
+
+impl Display for EntityTag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.get_tag())
+    }
+}
+
+fn if_none_match_matches(method: &Method, headers: &HeaderMap, etag: &EntityTag) -> bool {
+    (method == Method::GET || method == Method::HEAD)
+        && headers
+            .get_all(header::IF_NONE_MATCH)
+            .iter()
+            .filter_map(|h| h.to_str().ok())
+            .any(|tags| etag.weak_matches_any(tags))
+}
+
+/// Check if the client already has the representation tagged `etag`, i.e. if
+/// the request is a `GET` or `HEAD` whose `If-None-Match` header matches it,
+/// in which case it should be answered with `304 Not Modified`
+pub fn is_not_modified(req: &Request, etag: &EntityTag) -> bool {
+    if_none_match_matches(req.method(), req.headers(), etag)
+}
+
+/// Preconditions of a request modifying a resource, read from its `If-Match`
+/// and `If-Unmodified-Since` headers.
+///
+/// ```rust
+/// use saphir::{conditional::EntityTag, prelude::*};
+/// # use saphir::test::TestServer;
+///
+/// async fn update(req: Request) -> Result<Builder, SaphirError> {
+///     let current = EntityTag::from_content(b"version 1");
+///     req.check_precondition(&current)?;
+///     let updated = EntityTag::from_content(b"version 2");
+///     Ok(Builder::new().etag(&updated))
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), SaphirError> {
+/// # let client = TestServer::new(Server::builder().configure_router(|r| r.route("/", Method::PUT, update)))?.client();
+/// let current = EntityTag::from_content(b"version 1").to_string();
+/// let res = client.put("/").header(header::IF_MATCH, current.as_str()).send().await?;
+/// assert_eq!(res.status(), StatusCode::OK);
+/// let res = client.put("/").header(header::IF_MATCH, "\"stale\"").send().await?;
+/// assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Precondition {
+    if_match: Option<String>,
+    if_unmodified_since: Option<SystemTime>,
+}
+
+impl Precondition {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let if_match = headers.get_all(header::IF_MATCH).iter().filter_map(|h| h.to_str().ok()).collect::<Vec<_>>();
+        Precondition {
+            if_match: (!if_match.is_empty()).then(|| if_match.join(",")),
+            if_unmodified_since: headers.get(header::IF_UNMODIFIED_SINCE).and_then(|h| h.to_str().ok()).and_then(parse_http_date),
+        }
+    }
+
+    /// The entity tags listed by the `If-Match` header, `*` for any
+    pub fn if_match(&self) -> Option<Vec<EntityTag>> {
+        self.if_match
+            .as_ref()
+            .map(|tags| tags.split(',').map(str::trim).filter(|tag| !tag.is_empty()).map(EntityTag::parse).collect())
+    }
+
+    /// The date of the `If-Unmodified-Since` header, if valid
+    pub fn if_unmodified_since(&self) -> Option<SystemTime> {
+        self.if_unmodified_since
+    }
+
+    /// Whether the request has none of the precondition headers
+    pub fn is_empty(&self) -> bool {
+        self.if_match.is_none() && self.if_unmodified_since.is_none()
+    }
+
+    /// Evaluate the preconditions against the current version of the
+    /// resource, tagged `etag` and modified at `last_modified`.
+    ///
+    /// `If-Match` uses the strong comparison, so a weak `etag` never matches
+    /// a tag. `If-Unmodified-Since` is only evaluated without `If-Match`, and
+    /// when `last_modified` is known.
+    pub fn is_met(&self, etag: &EntityTag, last_modified: Option<SystemTime>) -> bool {
+        match (&self.if_match, self.if_unmodified_since, last_modified) {
+            (Some(if_match), ..) => etag.strong_matches_any(if_match),
+            (None, Some(since), Some(last_modified)) => unix_secs(last_modified) <= unix_secs(since),
+            _ => true,
+        }
+    }
+
+    /// Fail with [`SaphirError::PreconditionFailed`] unless the preconditions
+    /// are met, see [`is_met`](Self::is_met)
+    pub fn check(&self, etag: &EntityTag, last_modified: Option<SystemTime>) -> Result<(), SaphirError> {
+        if self.is_met(etag, last_modified) {
+            Ok(())
+        } else {
+            Err(SaphirError::PreconditionFailed)
+        }
+    }
+}
+
+impl FromRequest for Precondition {
+    type Err = SaphirError;
+    type Fut = futures::future::Ready<Result<Self, Self::Err>>;
+
+    fn from_request(req: &mut Request) -> Self::Fut {
+        futures::future::ready(Ok(Precondition::from_headers(req.headers())))
+    }
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Parse an IMF-fixdate, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`, the format of
+/// the dates sent in http headers
+fn parse_http_date(date: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+    let (_weekday, date) = date.trim().split_once(", ")?;
+    let mut parts = date.split(' ');
+    let day: u64 = parts.next()?.parse().ok()?;
+    let month = parts.next()?;
+    let month = MONTHS.iter().position(|m| *m == month)? as u64 + 1;
+    let year: u64 = parts.next()?.parse().ok()?;
+    let mut time = parts.next()?.split(':').map(|t| t.parse::<u64>().ok());
+    let (hours, minutes, seconds) = (time.next()??, time.next()??, time.next()??);
+    if parts.next()? != "GMT"
+        || parts.next().is_some()
+        || !(1970..=9999).contains(&year)
+        || !(1..=31).contains(&day)
+        || hours > 23
+        || minutes > 59
+        || seconds > 60
+    {
+        return None;
+    }
+
+    // Days since the epoch of the civil date, counting years from March so
+    // that leap days end them
+    let (year, month) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let days = year * 365 + year / 4 - year / 100 + year / 400 + day_of_year - 719_468;
+
+    UNIX_EPOCH.checked_add(Duration::from_secs(days * 86_400 + hours * 3_600 + minutes * 60 + seconds))
+}
+
+/// Middleware turning the `200 OK` responses whose `ETag` matches the
+/// `If-None-Match` header of their request into `304 Not Modified`, without
+/// their body
+#[derive(Default)]
+pub struct ConditionalGetMiddleware;
+
+impl ConditionalGetMiddleware {
+    pub fn new() -> Self {
+        Self
+    }
+
+    async fn next_inner(ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+        let conditions = ctx
+            .state
+            .request()
+            .filter(|req| req.headers().contains_key(header::IF_NONE_MATCH))
+            .map(|req| (req.method().clone(), req.headers().clone()));
+
+        let mut ctx = chain.next(ctx).await?;
+
+        if let (Some((method, headers)), Some(res)) = (conditions, ctx.state.response_mut()) {
+            let etag = res.headers().get(header::ETAG).and_then(|h| h.to_str().ok()).map(EntityTag::parse);
+            if res.status() == StatusCode::OK && etag.is_some_and(|etag| if_none_match_matches(&method, &headers, &etag)) {
+                *res.status_mut() = StatusCode::NOT_MODIFIED;
+                *res.body_mut() = Body::empty();
+                res.headers_mut().remove(header::CONTENT_LENGTH);
+                res.headers_mut().remove(header::CONTENT_TYPE);
+            }
+        }
+
+        Ok(ctx)
+    }
+}
+
+impl Middleware for ConditionalGetMiddleware {
+    fn next(&'static self, ctx: HttpContext, chain: &'static dyn MiddlewareChain) -> BoxFuture<'static, Result<HttpContext, SaphirError>> {
+        Self::next_inner(ctx, chain).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{response::Builder, server::Server, test::TestServer};
+
+    const BODY: &str = r#"{"name":"richer"}"#;
+
+    async fn user(req: Request) -> Builder {
+        let etag = EntityTag::from_content(BODY.as_bytes());
+        let etag = if req.uri().path() == "/weak" {
+            EntityTag::new(true, etag.as_ref())
+        } else {
+            etag
+        };
+        Builder::new().etag(&etag).header(header::CONTENT_TYPE, "application/json").body(BODY)
+    }
+
+    #[test]
+    fn compares_weak_and_strong_tags() {
+        let strong = EntityTag::parse("\"abc\"");
+        assert_eq!(strong, EntityTag::Strong("abc".to_string()));
+        assert!(strong.weak_eq(EntityTag::parse("W/\"abc\"")));
+        assert!(!strong.strong_eq(EntityTag::parse("W/\"abc\"")));
+        assert!(strong.strong_eq(EntityTag::parse("\"abc\"")));
+        assert!(strong.weak_matches_any("\"xyz\", W/\"abc\""));
+        assert!(strong.weak_matches_any(" * "));
+        assert!(!strong.weak_matches_any("\"xyz\""));
+
+        assert!(strong.strong_matches_any("\"xyz\", \"abc\""));
+        assert!(!strong.strong_matches_any("W/\"abc\""));
+        assert!(!EntityTag::parse("W/\"abc\"").strong_matches_any("W/\"abc\""));
+
+        assert_eq!(EntityTag::from_content(b"saphir"), EntityTag::from_content(b"saphir"));
+        assert_ne!(EntityTag::from_content(b"saphir"), EntityTag::from_content(b"hyper"));
+    }
+
+    #[test]
+    fn preconditions_are_evaluated() {
+        let etag = EntityTag::parse("\"v2\"");
+        let last_modified = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        let precondition = |name, value| {
+            let mut headers = HeaderMap::new();
+            headers.insert(name, http::HeaderValue::from_static(value));
+            Precondition::from_headers(&headers)
+        };
+
+        assert!(Precondition::default().is_met(&etag, Some(last_modified)));
+        let if_match = precondition(header::IF_MATCH, "\"v1\", \"v2\"");
+        assert_eq!(if_match.if_match(), Some(vec![EntityTag::parse("\"v1\""), etag.clone()]));
+        assert!(if_match.is_met(&etag, None));
+        assert!(precondition(header::IF_MATCH, "*").is_met(&etag, None));
+        assert!(!precondition(header::IF_MATCH, "\"v1\"").is_met(&etag, None));
+        assert!(!precondition(header::IF_MATCH, "W/\"v2\"").is_met(&etag, None));
+
+        let since = precondition(header::IF_UNMODIFIED_SINCE, "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(since.if_unmodified_since(), Some(last_modified));
+        assert!(since.is_met(&etag, Some(last_modified)));
+        assert!(since.is_met(&etag, None));
+        assert!(!since.is_met(&etag, Some(last_modified + Duration::from_secs(1))));
+        assert!(precondition(header::IF_UNMODIFIED_SINCE, "yesterday").is_empty());
+        assert!(precondition(header::IF_UNMODIFIED_SINCE, "Sun, 06 Nov 300000000000 08:49:37 GMT").is_empty());
+    }
+
+    #[test]
+    fn http_dates_are_parsed() {
+        assert_eq!(parse_http_date("Thu, 01 Jan 1970 00:00:00 GMT"), Some(UNIX_EPOCH));
+        assert_eq!(
+            parse_http_date("Tue, 29 Feb 2000 12:00:00 GMT"),
+            Some(UNIX_EPOCH + Duration::from_secs(951_825_600))
+        );
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 UTC"), None);
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 300000000000 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 18446744073709551615 08:49:37 GMT"), None);
+        assert!(parse_http_date("Fri, 31 Dec 9999 23:59:59 GMT").is_some());
+    }
+
+    #[tokio::test]
+    async fn matching_responses_are_not_modified() {
+        let server = TestServer::new(
+            Server::builder()
+                .configure_middlewares(|m| m.apply(ConditionalGetMiddleware::new(), vec!["/"], None))
+                .configure_router(|r| {
+                    r.route("/user", Method::GET, user)
+                        .route("/weak", Method::GET, user)
+                        .route("/user", Method::POST, user)
+                }),
+        )
+        .unwrap();
+        let client = server.client();
+
+        let res = client.get("/user").send().await.unwrap();
+        assert_eq!(res.status(), 200);
+        let etag = res.header(header::ETAG).unwrap().to_string();
+
+        let res = client.get("/user").header(header::IF_NONE_MATCH, etag.as_str()).send().await.unwrap();
+        assert_eq!(res.status(), 304);
+        assert!(res.body().is_empty());
+        assert_eq!(res.header(header::ETAG), Some(etag.as_str()));
+        assert_eq!(res.header(header::CONTENT_TYPE), None);
+
+        let weak = format!("W/{}", etag);
+        let res = client.get("/weak").header(header::IF_NONE_MATCH, etag.as_str()).send().await.unwrap();
+        assert_eq!((res.status().as_u16(), res.header(header::ETAG)), (304, Some(weak.as_str())));
+
+        let res = client.get("/user").header(header::IF_NONE_MATCH, "\"other\"").send().await.unwrap();
+        assert_eq!(res.text().unwrap(), BODY);
+        let res = client.post("/user").header(header::IF_NONE_MATCH, etag.as_str()).send().await.unwrap();
+        assert_eq!(res.status(), 200);
+    }
+}
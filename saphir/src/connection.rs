@@ -0,0 +1,147 @@
+//! Information about the connection a request was received on.
+//!
+//! Every request handled by the server carries a [`ConnectionInfo`] in its
+//! extensions, which can be retrieved with `req.extensions().get()` or with
+//! the [`ConnectionInfo`] extractor.
+
+use crate::{error::SaphirError, request::FromRequest, request::Request};
+use std::net::SocketAddr;
+
+/// Information about the connection a request was received on
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionInfo {
+    peer_addr: Option<SocketAddr>,
+    local_addr: Option<SocketAddr>,
+    #[cfg(feature = "https")]
+    tls: Option<TlsInfo>,
+}
+
+impl ConnectionInfo {
+    /// Information of a plaintext connection
+    pub fn new(peer_addr: Option<SocketAddr>, local_addr: Option<SocketAddr>) -> Self {
+        ConnectionInfo {
+            peer_addr,
+            local_addr,
+            #[cfg(feature = "https")]
+            tls: None,
+        }
+    }
+
+    /// Address of the remote end of the connection
+    pub fn peer_addr(&self) -> Option<&SocketAddr> {
+        self.peer_addr.as_ref()
+    }
+
+    /// Address the connection was accepted on
+    pub fn local_addr(&self) -> Option<&SocketAddr> {
+        self.local_addr.as_ref()
+    }
+
+    /// Returns true if the connection is encrypted with TLS
+    pub fn is_tls(&self) -> bool {
+        #[cfg(feature = "https")]
+        {
+            self.tls.is_some()
+        }
+
+        #[cfg(not(feature = "https"))]
+        {
+            false
+        }
+    }
+
+    /// Parameters negotiated during the TLS handshake, `None` for plaintext
+    /// connections
+    #[cfg(feature = "https")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "https")))]
+    pub fn tls(&self) -> Option<&TlsInfo> {
+        self.tls.as_ref()
+    }
+
+    #[cfg(feature = "https")]
+    pub(crate) fn with_tls(mut self, tls: TlsInfo) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+}
+
+/// Parameters negotiated during the TLS handshake of a connection
+#[cfg(feature = "https")]
+#[cfg_attr(docsrs, doc(cfg(feature = "https")))]
+#[derive(Clone, Debug)]
+pub struct TlsInfo {
+    protocol_version: Option<rustls::ProtocolVersion>,
+    cipher_suite: Option<rustls::SupportedCipherSuite>,
+    server_name: Option<String>,
+    alpn_protocol: Option<Vec<u8>>,
+}
+
+#[cfg(feature = "https")]
+impl TlsInfo {
+    pub(crate) fn from_connection(conn: &rustls::ServerConnection) -> Self {
+        TlsInfo {
+            protocol_version: conn.protocol_version(),
+            cipher_suite: conn.negotiated_cipher_suite(),
+            server_name: conn.server_name().map(|s| s.to_string()),
+            alpn_protocol: conn.alpn_protocol().map(|p| p.to_vec()),
+        }
+    }
+
+    /// Negotiated protocol version, e.g. `TLSv1_3`
+    pub fn protocol_version(&self) -> Option<rustls::ProtocolVersion> {
+        self.protocol_version
+    }
+
+    /// Negotiated cipher suite
+    pub fn cipher_suite(&self) -> Option<rustls::SupportedCipherSuite> {
+        self.cipher_suite
+    }
+
+    /// Server name requested by the client through SNI
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+
+    /// Negotiated ALPN protocol, e.g. `h2`
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.alpn_protocol.as_deref()
+    }
+}
+
+impl FromRequest for ConnectionInfo {
+    type Err = SaphirError;
+    type Fut = futures::future::Ready<Result<Self, Self::Err>>;
+
+    /// Requests which did not go through a listener (e.g. injected requests)
+    /// get a plaintext connection info built from their peer address
+    fn from_request(req: &mut Request) -> Self::Fut {
+        let info = req
+            .extensions()
+            .get::<ConnectionInfo>()
+            .cloned()
+            .unwrap_or_else(|| ConnectionInfo::new(req.peer_addr().copied(), None));
+        futures::future::ready(Ok(info))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::Body;
+
+    #[tokio::test]
+    async fn extracts_connection_info() {
+        let peer: SocketAddr = "10.0.0.1:4000".parse().unwrap();
+        let local: SocketAddr = "10.0.0.2:443".parse().unwrap();
+
+        let mut req = Request::new(http::Request::new(Body::empty()), Some(peer));
+        let info = ConnectionInfo::from_request(&mut req).await.unwrap();
+        assert_eq!(info.peer_addr(), Some(&peer));
+        assert_eq!(info.local_addr(), None);
+        assert!(!info.is_tls());
+
+        req.extensions_mut().insert(ConnectionInfo::new(Some(peer), Some(local)));
+        let info = ConnectionInfo::from_request(&mut req).await.unwrap();
+        assert_eq!(info.local_addr(), Some(&local));
+    }
+}
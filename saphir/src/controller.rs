@@ -55,6 +55,11 @@ pub trait Controller {
     /// controller
     const BASE_PATH: &'static str;
 
+    /// Name of the controller, available to middlewares and handlers in the
+    /// [`HandlerMetadata`](../http_context/struct.HandlerMetadata.html) of its
+    /// handlers. The `#[controller]` macro sets it to the controller name.
+    const NAME: Option<&'static str> = None;
+
     /// Returns a list of [`ControllerEndpoint`](type.ControllerEndpoint.html)
     ///
     /// Each [`ControllerEndpoint`](type.ControllerEndpoint.html) is then added
@@ -62,6 +67,14 @@ pub trait Controller {
     fn handlers(&self) -> Vec<ControllerEndpoint<Self>>
     where
         Self: Sized;
+
+    /// Returns the descriptions of the endpoints in the OpenAPI document
+    /// served by the router, see [`crate::openapi`]. None by default.
+    #[cfg(feature = "openapi-runtime")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "openapi-runtime")))]
+    fn operations(&self) -> Vec<(Method, &'static str, crate::openapi::Operation)> {
+        Vec::new()
+    }
 }
 
 /// Trait that defines a handler within a controller.
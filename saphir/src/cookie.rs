@@ -1,5 +1,12 @@
-use crate::{http_context::HttpContext, responder::Responder, response::Builder};
+use crate::{
+    error::SaphirError,
+    http_context::HttpContext,
+    request::{FromRequest, Request},
+    responder::Responder,
+    response::Builder,
+};
 pub use cookie::*;
+use std::sync::{Arc, Mutex, MutexGuard};
 
 impl Responder for Cookie<'static> {
     fn respond_with_builder(self, builder: Builder, _ctx: &HttpContext) -> Builder {
@@ -19,3 +26,138 @@ impl Responder for CookieJar {
         builder
     }
 }
+
+#[derive(Default)]
+struct CookiesInner {
+    jar: CookieJar,
+    loaded: bool,
+}
+
+/// Cookies of a request whose changes are sent back with the response.
+///
+/// The cookies added to or removed from this jar are written as `Set-Cookie`
+/// headers on the response of the request, with the attributes set on them,
+/// so handlers don't have to return them. Cookies set by the responder
+/// itself take precedence.
+/// ```rust
+/// # use saphir::{prelude::*, test::TestServer};
+/// async fn login(mut req: Request) -> Result<u16, SaphirError> {
+///     let cookies = Cookies::from_request(&mut req).await?;
+///     if cookies.get("session").is_none() {
+///         cookies.add(Cookie::build("session", "abc").http_only(true).secure(true).finish());
+///     }
+///     Ok(200)
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), SaphirError> {
+/// let client = TestServer::new(Server::builder().configure_router(|r| r.route("/login", Method::POST, login)))?.client();
+/// let res = client.post("/login").send().await?;
+/// assert_eq!(res.header("set-cookie"), Some("session=abc; HttpOnly; Secure"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct Cookies(Arc<Mutex<CookiesInner>>);
+
+impl Cookies {
+    fn lock(&self) -> MutexGuard<'_, CookiesInner> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns the cookie named `name`, sent by the client or added since
+    pub fn get(&self, name: &str) -> Option<Cookie<'static>> {
+        self.lock().jar.get(name).cloned()
+    }
+
+    /// Add a cookie, sent to the client with the response
+    pub fn add(&self, cookie: Cookie<'static>) {
+        self.lock().jar.add(cookie);
+    }
+
+    /// Remove a cookie. A cookie sent by the client is removed from the
+    /// client as well, its path and domain need to match the ones it was set
+    /// with.
+    pub fn remove(&self, cookie: Cookie<'static>) {
+        self.lock().jar.remove(cookie);
+    }
+
+    /// Cookies to send back with the response
+    pub(crate) fn delta(&self) -> Vec<Cookie<'static>> {
+        self.lock().jar.delta().cloned().collect()
+    }
+
+    fn load(&self, req: &mut Request) {
+        let mut inner = self.lock();
+        if !inner.loaded {
+            if req.cookies().iter().next().is_none() {
+                req.parse_cookies();
+            }
+            req.cookies().iter().for_each(|c| inner.jar.add_original(c.clone()));
+            inner.loaded = true;
+        }
+    }
+
+    /// Add the changes of the jar to the response cookies, except for the
+    /// cookies already set on it
+    pub(crate) fn write_back(&self, mut builder: Builder) -> Builder {
+        for cookie in self.delta() {
+            if builder.cookies_mut().get(cookie.name()).is_none() {
+                builder.cookies_mut().add(cookie);
+            }
+        }
+        builder
+    }
+}
+
+impl FromRequest for Cookies {
+    type Err = SaphirError;
+    type Fut = futures::future::Ready<Result<Self, Self::Err>>;
+
+    /// Requests which are not dispatched by the router get a jar whose
+    /// changes are not written back
+    fn from_request(req: &mut Request) -> Self::Fut {
+        let cookies = req.extensions().get::<Cookies>().cloned().unwrap_or_default();
+        cookies.load(req);
+        futures::future::ready(Ok(cookies))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{server::Server, test::TestServer};
+    use http::Method;
+
+    async fn session(mut req: Request) -> Result<String, SaphirError> {
+        let cookies = Cookies::from_request(&mut req).await?;
+        let user = cookies.get("user").map(|c| c.value().to_string()).unwrap_or_default();
+        cookies.add(Cookie::build("session", "abc").path("/").http_only(true).same_site(SameSite::Strict).finish());
+        cookies.remove(Cookie::named("user"));
+        Ok(user)
+    }
+
+    async fn overridden(mut req: Request) -> Result<Cookie<'static>, SaphirError> {
+        Cookies::from_request(&mut req).await?.add(Cookie::new("theme", "dark"));
+        Ok(Cookie::new("theme", "light"))
+    }
+
+    #[tokio::test]
+    async fn changes_are_written_back() {
+        let server =
+            TestServer::new(Server::builder().configure_router(|r| r.route("/session", Method::GET, session).route("/theme", Method::GET, overridden)))
+                .unwrap();
+        let client = server.client();
+
+        let res = client.get("/session").header("cookie", "user=richer; other=1").send().await.unwrap();
+        let mut set_cookies: Vec<_> = res.headers().get_all("set-cookie").iter().map(|h| h.to_str().unwrap().to_string()).collect();
+        set_cookies.sort();
+        assert_eq!(set_cookies.len(), 2);
+        assert_eq!(set_cookies[0], "session=abc; HttpOnly; SameSite=Strict; Path=/");
+        assert!(set_cookies[1].starts_with("user=; Max-Age=0"), "{}", set_cookies[1]);
+        assert_eq!(res.text().unwrap(), "richer");
+
+        let res = client.get("/theme").send().await.unwrap();
+        assert_eq!(res.headers().get_all("set-cookie").iter().collect::<Vec<_>>(), vec!["theme=light"]);
+    }
+}
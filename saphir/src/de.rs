@@ -0,0 +1,230 @@
+//! Deserialization of values grouped by name, shared by the query strings and
+//! the multipart forms.
+//!
+//! Members are read from the values of their name: a `Vec` member collects
+//! every value in order, while any other member takes a single value, the
+//! last one or the only one allowed depending on [`Repeated`].
+
+use serde::de::{
+    value::{Error as DeError, SeqDeserializer},
+    Deserializer, Error as _, IntoDeserializer, Visitor,
+};
+use std::collections::{hash_map::Entry, HashMap};
+
+/// Implement `into_inner`, `Deref`, `AsRef` and `Borrow` and their mutable
+/// counterparts for a newtype wrapper
+macro_rules! wrapper_impls {
+    ($wrapper:ident) => {
+        impl<T> $wrapper<T> {
+            pub fn into_inner(self) -> T {
+                self.0
+            }
+        }
+
+        impl<T> std::ops::Deref for $wrapper<T> {
+            type Target = T;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl<T> std::ops::DerefMut for $wrapper<T> {
+            fn deref_mut(&mut self) -> &mut Self::Target {
+                &mut self.0
+            }
+        }
+
+        impl<T> AsRef<T> for $wrapper<T> {
+            fn as_ref(&self) -> &T {
+                &self.0
+            }
+        }
+
+        impl<T> AsMut<T> for $wrapper<T> {
+            fn as_mut(&mut self) -> &mut T {
+                &mut self.0
+            }
+        }
+
+        impl<T> std::borrow::Borrow<T> for $wrapper<T> {
+            fn borrow(&self) -> &T {
+                &self.0
+            }
+        }
+
+        impl<T> std::borrow::BorrowMut<T> for $wrapper<T> {
+            fn borrow_mut(&mut self) -> &mut T {
+                &mut self.0
+            }
+        }
+    };
+}
+
+pub(crate) use wrapper_impls;
+
+/// Implement the deserialization of the primitive types by parsing the
+/// value, for a deserializer with a `parse` method
+macro_rules! deserialize_parsed {
+    () => {
+        deserialize_parsed! {
+            deserialize_bool => visit_bool,
+            deserialize_i8 => visit_i8,
+            deserialize_i16 => visit_i16,
+            deserialize_i32 => visit_i32,
+            deserialize_i64 => visit_i64,
+            deserialize_i128 => visit_i128,
+            deserialize_u8 => visit_u8,
+            deserialize_u16 => visit_u16,
+            deserialize_u32 => visit_u32,
+            deserialize_u64 => visit_u64,
+            deserialize_u128 => visit_u128,
+            deserialize_f32 => visit_f32,
+            deserialize_f64 => visit_f64,
+            deserialize_char => visit_char,
+        }
+    };
+    ($($method:ident => $visit:ident,)*) => {$(
+        fn $method<V: serde::de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            visitor.$visit(self.parse()?)
+        }
+    )*};
+}
+
+pub(crate) use deserialize_parsed;
+
+/// How a name sent more than once is read into a member other than a
+/// sequence
+#[derive(Clone, Copy)]
+pub(crate) enum Repeated {
+    /// The last value is read
+    Last,
+    /// The member is rejected as a duplicate field
+    #[cfg_attr(not(feature = "multipart"), allow(dead_code))]
+    Reject,
+}
+
+/// Group `values` by name, the names being ordered by their first occurrence
+pub(crate) fn group<D>(values: impl IntoIterator<Item = (String, D)>, repeated: Repeated) -> Vec<(String, Values<D>)> {
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut grouped: Vec<(String, Values<D>)> = Vec::new();
+    for (name, value) in values {
+        match index.entry(name) {
+            Entry::Occupied(entry) => grouped[*entry.get()].1.values.push(value),
+            Entry::Vacant(entry) => {
+                let name = entry.key().clone();
+                grouped.push((
+                    name.clone(),
+                    Values {
+                        name,
+                        values: vec![value],
+                        repeated,
+                    },
+                ));
+                entry.insert(grouped.len() - 1);
+            }
+        }
+    }
+
+    grouped
+}
+
+/// Every value sent for a name
+pub(crate) struct Values<D> {
+    name: String,
+    values: Vec<D>,
+    repeated: Repeated,
+}
+
+impl<D> Values<D> {
+    pub fn into_values(self) -> Vec<D> {
+        self.values
+    }
+
+    fn single(mut self) -> Result<D, DeError> {
+        if self.values.len() > 1 && matches!(self.repeated, Repeated::Reject) {
+            return Err(DeError::custom(format!("duplicate field `{}`", self.name)));
+        }
+        self.values
+            .pop()
+            .ok_or_else(|| DeError::custom(format!("missing value for field `{}`", self.name)))
+    }
+}
+
+impl<'de, D> IntoDeserializer<'de, DeError> for Values<D>
+where
+    D: Deserializer<'de, Error = DeError> + IntoDeserializer<'de, DeError, Deserializer = D>,
+{
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+macro_rules! forward_to_single {
+    ($($method:ident$(($($arg:ident: $ty:ty),*))?,)*) => {$(
+        fn $method<V: Visitor<'de>>(self, $($($arg: $ty,)*)? visitor: V) -> Result<V::Value, Self::Error> {
+            self.single()?.$method($($($arg,)*)? visitor)
+        }
+    )*};
+}
+
+impl<'de, D> Deserializer<'de> for Values<D>
+where
+    D: Deserializer<'de, Error = DeError> + IntoDeserializer<'de, DeError, Deserializer = D>,
+{
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.values.len() > 1 {
+            return self.deserialize_seq(visitor);
+        }
+        self.single()?.deserialize_any(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(SeqDeserializer::new(self.values.into_iter()))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_single! {
+        deserialize_bool,
+        deserialize_i8,
+        deserialize_i16,
+        deserialize_i32,
+        deserialize_i64,
+        deserialize_i128,
+        deserialize_u8,
+        deserialize_u16,
+        deserialize_u32,
+        deserialize_u64,
+        deserialize_u128,
+        deserialize_f32,
+        deserialize_f64,
+        deserialize_char,
+        deserialize_str,
+        deserialize_string,
+        deserialize_bytes,
+        deserialize_byte_buf,
+        deserialize_unit,
+        deserialize_map,
+        deserialize_identifier,
+        deserialize_ignored_any,
+        deserialize_unit_struct(name: &'static str),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]),
+    }
+}
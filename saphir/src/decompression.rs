@@ -0,0 +1,258 @@
+//! Decompression of the request bodies.
+//!
+//! The [`DecompressionMiddleware`] decodes the body of the requests sent with
+//! a `gzip`, `deflate` or `br` `Content-Encoding`, so handlers read it as if
+//! it was sent uncompressed, e.g. as `Json<T>`. The `Content-Encoding` header
+//! is removed and the `Content-Length` header is set to the decoded size.
+//!
+//! Compressed bodies are loaded in memory to be decoded. To protect the server
+//! from decompression bombs, a body whose decoded size is over
+//! [`max_size`](DecompressionMiddleware::max_size) or over
+//! [`max_ratio`](DecompressionMiddleware::max_ratio) times its compressed size
+//! is rejected with `413 Payload Too Large`. Unknown encodings are rejected
+//! with `415 Unsupported Media Type` and bodies which can't be decoded with
+//! `400 Bad Request`.
+//!
+//! ```rust
+//! use saphir::{decompression::DecompressionMiddleware, prelude::*};
+//!
+//! let server = Server::builder()
+//!     .configure_middlewares(|m| m.apply(DecompressionMiddleware::new().max_size(1024 * 1024), vec!["/**"], None))
+//!     .build();
+//! ```
+
+use crate::{
+    body::{Body, Bytes},
+    error::SaphirError,
+    file::Compression,
+    http_context::HttpContext,
+    middleware::{Middleware, MiddlewareChain},
+};
+use flate2::read::{DeflateDecoder, MultiGzDecoder, ZlibDecoder};
+use futures::future::{BoxFuture, FutureExt};
+use http::{header, HeaderMap, HeaderValue};
+use hyper::body::Body as HyperBody;
+use std::{io::Read, str::FromStr};
+
+/// Default maximum size of a decompressed body, 16MiB
+pub const DEFAULT_MAX_DECOMPRESSED_BYTES: usize = 16 * 1024 * 1024;
+/// Default maximum ratio between the decompressed and compressed size of a
+/// body
+pub const DEFAULT_MAX_COMPRESSION_RATIO: usize = 100;
+
+/// Middleware decoding the request bodies according to their
+/// `Content-Encoding`
+pub struct DecompressionMiddleware {
+    max_size: usize,
+    max_ratio: Option<usize>,
+}
+
+impl DecompressionMiddleware {
+    pub fn new() -> Self {
+        DecompressionMiddleware {
+            max_size: DEFAULT_MAX_DECOMPRESSED_BYTES,
+            max_ratio: Some(DEFAULT_MAX_COMPRESSION_RATIO),
+        }
+    }
+
+    /// Maximum size of a decompressed body
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Maximum ratio between the decompressed and compressed size of a body,
+    /// `None` to only enforce the maximum size
+    pub fn max_ratio<R: Into<Option<usize>>>(mut self, max_ratio: R) -> Self {
+        self.max_ratio = max_ratio.into();
+        self
+    }
+
+    fn limit(&self, compressed_len: usize) -> usize {
+        match self.max_ratio {
+            Some(ratio) => self.max_size.min(compressed_len.saturating_mul(ratio)),
+            None => self.max_size,
+        }
+    }
+
+    async fn next_inner(&self, mut ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+        if let Some(req) = ctx.state.request_mut() {
+            let encodings = content_encodings(req.headers())?;
+            if !encodings.is_empty() {
+                let mut body = req.body_mut().take().await?;
+                let limit = self.limit(body.len());
+                for compression in encodings.into_iter().rev() {
+                    body = decode(body, compression, limit)?;
+                }
+
+                req.headers_mut().remove(header::CONTENT_ENCODING);
+                req.headers_mut().insert(header::CONTENT_LENGTH, HeaderValue::from(body.len()));
+                *req.body_mut() = Body::from_raw(HyperBody::from(body));
+            }
+        }
+
+        chain.next(ctx).await
+    }
+}
+
+impl Default for DecompressionMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Middleware for DecompressionMiddleware {
+    fn next(&'static self, ctx: HttpContext, chain: &'static dyn MiddlewareChain) -> BoxFuture<'static, Result<HttpContext, SaphirError>> {
+        self.next_inner(ctx, chain).boxed()
+    }
+}
+
+/// Encodings applied to the body, in the order they were applied
+fn content_encodings(headers: &HeaderMap) -> Result<Vec<Compression>, SaphirError> {
+    headers
+        .get_all(header::CONTENT_ENCODING)
+        .iter()
+        .map(|h| h.to_str().map_err(SaphirError::from))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .flat_map(|h| h.split(','))
+        .map(|encoding| encoding.trim().to_ascii_lowercase())
+        .filter(|encoding| !encoding.is_empty())
+        .map(|encoding| Compression::from_str(&encoding).map_err(|_| SaphirError::UnsupportedMediaType(encoding)))
+        .filter(|compression| !matches!(compression, Ok(Compression::Raw)))
+        .collect()
+}
+
+/// Whether a `deflate` body has the zlib wrapper mandated by the RFC, rather
+/// than being a raw deflate stream as sent by some clients
+fn is_zlib(body: &[u8]) -> bool {
+    match body {
+        [cmf, flg, ..] => cmf & 0x0f == 8 && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0,
+        _ => false,
+    }
+}
+
+fn decode(body: Bytes, compression: Compression, limit: usize) -> Result<Bytes, SaphirError> {
+    let reader: Box<dyn Read + '_> = match compression {
+        Compression::Raw => return Ok(body),
+        Compression::Gzip => Box::new(MultiGzDecoder::new(&body[..])),
+        Compression::Deflate if is_zlib(&body) => Box::new(ZlibDecoder::new(&body[..])),
+        Compression::Deflate => Box::new(DeflateDecoder::new(&body[..])),
+        Compression::Brotli => Box::new(brotli::Decompressor::new(&body[..], 4096)),
+    };
+
+    let mut decoded = Vec::new();
+    reader
+        .take(limit as u64 + 1)
+        .read_to_end(&mut decoded)
+        .map_err(|e| SaphirError::InvalidContentEncoding(e.to_string()))?;
+    if decoded.len() > limit {
+        return Err(SaphirError::PayloadTooLarge);
+    }
+
+    Ok(decoded.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{request::Request, server::Server, test::TestServer};
+    use flate2::write::{GzEncoder, ZlibEncoder};
+    use http::Method;
+    use std::io::Write;
+
+    async fn echo(mut req: Request) -> Result<String, SaphirError> {
+        req.body_mut().take_as::<String>().await
+    }
+
+    fn client(middleware: DecompressionMiddleware) -> crate::test::TestClient {
+        TestServer::new(
+            Server::builder()
+                .configure_middlewares(|m| m.apply(middleware, vec!["/**"], None))
+                .configure_router(|r| r.route("/echo", Method::POST, echo)),
+        )
+        .unwrap()
+        .client()
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn brotli(data: &[u8]) -> Vec<u8> {
+        let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 6, 22);
+        encoder.write_all(data).unwrap();
+        encoder.into_inner()
+    }
+
+    #[tokio::test]
+    async fn bodies_are_decoded() {
+        let client = client(DecompressionMiddleware::new());
+        let payload = br#"{"name":"richer"}"#;
+
+        let res = client
+            .post("/echo")
+            .header("content-encoding", "gzip")
+            .body(gzip(payload))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.text().unwrap(), r#"{"name":"richer"}"#);
+
+        let res = client
+            .post("/echo")
+            .header("content-encoding", "BR")
+            .body(brotli(payload))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.text().unwrap(), r#"{"name":"richer"}"#);
+
+        let mut zlib = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        zlib.write_all(payload).unwrap();
+        let body = gzip(&zlib.finish().unwrap());
+        let res = client
+            .post("/echo")
+            .header("content-encoding", "deflate, gzip")
+            .body(body)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.text().unwrap(), r#"{"name":"richer"}"#);
+
+        let res = client.post("/echo").header("content-encoding", "identity").body("plain").send().await.unwrap();
+        assert_eq!(res.text().unwrap(), "plain");
+    }
+
+    #[tokio::test]
+    async fn invalid_bodies_are_rejected() {
+        let client = client(DecompressionMiddleware::new().max_size(1024).max_ratio(None));
+
+        let res = client
+            .post("/echo")
+            .header("content-encoding", "gzip")
+            .body(gzip(&[0; 2048]))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 413);
+
+        let res = client.post("/echo").header("content-encoding", "zstd").body("data").send().await.unwrap();
+        assert_eq!(res.status(), 415);
+
+        let res = client.post("/echo").header("content-encoding", "gzip").body("not gzip").send().await.unwrap();
+        assert_eq!(res.status(), 400);
+
+        let client = self::client(DecompressionMiddleware::new().max_ratio(10));
+        let res = client
+            .post("/echo")
+            .header("content-encoding", "gzip")
+            .body(gzip(&[0; 2048]))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 413);
+    }
+}
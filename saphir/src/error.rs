@@ -5,7 +5,7 @@ use crate::{
 };
 use http::{
     header::{InvalidHeaderValue, ToStrError},
-    Error as HttpCrateError,
+    Error as HttpCrateError, StatusCode,
 };
 use hyper::Error as HyperError;
 use std::{
@@ -29,13 +29,62 @@ pub enum InternalError {
     Stack,
 }
 
+/// Error of an application which can be propagated with `?` from a handler
+/// returning `Result<_, SaphirError>`. The error is logged and answered with
+/// the status returned by [`status_code`](Self::status_code), `500 Internal
+/// Server Error` by default.
+///
+/// Errors of other crates can be converted with [`SaphirError::from_std`], or
+/// wrapped in a type implementing this trait to answer them with another
+/// status.
+/// ```rust
+/// # use saphir::{error::HandlerError, prelude::*, test::TestServer};
+/// #[derive(Debug, thiserror::Error)]
+/// enum UserError {
+///     #[error("user {0} not found")]
+///     NotFound(u64),
+///     #[error("database unavailable")]
+///     Database,
+/// }
+///
+/// impl HandlerError for UserError {
+///     fn status_code(&self) -> StatusCode {
+///         match self {
+///             UserError::NotFound(_) => StatusCode::NOT_FOUND,
+///             UserError::Database => StatusCode::SERVICE_UNAVAILABLE,
+///         }
+///     }
+/// }
+///
+/// fn find_user(id: u64) -> Result<String, UserError> {
+///     Err(UserError::NotFound(id))
+/// }
+///
+/// async fn user(req: Request) -> Result<String, SaphirError> {
+///     let id = req.captures().get("id").and_then(|id| id.parse().ok()).unwrap_or_default();
+///     Ok(find_user(id)?)
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), SaphirError> {
+/// let client = TestServer::new(Server::builder().configure_router(|r| r.route("/users/{id}", Method::GET, user)))?.client();
+/// assert_eq!(client.get("/users/42").send().await?.status(), StatusCode::NOT_FOUND);
+/// # Ok(())
+/// # }
+/// ```
+pub trait HandlerError: StdError + Send + Sync + 'static {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
 /// Error type throughout the saphir stack
 #[derive(Error)]
 pub enum SaphirError {
-    ///
+    /// Answered with `500 Internal Server Error`
     #[error("Internal: {0}")]
     Internal(#[from] InternalError),
-    ///
+    /// Answered with `500 Internal Server Error`
     #[error("Io: {0}")]
     Io(#[from] IoError),
     /// Body was taken and cannot be polled
@@ -51,17 +100,32 @@ pub enum SaphirError {
     /// Custom error type to map any other error
     #[error("Custom: {0}")]
     Custom(Box<dyn StdError + Send + Sync + 'static>),
-    /// Custom error type to map any other error
+    /// Error of the application, see [`HandlerError`]
+    #[error("{1}")]
+    Handler(StatusCode, Box<dyn StdError + Send + Sync + 'static>),
+    /// Responder answering the error, with the status it sets
     #[error("Responder")]
     Responder(Box<dyn DynResponder + Send + Sync + 'static>),
-    ///
+    /// Answered with `500 Internal Server Error`
     #[error("Other: {0}")]
     Other(String),
-    /// Error from (de)serializing json data
+    /// Error from (de)serializing json data, answered with `400 Bad Request`
     #[cfg(feature = "json")]
     #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
     #[error("SerdeJson: {0}")]
     SerdeJson(#[from] serde_json::error::Error),
+    /// Error from deserializing a `Json<T>` body, along with the path of the
+    /// value which could not be deserialized, answered with `400 Bad Request`
+    /// and a json body locating the value.
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    #[error("SerdeJson: {0}")]
+    SerdeJsonPath(#[from] serde_path_to_error::Error<serde_json::error::Error>),
+    /// Error from deserializing lenient json data
+    #[cfg(feature = "json5")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json5")))]
+    #[error("Json5: {0}")]
+    Json5(#[from] json5::Error),
     /// Error from deserializing form data
     #[cfg(feature = "form")]
     #[cfg_attr(docsrs, doc(cfg(feature = "form")))]
@@ -72,22 +136,69 @@ pub enum SaphirError {
     #[cfg_attr(docsrs, doc(cfg(feature = "form")))]
     #[error("SerdeUrlSer: {0}")]
     SerdeUrlSer(#[from] serde_urlencoded::ser::Error),
-    ///
+    /// Error from deserializing MessagePack data
+    #[cfg(feature = "msgpack")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "msgpack")))]
+    #[error("MsgPackDe: {0}")]
+    MsgPackDe(#[from] rmp_serde::decode::Error),
+    /// Error from serializing MessagePack data
+    #[cfg(feature = "msgpack")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "msgpack")))]
+    #[error("MsgPackSer: {0}")]
+    MsgPackSer(#[from] rmp_serde::encode::Error),
+    /// Error from deserializing CBOR data
+    #[cfg(feature = "cbor")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "cbor")))]
+    #[error("CborDe: {0}")]
+    CborDe(#[from] ciborium::de::Error<std::io::Error>),
+    /// Error from serializing CBOR data
+    #[cfg(feature = "cbor")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "cbor")))]
+    #[error("CborSer: {0}")]
+    CborSer(#[from] ciborium::ser::Error<std::io::Error>),
+    /// A required path or query parameter is missing, answered with `400 Bad
+    /// Request`
     #[error("Missing parameter `{0}` (is_query: {1})")]
     MissingParameter(String, bool),
-    ///
+    /// A path or query parameter could not be parsed, answered with `400 Bad
+    /// Request`
     #[error("Invalid parameter `{0}` (is_query: {1})")]
     InvalidParameter(String, bool),
-    ///
+    /// The request body is larger than the configured limit
+    #[error("Payload too large")]
+    PayloadTooLarge,
+    /// A redirection was built with an invalid status or location
+    #[error("Invalid redirect: {0}")]
+    InvalidRedirect(String),
+    /// The request took longer than the configured timeout
     #[error("Request timed out")]
     RequestTimeout,
     /// Attempted to build stack twice
     #[error("Stack alrealy initialized")]
     StackAlreadyInitialized,
-    ///
+    /// The client exceeded the configured rate limit
     #[error("Too many requests")]
     TooManyRequests,
-    /// Validator error
+    /// The request body has a content type which cannot be decoded
+    #[error("Unsupported media type `{0}`")]
+    UnsupportedMediaType(String),
+    /// A handler or middleware panicked, caught by the
+    /// `CatchPanicMiddleware`
+    #[error("Panicked: {0}")]
+    Panicked(String),
+    /// The `If-Match` or `If-Unmodified-Since` precondition of the request
+    /// does not hold for the current version of the resource
+    #[error("Precondition failed")]
+    PreconditionFailed,
+    /// The request body could not be decoded according to its
+    /// `Content-Encoding`
+    #[cfg(feature = "decompression")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "decompression")))]
+    #[error("Invalid content encoding: {0}")]
+    InvalidContentEncoding(String),
+    /// The request payload was deserialized but does not pass validation,
+    /// answered with `422 Unprocessable Entity` and, with the `json` feature, a
+    /// json body listing the errors of each field
     #[cfg(feature = "validate-requests")]
     #[cfg_attr(docsrs, doc(cfg(feature = "validate-requests")))]
     #[error("ValidationErrors: {0}")]
@@ -103,19 +214,39 @@ impl Debug for SaphirError {
             SaphirError::RequestMovedBeforeHandler => f.write_str("RequestMovedBeforeHandler"),
             SaphirError::ResponseMoved => f.write_str("ResponseMoved"),
             SaphirError::Custom(d) => std::fmt::Debug::fmt(d, f),
+            SaphirError::Handler(_, d) => std::fmt::Debug::fmt(d, f),
             SaphirError::Responder(_) => f.write_str("Responder"),
             SaphirError::Other(d) => std::fmt::Debug::fmt(d, f),
             #[cfg(feature = "json")]
             SaphirError::SerdeJson(d) => std::fmt::Debug::fmt(d, f),
+            #[cfg(feature = "json")]
+            SaphirError::SerdeJsonPath(d) => std::fmt::Debug::fmt(d, f),
+            #[cfg(feature = "json5")]
+            SaphirError::Json5(d) => std::fmt::Debug::fmt(d, f),
             #[cfg(feature = "form")]
             SaphirError::SerdeUrlDe(d) => std::fmt::Debug::fmt(d, f),
             #[cfg(feature = "form")]
             SaphirError::SerdeUrlSer(d) => std::fmt::Debug::fmt(d, f),
+            #[cfg(feature = "msgpack")]
+            SaphirError::MsgPackDe(d) => std::fmt::Debug::fmt(d, f),
+            #[cfg(feature = "msgpack")]
+            SaphirError::MsgPackSer(d) => std::fmt::Debug::fmt(d, f),
+            #[cfg(feature = "cbor")]
+            SaphirError::CborDe(d) => std::fmt::Debug::fmt(d, f),
+            #[cfg(feature = "cbor")]
+            SaphirError::CborSer(d) => std::fmt::Debug::fmt(d, f),
             SaphirError::MissingParameter(d, _) => std::fmt::Debug::fmt(d, f),
             SaphirError::InvalidParameter(d, _) => std::fmt::Debug::fmt(d, f),
+            SaphirError::PayloadTooLarge => f.write_str("PayloadTooLarge"),
+            SaphirError::InvalidRedirect(d) => std::fmt::Debug::fmt(d, f),
             SaphirError::RequestTimeout => f.write_str("RequestTimeout"),
             SaphirError::StackAlreadyInitialized => f.write_str("StackAlreadyInitialized"),
             SaphirError::TooManyRequests => f.write_str("TooManyRequests"),
+            SaphirError::UnsupportedMediaType(d) => std::fmt::Debug::fmt(d, f),
+            SaphirError::Panicked(d) => std::fmt::Debug::fmt(d, f),
+            SaphirError::PreconditionFailed => f.write_str("PreconditionFailed"),
+            #[cfg(feature = "decompression")]
+            SaphirError::InvalidContentEncoding(d) => std::fmt::Debug::fmt(d, f),
             #[cfg(feature = "validate-requests")]
             SaphirError::ValidationErrors(d) => std::fmt::Debug::fmt(d, f),
         }
@@ -127,32 +258,129 @@ impl SaphirError {
         SaphirError::Responder(Box::new(Some(e)))
     }
 
-    pub(crate) fn response_builder(self, builder: Builder, ctx: &HttpContext) -> Builder {
+    /// Convert any error, answered with `500 Internal Server Error`, e.g.
+    /// `db.query().await.map_err(SaphirError::from_std)?`
+    pub fn from_std<E: StdError + Send + Sync + 'static>(e: E) -> Self {
+        SaphirError::Custom(Box::new(e))
+    }
+
+    /// Status the error is answered with by default. Payloads which cannot be
+    /// deserialized, as well as missing or invalid parameters, are answered
+    /// with `400 Bad Request`, while payloads failing validation are answered
+    /// with `422 Unprocessable Entity`. A [`Responder`](Self::Responder) sets
+    /// its own status when responding and reports `500 Internal Server Error`.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            SaphirError::Handler(status, _) => *status,
+            #[cfg(feature = "json")]
+            SaphirError::SerdeJson(_) | SaphirError::SerdeJsonPath(_) => StatusCode::BAD_REQUEST,
+            #[cfg(feature = "json5")]
+            SaphirError::Json5(_) => StatusCode::BAD_REQUEST,
+            #[cfg(feature = "form")]
+            SaphirError::SerdeUrlDe(_) | SaphirError::SerdeUrlSer(_) => StatusCode::BAD_REQUEST,
+            #[cfg(feature = "msgpack")]
+            SaphirError::MsgPackDe(_) => StatusCode::BAD_REQUEST,
+            #[cfg(feature = "cbor")]
+            SaphirError::CborDe(_) => StatusCode::BAD_REQUEST,
+            SaphirError::MissingParameter(..) | SaphirError::InvalidParameter(..) => StatusCode::BAD_REQUEST,
+            SaphirError::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            SaphirError::RequestTimeout => StatusCode::REQUEST_TIMEOUT,
+            SaphirError::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+            SaphirError::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            SaphirError::PreconditionFailed => StatusCode::PRECONDITION_FAILED,
+            #[cfg(feature = "decompression")]
+            SaphirError::InvalidContentEncoding(_) => StatusCode::BAD_REQUEST,
+            #[cfg(feature = "validate-requests")]
+            SaphirError::ValidationErrors(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Name of the variant, recorded on the request span
+    #[cfg(feature = "tracing-instrument")]
+    pub(crate) fn variant_name(&self) -> &'static str {
         match self {
-            SaphirError::Internal(_) => builder.status(500),
-            SaphirError::Io(_) => builder.status(500),
-            SaphirError::BodyAlreadyTaken => builder.status(500),
-            SaphirError::Custom(_) => builder.status(500),
-            SaphirError::Other(_) => builder.status(500),
+            SaphirError::Internal(_) => "Internal",
+            SaphirError::Io(_) => "Io",
+            SaphirError::BodyAlreadyTaken => "BodyAlreadyTaken",
+            SaphirError::RequestMovedBeforeHandler => "RequestMovedBeforeHandler",
+            SaphirError::ResponseMoved => "ResponseMoved",
+            SaphirError::Custom(_) => "Custom",
+            SaphirError::Handler(..) => "Handler",
+            SaphirError::Responder(_) => "Responder",
+            SaphirError::Other(_) => "Other",
             #[cfg(feature = "json")]
-            SaphirError::SerdeJson(_) => builder.status(400),
+            SaphirError::SerdeJson(_) => "SerdeJson",
+            #[cfg(feature = "json")]
+            SaphirError::SerdeJsonPath(_) => "SerdeJsonPath",
+            #[cfg(feature = "json5")]
+            SaphirError::Json5(_) => "Json5",
             #[cfg(feature = "form")]
-            SaphirError::SerdeUrlDe(_) => builder.status(400),
+            SaphirError::SerdeUrlDe(_) => "SerdeUrlDe",
             #[cfg(feature = "form")]
-            SaphirError::SerdeUrlSer(_) => builder.status(400),
-            SaphirError::MissingParameter(..) => builder.status(400),
-            SaphirError::InvalidParameter(..) => builder.status(400),
-            SaphirError::RequestMovedBeforeHandler => builder.status(500),
-            SaphirError::ResponseMoved => builder.status(500),
-            SaphirError::Responder(mut r) => r.dyn_respond(builder, ctx),
-            SaphirError::RequestTimeout => builder.status(408),
-            SaphirError::StackAlreadyInitialized => builder.status(500),
-            SaphirError::TooManyRequests => builder.status(429),
+            SaphirError::SerdeUrlSer(_) => "SerdeUrlSer",
+            #[cfg(feature = "msgpack")]
+            SaphirError::MsgPackDe(_) => "MsgPackDe",
+            #[cfg(feature = "msgpack")]
+            SaphirError::MsgPackSer(_) => "MsgPackSer",
+            #[cfg(feature = "cbor")]
+            SaphirError::CborDe(_) => "CborDe",
+            #[cfg(feature = "cbor")]
+            SaphirError::CborSer(_) => "CborSer",
+            SaphirError::MissingParameter(..) => "MissingParameter",
+            SaphirError::InvalidParameter(..) => "InvalidParameter",
+            SaphirError::PayloadTooLarge => "PayloadTooLarge",
+            SaphirError::InvalidRedirect(_) => "InvalidRedirect",
+            SaphirError::RequestTimeout => "RequestTimeout",
+            SaphirError::StackAlreadyInitialized => "StackAlreadyInitialized",
+            SaphirError::TooManyRequests => "TooManyRequests",
+            SaphirError::UnsupportedMediaType(_) => "UnsupportedMediaType",
+            SaphirError::Panicked(_) => "Panicked",
+            SaphirError::PreconditionFailed => "PreconditionFailed",
+            #[cfg(feature = "decompression")]
+            SaphirError::InvalidContentEncoding(_) => "InvalidContentEncoding",
             #[cfg(feature = "validate-requests")]
-            SaphirError::ValidationErrors(_) => builder.status(400),
+            SaphirError::ValidationErrors(_) => "ValidationErrors",
+        }
+    }
+
+    pub(crate) fn response_builder(self, builder: Builder, ctx: &HttpContext) -> Builder {
+        if let SaphirError::Responder(mut r) = self {
+            return r.dyn_respond(builder, ctx);
+        }
+
+        let builder = builder.status(self.status_code());
+        match self {
+            #[cfg(feature = "json")]
+            SaphirError::SerdeJsonPath(e) => {
+                let body = serde_json::json!({
+                    "message": "Invalid json body",
+                    "path": e.path().to_string(),
+                    "pointer": json_pointer(e.path()),
+                });
+                builder.json(&body).unwrap_or_else(|(b, _)| b)
+            }
+            #[cfg(all(feature = "json", feature = "validate-requests"))]
+            SaphirError::ValidationErrors(e) => {
+                let body = serde_json::json!({
+                    "message": "Validation failed",
+                    "errors": e,
+                });
+                builder.json(&body).unwrap_or_else(|(b, _)| b)
+            }
+            _ => builder,
         }
     }
 
+    /// Log the error and turn it into a response set on `ctx`, for middlewares
+    /// that need to see the response an error will produce
+    pub(crate) fn into_context_response(self, mut ctx: HttpContext) -> Result<HttpContext, SaphirError> {
+        self.log(&ctx);
+        let res = self.response_builder(Builder::new(), &ctx).build()?;
+        ctx.after(res);
+        Ok(ctx)
+    }
+
     #[allow(unused_variables)]
     pub(crate) fn log(&self, ctx: &HttpContext) {
         let op_id = {
@@ -180,6 +408,13 @@ impl SaphirError {
             SaphirError::Custom(e) => {
                 warn!("{}A custom error was returned as a responder: {:?}", op_id, e);
             }
+            SaphirError::Handler(status, e) => {
+                if status.is_server_error() {
+                    warn!("{}A handler returned an error: {:?}", op_id, e);
+                } else {
+                    debug!("{}A handler returned an error: {:?}", op_id, e);
+                }
+            }
             SaphirError::Other(e) => {
                 warn!("{}Saphir encountered an Unknown error that was returned as a responder: {:?}", op_id, e);
             }
@@ -187,6 +422,14 @@ impl SaphirError {
             SaphirError::SerdeJson(e) => {
                 debug!("{}Unable to de/serialize json type: {:?}", op_id, e);
             }
+            #[cfg(feature = "json")]
+            SaphirError::SerdeJsonPath(e) => {
+                debug!("{}Unable to deserialize json type at `{}`: {:?}", op_id, e.path(), e.inner());
+            }
+            #[cfg(feature = "json5")]
+            SaphirError::Json5(e) => {
+                debug!("{}Unable to deserialize json5 type: {:?}", op_id, e);
+            }
             #[cfg(feature = "form")]
             SaphirError::SerdeUrlDe(e) => {
                 debug!("{}Unable to deserialize form type: {:?}", op_id, e);
@@ -195,6 +438,22 @@ impl SaphirError {
             SaphirError::SerdeUrlSer(e) => {
                 debug!("{}Unable to serialize form type: {:?}", op_id, e);
             }
+            #[cfg(feature = "msgpack")]
+            SaphirError::MsgPackDe(e) => {
+                debug!("{}Unable to deserialize msgpack type: {:?}", op_id, e);
+            }
+            #[cfg(feature = "msgpack")]
+            SaphirError::MsgPackSer(e) => {
+                warn!("{}Unable to serialize msgpack type: {:?}", op_id, e);
+            }
+            #[cfg(feature = "cbor")]
+            SaphirError::CborDe(e) => {
+                debug!("{}Unable to deserialize cbor type: {:?}", op_id, e);
+            }
+            #[cfg(feature = "cbor")]
+            SaphirError::CborSer(e) => {
+                warn!("{}Unable to serialize cbor type: {:?}", op_id, e);
+            }
             SaphirError::MissingParameter(name, is_query) => {
                 if *is_query {
                     debug!("{}Missing query parameter {}", op_id, name);
@@ -218,6 +477,12 @@ impl SaphirError {
             SaphirError::ResponseMoved => {
                 warn!("{}A response was moved before being sent to the client", op_id);
             }
+            SaphirError::PayloadTooLarge => {
+                debug!("{}Request body exceeded the size limit", op_id);
+            }
+            SaphirError::InvalidRedirect(e) => {
+                warn!("{}A handler built an invalid redirection: {}", op_id, e);
+            }
             SaphirError::RequestTimeout => {
                 warn!("{}Request timed out", op_id);
             }
@@ -228,6 +493,19 @@ impl SaphirError {
             SaphirError::TooManyRequests => {
                 warn!("{}Made too many requests", op_id);
             }
+            SaphirError::UnsupportedMediaType(content_type) => {
+                debug!("{}Unsupported request content type: {:?}", op_id, content_type);
+            }
+            SaphirError::Panicked(payload) => {
+                error!("{}A handler panicked: {}", op_id, payload);
+            }
+            SaphirError::PreconditionFailed => {
+                debug!("{}Request precondition failed", op_id);
+            }
+            #[cfg(feature = "decompression")]
+            SaphirError::InvalidContentEncoding(e) => {
+                debug!("{}Unable to decode the request body: {}", op_id, e);
+            }
             #[cfg(feature = "validate-requests")]
             SaphirError::ValidationErrors(e) => {
                 debug!("{}Validation error: {:?}", op_id, e);
@@ -236,6 +514,25 @@ impl SaphirError {
     }
 }
 
+/// JSON pointer (RFC 6901) of a deserialization path, e.g. `/items/3/price`
+#[cfg(feature = "json")]
+fn json_pointer(path: &serde_path_to_error::Path) -> String {
+    use serde_path_to_error::Segment;
+    path.iter()
+        .filter_map(|segment| match segment {
+            Segment::Seq { index } => Some(index.to_string()),
+            Segment::Map { key } => Some(key.replace('~', "~0").replace('/', "~1")),
+            Segment::Enum { .. } | Segment::Unknown => None,
+        })
+        .fold(String::new(), |pointer, segment| pointer + "/" + &segment)
+}
+
+impl<E: HandlerError> From<E> for SaphirError {
+    fn from(e: E) -> Self {
+        SaphirError::Handler(e.status_code(), Box::new(e))
+    }
+}
+
 impl From<HttpCrateError> for SaphirError {
     fn from(e: HttpCrateError) -> Self {
         SaphirError::Internal(InternalError::Http(e))
@@ -267,3 +564,37 @@ impl Responder for SaphirError {
         self.response_builder(builder, ctx)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_map_to_their_default_status() {
+        assert_eq!(SaphirError::MissingParameter("id".into(), false).status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(SaphirError::InvalidParameter("id".into(), true).status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(SaphirError::PayloadTooLarge.status_code(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(SaphirError::PreconditionFailed.status_code(), StatusCode::PRECONDITION_FAILED);
+        assert_eq!(
+            SaphirError::Handler(StatusCode::NOT_FOUND, "missing".into()).status_code(),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(SaphirError::Other("oops".into()).status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(SaphirError::responder(StatusCode::OK).status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn deserialization_errors_are_bad_requests() {
+        let e = serde_json::from_str::<u32>("\"a\"").unwrap_err();
+        assert_eq!(SaphirError::from(e).status_code(), StatusCode::BAD_REQUEST);
+    }
+
+    #[cfg(feature = "validate-requests")]
+    #[test]
+    fn validation_errors_are_unprocessable() {
+        let mut errors = validator::ValidationErrors::new();
+        errors.add("name", validator::ValidationError::new("length"));
+        assert_eq!(SaphirError::from(errors).status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}
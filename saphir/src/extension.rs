@@ -1,3 +1,53 @@
+//! Request scoped typed values.
+//!
+//! The extensions of a request are its typed store: middlewares insert
+//! values with [`HttpContext::insert`], guards with
+//! `req.extensions_mut().insert(..)`, and the values are visible to everything
+//! handling the request after them. Handlers get them with an [`Ext<T>`]
+//! parameter, or all at once with an [`Extensions`] parameter. An `Ext<T>`
+//! returned by a handler is inserted in the extensions of the response,
+//! where the middlewares find it with [`HttpContext::get`] once the next
+//! middleware returned.
+//!
+//! ```rust
+//! # #[macro_use] extern crate saphir_macro;
+//! # use saphir::{prelude::*, test::TestServer};
+//! #[derive(Clone)]
+//! struct Tenant(&'static str);
+//!
+//! async fn tenant(mut ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+//!     ctx.insert(Tenant("acme"));
+//!     chain.next(ctx).await
+//! }
+//!
+//! struct MyController {}
+//!
+//! #[controller(name = "tenant")]
+//! impl MyController {
+//!     #[get("/")]
+//!     async fn name(&self, tenant: Ext<Tenant>) -> &'static str {
+//!         tenant.into_inner().0
+//!     }
+//!
+//!     #[get("/count")]
+//!     async fn count(&self, extensions: Extensions) -> String {
+//!         extensions.len().to_string()
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), SaphirError> {
+//! let server = TestServer::new(
+//!     Server::builder()
+//!         .configure_middlewares(|m| m.apply(tenant, vec!["/"], None))
+//!         .configure_router(|r| r.controller(MyController {})),
+//! )?;
+//! assert_eq!(server.client().get("/tenant").send().await?.text()?, "acme");
+//! assert_ne!(server.client().get("/tenant/count").send().await?.text()?, "0");
+//! # Ok(())
+//! # }
+//! ```
+
 use crate::{
     http_context::HttpContext,
     request::{FromRequest, Request},
@@ -21,7 +71,9 @@ pub enum ExtError {
 
 impl Responder for ExtError {
     fn respond_with_builder(self, builder: Builder, _ctx: &HttpContext) -> Builder {
-        debug!("Missing extension of type: {}", std::any::type_name::<Self>());
+        match self {
+            ExtError::MissingExtension(type_name) => debug!("Missing extension of type: {}", type_name),
+        }
         builder.status(500)
     }
 }
@@ -103,3 +155,54 @@ impl FromRequest for Extensions {
         futures::future::ready(Ok(std::mem::take(req.extensions_mut())))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::SaphirError, middleware::MiddlewareChain, server::Server, test::TestServer};
+    use http::Method;
+
+    struct User(&'static str);
+    struct Role(&'static str);
+    struct Served(&'static str);
+
+    async fn authenticate(mut ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+        assert!(ctx.insert(User("richer")).is_none());
+        assert_eq!(ctx.get::<User>().map(|u| u.0), Some("richer"));
+        let mut ctx = chain.next(ctx).await?;
+        if let Some(Served(by)) = ctx.remove::<Served>() {
+            ctx.state.response_mut().unwrap().headers_mut().insert("x-served-by", by.parse().unwrap());
+        }
+        Ok(ctx)
+    }
+
+    async fn authorize(mut req: Request) -> Result<Request, u16> {
+        let role = match req.extensions().get::<User>() {
+            Some(User("richer")) => Role("admin"),
+            Some(_) => Role("user"),
+            None => return Err(401),
+        };
+        req.extensions_mut().insert(role);
+        Ok(req)
+    }
+
+    async fn me(mut req: Request) -> Result<(String, Ext<Served>), ExtError> {
+        let Ext(User(user)) = Ext::from_request(&mut req).await?;
+        let Ext(Role(role)) = Ext::from_request(&mut req).await?;
+        Ok((format!("{} {}", user, role), Ext(Served("me"))))
+    }
+
+    #[tokio::test]
+    async fn values_flow_through_the_request() {
+        let server = TestServer::new(
+            Server::builder()
+                .configure_middlewares(|m| m.apply(authenticate, vec!["/"], None))
+                .configure_router(|r| r.route_with_guards("/me", Method::GET, me, |g| g.apply(authorize))),
+        )
+        .unwrap();
+
+        let res = server.client().get("/me").send().await.unwrap();
+        assert_eq!(res.header("x-served-by"), Some("me"));
+        assert_eq!(res.text().unwrap(), "richer admin");
+    }
+}
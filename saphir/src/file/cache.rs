@@ -1,6 +1,6 @@
 use crate::{
     error::SaphirError,
-    file::{compress_file, Compression, Encoder, File, FileCursor, FileInfo, FileStream, SaphirFile, MAX_BUFFER},
+    file::{compress_file, middleware::PathExt, Compression, Encoder, File, FileCursor, FileInfo, FileStream, SaphirFile, MAX_BUFFER},
 };
 use futures::{
     io::{AsyncRead, AsyncSeek, Cursor},
@@ -74,12 +74,14 @@ impl FileCache {
         } else {
             let file: Pin<Box<dyn SaphirFile>> = match self.get((path_str.to_string(), Compression::Raw)).await {
                 Some(file) => Box::pin(file),
+                // Too large to be cached, so streamed as is
+                None if compression == Compression::Raw && path.size() > self.max_file_size => return Ok(FileStream::new(File::open(path_str).await?)),
                 None => Box::pin(File::open(path_str).await?),
             };
             let file_size = file.get_size();
             let mime = file.get_mime().cloned();
             let compressed_file = compress_file(file, Encoder::None, compression).await?;
-            if file_size + self.get_size().await <= self.max_capacity && file_size <= self.max_file_size {
+            if compressed_file.len() as u64 + self.get_size().await <= self.max_capacity && file_size <= self.max_file_size {
                 Ok(FileStream::new(FileCacher::new(
                     (path_str.to_string(), compression),
                     Box::pin(FileCursor::new(compressed_file, mime, path.to_owned())) as Pin<Box<dyn SaphirFile>>,
@@ -91,17 +93,32 @@ impl FileCache {
         }
     }
 
-    pub async fn open_file_with_range(&mut self, path: &Path, range: (u64, u64)) -> Result<FileStream, SaphirError> {
+    /// Open a file which can be seeked into, to serve ranges of the
+    /// representation matching `compression`.
+    ///
+    /// Unlike `open_file`, a compressed file is never wrapped into a
+    /// `FileCacher`, since a partial read would end up in the cache. It is
+    /// instead compressed in memory and cached right away.
+    pub async fn open_seekable_file(&mut self, path: &Path, compression: Compression) -> Result<FileStream, SaphirError> {
         let path_str = path.to_str().unwrap_or_default();
-        if let Some(cached_file) = self.get((path_str.to_string(), Compression::Raw)).await {
-            let mut file_stream = FileStream::new(cached_file);
-            file_stream.set_range(range).await?;
-            Ok(file_stream)
-        } else {
-            let mut file_stream = FileStream::new(File::open(path_str).await?);
-            file_stream.set_range(range).await?;
-            Ok(file_stream)
+        if let Some(cached_file) = self.get((path_str.to_string(), compression)).await {
+            return Ok(FileStream::new(cached_file));
         }
+
+        let raw_file: Pin<Box<dyn SaphirFile>> = match self.get((path_str.to_string(), Compression::Raw)).await {
+            Some(file) => Box::pin(file),
+            None if compression == Compression::Raw => return Ok(FileStream::new(File::open(path_str).await?)),
+            None => Box::pin(File::open(path_str).await?),
+        };
+
+        let file_size = raw_file.get_size();
+        let mime = raw_file.get_mime().cloned();
+        let compressed_file = compress_file(raw_file, Encoder::None, compression).await?;
+        if compressed_file.len() as u64 + self.get_size().await <= self.max_capacity && file_size <= self.max_file_size {
+            self.insert((path_str.to_string(), compression), compressed_file.clone()).await;
+        }
+
+        Ok(FileStream::new(FileCursor::new(compressed_file, mime, path.to_owned())))
     }
 }
 
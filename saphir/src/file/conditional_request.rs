@@ -32,7 +32,7 @@ const DEPRECATED_HEADER_DATE_FORMAT2: &[FormatItem<'static>] =
 ///
 /// [RFC7232: If-Match](https://tools.ietf.org/html/rfc7232#section-3.1)
 fn check_if_match(etag: &EntityTag, if_match: &str) -> bool {
-    if_match.trim() == "*" || if_match.split(',').any(|string| etag.strong_eq(EntityTag::parse(string.trim())))
+    etag.strong_matches_any(if_match)
 }
 
 /// Validate precondition of `If-None-Match` header.
@@ -42,7 +42,7 @@ fn check_if_match(etag: &EntityTag, if_match: &str) -> bool {
 ///
 /// [RFC7232: If-None-Match](https://tools.ietf.org/html/rfc7232#section-3.2)
 fn check_if_none_match(etag: &EntityTag, if_none_match: &str) -> bool {
-    if_none_match.trim() != "*" && if_none_match.split(',').all(|string| !etag.weak_eq(EntityTag::parse(string.trim())))
+    !etag.weak_matches_any(if_none_match)
 }
 
 /// Validate precondition of `If-Unmodified-Since` header.
@@ -2,10 +2,11 @@ use crate::{
     file::{
         cache::FileCache,
         conditional_request::{format_systemtime, is_fresh, is_precondition_failed},
-        etag::{EntityTag, SystemTimeExt},
+        content_range::ContentRange,
+        etag::file_etag,
         range::Range,
-        range_requests::{extract_range, is_range_fresh, is_satisfiable_range},
-        Compression,
+        range_requests::{is_range_fresh, satisfiable_ranges},
+        Compression, FileStream,
     },
     handler::DynHandler,
     prelude::*,
@@ -24,6 +25,9 @@ const DEFAULT_CACHE_MAX_CAPACITY: u64 = 536_870_912;
 const DEFAULT_MAX_AGE: i64 = 0;
 const DEFAULT_INDEX_FILES: [&str; 2] = ["index.html", "index.htm"];
 const DEFAULT_TRY_FILES: [&str; 2] = ["$uri", "$uri/"];
+/// Most ranges answered in a `multipart/byteranges` body, the whole file is
+/// sent for requests with more
+const MAX_RANGES: usize = 16;
 
 pub struct FileMiddleware {
     base_path: PathBuf,
@@ -49,11 +53,10 @@ impl FileMiddleware {
     }
 
     async fn next_inner(&self, mut ctx: HttpContext, _chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
-        let mut builder = Builder::new();
+        let builder = Builder::new();
         let mut cache = self.cache.clone();
         let req = ctx.state.request_unchecked();
         let req_path = req.uri().path();
-        let is_head_request = matches!(req.method(), &Method::HEAD);
 
         let mut file_path = None;
         let mut response_code: Option<u16> = None;
@@ -112,67 +115,19 @@ impl FileMiddleware {
             return Ok(ctx);
         }
 
-        let (last_modified, mut size) = (path.mtime(), path.size());
-        let etag = EntityTag::new(false, format!("{}-{}", last_modified.timestamp(), size).as_str());
-
-        if is_precondition_failed(req, &etag, &last_modified) {
-            ctx.after(builder.status(412).build()?);
-            return Ok(ctx);
-        }
-
-        if is_fresh(req, &etag, &last_modified) {
-            ctx.after(builder.status(304).header(header::LAST_MODIFIED, format_systemtime(last_modified)).build()?);
-            return Ok(ctx);
-        }
-
-        let mut is_partial_content = false;
-
-        let compression = req
-            .headers()
-            .get(header::ACCEPT_ENCODING)
-            .and_then(|header| header.to_str().ok())
-            .and_then(|str| str.split(',').map(|encoding| Compression::from_str(encoding.trim()).unwrap_or_default()).max())
-            .unwrap_or_default();
-
-        if let Some(range) = req
-            .headers()
-            .get(header::RANGE)
-            .and_then(|header| header.to_str().ok())
-            .and_then(|header| Range::from_str(header).ok())
-        {
-            if let (true, Some(content_range)) = (is_range_fresh(req, &etag, &last_modified), is_satisfiable_range(&range, size)) {
-                if let Some(range) = extract_range(&content_range) {
-                    if !is_head_request {
-                        let file = cache.open_file_with_range(&path, range).await?;
-                        size = (range.1 - range.0) + 1;
-                        builder = builder.file(file);
-                    }
-                }
-                builder = builder
-                    .header(http::header::CONTENT_RANGE, content_range.to_string())
-                    .status(StatusCode::PARTIAL_CONTENT);
-                is_partial_content = true;
-            }
-        }
-
-        if !is_partial_content && !is_head_request {
-            let file = cache.open_file(&path, compression).await?;
-            size = file.get_size();
-            builder = builder.file(file);
-        }
-
-        if compression != Compression::Raw {
-            builder = builder.header(header::CONTENT_ENCODING, compression.to_string())
-        }
-
-        builder = builder
-            .header(http::header::ACCEPT_RANGES, "bytes")
-            .header(header::CONTENT_TYPE, Self::guess_path_mime(&path).to_string())
-            .header(header::CONTENT_LENGTH, size)
+        let accepted = accepted_encodings(req.headers());
+        let builder = builder
             .header(header::CACHE_CONTROL, format!("public, max-age={}", self.max_age))
-            .header(header::ETAG, etag.get_tag());
-        ctx.after(builder.build()?);
+            .vary(header::ACCEPT_ENCODING);
 
+        let res = match precompressed_variant(&path, &accepted) {
+            Some((variant, compression)) => respond_file(req, &variant, Compression::Raw, Some(compression), &mut cache, builder).await?,
+            None => {
+                let compression = accepted.first().map(|(compression, _)| *compression).unwrap_or_default();
+                respond_file(req, &path, compression, None, &mut cache, builder).await?
+            }
+        };
+        ctx.after(res.build()?);
         Ok(ctx)
     }
 
@@ -226,6 +181,184 @@ impl Middleware for FileMiddleware {
     }
 }
 
+/// Encodings of the `Accept-Encoding` headers supported for files, most
+/// preferred first: by `q` value, then brotli over gzip over deflate. The
+/// encodings with `q=0` are left out.
+fn accepted_encodings(headers: &http::HeaderMap) -> Vec<(Compression, f32)> {
+    let mut accepted: Vec<(Compression, f32)> = headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|h| h.to_str().ok())
+        .flat_map(|h| h.split(','))
+        .filter_map(|entry| {
+            let mut parts = entry.split(';').map(str::trim);
+            let compression = Compression::from_str(&parts.next()?.to_ascii_lowercase()).ok()?;
+            let q = parts
+                .filter_map(|param| param.split_once('='))
+                .find(|(key, _)| key.trim().eq_ignore_ascii_case("q"))
+                .map(|(_, q)| q.trim().parse::<f32>().unwrap_or(0.0))
+                .unwrap_or(1.0);
+            Some((compression, q)).filter(|_| q > 0.0)
+        })
+        .collect();
+    accepted.sort_by(|(a, qa), (b, qb)| qb.total_cmp(qa).then(b.cmp(a)));
+    accepted
+}
+
+/// Precompressed variant of the file at `path` for the most preferred of the
+/// `accepted` encodings which has one, e.g. `app.js.br` or `app.js.gz` next
+/// to `app.js`
+fn precompressed_variant(path: &Path, accepted: &[(Compression, f32)]) -> Option<(PathBuf, Compression)> {
+    accepted.iter().find_map(|(compression, _)| {
+        let extension = match compression {
+            Compression::Brotli => "br",
+            Compression::Gzip => "gz",
+            _ => return None,
+        };
+        let mut variant = path.as_os_str().to_owned();
+        variant.push(".");
+        variant.push(extension);
+        Some((PathBuf::from(variant), *compression)).filter(|(variant, _)| variant.is_file())
+    })
+}
+
+/// Answer a request for the file at `path`, served with `compression`:
+/// evaluate its preconditions, answer `304 Not Modified` when the client is
+/// up to date, and serve the requested ranges of the file, or all of it.
+///
+/// When `precompressed` is set, the file is a precompressed variant, e.g.
+/// `app.js.br`, sent with this encoding and the mime type of the original
+/// file. Its validators are those of the variant.
+pub(crate) async fn respond_file(
+    req: &Request,
+    path: &Path,
+    compression: Compression,
+    precompressed: Option<Compression>,
+    cache: &mut FileCache,
+    mut builder: Builder,
+) -> Result<Builder, SaphirError> {
+    let is_head_request = matches!(req.method(), &Method::HEAD);
+    let (last_modified, mut size) = (path.mtime(), path.size());
+    let etag = file_etag(&last_modified, size);
+
+    if is_precondition_failed(req, &etag, &last_modified) {
+        return Ok(builder.status(412));
+    }
+
+    if is_fresh(req, &etag, &last_modified) {
+        return Ok(builder
+            .status(304)
+            .header(header::ETAG, etag.get_tag())
+            .header(header::LAST_MODIFIED, format_systemtime(last_modified)));
+    }
+
+    let mime_path = match precompressed {
+        Some(_) => path.with_extension(""),
+        None => path.to_path_buf(),
+    };
+    let mut content_type = FileMiddleware::guess_path_mime(mime_path).to_string();
+    let mut body_set = false;
+
+    if let Some(range) = req
+        .headers()
+        .get(header::RANGE)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| Range::from_str(header).ok())
+        .filter(|_| is_range_fresh(req, &etag, &last_modified))
+    {
+        let mut file = cache.open_seekable_file(path, compression).await?;
+        size = file.get_size();
+
+        match satisfiable_ranges(&range, size).as_deref() {
+            Some([]) => {
+                let content_range = ContentRange::Bytes {
+                    range: None,
+                    instance_length: Some(size),
+                };
+                return Ok(builder
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .header(header::CONTENT_RANGE, content_range.to_string()));
+            }
+            Some([range]) => {
+                let content_range = ContentRange::Bytes {
+                    range: Some(*range),
+                    instance_length: Some(size),
+                };
+                file.set_range(*range).await?;
+                size = (range.1 - range.0) + 1;
+                builder = builder
+                    .header(header::CONTENT_RANGE, content_range.to_string())
+                    .status(StatusCode::PARTIAL_CONTENT);
+                if !is_head_request {
+                    builder = builder.file(file);
+                }
+            }
+            Some(ranges) if ranges.len() <= MAX_RANGES => {
+                let (boundary, body) = multipart_byteranges(&mut file, ranges, &content_type).await?;
+                content_type = format!("multipart/byteranges; boundary={}", boundary);
+                size = body.len() as u64;
+                builder = builder.status(StatusCode::PARTIAL_CONTENT);
+                if !is_head_request {
+                    builder = builder.body(body);
+                }
+            }
+            _ => {
+                if !is_head_request {
+                    builder = builder.file(file);
+                }
+            }
+        }
+        body_set = true;
+    }
+
+    if !body_set && !is_head_request {
+        let file = cache.open_file(path, compression).await?;
+        size = file.get_size();
+        builder = builder.file(file);
+    }
+
+    let encoding = precompressed.unwrap_or(compression);
+    if encoding != Compression::Raw {
+        builder = builder.header(header::CONTENT_ENCODING, encoding.to_string())
+    }
+
+    builder = builder
+        .header(http::header::ACCEPT_RANGES, "bytes")
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CONTENT_LENGTH, size)
+        .header(header::ETAG, etag.get_tag())
+        .header(header::LAST_MODIFIED, format_systemtime(last_modified));
+    Ok(builder)
+}
+
+/// Build a `multipart/byteranges` body for every range, as described in
+/// [RFC7233](https://tools.ietf.org/html/rfc7233#appendix-A).
+///
+/// Returns the boundary along with the body.
+async fn multipart_byteranges(file: &mut FileStream, ranges: &[(u64, u64)], content_type: &str) -> Result<(String, Vec<u8>), SaphirError> {
+    let size = file.get_size();
+    let nanos = SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let boundary = format!("saphir-{:x}", nanos);
+
+    let mut body = Vec::new();
+    for range in ranges {
+        let content_range = ContentRange::Bytes {
+            range: Some(*range),
+            instance_length: Some(size),
+        };
+        body.extend_from_slice(format!("\r\n--{}\r\n", boundary).as_bytes());
+        body.extend_from_slice(format!("{}: {}\r\n", header::CONTENT_TYPE, content_type).as_bytes());
+        body.extend_from_slice(format!("{}: {}\r\n\r\n", header::CONTENT_RANGE, content_range).as_bytes());
+        body.extend_from_slice(&file.read_range(*range).await?);
+    }
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+    Ok((boundary, body))
+}
+
 pub struct FileMiddlewareBuilder {
     base_path: PathBuf,
     www_path: PathBuf,
@@ -363,3 +496,208 @@ impl PathExt for Path {
         from_path(self).first()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{conditional::ConditionalGetMiddleware, file::FileCursor, test::TestServer};
+
+    #[tokio::test]
+    async fn multipart_byteranges_from_cursor() {
+        let mut file = FileStream::new(FileCursor::new(b"0123456789".to_vec(), None, PathBuf::from("digits.txt")));
+        let (boundary, body) = multipart_byteranges(&mut file, &[(0, 1), (7, 9)], "text/plain").await.unwrap();
+
+        let expected = format!(
+            "\r\n--{b}\r\ncontent-type: text/plain\r\ncontent-range: bytes 0-1/10\r\n\r\n01\
+             \r\n--{b}\r\ncontent-type: text/plain\r\ncontent-range: bytes 7-9/10\r\n\r\n789\
+             \r\n--{b}--\r\n",
+            b = boundary
+        );
+        assert_eq!(String::from_utf8(body).unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn seekable_compressed_file_is_cached() {
+        let path = std::env::temp_dir().join(format!("saphir-range-{}.txt", std::process::id()));
+        std::fs::write(&path, "hello hello hello hello").unwrap();
+
+        let mut cache = FileCache::new(DEFAULT_CACHE_MAX_FILE_SIZE, DEFAULT_CACHE_MAX_CAPACITY);
+        let mut file = cache.open_seekable_file(&path, Compression::Gzip).await.unwrap();
+        let compressed_size = file.get_size();
+        assert_eq!(file.read_range((0, 1)).await.unwrap(), vec![0x1f, 0x8b]);
+        assert_eq!(cache.get_size().await, compressed_size);
+
+        let mut cached = cache.open_seekable_file(&path, Compression::Gzip).await.unwrap();
+        assert_eq!(cached.get_size(), compressed_size);
+        assert_eq!(cached.read_range((0, 1)).await.unwrap(), vec![0x1f, 0x8b]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn file_responses_are_validated() {
+        let dir = std::env::temp_dir().join(format!("saphir-etag-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("hello.txt");
+        std::fs::write(&path, "hello").unwrap();
+
+        let file_path = path.to_str().unwrap().to_string();
+        let file_middleware = FileMiddlewareBuilder::new("static", dir.to_str().unwrap()).build().unwrap();
+        let client = TestServer::new(
+            Server::builder()
+                .configure_middlewares(|m| {
+                    m.apply(file_middleware, vec!["/static/"], None)
+                        .apply(ConditionalGetMiddleware::new(), vec!["/file"], None)
+                })
+                .configure_router(|r| {
+                    r.route("/file", Method::GET, move |_req: Request| {
+                        let file_path = file_path.clone();
+                        async move { File::open(&file_path).await.ok() }
+                    })
+                }),
+        )
+        .unwrap()
+        .client();
+
+        let res = client.get("/file").send().await.unwrap();
+        assert_eq!(res.text().unwrap(), "hello");
+        let etag = res.header(header::ETAG).unwrap().to_string();
+        assert!(res.header(header::LAST_MODIFIED).is_some());
+
+        let res = client.get("/file").header(header::IF_NONE_MATCH, etag.as_str()).send().await.unwrap();
+        assert_eq!(res.status(), 304);
+
+        let res = client.get("/static/hello.txt").send().await.unwrap();
+        assert_eq!(res.header(header::ETAG), Some(etag.as_str()));
+        let res = client
+            .get("/static/hello.txt")
+            .header(header::IF_NONE_MATCH, etag.as_str())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!((res.status().as_u16(), res.header(header::ETAG)), (304, Some(etag.as_str())));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn precompressed_variants_are_served() {
+        let dir = std::env::temp_dir().join(format!("saphir-precompressed-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("app.js"), "raw").unwrap();
+        std::fs::write(dir.join("app.js.br"), "brotli").unwrap();
+        std::fs::write(dir.join("app.js.gz"), "gzip").unwrap();
+
+        let middleware = FileMiddleware::new("static", dir.to_str().unwrap());
+        let client = TestServer::new(Server::builder().configure_middlewares(|m| m.apply(middleware, vec!["/static/"], None)))
+            .unwrap()
+            .client();
+        let get = |accept_encoding: &'static str| client.get("/static/app.js").header(header::ACCEPT_ENCODING, accept_encoding).send();
+        let js = FileMiddleware::guess_path_mime("app.js").to_string();
+
+        let res = get("gzip, br").await.unwrap();
+        assert_eq!(res.header(header::CONTENT_ENCODING), Some("br"));
+        assert_eq!(res.header(header::CONTENT_TYPE), Some(js.as_str()));
+        let br_etag = res.header(header::ETAG).unwrap().to_string();
+        assert_eq!(res.text().unwrap(), "brotli");
+
+        let res = get("br;q=0.5, gzip").await.unwrap();
+        assert_eq!(res.header(header::CONTENT_ENCODING), Some("gzip"));
+        assert_ne!(res.header(header::ETAG), Some(br_etag.as_str()));
+        assert_eq!(res.text().unwrap(), "gzip");
+
+        let res = get("br;q=0, deflate").await.unwrap();
+        assert_eq!(res.header(header::CONTENT_ENCODING), Some("deflate"));
+        assert_ne!(res.text().unwrap(), "raw");
+
+        let res = get("identity").await.unwrap();
+        assert_eq!(res.header(header::CONTENT_ENCODING), None);
+        assert_eq!(res.text().unwrap(), "raw");
+
+        let res = client
+            .get("/static/app.js")
+            .header(header::ACCEPT_ENCODING, "br")
+            .header(header::IF_NONE_MATCH, br_etag.as_str())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 304);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn byte_ranges_are_served() {
+        let dir = std::env::temp_dir().join(format!("saphir-byteranges-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let alphabet = "abcdefghijklmnopqrstuvwxyz".repeat(2);
+        std::fs::write(dir.join("alphabet.txt"), &alphabet).unwrap();
+
+        let middleware = FileMiddleware::new("static", dir.to_str().unwrap());
+        let client = TestServer::new(Server::builder().configure_middlewares(|m| m.apply(middleware, vec!["/static/"], None)))
+            .unwrap()
+            .client();
+        let get = |range: String| client.get("/static/alphabet.txt").header(header::RANGE, range.as_str()).send();
+
+        let res = get("bytes=2-4".to_string()).await.unwrap();
+        assert_eq!(res.status(), 206);
+        assert_eq!(res.header(header::CONTENT_RANGE), Some("bytes 2-4/52"));
+        assert_eq!(res.text().unwrap(), "cde");
+
+        let res = get(format!("bytes={}", vec!["0-"; 1000].join(","))).await.unwrap();
+        assert_eq!(res.status(), 206);
+        assert_eq!(res.header(header::CONTENT_RANGE), Some("bytes 0-51/52"));
+        assert_eq!(res.text().unwrap(), alphabet);
+
+        let res = get("bytes=0-1,20-21".to_string()).await.unwrap();
+        assert_eq!(res.status(), 206);
+        assert!(res.header(header::CONTENT_TYPE).unwrap().starts_with("multipart/byteranges"));
+
+        let many = (0..MAX_RANGES + 1).map(|i| format!("{0}-{0}", i * 2)).collect::<Vec<_>>().join(",");
+        let res = get(format!("bytes={}", many)).await.unwrap();
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.text().unwrap(), alphabet);
+
+        let res = get("bytes=60-70".to_string()).await.unwrap();
+        assert_eq!(res.status(), 416);
+        assert_eq!(res.header(header::CONTENT_RANGE), Some("bytes */52"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn files_are_served_as_attachments() {
+        let file = crate::file::File::open("examples/files_to_serve/index.html")
+            .await
+            .unwrap()
+            .attachment("page.html");
+        let res = file.into_builder(Builder::new()).build().unwrap();
+        assert_eq!(res.headers()[header::CONTENT_DISPOSITION], "attachment; filename=\"page.html\"");
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "text/html");
+    }
+
+    #[tokio::test]
+    async fn files_are_served_from_their_path() {
+        async fn file(req: Request) -> Result<Builder, SaphirError> {
+            match req.uri().query() {
+                Some("missing") => Builder::new().file_path("examples/files_to_serve/missing.html").await,
+                Some("for") => Builder::new().file_path_for(&req, "examples/files_to_serve/index.html").await,
+                _ => Builder::new().file_path("examples/files_to_serve/index.html").await,
+            }
+        }
+
+        let client = TestServer::new(Server::builder().configure_router(|r| r.route("/file", Method::GET, file)))
+            .unwrap()
+            .client();
+
+        let res = client.get("/file").send().await.unwrap();
+        assert_eq!(res.header(header::CONTENT_TYPE), Some("text/html"));
+        assert!(res.header(header::ETAG).is_some());
+        assert!(res.text().unwrap().starts_with("<!doctype html>"));
+
+        assert_eq!(client.get("/file?missing").send().await.unwrap().status(), 404);
+
+        let res = client.get("/file?for").header(header::IF_MATCH, "\"other\"").send().await.unwrap();
+        assert_eq!(res.status(), 412);
+    }
+}
@@ -13,7 +13,13 @@ use tokio::{
     macros::support::Pin,
 };
 
-use crate::{error::SaphirError, file::middleware::PathExt, http_context::HttpContext, responder::Responder, response::Builder};
+use crate::{
+    error::SaphirError,
+    file::{conditional_request::format_systemtime, etag::file_etag, middleware::PathExt},
+    http_context::HttpContext,
+    responder::Responder,
+    response::{content_disposition, Builder},
+};
 use flate2::write::{DeflateEncoder, GzEncoder};
 use futures::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, Cursor};
 use mime::Mime;
@@ -21,16 +27,20 @@ use std::{
     fmt::Display,
     io::{Cursor as CursorSync, Write},
     str::FromStr,
+    time::SystemTime,
 };
 use tokio::io::ReadBuf;
 
-mod cache;
+pub(crate) mod cache;
 pub mod conditional_request;
 pub mod content_range;
 pub mod etag;
 pub mod middleware;
 pub mod range;
 pub mod range_requests;
+#[cfg(feature = "content-sniffing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "content-sniffing")))]
+pub mod sniff;
 
 pub const MAX_BUFFER: usize = 65534;
 
@@ -42,6 +52,23 @@ pub trait FileInfo {
     fn get_path(&self) -> &PathBuf;
     fn get_mime(&self) -> Option<&mime::Mime>;
     fn get_size(&self) -> u64;
+
+    /// Modification time of the file, `None` when it can't be read from its
+    /// path
+    fn get_last_modified(&self) -> Option<SystemTime> {
+        self.get_path().metadata().and_then(|meta| meta.modified()).ok()
+    }
+}
+
+/// Add the `ETag` and `Last-Modified` headers of a file, computed from its
+/// size and modification time
+fn with_validators<F: FileInfo + ?Sized>(builder: Builder, file: &F) -> Builder {
+    match file.get_last_modified() {
+        Some(last_modified) => builder
+            .etag(&file_etag(&last_modified, file.get_size()))
+            .header(http::header::LAST_MODIFIED, format_systemtime(last_modified)),
+        None => builder,
+    }
 }
 
 pub struct File {
@@ -49,6 +76,7 @@ pub struct File {
     path: PathBuf,
     mime: Option<mime::Mime>,
     seek_has_started: bool,
+    disposition: Option<String>,
 }
 
 impl FileInfo for File {
@@ -69,16 +97,71 @@ impl File {
     pub async fn open(path_str: &str) -> tokio::io::Result<File> {
         let path = path_str.to_string();
         match TokioFile::open(path_str).await {
-            Ok(file) => Ok(File {
-                inner: Box::pin(file),
-                path: PathBuf::from(path),
-                mime: None,
-                seek_has_started: false,
-            }),
+            Ok(file) => {
+                let file = File {
+                    inner: Box::pin(file),
+                    path: PathBuf::from(path),
+                    mime: None,
+                    seek_has_started: false,
+                    disposition: None,
+                };
+                #[cfg(feature = "content-sniffing")]
+                let file = file.sniffed().await?;
+                Ok(file)
+            }
 
             Err(e) => Err(e),
         }
     }
+
+    /// Set the mime type sent as the `Content-Type` of the file, instead of
+    /// the one guessed from its extension or content
+    pub fn content_type(mut self, mime: Mime) -> Self {
+        self.mime = Some(mime);
+        self
+    }
+
+    /// Guess the mime type from the first bytes of a file whose extension is
+    /// unknown, see [`sniff_mime`](sniff::sniff_mime)
+    #[cfg(feature = "content-sniffing")]
+    async fn sniffed(mut self) -> io::Result<Self> {
+        if self.path.mime().is_some() || self.path.is_dir() {
+            return Ok(self);
+        }
+
+        let mut head = [0; sniff::SNIFF_LEN];
+        let mut len = 0;
+        while len < head.len() {
+            match self.read(&mut head[len..]).await? {
+                0 => break,
+                n => len += n,
+            }
+        }
+        self.seek(SeekFrom::Start(0)).await?;
+        self.mime = sniff::sniff_mime(&head[..len]);
+        Ok(self)
+    }
+
+    /// Serve the file as a download named `filename`, see
+    /// [`Builder::attachment`]
+    ///
+    /// ```rust
+    /// # use saphir::{file::File, prelude::*};
+    /// async fn report(_req: Request) -> Result<File, SaphirError> {
+    ///     Ok(File::open("examples/files_to_serve/index.html").await?.attachment("report.html"))
+    /// }
+    /// ```
+    pub fn attachment(mut self, filename: &str) -> Self {
+        self.disposition = Some(content_disposition("attachment", filename));
+        self
+    }
+
+    /// Serve the file to be displayed, named `filename` when it is saved, see
+    /// [`Builder::inline`]
+    pub fn inline(mut self, filename: &str) -> Self {
+        self.disposition = Some(content_disposition("inline", filename));
+        self
+    }
 }
 
 impl AsyncRead for File {
@@ -119,6 +202,13 @@ impl AsyncSeek for File {
 
 impl Responder for File {
     fn respond_with_builder(self, builder: Builder, _ctx: &HttpContext) -> Builder {
+        self.into_builder(builder)
+    }
+}
+
+impl File {
+    /// Set the file as the body of `builder`, along with its headers
+    pub(crate) fn into_builder(self, builder: Builder) -> Builder {
         let mime = if let Some(mime) = &self.get_mime() {
             mime.as_ref().to_string()
         } else {
@@ -136,7 +226,11 @@ impl Responder for File {
         };
 
         let len = self.get_size();
-        builder
+        let builder = match &self.disposition {
+            Some(disposition) => builder.header(http::header::CONTENT_DISPOSITION, disposition.as_str()),
+            None => builder,
+        };
+        with_validators(builder, &self)
             .file(self)
             .header(http::header::ACCEPT_RANGES, "bytes")
             .header(http::header::CONTENT_TYPE, mime)
@@ -333,6 +427,15 @@ impl FileStream {
     pub fn get_size(&self) -> u64 {
         self.inner.get_size()
     }
+
+    /// Read the end-inclusive `range` into memory
+    pub async fn read_range(&mut self, range: (u64, u64)) -> io::Result<Vec<u8>> {
+        let (start, end) = range;
+        let mut buffer = vec![0; ((end - start) + 1) as usize];
+        self.inner.seek(SeekFrom::Start(start)).await?;
+        self.inner.read_exact(buffer.as_mut_slice()).await?;
+        Ok(buffer)
+    }
 }
 
 impl Stream for FileStream {
@@ -414,7 +517,7 @@ impl Responder for FileStream {
 
         let len = self.inner.get_size();
 
-        builder
+        with_validators(builder, &*self.inner)
             .file(self)
             .header(http::header::ACCEPT_RANGES, "bytes")
             .header(http::header::CONTENT_TYPE, mime)
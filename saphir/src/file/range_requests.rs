@@ -65,6 +65,32 @@ pub fn is_satisfiable_range(range: &Range, instance_length: u64) -> Option<Conte
     })
 }
 
+/// Resolve every byte-range of a `Range` header field against the length of
+/// the representation.
+///
+/// - Non-bytes range unit -> None, the header should be ignored
+/// - Bytes range unit -> Some, with every satisfiable byte-range, sorted and
+///   with the overlapping or adjacent ones merged
+///
+/// An empty vector means that the byte-range-set is unsatisfiable and should
+/// be answered with a `416 Range Not Satisfiable`.
+pub fn satisfiable_ranges(range: &Range, instance_length: u64) -> Option<Vec<(u64, u64)>> {
+    let mut ranges: Vec<(u64, u64)> = match *range {
+        Range::Bytes(ref byte_range_specs) => byte_range_specs.iter().filter_map(|spec| spec.to_satisfiable_range(instance_length)).collect(),
+        _ => return None,
+    };
+
+    ranges.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    Some(merged)
+}
+
 /// Extract range from `ContentRange` header field.
 pub fn extract_range(content_range: &ContentRange) -> Option<(u64, u64)> {
     match *content_range {
@@ -225,4 +251,25 @@ mod t_satisfiable {
         let range = &bytes_multi(vec![(0, 5), (5, 6)]);
         assert!(is_satisfiable_range(range, 10).is_none());
     }
+
+    #[test]
+    fn all_satisfiable_byte_ranges() {
+        let range = &Range::Bytes(vec![ByteRangeSpec::FromTo(0, 1), ByteRangeSpec::FromTo(20, 30), ByteRangeSpec::Last(3)]);
+        assert_eq!(satisfiable_ranges(range, 10), Some(vec![(0, 1), (7, 9)]));
+    }
+
+    #[test]
+    fn overlapping_byte_ranges_are_merged() {
+        let range = &Range::Bytes(vec![ByteRangeSpec::FromTo(6, 7), ByteRangeSpec::AllFrom(0), ByteRangeSpec::AllFrom(0)]);
+        assert_eq!(satisfiable_ranges(range, 10), Some(vec![(0, 9)]));
+
+        let range = &Range::Bytes(vec![ByteRangeSpec::FromTo(4, 5), ByteRangeSpec::FromTo(0, 3), ByteRangeSpec::FromTo(7, 8)]);
+        assert_eq!(satisfiable_ranges(range, 10), Some(vec![(0, 5), (7, 8)]));
+    }
+
+    #[test]
+    fn no_satisfiable_byte_ranges() {
+        assert_eq!(satisfiable_ranges(&bytes(20, 30), 10), Some(vec![]));
+        assert_eq!(satisfiable_ranges(&Range::Unregistered("".to_owned(), "".to_owned()), 10), None);
+    }
 }
@@ -0,0 +1,79 @@
+use mime::Mime;
+
+/// Number of leading bytes needed to recognize every known magic number
+pub const SNIFF_LEN: usize = 12;
+
+/// Guess the mime type of a content from the magic number of its first
+/// bytes.
+///
+/// Only a few well-known binary formats are recognized, `None` is returned
+/// for anything else rather than a guess.
+///
+/// ```rust
+/// # use saphir::file::sniff::sniff_mime;
+/// assert_eq!(sniff_mime(b"%PDF-1.7\n"), Some(mime::APPLICATION_PDF));
+/// assert_eq!(sniff_mime(b"plain text"), None);
+/// ```
+pub fn sniff_mime(bytes: &[u8]) -> Option<Mime> {
+    let mime = match bytes {
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => mime::IMAGE_PNG,
+        [0xFF, 0xD8, 0xFF, ..] => mime::IMAGE_JPEG,
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => mime::IMAGE_GIF,
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "image/webp".parse().ok()?,
+        [b'%', b'P', b'D', b'F', b'-', ..] => mime::APPLICATION_PDF,
+        [0x1F, 0x8B, 0x08, ..] => "application/gzip".parse().ok()?,
+        [b'P', b'K', 0x03, 0x04, ..] => "application/zip".parse().ok()?,
+        _ => return None,
+    };
+    Some(mime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::{File, FileInfo};
+    use futures::io::AsyncReadExt;
+
+    #[test]
+    fn magic_numbers_are_recognized() {
+        let sniffed = |bytes: &[u8]| sniff_mime(bytes).map(|mime| mime.to_string());
+        assert_eq!(sniffed(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").as_deref(), Some("image/png"));
+        assert_eq!(sniffed(b"\xff\xd8\xff\xe0\0\x10JFIF").as_deref(), Some("image/jpeg"));
+        assert_eq!(sniffed(b"GIF89a").as_deref(), Some("image/gif"));
+        assert_eq!(sniffed(b"RIFF\x24\0\0\0WEBPVP8 ").as_deref(), Some("image/webp"));
+        assert_eq!(sniffed(b"\x1f\x8b\x08\0").as_deref(), Some("application/gzip"));
+        assert_eq!(sniffed(b"PK\x03\x04\x14\0").as_deref(), Some("application/zip"));
+    }
+
+    #[test]
+    fn unknown_content_is_not_guessed() {
+        assert_eq!(sniff_mime(b""), None);
+        assert_eq!(sniff_mime(b"\xff\xd8"), None);
+        assert_eq!(sniff_mime(b"RIFF\x24\0\0\0WAVEfmt "), None);
+        assert_eq!(sniff_mime(b"<html></html>"), None);
+    }
+
+    #[tokio::test]
+    async fn files_without_extension_are_sniffed() {
+        let dir = std::env::temp_dir().join(format!("saphir-sniff-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let upload = dir.join("upload");
+        std::fs::write(&upload, b"%PDF-1.7\n%%EOF").unwrap();
+        let notes = dir.join("notes.txt");
+        std::fs::write(&notes, b"%PDF-1.7\n%%EOF").unwrap();
+
+        let mut file = File::open(upload.to_str().unwrap()).await.unwrap();
+        assert_eq!(file.get_mime(), Some(&mime::APPLICATION_PDF));
+        let mut content = Vec::new();
+        file.read_to_end(&mut content).await.unwrap();
+        assert_eq!(content, b"%PDF-1.7\n%%EOF");
+
+        let file = File::open(notes.to_str().unwrap()).await.unwrap();
+        assert_eq!(file.get_mime(), None);
+
+        let file = File::open(upload.to_str().unwrap()).await.unwrap().content_type(mime::TEXT_PLAIN);
+        assert_eq!(file.get_mime(), Some(&mime::TEXT_PLAIN));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
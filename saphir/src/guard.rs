@@ -1,14 +1,152 @@
 //! A guard is called before the request is processed by the router and
 //! can modify the request data or stops request processing by returning a
 //! response immediately.
+//!
+//! Guards of a handler run in the order they are declared on a controller
+//! handler, while with the guard [`Builder`] the last applied guard runs
+//! first. Each guard receives the request returned by the previous one. Values a guard inserts in the
+//! request extensions are therefore visible to the following guards and to the
+//! handler, which can retrieve them with the `Ext<T>` extractor or, with the
+//! controller macro, with the `#[ext(...)]` attribute. The first guard
+//! returning an error stops the chain: its responder is sent back and neither
+//! the remaining guards nor the handler are called, so no parameter is
+//! extracted and the body is not read.
+//!
+//! ```rust
+//! use saphir::prelude::*;
+//!
+//! #[derive(Clone)]
+//! struct Claims {
+//!     user: String,
+//! }
+//!
+//! async fn auth(mut req: Request) -> Result<Request, u16> {
+//!     let user = req
+//!         .headers()
+//!         .get(header::AUTHORIZATION)
+//!         .and_then(|h| h.to_str().ok())
+//!         .and_then(|h| h.strip_prefix("Bearer "))
+//!         .ok_or(401u16)?
+//!         .to_string();
+//!     req.extensions_mut().insert(Claims { user });
+//!     Ok(req)
+//! }
+//!
+//! async fn me(req: Request) -> (u16, String) {
+//!     match req.extensions().get::<Claims>() {
+//!         Some(claims) => (200, claims.user.clone()),
+//!         None => (500, String::new()),
+//!     }
+//! }
+//!
+//! let server = Server::builder()
+//!     .configure_router(|r| r.route_with_guards("/me", Method::GET, me, |g| g.apply(auth)))
+//!     .build();
+//! ```
+//!
+//! A guard which needs the payload of the request, e.g. to verify the
+//! signature of a webhook, can load it with `Body::buffer`. The bytes are kept
+//! as the body of the request, so the handler can still read it, as `Json<T>`
+//! for instance. The whole body is held in memory, up to the request body size
+//! limit of the server.
+//!
+//! ```rust
+//! use base64::engine::{general_purpose::STANDARD, Engine};
+//! use ring::hmac;
+//! use saphir::prelude::*;
+//!
+//! async fn verify_signature(mut req: Request) -> Result<Request, u16> {
+//!     let payload = req.body_mut().buffer().await.map_err(|_| 400u16)?;
+//!     let signature = req
+//!         .headers()
+//!         .get("x-signature")
+//!         .and_then(|h| STANDARD.decode(h.as_bytes()).ok())
+//!         .ok_or(401u16)?;
+//!     // `verify` compares the signatures in constant time, unlike `==`
+//!     let key = hmac::Key::new(hmac::HMAC_SHA256, b"webhook secret");
+//!     hmac::verify(&key, &payload, &signature).map_err(|_| 401u16)?;
+//!     Ok(req)
+//! }
+//!
+//! async fn webhook(mut req: Request) -> Result<u16, SaphirError> {
+//!     let _event = req.body_mut().take_as::<String>().await?;
+//!     Ok(200)
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), SaphirError> {
+//! let server = Server::builder().configure_router(|r| r.route_with_guards("/webhook", Method::POST, webhook, |g| g.apply(verify_signature)));
+//! let client = saphir::test::TestServer::new(server)?.client();
+//!
+//! let signature = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, b"webhook secret"), b"{}");
+//! let signed = client.post("/webhook").header("x-signature", STANDARD.encode(signature)).body("{}");
+//! assert_eq!(signed.send().await?.status(), StatusCode::OK);
+//! let forged = client.post("/webhook").header("x-signature", STANDARD.encode([0; 32])).body("{}");
+//! assert_eq!(forged.send().await?.status(), StatusCode::UNAUTHORIZED);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! When the decision depends on the payload itself, e.g. a user may only
+//! modify their own resources, identified in the body, [`Builder::load_body`]
+//! applies a guard loading the body and deserializing it, like `Json<T>`. The
+//! value is stored in the request extensions, where the following guards
+//! inspect it and from which the handler takes it, e.g. with the `Ext<T>`
+//! extractor, so the body is only deserialized once. The body is loaded when
+//! this guard runs: the guards which don't need the payload, like
+//! authentication, should run before it so that rejected requests don't pay
+//! for loading and deserializing their body.
+//!
+//! ```rust
+//! # #[cfg(feature = "json")]
+//! # mod example {
+//! use saphir::prelude::*;
+//! # use serde_derive::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Post {
+//!     author: String,
+//!     text: String,
+//! }
+//!
+//! async fn own_posts_only(req: Request) -> Result<Request, u16> {
+//!     let user = req.headers().get("x-user").and_then(|h| h.to_str().ok()).ok_or(401u16)?;
+//!     match req.extensions().get::<Post>() {
+//!         Some(post) if post.author == user => Ok(req),
+//!         _ => Err(403),
+//!     }
+//! }
+//!
+//! async fn update_post(mut req: Request) -> Result<String, u16> {
+//!     let post = req.extensions_mut().remove::<Post>().ok_or(500u16)?;
+//!     Ok(post.text)
+//! }
+//!
+//! # pub fn main() {
+//! let server = Server::builder()
+//!     .configure_router(|r| {
+//!         // Runs `load_body`, then `own_posts_only`
+//!         r.route_with_guards("/posts/{id}", Method::PUT, update_post, |g| g.apply(own_posts_only).load_body::<Json<Post>>())
+//!     })
+//!     .build();
+//! # }
+//! # }
+//! ```
+//!
+//! Guard types can describe the authentication they enforce with the
+//! `#[openapi(security = "...")]` attribute, so that the OpenAPI documentation
+//! generated by `saphir-cli` lists the security requirements of the handlers
+//! they protect.
 
 use crate::{
-    body::Body,
+    body::{Body, FromBytes},
+    error::SaphirError,
     request::Request,
     responder::{DynResponder, Responder},
 };
 use futures::{future::BoxFuture, FutureExt};
 use futures_util::future::Future;
+use std::marker::PhantomData;
 
 /// Auto trait implementation over every function that match the definition of a
 /// guard.
@@ -55,11 +193,46 @@ impl<Chain: GuardChain + 'static> Builder<Chain> {
         }
     }
 
+    /// Apply a guard loading the body of the request and deserializing it as
+    /// `T`, e.g. `Json<Post>`, into the request extensions, where the next
+    /// guards and the handler find it. Requests whose body can't be loaded or
+    /// deserialized are rejected with the status of the error, e.g. `400 Bad
+    /// Request`.
+    pub fn load_body<T>(self) -> Builder<GuardChainLink<LoadBody<T>, Chain>>
+    where
+        T: FromBytes + 'static,
+        T::Out: Send + Sync + 'static,
+    {
+        self.apply(LoadBody(PhantomData))
+    }
+
     pub(crate) fn build(self) -> Box<dyn GuardChain> {
         Box::new(self.chain)
     }
 }
 
+/// Guard deserializing the body of the request, see [`Builder::load_body`]
+pub struct LoadBody<T>(PhantomData<fn() -> T>);
+
+impl<T> Guard for LoadBody<T>
+where
+    T: FromBytes + 'static,
+    T::Out: Send + Sync + 'static,
+{
+    type Future = BoxFuture<'static, Result<Request<Body>, SaphirError>>;
+    type Responder = SaphirError;
+
+    fn validate(&'static self, mut req: Request<Body>) -> Self::Future {
+        async move {
+            let bytes = req.body_mut().buffer().await?;
+            let (value, _) = T::from_bytes(bytes)?;
+            req.extensions_mut().insert(value);
+            Ok(req)
+        }
+        .boxed()
+    }
+}
+
 #[doc(hidden)]
 pub trait GuardChain: Sync + Send {
     fn validate(&'static self, req: Request<Body>) -> BoxFuture<'static, Result<Request<Body>, Box<dyn DynResponder + Send>>>;
@@ -116,3 +289,101 @@ where
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::SaphirError, request::Request, server::Server, test::TestServer};
+    use http::Method;
+
+    fn checksum(payload: &[u8]) -> String {
+        payload.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)).to_string()
+    }
+
+    async fn verify(mut req: Request) -> Result<Request, u16> {
+        let payload = req.body_mut().buffer().await.map_err(|_| 400u16)?;
+        match req.headers().get("x-checksum").and_then(|h| h.to_str().ok()) {
+            Some(sum) if sum == checksum(&payload) => Ok(req),
+            _ => Err(401),
+        }
+    }
+
+    async fn echo(mut req: Request) -> Result<String, SaphirError> {
+        req.body_mut().take_as::<String>().await
+    }
+
+    #[tokio::test]
+    async fn buffered_body_reaches_the_handler() {
+        let server = TestServer::new(Server::builder().configure_router(|r| r.route_with_guards("/echo", Method::POST, echo, |g| g.apply(verify)))).unwrap();
+        let client = server.client();
+
+        let res = client
+            .post("/echo")
+            .header("x-checksum", checksum(b"saphir"))
+            .body("saphir")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.text().unwrap(), "saphir");
+
+        let res = client.post("/echo").header("x-checksum", "0").body("saphir").send().await.unwrap();
+        assert_eq!(res.status(), 401);
+    }
+
+    #[cfg(feature = "json")]
+    #[tokio::test]
+    async fn loaded_body_reaches_the_guards_and_handler() {
+        use crate::{body::Json, extension::Ext, request::FromRequest};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        #[derive(serde_derive::Deserialize)]
+        struct Post {
+            author: String,
+        }
+
+        static AUTHENTICATED: AtomicUsize = AtomicUsize::new(0);
+
+        async fn authenticated(req: Request) -> Result<Request, u16> {
+            AUTHENTICATED.fetch_add(1, Ordering::SeqCst);
+            if req.headers().contains_key("x-user") {
+                Ok(req)
+            } else {
+                Err(401)
+            }
+        }
+
+        async fn own_posts_only(req: Request) -> Result<Request, u16> {
+            let user = req.headers().get("x-user").and_then(|h| h.to_str().ok());
+            match req.extensions().get::<Post>() {
+                Some(post) if Some(post.author.as_str()) == user => Ok(req),
+                _ => Err(403),
+            }
+        }
+
+        async fn update(mut req: Request) -> Result<String, SaphirError> {
+            let post = Ext::<Post>::from_request(&mut req).await.map_err(|_| SaphirError::Other("no post".into()))?;
+            let body = req.body_mut().take_as::<String>().await?;
+            Ok(format!("{} {}", post.author, body))
+        }
+
+        let server = TestServer::new(Server::builder().configure_router(|r| {
+            r.route_with_guards("/posts", Method::PUT, update, |g| {
+                g.apply(own_posts_only).load_body::<Json<Post>>().apply(authenticated)
+            })
+        }))
+        .unwrap();
+        let client = server.client();
+
+        let res = client.put("/posts").header("x-user", "jo").body(r#"{"author":"jo"}"#).send().await.unwrap();
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.text().unwrap(), r#"jo {"author":"jo"}"#);
+
+        let res = client.put("/posts").header("x-user", "al").body(r#"{"author":"jo"}"#).send().await.unwrap();
+        assert_eq!(res.status(), 403);
+        let res = client.put("/posts").header("x-user", "jo").body("not json").send().await.unwrap();
+        assert_eq!(res.status(), 400);
+        let res = client.put("/posts").body("not json").send().await.unwrap();
+        assert_eq!(res.status(), 401);
+        assert_eq!(AUTHENTICATED.load(Ordering::SeqCst), 4);
+    }
+}
@@ -9,8 +9,40 @@ use std::pin::Pin;
 /// Define a Handler of a potential http request
 ///
 /// Implementing this trait on any type will allow the router to route request
-/// towards it. Implemented by default on Controllers and on any `async
-/// fn(Request<Body>) -> impl Responder`
+/// towards it. Implemented by default on Controllers, on any `async
+/// fn(Request<Body>) -> impl Responder` and on closures returning a future of
+/// a responder.
+///
+/// Handlers are moved into the stack of the server, which lives as long as
+/// the process, so closures must be `'static + Send + Sync` and own what they
+/// capture, e.g. an `Arc` rather than a reference. The future returned for
+/// each request must be `'static` too: clone the captured state into it
+/// instead of borrowing it from the closure.
+/// ```rust
+/// # use saphir::{prelude::*, test::TestServer};
+/// # use std::sync::{
+/// #     atomic::{AtomicUsize, Ordering},
+/// #     Arc,
+/// # };
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), SaphirError> {
+/// let hits = Arc::new(AtomicUsize::new(0));
+/// let counter = hits.clone();
+///
+/// let server = Server::builder().configure_router(|r| {
+///     r.route("/hits", Method::POST, move |_req: Request| {
+///         let counter = counter.clone();
+///         async move { (counter.fetch_add(1, Ordering::SeqCst) + 1).to_string() }
+///     })
+/// });
+///
+/// let client = TestServer::new(server)?.client();
+/// client.post("/hits").send().await?;
+/// assert_eq!(client.post("/hits").send().await?.text()?, "2");
+/// assert_eq!(hits.load(Ordering::SeqCst), 2);
+/// # Ok(())
+/// # }
+/// ```
 pub trait Handler<T> {
     /// Responder returned by the handler
     type Responder: Responder;
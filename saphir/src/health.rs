@@ -0,0 +1,160 @@
+//! Liveness and readiness probes.
+//!
+//! The [`HealthController`] answers the probes of orchestrators such as
+//! Kubernetes: the liveness endpoint, `/health` by default, is always `200 OK`
+//! once the server runs, while the readiness endpoint, `/ready` by default,
+//! is `200 OK` or `503 Service Unavailable` depending on a user supplied
+//! check, e.g. pinging a database. During the drain of a graceful shutdown,
+//! the readiness endpoint is `503 Service Unavailable` so that the traffic is
+//! steered away from the server.
+//!
+//! ```rust
+//! use saphir::{health::HealthController, prelude::*};
+//! # struct Db;
+//! # impl Db { async fn ping(&self) -> bool { true } }
+//!
+//! let db = std::sync::Arc::new(Db);
+//! let server = Server::builder()
+//!     .configure_router(|r| {
+//!         r.controller(HealthController::new().ready_path("/readyz").readiness(move || {
+//!             let db = db.clone();
+//!             async move { db.ping().await }
+//!         }))
+//!     })
+//!     .build();
+//! ```
+
+use crate::{
+    controller::{Controller, ControllerEndpoint, EndpointsBuilder},
+    request::Request,
+    server::SeverShutdownState,
+};
+use futures::future::{BoxFuture, FutureExt};
+use http::{Method, StatusCode};
+use std::{future::Future, sync::Arc};
+
+/// Path of the liveness endpoint when none is configured
+pub const DEFAULT_HEALTH_PATH: &str = "/health";
+/// Path of the readiness endpoint when none is configured
+pub const DEFAULT_READY_PATH: &str = "/ready";
+
+/// Controller registering the liveness and readiness endpoints
+pub struct HealthController {
+    health_path: &'static str,
+    ready_path: &'static str,
+    readiness: Option<Box<dyn Fn() -> BoxFuture<'static, bool> + Send + Sync>>,
+}
+
+impl Default for HealthController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HealthController {
+    pub fn new() -> Self {
+        HealthController {
+            health_path: DEFAULT_HEALTH_PATH,
+            ready_path: DEFAULT_READY_PATH,
+            readiness: None,
+        }
+    }
+
+    /// Set the path of the liveness endpoint
+    pub fn health_path(mut self, path: &'static str) -> Self {
+        self.health_path = path;
+        self
+    }
+
+    /// Set the path of the readiness endpoint
+    pub fn ready_path(mut self, path: &'static str) -> Self {
+        self.ready_path = path;
+        self
+    }
+
+    /// Check run by every readiness probe, the server is ready when it
+    /// returns `true`. Without a check, the server is ready once it runs.
+    pub fn readiness<F, Fut>(mut self, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        self.readiness = Some(Box::new(move || check().boxed()));
+        self
+    }
+
+    async fn health(&self, _req: Request) -> StatusCode {
+        StatusCode::OK
+    }
+
+    async fn ready(&self, req: Request) -> StatusCode {
+        if req.extensions().get::<Arc<SeverShutdownState>>().is_some_and(|state| state.draining()) {
+            return StatusCode::SERVICE_UNAVAILABLE;
+        }
+        match &self.readiness {
+            Some(check) if !check().await => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::OK,
+        }
+    }
+}
+
+impl Controller for HealthController {
+    const BASE_PATH: &'static str = "";
+
+    fn handlers(&self) -> Vec<ControllerEndpoint<Self>>
+    where
+        Self: Sized,
+    {
+        EndpointsBuilder::new()
+            .add(Method::GET, self.health_path, HealthController::health)
+            .add(Method::GET, self.ready_path, HealthController::ready)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{server::Server, test::TestServer};
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[tokio::test]
+    async fn probes_report_the_server_state() {
+        let db_up = Arc::new(AtomicBool::new(true));
+        let check = db_up.clone();
+        let server = TestServer::new(Server::builder().configure_router(|r| {
+            r.controller(HealthController::new().health_path("/live").readiness(move || {
+                let check = check.clone();
+                async move { check.load(Ordering::SeqCst) }
+            }))
+        }))
+        .unwrap();
+        let client = server.client();
+
+        assert_eq!(client.get("/live").send().await.unwrap().status(), 200);
+        assert_eq!(client.get("/health").send().await.unwrap().status(), 404);
+        assert_eq!(client.get("/ready").send().await.unwrap().status(), 200);
+
+        db_up.store(false, Ordering::SeqCst);
+        assert_eq!(client.get("/ready").send().await.unwrap().status(), 503);
+        assert_eq!(client.get("/live").send().await.unwrap().status(), 200);
+
+        db_up.store(true, Ordering::SeqCst);
+        assert_eq!(client.get("/ready").send().await.unwrap().status(), 200);
+    }
+
+    #[tokio::test]
+    async fn draining_server_is_not_ready() {
+        let controller = HealthController::new();
+        let state = Arc::new(SeverShutdownState::default());
+        let req = || {
+            let mut req = Request::new(http::Request::get("/ready").body(crate::body::Body::empty()).unwrap(), None);
+            req.extensions_mut().insert(state.clone());
+            req
+        };
+
+        assert_eq!(controller.ready(req()).await, StatusCode::OK);
+        state.draining.store(true, Ordering::SeqCst);
+        assert_eq!(controller.ready(req()).await, StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
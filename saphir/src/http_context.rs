@@ -1,4 +1,15 @@
-use crate::{request::Request, response::Response, router::Router};
+use crate::{
+    extension::{ExtError, Extensions},
+    request::{FromRequest, Request},
+    response::Response,
+    router::Router,
+};
+use std::{
+    fmt::{Display, Formatter},
+    ops::Deref,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 #[cfg(feature = "operation")]
 #[cfg_attr(docsrs, doc(cfg(feature = "operation")))]
@@ -75,7 +86,7 @@ impl State {
 
     /// Returns `Some` of the current request as a mutable ref if state if
     /// `Before`
-    pub fn request_mut(&mut self) -> Option<&Request> {
+    pub fn request_mut(&mut self) -> Option<&mut Request> {
         match self {
             State::Before(r) => Some(r),
             _ => None,
@@ -167,6 +178,12 @@ impl Default for RouteId {
 pub struct HandlerMetadata {
     pub route_id: RouteId,
     pub name: Option<&'static str>,
+    /// Name of the controller the handler belongs to, `None` for the handlers
+    /// which are not part of a controller
+    pub controller: Option<&'static str>,
+    /// Path the handler was registered with, e.g. `/users/{id}`. `None` when
+    /// no route matched the request
+    pub route_template: Option<Arc<str>>,
 }
 
 impl HandlerMetadata {
@@ -174,6 +191,8 @@ impl HandlerMetadata {
         HandlerMetadata {
             route_id: Default::default(),
             name: None,
+            controller: None,
+            route_template: None,
         }
     }
 
@@ -181,8 +200,104 @@ impl HandlerMetadata {
         HandlerMetadata {
             route_id: RouteId::Error(405),
             name: None,
+            controller: None,
+            route_template: None,
         }
     }
+
+    pub(crate) fn trailing_slash_redirect() -> Self {
+        HandlerMetadata {
+            route_id: RouteId::Error(308),
+            name: None,
+            controller: None,
+            route_template: None,
+        }
+    }
+
+    pub(crate) fn options() -> Self {
+        HandlerMetadata {
+            route_id: RouteId::Error(204),
+            name: None,
+            controller: None,
+            route_template: None,
+        }
+    }
+}
+
+/// Route template which matched a request, e.g. `/users/{id}`.
+///
+/// The router inserts it in the extensions of the requests it dispatches, so
+/// it is available to guards and handlers, either from the extensions or as
+/// an extractor. Middlewares get it from [`HttpContext::matched_route`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct MatchedRoute(Arc<str>);
+
+impl MatchedRoute {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for MatchedRoute {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Display for MatchedRoute {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromRequest for MatchedRoute {
+    type Err = ExtError;
+    type Fut = futures::future::Ready<Result<Self, Self::Err>>;
+
+    fn from_request(req: &mut Request) -> Self::Fut {
+        futures::future::ready(
+            req.extensions()
+                .get::<MatchedRoute>()
+                .cloned()
+                .ok_or_else(|| ExtError::MissingExtension(std::any::type_name::<MatchedRoute>())),
+        )
+    }
+}
+
+/// Time at which the server started handling a request, before any
+/// middleware ran.
+///
+/// It is inserted in the extensions of every request dispatched by the server,
+/// so it is available to guards and handlers, either from the extensions or as
+/// an extractor. Middlewares get it from [`HttpContext::started_at`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct RequestStart(Instant);
+
+impl RequestStart {
+    pub fn instant(&self) -> Instant {
+        self.0
+    }
+
+    /// Time the request has been in flight for
+    pub fn elapsed(&self) -> Duration {
+        self.0.elapsed()
+    }
+}
+
+impl FromRequest for RequestStart {
+    type Err = ExtError;
+    type Fut = futures::future::Ready<Result<Self, Self::Err>>;
+
+    fn from_request(req: &mut Request) -> Self::Fut {
+        futures::future::ready(
+            req.extensions()
+                .get::<RequestStart>()
+                .copied()
+                .ok_or_else(|| ExtError::MissingExtension(std::any::type_name::<RequestStart>())),
+        )
+    }
 }
 
 /// Context representing the relationship between a request and a response
@@ -202,21 +317,29 @@ pub struct HttpContext {
     pub operation_id: crate::http_context::operation::OperationId,
     pub metadata: HandlerMetadata,
     pub(crate) router: Option<Router>,
+    started_at: Instant,
 }
 
 impl HttpContext {
-    pub(crate) fn new(request: Request, router: Router, metadata: HandlerMetadata) -> Self {
+    pub(crate) fn new(mut request: Request, router: Router, metadata: HandlerMetadata) -> Self {
+        let started_at = Instant::now();
+        request.extensions_mut().insert(RequestStart(started_at));
+
         #[cfg(not(feature = "operation"))]
         {
             let state = State::Before(Box::new(request));
             let router = Some(router);
-            HttpContext { state, metadata, router }
+            HttpContext {
+                state,
+                metadata,
+                router,
+                started_at,
+            }
         }
 
         #[cfg(feature = "operation")]
         {
             use std::str::FromStr;
-            let mut request = request;
             let operation_id = request
                 .headers()
                 .get(OPERATION_ID_HEADER)
@@ -231,10 +354,20 @@ impl HttpContext {
                 operation_id,
                 metadata,
                 router,
+                started_at,
             }
         }
     }
 
+    /// Replace the operation id of the context and of its request
+    #[cfg(feature = "operation")]
+    pub(crate) fn set_operation_id(&mut self, operation_id: operation::OperationId) {
+        self.operation_id = operation_id;
+        if let Some(request) = self.state.request_mut() {
+            *request.operation_id_mut() = operation_id;
+        }
+    }
+
     pub fn clone_with_empty_state(&self) -> Self {
         HttpContext {
             state: State::Empty,
@@ -242,9 +375,88 @@ impl HttpContext {
             metadata: self.metadata.clone(),
             #[cfg(feature = "operation")]
             operation_id: self.operation_id,
+            started_at: self.started_at,
+        }
+    }
+
+    /// Time at which the server started handling the request, before any
+    /// middleware ran
+    pub fn started_at(&self) -> Instant {
+        self.started_at
+    }
+
+    /// Time the request has been in flight for, middlewares included
+    pub fn elapsed(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    /// Route template which matched the request, `None` when no route did
+    pub fn matched_route(&self) -> Option<MatchedRoute> {
+        self.metadata.route_template.clone().map(MatchedRoute)
+    }
+
+    /// Typed values scoped to the request: the extensions of the request
+    /// `Before` it is handled, then those of the response `After`
+    fn extensions(&self) -> Option<&Extensions> {
+        match &self.state {
+            State::Before(req) => Some(req.extensions()),
+            State::After(res) => Some(res.extensions()),
+            State::Empty => None,
         }
     }
 
+    fn extensions_mut(&mut self) -> Option<&mut Extensions> {
+        match &mut self.state {
+            State::Before(req) => Some(req.extensions_mut()),
+            State::After(res) => Some(res.extensions_mut()),
+            State::Empty => None,
+        }
+    }
+
+    /// Insert a value in the request scoped store, returning the value of the
+    /// same type it replaces.
+    ///
+    /// Values inserted by a middleware before calling the next one are
+    /// visible to the following middlewares, to the guards and to the handler,
+    /// which gets them with an [`Ext<T>`](crate::extension::Ext) parameter
+    /// or from the extensions of its request. After the response is produced,
+    /// the store holds the extensions of the response instead. Nothing is
+    /// inserted if the state was moved out of the context.
+    /// ```rust
+    /// # use saphir::prelude::*;
+    /// struct User(String);
+    ///
+    /// async fn authenticate(mut ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+    ///     let user = ctx.state.request().and_then(|r| r.headers().get("x-user")).and_then(|h| h.to_str().ok()).map(|u| User(u.to_string()));
+    ///     if let Some(user) = user {
+    ///         ctx.insert(user);
+    ///     }
+    ///     chain.next(ctx).await
+    /// }
+    ///
+    /// async fn me(req: Request) -> String {
+    ///     req.extensions().get::<User>().map(|u| u.0.clone()).unwrap_or_default()
+    /// }
+    /// ```
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.extensions_mut().and_then(|e| e.insert(value))
+    }
+
+    /// Get a value of the request scoped store, see [`insert`](Self::insert)
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.extensions().and_then(|e| e.get::<T>())
+    }
+
+    /// Get a value of the request scoped store mutably
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.extensions_mut().and_then(|e| e.get_mut::<T>())
+    }
+
+    /// Remove a value from the request scoped store
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.extensions_mut().and_then(|e| e.remove::<T>())
+    }
+
     /// Explicitly set the inner state to `Before` with the given response
     pub fn before(&mut self, request: Request) {
         self.state = State::Before(Box::new(request))
@@ -259,6 +471,7 @@ impl HttpContext {
 #[cfg(feature = "operation")]
 #[cfg_attr(docsrs, doc(cfg(feature = "operation")))]
 pub mod operation {
+    use crate::request::Request;
     use serde::{de::Visitor, Deserialize, Deserializer, Serialize, Serializer};
     use std::{
         fmt::{Debug, Display, Formatter},
@@ -279,6 +492,30 @@ pub mod operation {
         pub fn to_u128(self) -> u128 {
             self.0.as_u128()
         }
+
+        /// Operation id from its 128 bits representation, e.g. a ULID or a
+        /// sequence number
+        pub fn from_u128(id: u128) -> OperationId {
+            OperationId(Uuid::from_u128(id))
+        }
+    }
+
+    /// Strategy producing the [`OperationId`] of every incoming request,
+    /// configured with
+    /// [`Builder::operation_id_generator`](crate::server::Builder::operation_id_generator).
+    ///
+    /// It is implemented for closures taking the request.
+    pub trait OperationIdGenerator: Send + Sync {
+        fn generate(&self, req: &Request) -> OperationId;
+    }
+
+    impl<F> OperationIdGenerator for F
+    where
+        F: Fn(&Request) -> OperationId + Send + Sync,
+    {
+        fn generate(&self, req: &Request) -> OperationId {
+            self(req)
+        }
     }
 
     impl Display for OperationId {
@@ -343,3 +580,37 @@ pub mod operation {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::SaphirError, middleware::MiddlewareChain, server::Server, test::TestServer};
+    use http::Method;
+
+    async fn slow(ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        chain.next(ctx).await
+    }
+
+    async fn in_flight(mut req: Request) -> Result<String, SaphirError> {
+        let start = RequestStart::from_request(&mut req).await.map_err(SaphirError::responder)?;
+        Ok(start.elapsed().as_millis().to_string())
+    }
+
+    #[tokio::test]
+    async fn elapsed_time_covers_middlewares() {
+        for timeout in [None, Some(1000)] {
+            let client = TestServer::new(
+                Server::builder()
+                    .configure_listener(|l| l.request_timeout(timeout))
+                    .configure_middlewares(|m| m.apply(slow, vec!["/**"], None))
+                    .configure_router(|r| r.route("/in-flight", Method::GET, in_flight)),
+            )
+            .unwrap()
+            .client();
+
+            let res = client.get("/in-flight").send().await.unwrap();
+            assert!(res.text().unwrap().parse::<u128>().unwrap() >= 20);
+        }
+    }
+}
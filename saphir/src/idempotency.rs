@@ -0,0 +1,539 @@
+//! Idempotent retries of unsafe requests.
+//!
+//! Clients retrying a request whose response was lost, e.g. a payment, send
+//! the same `Idempotency-Key` header with every attempt. The
+//! [`IdempotencyMiddleware`] runs the first request carrying a key and stores
+//! its response, keyed by the key, the method and the path of the request.
+//! The following requests with the same key are answered with the stored
+//! response, along with an `Idempotent-Replayed: true` header, without
+//! reaching the handler again.
+//!
+//! ```rust
+//! use saphir::{idempotency::IdempotencyMiddleware, prelude::*};
+//! use std::time::Duration;
+//!
+//! let server = Server::builder()
+//!     .configure_middlewares(|m| m.apply(IdempotencyMiddleware::new().ttl(Duration::from_secs(3600)), vec!["/payments/**"], None))
+//!     .build();
+//! ```
+//!
+//! - Requests with a safe method, like `GET`, and requests without the header
+//!   go through untouched.
+//! - A request sent while the first one with the same key is still in
+//!   progress is answered with `409 Conflict`, so that the client retries it
+//!   later.
+//! - Server errors (`5xx`) are not stored, nor are the requests interrupted
+//!   before completing: their key is released so that a retry runs the
+//!   request again.
+//!
+//! Responses are buffered in memory to be stored, so the middleware should
+//! not be applied to endpoints streaming large bodies. They are kept in a
+//! bounded [`InMemoryStore`] by default, and are therefore per server
+//! instance. Another backend, e.g.
+//! shared between instances, can be used by implementing
+//! [`IdempotencyStore`].
+
+use crate::{
+    body::Body,
+    error::SaphirError,
+    http_context::HttpContext,
+    middleware::{Middleware, MiddlewareChain},
+    response::Response,
+};
+use futures::future::{BoxFuture, FutureExt};
+use http::{header::HeaderName, HeaderMap, HeaderValue, StatusCode};
+use hyper::body::Bytes;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Stored responses are kept for a day by default
+const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Most keys kept by the in-memory store by default
+const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+/// Most bytes of response bodies kept by the in-memory store by default
+const DEFAULT_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+/// Response of a request, replayed for its retries
+#[derive(Clone, Debug)]
+pub struct StoredResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl StoredResponse {
+    fn into_replay(self) -> Response {
+        let mut res = Response::new(Body::from_raw(self.body.into()));
+        *res.status_mut() = self.status;
+        *res.headers_mut() = self.headers;
+        res.headers_mut()
+            .insert(HeaderName::from_static("idempotent-replayed"), HeaderValue::from_static("true"));
+        res
+    }
+}
+
+/// State of a key, when a request tries to reserve it
+#[derive(Clone, Debug)]
+pub enum Reservation {
+    /// The key was free and is now reserved by the request
+    Acquired,
+    /// Another request holding the key is in progress
+    InFlight,
+    /// The request holding the key completed with this response
+    Completed(StoredResponse),
+}
+
+/// Storage of the idempotency keys and of the responses of their requests.
+///
+/// A key is reserved by the first request carrying it, then either completed
+/// with its response or released if the request could not complete. Keys
+/// reserved or completed are kept for `ttl`.
+pub trait IdempotencyStore: Send + Sync {
+    fn reserve<'a>(&'a self, key: &'a str, ttl: Duration) -> BoxFuture<'a, Result<Reservation, SaphirError>>;
+
+    fn complete<'a>(&'a self, key: &'a str, response: StoredResponse, ttl: Duration) -> BoxFuture<'a, Result<(), SaphirError>>;
+
+    fn release<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<(), SaphirError>>;
+}
+
+struct Entry {
+    response: Option<StoredResponse>,
+    expires: Instant,
+}
+
+#[derive(Default)]
+struct Entries {
+    map: HashMap<String, Entry>,
+    /// Keys in the order they were stored, along with the expiry they were
+    /// stored with, which tells apart the keys stored again since
+    order: VecDeque<(Instant, String)>,
+    bytes: usize,
+}
+
+impl Entries {
+    fn insert(&mut self, key: &str, entry: Entry) {
+        self.order.push_back((entry.expires, key.to_string()));
+        self.bytes += entry.response.as_ref().map_or(0, |res| res.body.len());
+        if let Some(old) = self.map.insert(key.to_string(), entry) {
+            self.bytes -= old.response.map_or(0, |res| res.body.len());
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(old) = self.map.remove(key) {
+            self.bytes -= old.response.map_or(0, |res| res.body.len());
+        }
+    }
+
+    /// Remove the oldest entries while they are expired or the store is over
+    /// its bounds. Each key being queued once per write, this is amortized
+    /// constant time.
+    fn evict(&mut self, now: Instant, max_entries: usize, max_bytes: usize) {
+        while let Some((expires, key)) = self.order.front() {
+            let current = self.map.get(key).is_some_and(|entry| entry.expires == *expires);
+            if current && *expires > now && self.map.len() <= max_entries && self.bytes <= max_bytes {
+                break;
+            }
+            if let Some((_, key)) = self.order.pop_front() {
+                if current {
+                    self.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+/// Keys and responses kept in the server memory.
+///
+/// The store holds at most 10 000 keys and 64 MiB of response bodies by
+/// default, the oldest entries being evicted first when it is full.
+pub struct InMemoryStore {
+    entries: Mutex<Entries>,
+    max_entries: usize,
+    max_bytes: usize,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        InMemoryStore {
+            entries: Mutex::new(Entries::default()),
+            max_entries: DEFAULT_MAX_ENTRIES,
+            max_bytes: DEFAULT_MAX_BYTES,
+        }
+    }
+
+    /// Set the most keys kept, including the ones of requests in progress
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Set the most bytes of response bodies kept. Larger responses are not
+    /// stored, their key being released.
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    fn reserve_at(&self, key: &str, ttl: Duration, now: Instant) -> Reservation {
+        let mut entries = self.entries.lock().expect("idempotency store lock poisoned");
+
+        match entries.map.get(key) {
+            Some(entry) if entry.expires > now => match &entry.response {
+                Some(response) => Reservation::Completed(response.clone()),
+                None => Reservation::InFlight,
+            },
+            _ => {
+                entries.insert(
+                    key,
+                    Entry {
+                        response: None,
+                        expires: now + ttl,
+                    },
+                );
+                entries.evict(now, self.max_entries, self.max_bytes);
+                Reservation::Acquired
+            }
+        }
+    }
+
+    fn complete_at(&self, key: &str, response: StoredResponse, ttl: Duration, now: Instant) {
+        let mut entries = self.entries.lock().expect("idempotency store lock poisoned");
+        if response.body.len() > self.max_bytes {
+            entries.remove(key);
+            return;
+        }
+        entries.insert(
+            key,
+            Entry {
+                response: Some(response),
+                expires: now + ttl,
+            },
+        );
+        entries.evict(now, self.max_entries, self.max_bytes);
+    }
+}
+
+impl Default for InMemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IdempotencyStore for InMemoryStore {
+    fn reserve<'a>(&'a self, key: &'a str, ttl: Duration) -> BoxFuture<'a, Result<Reservation, SaphirError>> {
+        futures::future::ready(Ok(self.reserve_at(key, ttl, Instant::now()))).boxed()
+    }
+
+    fn complete<'a>(&'a self, key: &'a str, response: StoredResponse, ttl: Duration) -> BoxFuture<'a, Result<(), SaphirError>> {
+        self.complete_at(key, response, ttl, Instant::now());
+        futures::future::ready(Ok(())).boxed()
+    }
+
+    fn release<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<(), SaphirError>> {
+        self.entries.lock().expect("idempotency store lock poisoned").remove(key);
+        futures::future::ready(Ok(())).boxed()
+    }
+}
+
+/// Key reserved by a request in progress, released when dropped before the
+/// response is stored, e.g. when the client disconnects
+struct Reserved {
+    store: &'static dyn IdempotencyStore,
+    key: Option<String>,
+}
+
+impl Drop for Reserved {
+    fn drop(&mut self) {
+        if let (Some(key), Ok(handle)) = (self.key.take(), tokio::runtime::Handle::try_current()) {
+            let store = self.store;
+            handle.spawn(async move {
+                if let Err(e) = store.release(&key).await {
+                    warn!("Unable to release idempotency key {}: {}", key, e);
+                }
+            });
+        }
+    }
+}
+
+/// Middleware replaying the stored response of the requests retried with the
+/// same idempotency key
+pub struct IdempotencyMiddleware {
+    store: Box<dyn IdempotencyStore>,
+    ttl: Duration,
+    header: HeaderName,
+}
+
+impl IdempotencyMiddleware {
+    /// Read the key from the `Idempotency-Key` header and keep the responses
+    /// in memory for a day
+    pub fn new() -> Self {
+        IdempotencyMiddleware {
+            store: Box::new(InMemoryStore::new()),
+            ttl: DEFAULT_TTL,
+            header: HeaderName::from_static("idempotency-key"),
+        }
+    }
+
+    /// Set how long the responses are replayed
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Set the header the key is read from
+    ///
+    /// # Panics
+    /// Panics if `header` is not a valid header name
+    pub fn header_name(mut self, header: &str) -> Self {
+        self.header = HeaderName::from_bytes(header.as_bytes()).expect("invalid idempotency key header name");
+        self
+    }
+
+    /// Keep the keys and responses in another store
+    pub fn store<S: IdempotencyStore + 'static>(mut self, store: S) -> Self {
+        self.store = Box::new(store);
+        self
+    }
+
+    async fn next_inner(&'static self, mut ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+        let key = ctx.state.request().filter(|req| !req.method().is_safe()).and_then(|req| {
+            let key = req.headers().get(&self.header)?.to_str().ok()?;
+            Some(format!("{} {} {}", req.method(), req.uri().path(), key))
+        });
+        let key = if let Some(key) = key {
+            key
+        } else {
+            return chain.next(ctx).await;
+        };
+
+        match self.store.reserve(&key, self.ttl).await? {
+            Reservation::Acquired => {}
+            Reservation::InFlight => {
+                return Err(SaphirError::Handler(
+                    StatusCode::CONFLICT,
+                    "A request with the same idempotency key is in progress".into(),
+                ))
+            }
+            Reservation::Completed(stored) => {
+                ctx.after(stored.into_replay());
+                return Ok(ctx);
+            }
+        }
+
+        // Until the response is stored, the key is released if anything fails
+        // or the client disconnects
+        let mut reserved = Reserved {
+            store: &*self.store,
+            key: Some(key.clone()),
+        };
+        let mut ctx = chain.next_with_response(ctx).await?;
+
+        if let Some(res) = ctx.state.response_mut().filter(|res| !res.status().is_server_error()) {
+            let body = hyper::body::to_bytes(res.body_mut().take().into_raw()).await?;
+            *res.body_mut() = Body::from_raw(body.clone().into());
+            let stored = StoredResponse {
+                status: res.status(),
+                headers: res.headers().clone(),
+                body,
+            };
+            self.store.complete(&key, stored, self.ttl).await?;
+        } else {
+            self.store.release(&key).await?;
+        }
+        reserved.key = None;
+
+        Ok(ctx)
+    }
+}
+
+impl Default for IdempotencyMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Middleware for IdempotencyMiddleware {
+    fn next(&'static self, ctx: HttpContext, chain: &'static dyn MiddlewareChain) -> BoxFuture<'static, Result<HttpContext, SaphirError>> {
+        self.next_inner(ctx, chain).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{request::Request, server::Server, test::TestServer};
+    use http::Method;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    use tokio::sync::Notify;
+
+    static PAYMENTS: AtomicUsize = AtomicUsize::new(0);
+
+    /// Takes a while so that concurrent requests overlap, the tests run with a
+    /// paused clock
+    async fn pay(req: Request) -> (u16, String) {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let status = if req.uri().query() == Some("fail") { 503 } else { 201 };
+        (status, format!("payment {}", PAYMENTS.fetch_add(1, Ordering::SeqCst)))
+    }
+
+    fn stored(body: &'static str) -> StoredResponse {
+        StoredResponse {
+            status: StatusCode::CREATED,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(body.as_bytes()),
+        }
+    }
+
+    #[test]
+    fn keys_are_reserved_then_completed() {
+        let store = InMemoryStore::new();
+        let ttl = Duration::from_secs(10);
+        let now = Instant::now();
+
+        assert!(matches!(store.reserve_at("a", ttl, now), Reservation::Acquired));
+        assert!(matches!(store.reserve_at("a", ttl, now), Reservation::InFlight));
+        assert!(matches!(store.reserve_at("b", ttl, now), Reservation::Acquired));
+
+        store.complete_at("a", stored("done"), ttl, now);
+        assert!(matches!(store.reserve_at("a", ttl, now), Reservation::Completed(res) if res.body == "done"));
+        assert!(matches!(store.reserve_at("a", ttl, now + ttl), Reservation::Acquired));
+    }
+
+    #[test]
+    fn stores_are_bounded() {
+        let ttl = Duration::from_secs(10);
+        let now = Instant::now();
+
+        let store = InMemoryStore::new().max_entries(2);
+        for key in ["a", "b", "c"] {
+            assert!(matches!(store.reserve_at(key, ttl, now), Reservation::Acquired));
+        }
+        assert!(matches!(store.reserve_at("c", ttl, now), Reservation::InFlight));
+        assert!(matches!(store.reserve_at("a", ttl, now), Reservation::Acquired));
+
+        let store = InMemoryStore::new().max_bytes(6);
+        store.complete_at("a", stored("done"), ttl, now);
+        store.complete_at("b", stored("done"), ttl, now);
+        assert!(matches!(store.reserve_at("a", ttl, now), Reservation::Acquired));
+        assert!(matches!(store.reserve_at("b", ttl, now), Reservation::Completed(_)));
+        store.complete_at("c", stored("too large"), ttl, now);
+        assert!(matches!(store.reserve_at("c", ttl, now), Reservation::Acquired));
+    }
+
+    /// Store failing to complete the first key, as when the connection to a
+    /// shared store is lost
+    struct FailingStore {
+        inner: InMemoryStore,
+        failed: AtomicUsize,
+        released: Arc<Notify>,
+    }
+
+    impl IdempotencyStore for FailingStore {
+        fn reserve<'a>(&'a self, key: &'a str, ttl: Duration) -> BoxFuture<'a, Result<Reservation, SaphirError>> {
+            self.inner.reserve(key, ttl)
+        }
+
+        fn complete<'a>(&'a self, key: &'a str, response: StoredResponse, ttl: Duration) -> BoxFuture<'a, Result<(), SaphirError>> {
+            if self.failed.fetch_add(1, Ordering::SeqCst) == 0 {
+                return futures::future::ready(Err(SaphirError::Other("store unavailable".to_string()))).boxed();
+            }
+            self.inner.complete(key, response, ttl)
+        }
+
+        fn release<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<(), SaphirError>> {
+            async move {
+                self.inner.release(key).await?;
+                self.released.notify_one();
+                Ok(())
+            }
+            .boxed()
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn keys_are_released_when_the_response_is_not_stored() {
+        async fn interrupted(_req: Request) -> crate::responder::StreamResponder<impl futures::Stream<Item = Result<Bytes, std::io::Error>> + Send> {
+            crate::responder::StreamResponder::new(futures::stream::iter(vec![
+                Ok(Bytes::from_static(b"partial")),
+                Err(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "disconnected")),
+            ]))
+        }
+
+        let released = Arc::new(Notify::new());
+        let store = FailingStore {
+            inner: InMemoryStore::new(),
+            failed: AtomicUsize::new(0),
+            released: released.clone(),
+        };
+        let client = TestServer::new(
+            Server::builder()
+                .configure_middlewares(|m| m.apply(IdempotencyMiddleware::new().store(store), vec!["/**"], None))
+                .configure_router(|r| r.route("/pay", Method::POST, pay).route("/interrupted", Method::POST, interrupted)),
+        )
+        .unwrap()
+        .client();
+
+        let failed = client.post("/pay").header("Idempotency-Key", "a").send().await.unwrap();
+        assert_eq!(failed.status(), 500);
+        released.notified().await;
+        let retry = client.post("/pay").header("Idempotency-Key", "a").send().await.unwrap();
+        assert_eq!(retry.status(), 201);
+        assert_eq!(retry.header("Idempotent-Replayed"), None);
+
+        for _ in 0..2 {
+            let res = client.post("/interrupted").header("Idempotency-Key", "b").send().await.unwrap();
+            assert_ne!(res.status(), 409);
+            released.notified().await;
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_replay_the_first_response() {
+        let client = TestServer::new(
+            Server::builder()
+                .configure_middlewares(|m| m.apply(IdempotencyMiddleware::new(), vec!["/**"], None))
+                .configure_router(|r| r.route("/pay", Method::POST, pay).route("/pay", Method::GET, pay)),
+        )
+        .unwrap()
+        .client();
+
+        let first = client.post("/pay").header("Idempotency-Key", "a").send().await.unwrap();
+        assert_eq!(first.status(), 201);
+        assert_eq!(first.header("Idempotent-Replayed"), None);
+        let first = first.text().unwrap();
+
+        let retry = client.post("/pay").header("Idempotency-Key", "a").send().await.unwrap();
+        assert_eq!(retry.status(), 201);
+        assert_eq!(retry.header("Idempotent-Replayed"), Some("true"));
+        assert_eq!(retry.text().unwrap(), first);
+
+        let other = client.post("/pay").header("Idempotency-Key", "b").send().await.unwrap();
+        assert_ne!(other.text().unwrap(), first);
+        let get = client.get("/pay").header("Idempotency-Key", "a").send().await.unwrap();
+        assert_eq!(get.header("Idempotent-Replayed"), None);
+
+        let (res, concurrent) = tokio::join!(
+            client.post("/pay").header("Idempotency-Key", "c").send(),
+            client.post("/pay").header("Idempotency-Key", "c").send()
+        );
+        let mut statuses = [res.unwrap().status().as_u16(), concurrent.unwrap().status().as_u16()];
+        statuses.sort();
+        assert_eq!(statuses, [201, 409]);
+
+        let failed = client.post("/pay?fail").header("Idempotency-Key", "d").send().await.unwrap();
+        assert_eq!(failed.status(), 503);
+        let failed = failed.text().unwrap();
+        let retry = client.post("/pay?fail").header("Idempotency-Key", "d").send().await.unwrap();
+        assert_eq!(retry.header("Idempotent-Replayed"), None);
+        assert_ne!(retry.text().unwrap(), failed);
+    }
+}
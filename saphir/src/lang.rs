@@ -0,0 +1,266 @@
+//! Language negotiation.
+//!
+//! The [`LangMiddleware`] picks, among the languages supported by the
+//! application, the one preferred by the client according to the
+//! `Accept-Language` header of the request, falling back to a default
+//! language. The result is available to the handlers through the [`Lang`]
+//! extractor.
+//!
+//! Language ranges are ranked by their `q` value, then by their order in the
+//! header. A range matches a supported language equal to it, e.g. `en-US`,
+//! or more specific than it, e.g. `en` matches `en-GB`; otherwise its last
+//! subtag is dropped and the shorter range is tried, so `fr-CH` matches `fr`.
+//! The `*` range matches the default language, and ranges with `q=0` exclude
+//! the languages they match. Malformed entries are ignored.
+//!
+//! `Accept-Language` is added to the `Vary` header of the responses, so that
+//! caches keep a response per language.
+//!
+//! ```rust
+//! # #[macro_use] extern crate saphir_macro;
+//! # use saphir::{lang::LangMiddleware, prelude::*, test::TestServer};
+//! struct PageController {}
+//!
+//! #[controller(name = "page")]
+//! impl PageController {
+//!     #[get("/")]
+//!     async fn page(&self, lang: Lang) -> &'static str {
+//!         match lang.as_str() {
+//!             "fr" => "Bonjour",
+//!             _ => "Hello",
+//!         }
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), SaphirError> {
+//! let client = TestServer::new(
+//!     Server::builder()
+//!         .configure_middlewares(|m| m.apply(LangMiddleware::new("en").supported(["fr", "en-GB"]), vec!["/"], None))
+//!         .configure_router(|r| r.controller(PageController {})),
+//! )?
+//! .client();
+//!
+//! let res = client.get("/page").header("accept-language", "fr-CA, en;q=0.8").send().await?;
+//! assert_eq!(res.header("vary"), Some("accept-language"));
+//! assert_eq!(res.text()?, "Bonjour");
+//! let res = client.get("/page").header("accept-language", "de").send().await?;
+//! assert_eq!(res.text()?, "Hello");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    error::SaphirError,
+    extension::ExtError,
+    http_context::HttpContext,
+    middleware::{Middleware, MiddlewareChain},
+    request::{FromRequest, Request},
+    response::append_vary,
+};
+use futures::future::{BoxFuture, FutureExt};
+use http::{header, HeaderMap};
+use std::{
+    fmt::{Display, Formatter},
+    ops::Deref,
+};
+
+/// Language negotiated for a request, as configured on the [`LangMiddleware`]
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Lang(String);
+
+impl Lang {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl Deref for Lang {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Display for Lang {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromRequest for Lang {
+    type Err = ExtError;
+    type Fut = futures::future::Ready<Result<Self, Self::Err>>;
+
+    fn from_request(req: &mut Request) -> Self::Fut {
+        futures::future::ready(
+            req.extensions()
+                .get::<Lang>()
+                .cloned()
+                .ok_or_else(|| ExtError::MissingExtension(std::any::type_name::<Lang>())),
+        )
+    }
+}
+
+/// Middleware negotiating the [`Lang`] of every request
+pub struct LangMiddleware {
+    default: String,
+    supported: Vec<String>,
+}
+
+impl LangMiddleware {
+    /// Create a middleware supporting only the `default` language
+    pub fn new(default: &str) -> Self {
+        LangMiddleware {
+            default: default.to_string(),
+            supported: vec![default.to_string()],
+        }
+    }
+
+    /// Add languages supported by the application, in addition to the
+    /// default one
+    pub fn supported<I, S>(mut self, languages: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        for language in languages {
+            let language = language.into();
+            if !self.supported.iter().any(|s| s.eq_ignore_ascii_case(&language)) {
+                self.supported.push(language);
+            }
+        }
+        self
+    }
+
+    /// Pick the supported language best matching the `Accept-Language`
+    /// headers, or the default one
+    pub fn negotiate(&self, headers: &HeaderMap) -> Lang {
+        let mut ranges: Vec<(&str, f32)> = headers
+            .get_all(header::ACCEPT_LANGUAGE)
+            .iter()
+            .filter_map(|h| h.to_str().ok())
+            .flat_map(|h| h.split(','))
+            .filter_map(parse_range)
+            .collect();
+        ranges.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+        let excluded: Vec<&str> = ranges.iter().filter(|(_, q)| *q == 0.0).map(|(range, _)| *range).collect();
+        let allowed = |language: &&String| !excluded.iter().any(|range| matches(range, language));
+
+        let language = ranges
+            .iter()
+            .filter(|(_, q)| *q > 0.0)
+            .find_map(|(range, _)| {
+                if *range == "*" {
+                    return Some(&self.default).filter(allowed).or_else(|| self.supported.iter().find(allowed));
+                }
+
+                let mut range = *range;
+                loop {
+                    if let Some(language) = self.supported.iter().filter(allowed).find(|s| matches(range, s)) {
+                        return Some(language);
+                    }
+                    range = &range[..range.rfind('-')?];
+                }
+            })
+            .unwrap_or(&self.default);
+
+        Lang(language.clone())
+    }
+
+    async fn next_inner(&self, mut ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+        if let Some(req) = ctx.state.request_mut() {
+            let lang = self.negotiate(req.headers());
+            req.extensions_mut().insert(lang);
+        }
+
+        let mut ctx = chain.next(ctx).await?;
+        if let Some(res) = ctx.state.response_mut() {
+            append_vary(res.headers_mut(), &header::ACCEPT_LANGUAGE);
+        }
+        Ok(ctx)
+    }
+}
+
+impl Middleware for LangMiddleware {
+    fn next(&'static self, ctx: HttpContext, chain: &'static dyn MiddlewareChain) -> BoxFuture<'static, Result<HttpContext, SaphirError>> {
+        self.next_inner(ctx, chain).boxed()
+    }
+}
+
+/// Parse a `language-range;q=value` entry, `None` when it is malformed
+fn parse_range(entry: &str) -> Option<(&str, f32)> {
+    let mut parts = entry.split(';').map(str::trim);
+    let range = parts.next().filter(|r| is_language_range(r))?;
+    let mut q = 1.0;
+    for param in parts {
+        let (key, value) = param.split_once('=')?;
+        if key.trim().eq_ignore_ascii_case("q") {
+            q = value.trim().parse::<f32>().ok().filter(|q| (0.0..=1.0).contains(q))?;
+        }
+    }
+
+    Some((range, q))
+}
+
+fn is_language_range(range: &str) -> bool {
+    range == "*"
+        || range.split('-').enumerate().all(|(i, subtag)| {
+            (1..=8).contains(&subtag.len())
+                && if i == 0 {
+                    subtag.bytes().all(|b| b.is_ascii_alphabetic())
+                } else {
+                    subtag.bytes().all(|b| b.is_ascii_alphanumeric())
+                }
+        })
+}
+
+/// Whether `language` is `range` or a more specific language of it
+fn matches(range: &str, language: &str) -> bool {
+    range == "*"
+        || language.eq_ignore_ascii_case(range)
+        || (language.len() > range.len() && language.as_bytes()[range.len()] == b'-' && language[..range.len()].eq_ignore_ascii_case(range))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    fn negotiate(accept_language: &str) -> String {
+        let middleware = LangMiddleware::new("en").supported(["fr", "en-GB", "pt-BR"]);
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT_LANGUAGE, HeaderValue::from_str(accept_language).unwrap());
+        middleware.negotiate(&headers).into_inner()
+    }
+
+    #[test]
+    fn languages_are_negotiated() {
+        assert_eq!(negotiate("fr"), "fr");
+        assert_eq!(negotiate("FR-ca"), "fr");
+        assert_eq!(negotiate("en-gb"), "en-GB");
+        assert_eq!(negotiate("en-US"), "en");
+        assert_eq!(negotiate("pt"), "pt-BR");
+        assert_eq!(negotiate("de, fr;q=0.5, en-GB;q=0.7"), "en-GB");
+        assert_eq!(negotiate("de"), "en");
+        assert_eq!(negotiate("*"), "en");
+        assert_eq!(negotiate("de, *;q=0.1"), "en");
+        assert_eq!(negotiate("en;q=0, *"), "fr");
+        assert_eq!(negotiate(""), "en");
+    }
+
+    #[test]
+    fn malformed_entries_are_ignored() {
+        assert_eq!(negotiate("fr;q=2, pt;q=0.1"), "pt-BR");
+        assert_eq!(negotiate("en_GB, fr;q=0.5"), "fr");
+        assert_eq!(negotiate("fr;q=abc, en-GB;q=0.3"), "en-GB");
+        assert_eq!(negotiate(",, ;q=1, fr-"), "en");
+        assert_eq!(negotiate("toolongsubtag, fr;q=0.9"), "fr");
+    }
+}
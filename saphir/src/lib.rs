@@ -22,7 +22,7 @@
 //!
 //! #[controller]
 //! impl TestController {
-//!     #[get("/{var}/print")]
+//!     #[get("/<var>/print")]
 //!     async fn print_test(&self, var: String) -> (u16, String) {
 //!         (200, var)
 //!     }
@@ -61,9 +61,49 @@
 //!   Recommended and active by default
 //! - `https` : Provide everything to allow Saphir server to listen an accept
 //!   HTTPS traffic
+//! - `http3` : *Experimental*, also accept HTTP/3 traffic on a QUIC listener
+//!   using the same certificate and key as `https`
 //! - `json`  : Add the `Json` wrapper type to simplify working with json data
+//! - `json5` : Add the `Json5` wrapper type, parsing lenient JSON5 bodies
+//!   with comments and trailing commas, and responding with strict json
 //! - `form`  : Add the `Form` wrapper type to simplify working with urlencoded
-//!   data
+//!   data, and the `Query` extractor for query strings
+//! - `msgpack` : Add the `MsgPack` wrapper type to simplify working with
+//!   MessagePack data
+//! - `cbor` : Add the `Cbor` wrapper type to simplify working with CBOR data,
+//!   and with `json` the `Negotiated` responder picking JSON or CBOR from the
+//!   `Accept` header
+//! - `decompression` : Add the `DecompressionMiddleware`, decoding `gzip`,
+//!   `deflate` and `br` encoded request bodies
+//! - `temp-file` : Add the `TempFile` extractor, streaming raw request bodies
+//!   to a temporary file
+//! - `content-sniffing` : Set the `Content-Type` of a `File` without a known
+//!   extension from the magic number of its first bytes
+//! - `request-id` : Add the `RequestIdMiddleware`, propagating or generating
+//!   an `X-Request-Id` for every request
+//! - `lang` : Add the `LangMiddleware` and the `Lang` extractor, negotiating
+//!   the language of every request from its `Accept-Language` header
+//! - `access-log` : Add the `AccessLogMiddleware`, logging every request in
+//!   the common, combined or json format
+//! - `catch-panic` : Add the `CatchPanicMiddleware`, answering a `500
+//!   Internal Server Error` when a handler panics
+//! - `metrics` : Add the `MetricsMiddleware`, recording per-route request
+//!   counts and latencies and serving them in the Prometheus text format
+//! - `basic-auth` : Add the `BasicAuth` guard, authenticating requests with
+//!   the HTTP basic authentication scheme
+//! - `health` : Add the `HealthController`, answering liveness and readiness
+//!   probes
+//! - `security-headers` : Add the `SecurityHeadersMiddleware`, sending
+//!   hardening headers like `Content-Security-Policy` with every response
+//! - `proxy` : Add the reverse proxy helpers, forwarding requests and
+//!   responses without buffering their body
+//! - `idempotency` : Add the `IdempotencyMiddleware`, replaying the response
+//!   of the requests retried with the same `Idempotency-Key` header
+//! - `openapi-runtime` : Add the `openapi` module, describing the routes with
+//!   schemas derived by [`schemars`](https://docs.rs/schemars) and serving
+//!   their OpenAPI document without the CLI
+//! - `rate-limit` : Add the `RateLimit` guard, throttling the requests of each
+//!   client to a quota per time window
 //! - `validate-requests` : Enable the `#[controller]` macro to generate validation
 //!   code for all `Json<T>` and `Form<T>`request payloads using the
 //!   [`validator`](https://github.com/Keats/validator) crate.
@@ -77,12 +117,34 @@
 extern crate log;
 extern crate core;
 
+/// Access logs of the requests
+#[cfg(feature = "access-log")]
+#[cfg_attr(docsrs, doc(cfg(feature = "access-log")))]
+pub mod access_log;
+/// HTTP basic authentication guard
+#[cfg(feature = "basic-auth")]
+#[cfg_attr(docsrs, doc(cfg(feature = "basic-auth")))]
+pub mod basic_auth;
 ///
 pub mod body;
+/// Recovery from panicking handlers
+#[cfg(feature = "catch-panic")]
+#[cfg_attr(docsrs, doc(cfg(feature = "catch-panic")))]
+pub mod catch_panic;
+/// Conditional GET for dynamic responses
+pub mod conditional;
+/// Information about the connection a request was received on
+pub mod connection;
 ///
 pub mod controller;
 ///
 pub mod cookie;
+#[cfg(feature = "form")]
+mod de;
+/// Decompression of the request bodies
+#[cfg(feature = "decompression")]
+#[cfg_attr(docsrs, doc(cfg(feature = "decompression")))]
+pub mod decompression;
 /// Error definitions
 pub mod error;
 ///
@@ -95,32 +157,74 @@ pub mod file;
 pub mod guard;
 /// Definition of types which can handle an http request
 pub mod handler;
+/// Liveness and readiness probes
+#[cfg(feature = "health")]
+#[cfg_attr(docsrs, doc(cfg(feature = "health")))]
+pub mod health;
 /// Context enveloping every request <-> response
 pub mod http_context;
+/// Idempotent retries with the `Idempotency-Key` header
+#[cfg(feature = "idempotency")]
+#[cfg_attr(docsrs, doc(cfg(feature = "idempotency")))]
+pub mod idempotency;
+/// Language negotiation from the `Accept-Language` header
+#[cfg(feature = "lang")]
+#[cfg_attr(docsrs, doc(cfg(feature = "lang")))]
+pub mod lang;
 /// Saphir macro for code generation
 #[cfg(feature = "macro")]
 #[cfg_attr(docsrs, doc(cfg(feature = "macro")))]
 pub mod macros;
+/// Request count and latency metrics, per route
+#[cfg(feature = "metrics")]
+#[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
+pub mod metrics;
 ///
 pub mod middleware;
 /// The async Multipart Form-Data representation
 #[cfg(feature = "multipart")]
 #[cfg_attr(docsrs, doc(cfg(feature = "multipart")))]
 pub mod multipart;
+/// OpenAPI document assembled at runtime
+#[cfg(feature = "openapi-runtime")]
+#[cfg_attr(docsrs, doc(cfg(feature = "openapi-runtime")))]
+pub mod openapi;
+/// Reverse proxy helpers
+#[cfg(feature = "proxy")]
+#[cfg_attr(docsrs, doc(cfg(feature = "proxy")))]
+pub mod proxy;
+/// Query string deserialization keeping repeated keys
+#[cfg(feature = "form")]
+#[cfg_attr(docsrs, doc(cfg(feature = "form")))]
+pub mod query;
+/// Request rate limiting guard
+#[cfg(feature = "rate-limit")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rate-limit")))]
+pub mod rate_limit;
 ///
 #[cfg(feature = "redirect")]
 #[cfg_attr(docsrs, doc(cfg(feature = "redirect")))]
 pub mod redirect;
 /// The Http Request type
 pub mod request;
+/// Request correlation ids
+#[cfg(feature = "request-id")]
+#[cfg_attr(docsrs, doc(cfg(feature = "request-id")))]
+pub mod request_id;
 /// Definition of type which can map to a response
 pub mod responder;
 /// The Http Response type
 pub mod response;
 ///
 pub mod router;
+/// Security headers middleware
+#[cfg(feature = "security-headers")]
+#[cfg_attr(docsrs, doc(cfg(feature = "security-headers")))]
+pub mod security_headers;
 /// Server implementation and default runtime
 pub mod server;
+/// Testing utilities to send requests to a stack without binding a socket
+pub mod test;
 ///
 pub mod utils;
 ///
@@ -131,6 +235,57 @@ pub use hyper;
 #[doc(hidden)]
 pub use tracing;
 
+/// Names of the features saphir was compiled with, e.g. to log them at
+/// startup when diagnosing a build which behaves differently than another.
+///
+/// ```rust
+/// let features = saphir::enabled_features();
+/// assert!(features.contains(&"http1"));
+/// println!("saphir features: {}", features.join(", "));
+/// ```
+pub fn enabled_features() -> &'static [&'static str] {
+    static FEATURES: std::sync::OnceLock<Vec<&'static str>> = std::sync::OnceLock::new();
+    FEATURES.get_or_init(|| {
+        [
+            ("access-log", cfg!(feature = "access-log")),
+            ("basic-auth", cfg!(feature = "basic-auth")),
+            ("catch-panic", cfg!(feature = "catch-panic")),
+            ("cbor", cfg!(feature = "cbor")),
+            ("content-sniffing", cfg!(feature = "content-sniffing")),
+            ("decompression", cfg!(feature = "decompression")),
+            ("file", cfg!(feature = "file")),
+            ("form", cfg!(feature = "form")),
+            ("health", cfg!(feature = "health")),
+            ("http1", cfg!(feature = "http1")),
+            ("http2", cfg!(feature = "http2")),
+            ("http3", cfg!(feature = "http3")),
+            ("https", cfg!(feature = "https")),
+            ("idempotency", cfg!(feature = "idempotency")),
+            ("json", cfg!(feature = "json")),
+            ("json5", cfg!(feature = "json5")),
+            ("lang", cfg!(feature = "lang")),
+            ("macro", cfg!(feature = "macro")),
+            ("metrics", cfg!(feature = "metrics")),
+            ("msgpack", cfg!(feature = "msgpack")),
+            ("multipart", cfg!(feature = "multipart")),
+            ("openapi-runtime", cfg!(feature = "openapi-runtime")),
+            ("operation", cfg!(feature = "operation")),
+            ("post-redirect", cfg!(feature = "post-redirect")),
+            ("proxy", cfg!(feature = "proxy")),
+            ("rate-limit", cfg!(feature = "rate-limit")),
+            ("redirect", cfg!(feature = "redirect")),
+            ("request-id", cfg!(feature = "request-id")),
+            ("security-headers", cfg!(feature = "security-headers")),
+            ("temp-file", cfg!(feature = "temp-file")),
+            ("tracing-instrument", cfg!(feature = "tracing-instrument")),
+            ("validate-requests", cfg!(feature = "validate-requests")),
+        ]
+        .into_iter()
+        .filter_map(|(feature, enabled)| enabled.then_some(feature))
+        .collect()
+    })
+}
+
 /// Contains everything you need to bootstrap your http server
 ///
 /// ```rust
@@ -139,18 +294,46 @@ pub use tracing;
 /// // implement magic
 /// ```
 pub mod prelude {
+    ///
+    #[cfg(any(feature = "json", feature = "form"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "json", feature = "form"))))]
+    pub use crate::body::AnyBody;
     ///
     pub use crate::body::Body;
     ///
     pub use crate::body::Bytes;
     ///
+    #[cfg(feature = "cbor")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "cbor")))]
+    pub use crate::body::Cbor;
+    ///
     #[cfg(feature = "form")]
     #[cfg_attr(docsrs, doc(cfg(feature = "form")))]
     pub use crate::body::Form;
     ///
+    #[cfg(feature = "json5")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json5")))]
+    pub use crate::body::Json5;
+    ///
+    #[cfg(feature = "msgpack")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "msgpack")))]
+    pub use crate::body::MsgPack;
+    ///
+    #[cfg(all(feature = "cbor", feature = "json"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "cbor", feature = "json"))))]
+    pub use crate::body::Negotiated;
+    ///
+    pub use crate::body::RawBody;
+    ///
+    #[cfg(feature = "temp-file")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "temp-file")))]
+    pub use crate::body::TempFile;
+    ///
     #[cfg(feature = "json")]
     #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
-    pub use crate::body::Json;
+    pub use crate::body::{Json, JsonLines};
+    ///
+    pub use crate::connection::ConnectionInfo;
     ///
     pub use crate::controller::Controller;
     ///
@@ -164,6 +347,8 @@ pub mod prelude {
     ///
     pub use crate::cookie::CookieJar;
     ///
+    pub use crate::cookie::Cookies;
+    ///
     pub use crate::error::SaphirError;
     ///
     pub use crate::extension::Ext;
@@ -183,6 +368,14 @@ pub mod prelude {
     ///
     pub use crate::http_context::HttpContext;
     ///
+    pub use crate::http_context::MatchedRoute;
+    ///
+    pub use crate::http_context::RequestStart;
+    ///
+    #[cfg(feature = "lang")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "lang")))]
+    pub use crate::lang::Lang;
+    ///
     #[cfg(feature = "macro")]
     #[cfg_attr(docsrs, doc(cfg(feature = "macro")))]
     pub use crate::macros::*;
@@ -195,6 +388,14 @@ pub mod prelude {
     #[cfg_attr(docsrs, doc(cfg(feature = "multipart")))]
     pub use crate::multipart::Multipart;
     ///
+    #[cfg(all(feature = "multipart", feature = "form"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "multipart", feature = "form"))))]
+    pub use crate::multipart::MultipartForm;
+    ///
+    #[cfg(feature = "form")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "form")))]
+    pub use crate::query::Query;
+    ///
     #[cfg(feature = "redirect")]
     #[cfg_attr(docsrs, doc(cfg(feature = "redirect")))]
     pub use crate::redirect::Redirect;
@@ -203,6 +404,10 @@ pub mod prelude {
     ///
     pub use crate::request::Request;
     ///
+    #[cfg(feature = "request-id")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "request-id")))]
+    pub use crate::request_id::RequestId;
+    ///
     pub use crate::responder::Responder;
     ///
     pub use crate::response::Builder;
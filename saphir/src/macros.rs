@@ -3,16 +3,82 @@
 //! # The `#[controller]` Macro
 //!
 //! This macro is an attribute macro that need to be place on the `impl block`
-//! of a Saphir controller. It has 3 optionnal parameters:
+//! of a Saphir controller. It has 7 optionnal parameters:
 //! - `prefix="<pre>"` : This will prefix any controller route by the specified
 //!   route prefix
 //! - `version=<u16>`  : This will insert the `/v#` path segment between the
 //!   prefix and the base controller route
 //! - `name="<name>"`  : This will route the controller at /<name>.
+//! - `guard(...)`     : This will add a guard to every endpoint of the
+//!   controller, see the `#[guard]` attribute below. It can be repeated.
+//! - `openapi_tag="<tag>"` : This will group the endpoints of the controller
+//!   under this tag in the OpenAPI documentation generated by saphir's CLI,
+//!   instead of the controller name.
+//! - `openapi_tag_description="<description>"` : This will describe the tag
+//!   of the controller in the OpenAPI documentation.
+//! - `openapi_hidden` : This will leave the controller out of the OpenAPI
+//!   documentation. Its endpoints are still served.
 //!
 //! If none of these are used, the controller will be routed at its own name, in
 //! lowercase, with the controller keyword trimmed.
 //!
+//! ```rust
+//! # #[macro_use] extern crate saphir_macro;
+//! # use saphir::prelude::*;
+//! struct AdminController {}
+//!
+//! #[controller(name = "admin", openapi_tag = "Administration", openapi_tag_description = "Internal tools", openapi_hidden)]
+//! impl AdminController {
+//!     #[post("/reindex")]
+//!     async fn reindex(&self) -> u16 {
+//!         202
+//!     }
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! The resolved name is also attached to the `HandlerMetadata` of every
+//! endpoint, along with the handler name, so middlewares can group logs or
+//! metrics by controller:
+//! ```rust
+//! # #[macro_use] extern crate saphir_macro;
+//! # use saphir::{prelude::*, test::TestServer};
+//! # use saphir::http::HeaderValue;
+//! #
+//! struct UserController {}
+//!
+//! #[controller]
+//! impl UserController {
+//!     #[get("/")]
+//!     async fn list_users(&self) -> &'static str {
+//!         "[]"
+//!     }
+//! }
+//!
+//! async fn handled_by(ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+//!     let handler = format!("{}::{}", ctx.metadata.controller.unwrap_or("-"), ctx.metadata.name.unwrap_or("-"));
+//!     let mut ctx = chain.next(ctx).await?;
+//!     if let Some(res) = ctx.state.response_mut() {
+//!         res.headers_mut().insert("x-handler", HeaderValue::from_str(&handler)?);
+//!     }
+//!     Ok(ctx)
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), SaphirError> {
+//! let client = TestServer::new(
+//!     Server::builder()
+//!         .configure_middlewares(|m| m.apply(handled_by, vec!["/"], None))
+//!         .configure_router(|r| r.controller(UserController {})),
+//! )?
+//! .client();
+//!
+//! let res = client.get("/user").send().await?;
+//! assert_eq!(res.header("x-handler"), Some("user::list_users"));
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! # Function Attributes
 //! We also parse several function attributes that can be placed above a
 //! controller function (endpoint).
@@ -28,17 +94,61 @@
 //!
 //! The following parameters types are supported:
 //!  - `CookieJar`: Collection of all the cookies in the request
+//!  - `Cookies`: The cookies of the request, as a jar whose added and removed
+//!    cookies are sent back as `Set-Cookie` headers on the response
 //!  - `Json`: The request body interpreted in Json. If the request body is not
 //!    valid Json, a 400 Bad Request response is returned.
+//!  - `Json5`: The request body interpreted as lenient JSON5, allowing
+//!    comments and trailing commas, with the `json5` feature. If the request
+//!    body is not valid JSON5, a 400 Bad Request response is returned.
 //!  - `Form`: The request body interpreted as a standard form.
 //!    (application/x-www-form-urlencoded) If the request body is not a valid
 //!    Form, a 400 Bad Request response is returned.
+//!  - `MsgPack`: The request body interpreted as MessagePack
+//!    (application/msgpack), with the `msgpack` feature. If the request body is
+//!    not valid MessagePack, a 400 Bad Request response is returned.
+//!  - `Cbor`: The request body interpreted as CBOR (application/cbor), with the
+//!    `cbor` feature. If the request body is not valid CBOR, a 400 Bad Request
+//!    response is returned.
+//!  - `AnyBody`: The request body interpreted as Json or as a Form, depending
+//!    on its `Content-Type`. Any other content type results in a 415
+//!    Unsupported Media Type response.
 //!  - `Multipart`: The request body interpreted as multipart form data
 //!    (multipart/form-data) If the request body is not a valid multipart form,
 //!    a 400 Bad Request response is returned.
+//!  - `MultipartForm`: The multipart form data deserialized into a struct,
+//!    with the `multipart` and `form` features. Text fields are parsed into its
+//!    members and file fields loaded into `MultipartFile` or `TempFile`
+//!    members. Invalid or missing fields result in a 400 Bad Request response.
+//!  - `Query<T>`: The whole query string deserialized into T (requires the
+//!    `form` feature). Repeated keys, also written `key[]`, are collected into
+//!    `Vec` members.
+//!  - `RawBody`: The whole request body as bytes, whatever its content type.
+//!    Bodies over the configured size limit result in a 413 Payload Too Large
+//!    response.
+//!  - `TempFile`: The request body streamed to a temporary file, for single
+//!    file uploads which are not multipart forms (requires the `temp-file`
+//!    feature). The file is created in the `temp_dir` of the listener and
+//!    deleted when dropped, unless persisted.
 //!  - `Ext<MyExtensionType>`: Retrieve the MyExtensionType from the request
 //!    extensions. Request extensions are data that you can attach to the
-//!    request within Middlewares and Guards.
+//!    request within Middlewares, with `HttpContext::insert`, and Guards. See
+//!    also the `#[ext(...)]` attribute below.
+//!  - `JsonLines<JsonLinesStream<T>>`: The request body parsed as newline
+//!    delimited json, one `T` per line, while it is received (requires the
+//!    `json` feature).
+//!  - `ConnectionInfo`: Information about the connection the request was
+//!    received on, such as the peer address and the negotiated TLS parameters.
+//!  - `MatchedRoute`: The route template which matched the request, e.g.
+//!    `/users/<user_id>`, for logs and metrics.
+//!  - `RequestStart`: The time at which the server started handling the
+//!    request, to measure how long it has been in flight.
+//!  - `Precondition`: The `If-Match` and `If-Unmodified-Since` headers of
+//!    the request, to reject outdated updates with `412 Precondition Failed`.
+//!  - `RequestId`: The correlation id assigned by the `RequestIdMiddleware`
+//!    (requires the `request-id` feature).
+//!  - `Lang`: The language negotiated by the `LangMiddleware` from the
+//!    `Accept-Language` header (requires the `lang` feature).
 //!  - `Extensions`: Collection of all the extensions attached to the request.
 //!    This is the whole owned collection, so it cannot be used in conjunction
 //!    with single Ext<T> parameters.
@@ -48,27 +158,279 @@
 //!  - `Option`: Any body parameter, path parameter or query string parameter
 //!    (see below) can be marked as optionnal.
 //!  - `<T>`: Any other unhandled parameter type is considered a query string
-//!    parameter. T must implement FromStr.
+//!    parameter. T must implement FromStr. A `Vec<T>` parameter collects every
+//!    value of a repeated key, e.g. `?tag=a&tag=b` or `?tag[]=a&tag[]=b`, and
+//!    is empty when the key is missing; other parameters take the last value.
 //!
 //! We support even custom methods, and for convinience, `#[any(/your/path)]`
 //! will be treated as : _any method_ being accepted.
 //!
+//! ### The `status` parameter
+//! **Syntax: `#[<method>("/<path>", status = <code>)]`**
+//!
+//! Set the status of the response when the returned responder leaves the
+//! default one, e.g. to answer `201 Created` from a handler returning a
+//! `Json<T>`. A status set by the responder itself, like in `(u16, Json<T>)`,
+//! takes precedence. The status is also documented as the success code of
+//! the endpoint by the OpenAPI generation.
+//! ```rust
+//! # #[macro_use] extern crate saphir_macro;
+//! # use saphir::{prelude::*, test::TestServer};
+//! #
+//! struct MyController {}
+//!
+//! #[controller(name = "users")]
+//! impl MyController {
+//!     #[post("/<name>", status = 201)]
+//!     async fn create_user(&self, name: String) -> Option<String> {
+//!         (name != "nobody").then(|| name)
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), SaphirError> {
+//! let client = TestServer::new(Server::builder().configure_router(|r| r.controller(MyController {})))?.client();
+//!
+//! let res = client.post("/users/richer").send().await?;
+//! assert_eq!((res.status().as_u16(), res.text()?.as_str()), (201, "richer"));
+//!
+//! let res = client.post("/users/nobody").send().await?;
+//! assert_eq!(res.status(), 404);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ### Status only handlers
+//! Handlers returning `()`, or returning nothing at all, answer `204 No
+//! Content`, unless a `status` parameter is set. Handlers returning a
+//! `StatusCode` or a `u16` answer an empty response with that status.
+//! ```rust
+//! # #[macro_use] extern crate saphir_macro;
+//! # use saphir::{prelude::*, test::TestServer};
+//! #
+//! struct HealthController {}
+//!
+//! #[controller(name = "health")]
+//! impl HealthController {
+//!     #[get("/live")]
+//!     async fn live(&self) {}
+//!
+//!     #[get("/ready")]
+//!     async fn ready(&self) -> StatusCode {
+//!         StatusCode::SERVICE_UNAVAILABLE
+//!     }
+//!
+//!     #[post("/refresh", status = 202)]
+//!     async fn refresh(&self) -> () {}
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), SaphirError> {
+//! let client = TestServer::new(Server::builder().configure_router(|r| r.controller(HealthController {})))?.client();
+//!
+//! assert_eq!(client.get("/health/live").send().await?.status(), 204);
+//! assert_eq!(client.get("/health/ready").send().await?.status(), 503);
+//! assert_eq!(client.post("/health/refresh").send().await?.status(), 202);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## The `#[route(...)]` Attribute
+//! **Syntax: `#[route("/<path>", methods("<METHOD>", ..))]`**
+//!
+//! Route a single endpoint function under several HTTP methods, each method
+//! being registered with the same path and handler. Methods are written in
+//! uppercase, custom methods such as `PURGE` included; since they are case
+//! sensitive, a method with lowercase letters like `"get"` is a compile
+//! error. The `status` parameter is also accepted.
+//! ```rust
+//! # #[macro_use] extern crate saphir_macro;
+//! # use saphir::{prelude::*, test::TestServer};
+//! #
+//! struct CacheController {}
+//!
+//! #[controller(name = "cache")]
+//! impl CacheController {
+//!     #[route("/<key>", methods("POST", "PURGE"))]
+//!     async fn purge(&self, key: String) -> String {
+//!         format!("purged {}", key)
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), SaphirError> {
+//! let client = TestServer::new(Server::builder().configure_router(|r| r.controller(CacheController {})))?.client();
+//!
+//! let res = client.request(Method::from_bytes(b"PURGE").unwrap(), "/cache/users").send().await?;
+//! assert_eq!(res.text()?, "purged users");
+//! assert_eq!(client.post("/cache/users").send().await?.text()?, "purged users");
+//! assert_eq!(client.get("/cache/users").send().await?.status(), 405);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ```compile_fail
+//! # #[macro_use] extern crate saphir_macro;
+//! # use saphir::prelude::*;
+//! struct CacheController {}
+//!
+//! #[controller(name = "cache")]
+//! impl CacheController {
+//!     #[route("/<key>", methods("get"))]
+//!     async fn get(&self, key: String) -> String {
+//!         key
+//!     }
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! ## Return types
+//! An endpoint can return any type implementing `Responder`. This includes
+//! `Result<T, E>` where both `T` and `E` are responders: the error arm is
+//! rendered by its own responder rather than being converted into a
+//! `SaphirError`, and gets a 500 status only if its responder did not set one.
+//! Errors raised while extracting the parameters (e.g. an invalid json body)
+//! are still reported as a `SaphirError`.
+//! ```rust
+//! # #[macro_use] extern crate saphir_macro;
+//! # use saphir::{prelude::*, test::TestServer};
+//! #
+//! enum MyError {
+//!     NotFound,
+//! }
+//!
+//! impl Responder for MyError {
+//!     fn respond_with_builder(self, builder: Builder, _ctx: &HttpContext) -> Builder {
+//!         match self {
+//!             MyError::NotFound => builder.status(404).body("no such user"),
+//!         }
+//!     }
+//! }
+//!
+//! struct MyController {}
+//!
+//! #[controller(name = "users")]
+//! impl MyController {
+//!     #[get("/<id>")]
+//!     async fn get_user(&self, id: u32) -> Result<(u16, String), MyError> {
+//!         match id {
+//!             1 => Ok((200, "richer".to_string())),
+//!             _ => Err(MyError::NotFound),
+//!         }
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), SaphirError> {
+//! let client = TestServer::new(Server::builder().configure_router(|r| r.controller(MyController {})))?.client();
+//!
+//! let res = client.get("/users/1").send().await?;
+//! assert_eq!((res.status().as_u16(), res.text()?.as_str()), (200, "richer"));
+//!
+//! let res = client.get("/users/2").send().await?;
+//! assert_eq!((res.status().as_u16(), res.text()?.as_str()), (404, "no such user"));
+//!
+//! let res = client.get("/users/not-a-number").send().await?;
+//! assert_eq!(res.status(), 400);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The responder doesn't need to be named: endpoints can return `impl
+//! Responder`, alone or within another responder such as `Result`, whatever
+//! their parameters are. Since the concrete type is hidden, the OpenAPI
+//! generation can't document such a response on its own, use the
+//! `#[openapi(return(...))]` attribute below to describe it.
+//! ```rust
+//! # #[macro_use] extern crate saphir_macro;
+//! # use saphir::{prelude::*, test::TestServer};
+//! #
+//! struct MyController {}
+//!
+//! #[controller(name = "users")]
+//! impl MyController {
+//!     #[get("/<id>")]
+//!     async fn get_user(&self, id: u32) -> impl Responder {
+//!         (200, format!("user {}", id))
+//!     }
+//!
+//!     #[post("/<name>", status = 201)]
+//!     fn create_user(&self, name: String) -> Result<impl Responder, SaphirError> {
+//!         Ok(name)
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), SaphirError> {
+//! let client = TestServer::new(Server::builder().configure_router(|r| r.controller(MyController {})))?.client();
+//!
+//! let res = client.get("/users/1").send().await?;
+//! assert_eq!(res.text()?, "user 1");
+//!
+//! let res = client.post("/users/richer").send().await?;
+//! assert_eq!((res.status().as_u16(), res.text()?.as_str()), (201, "richer"));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Streaming responders, such as
+//! [`StreamResponder`](crate::responder::StreamResponder), are returned the
+//! same way. The request body is only loaded when a parameter needs it, and
+//! the response is sent as the stream produces it.
+//! ```rust
+//! # #[macro_use] extern crate saphir_macro;
+//! # use saphir::{prelude::*, responder::StreamResponder, test::TestServer};
+//! # use futures::{stream, Stream};
+//! #
+//! struct ExportController {}
+//!
+//! #[controller(name = "exports")]
+//! impl ExportController {
+//!     #[get("/<count>")]
+//!     async fn export(&self, count: u32) -> StreamResponder<impl Stream<Item = Result<Bytes, std::io::Error>> + Send> {
+//!         let rows = (1..=count).map(|i| Ok(Bytes::from(format!("row {}\n", i))));
+//!         StreamResponder::new(stream::iter(rows)).content_type("text/plain")
+//!     }
+//!
+//!     #[post("/", status = 202)]
+//!     async fn echo(&self, req: Request<Bytes>) -> StreamResponder<impl Stream<Item = Result<Bytes, std::io::Error>> + Send> {
+//!         let body = req.into_body();
+//!         let chunks = body.chunks(4).map(|c| Ok(Bytes::copy_from_slice(c))).collect::<Vec<_>>();
+//!         StreamResponder::new(stream::iter(chunks))
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), SaphirError> {
+//! let client = TestServer::new(Server::builder().configure_router(|r| r.controller(ExportController {})))?.client();
+//!
+//! let res = client.get("/exports/2").send().await?;
+//! assert_eq!(res.text()?, "row 1\nrow 2\n");
+//!
+//! let res = client.post("/exports").body("streamed back").send().await?;
+//! assert_eq!((res.status().as_u16(), res.text()?.as_str()), (202, "streamed back"));
+//! # Ok(())
+//! # }
+//! ```
+
 //! ## The `#[openapi(...)]` Attribute
 //! This attribute can be added to a controller function (endpoint) to add
 //! informations about the endpoint for OpenAPI generation through saphir's
 //! CLI.
 //! This attribute can be present multiple times and can include any number of
-//! `return`, `return_override` and `params` parameters:
+//! `return`, `return_override` and `params` parameters, or the `hidden` flag:
 //!
 //! ### The `return(...)` openapi parameter
-//! **Syntax: `return(code = <code>, type = "<type_path>"[, mime = <mime>])`**
+//! **Syntax: `return(code = <code>, type = "<type_path>"[, mime = <mime>][,
+//! example = <example>])`**
 //!
-//! Specify a possible return code & type, and optionally a mime type.
+//! Specify a possible return code & type, and optionally a mime type and an
+//! example of the response body.
 //! The type must be a valid type path included (`use`) in the file.
 //! E.g. `#[openapi(return(code = 200, type = "Json<MyType>")]`
 //!
 //! `type` support infering the mimetype of built-in responders such as
-//! `Json<T>` and `Form<T>`, so the following are ecquivalent :
+//! `Json<T>`, `Form<T>`, `MsgPack<T>` and `Cbor<T>`, so the following are ecquivalent :
 //! - `#[openapi(return(code = 200, type = "Json<MyType>")]`
 //! - `#[openapi(return(code = 200, type = "self::MyType", mime = "json")]`
 //! - `#[openapi(return(code = 200, type = "MyType", mime =
@@ -170,6 +532,41 @@
 //! # }
 //! ```
 //!
+//! ### The `hidden` openapi parameter
+//! **Syntax: `#[openapi(hidden)]`**
+//!
+//! Leave the endpoint out of the generated document, e.g. for internal or
+//! administration routes. The endpoint is still served normally.
+//! ```rust
+//! # #[macro_use] extern crate saphir_macro;
+//! # use saphir::{prelude::*, test::TestServer};
+//! #
+//! struct MyController {}
+//!
+//! #[controller(name = "status")]
+//! impl MyController {
+//!     #[get("/")]
+//!     async fn status(&self) -> &'static str {
+//!         "up"
+//!     }
+//!
+//!     #[get("/internal")]
+//!     #[openapi(hidden)]
+//!     async fn internal_status(&self) -> &'static str {
+//!         "up, 3 workers"
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), SaphirError> {
+//! let client = TestServer::new(Server::builder().configure_router(|r| r.controller(MyController {})))?.client();
+//!
+//! let res = client.get("/status/internal").send().await?;
+//! assert_eq!(res.text()?, "up, 3 workers");
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! ## The `#[cookies]` Attribute
 //! This will ensure cookies are parsed in the request before the endpoint
 //! function is called, cookies can than be accessed with
@@ -183,29 +580,206 @@
 //!   the data that will be passed to the guard function. this function takes a
 //!   reference of the controller type it is used in.
 //!
+//! Guards run in the order of their attributes, before any parameter of the
+//! endpoint is extracted. The first guard returning an error short-circuits the
+//! request and its responder is sent back.
+//!
+//! Guards shared by every endpoint of a controller can be specified once on
+//! the `#[controller]` attribute, with the same syntax. They run before the
+//! guards of the endpoint itself.
+//! ```rust
+//! # #[macro_use] extern crate saphir_macro;
+//! # use saphir::{prelude::*, test::TestServer};
+//! #
+//! struct AuthGuard;
+//!
+//! #[guard]
+//! impl AuthGuard {
+//!     pub fn new() -> Self {
+//!         AuthGuard
+//!     }
+//!
+//!     async fn validate(&self, req: Request) -> Result<Request, u16> {
+//!         req.headers().get("X-User").ok_or(401u16)?;
+//!         Ok(req)
+//!     }
+//! }
+//!
+//! struct AdminGuard;
+//!
+//! #[guard]
+//! impl AdminGuard {
+//!     pub fn new() -> Self {
+//!         AdminGuard
+//!     }
+//!
+//!     async fn validate(&self, req: Request) -> Result<Request, u16> {
+//!         match req.headers().get("X-User") {
+//!             Some(user) if user == "admin" => Ok(req),
+//!             _ => Err(403),
+//!         }
+//!     }
+//! }
+//!
+//! struct AdminController {}
+//!
+//! #[controller(name = "admin", guard(AuthGuard))]
+//! impl AdminController {
+//!     #[get("/status")]
+//!     async fn status(&self) -> (u16, String) {
+//!         (200, "ok".to_string())
+//!     }
+//!
+//!     #[post("/shutdown")]
+//!     #[guard(AdminGuard)]
+//!     async fn shutdown(&self) -> (u16, String) {
+//!         (200, "bye".to_string())
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), SaphirError> {
+//! let client = TestServer::new(Server::builder().configure_router(|r| r.controller(AdminController {})))?.client();
+//!
+//! assert_eq!(client.get("/admin/status").send().await?.status(), 401);
+//! assert_eq!(client.get("/admin/status").header("X-User", "richer").send().await?.status(), 200);
+//!
+//! assert_eq!(client.post("/admin/shutdown").send().await?.status(), 401);
+//! assert_eq!(client.post("/admin/shutdown").header("X-User", "richer").send().await?.status(), 403);
+//! assert_eq!(client.post("/admin/shutdown").header("X-User", "admin").send().await?.status(), 200);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## The `#[ext(...)]` Attribute
+//! **Syntax: `#[ext(param_1, param_2)]`**
+//!
+//! The listed parameters are taken from the request extensions instead of the
+//! query string, as an `Ext<T>` parameter would. This pairs with guards and
+//! middlewares inserting values in the request extensions. A missing value
+//! results in a 500 response, unless the parameter is an `Option`.
+//! ```rust
+//! # #[macro_use] extern crate saphir_macro;
+//! # use saphir::{prelude::*, test::TestServer};
+//! #
+//! #[derive(Clone)]
+//! struct AuthClaims {
+//!     user: String,
+//! }
+//!
+//! struct AuthGuard;
+//!
+//! #[guard]
+//! impl AuthGuard {
+//!     pub fn new() -> Self {
+//!         AuthGuard
+//!     }
+//!
+//!     async fn validate(&self, mut req: Request) -> Result<Request, u16> {
+//!         let user = req.headers().get("X-User").and_then(|h| h.to_str().ok()).ok_or(401u16)?.to_string();
+//!         req.extensions_mut().insert(AuthClaims { user });
+//!         Ok(req)
+//!     }
+//! }
+//!
+//! struct MyController {}
+//!
+//! #[controller(name = "my-controller")]
+//! impl MyController {
+//!     #[get("/me")]
+//!     #[guard(AuthGuard)]
+//!     #[ext(claims)]
+//!     async fn me(&self, claims: AuthClaims) -> (u16, String) {
+//!         (200, claims.user)
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), SaphirError> {
+//! let server = TestServer::new(Server::builder().configure_router(|r| r.controller(MyController {})))?;
+//! let client = server.client();
+//!
+//! let res = client.get("/my-controller/me").header("X-User", "richer").send().await?;
+//! assert_eq!(res.text()?, "richer");
+//!
+//! let res = client.get("/my-controller/me").send().await?;
+//! assert_eq!(res.status(), 401);
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! ## The `#[validate(...)` Attribute
 //! **Syntax: `#[validate(exclude("excluded_param_1", "excluded_param_2"))]`**
 //!
 //! When using the `validate-requests` feature flag, saphir will generate
-//! validation code for all `Json<T>` and `Form<T>` request payloads using the [`validator`](https://github.com/Keats/validator) crate.
+//! validation code for all `Json<T>`, `Json5<T>`, `Form<T>`, `MsgPack<T>` and `Cbor<T>` request payloads using the [`validator`](https://github.com/Keats/validator) crate.
 //! Any `T` which does not implement the `validator::Validate` trait will cause
 //! compilation error.
+//! Payloads of `Vec<T>`, `Option<T>` or `Option<Vec<T>>`, e.g.
+//! `Json<Option<Vec<Item>>>`, validate each present `T` instead. Nested
+//! structs, including flattened ones, are validated when their field is
+//! marked `#[validate]`, as the `validator` crate requires.
+//! ```rust
+//! # #[macro_use] extern crate saphir_macro;
+//! # #[cfg(all(feature = "json", feature = "validate-requests"))]
+//! # mod example {
+//! # use saphir::{prelude::*, test::TestServer};
+//! # use serde_derive::Deserialize;
+//! # use validator::Validate;
+//! #
+//! #[derive(Deserialize, Validate)]
+//! struct Item {
+//!     #[validate(length(min = 1))]
+//!     name: String,
+//! }
+//!
+//! struct ItemsController {}
+//!
+//! #[controller(name = "items")]
+//! impl ItemsController {
+//!     #[post("/")]
+//!     async fn create(&self, items: Json<Option<Vec<Item>>>) -> String {
+//!         items.iter().flatten().count().to_string()
+//!     }
+//! }
+//!
+//! # pub async fn run() -> Result<(), SaphirError> {
+//! let client = TestServer::new(Server::builder().configure_router(|r| r.controller(ItemsController {})))?.client();
+//!
+//! let res = client.post("/items").json(&serde_json::json!([{ "name": "a" }])).send().await?;
+//! assert_eq!(res.text()?, "1");
+//! let res = client.post("/items").json(&serde_json::json!(null)).send().await?;
+//! assert_eq!(res.text()?, "0");
+//! let res = client.post("/items").json(&serde_json::json!([{ "name": "a" }, { "name": "" }])).send().await?;
+//! assert_eq!(res.status(), 422);
+//! assert!(res.text()?.contains("\"name\""));
+//! # Ok(())
+//! # }
+//! # }
+//! #
+//! # #[tokio::main]
+//! # async fn main() {
+//! #     #[cfg(all(feature = "json", feature = "validate-requests"))]
+//! #     example::run().await.unwrap();
+//! # }
+//! ```
+//!
 //! This macro attribute can be used to exclude validation on certain request
 //! parameters.
 //! Example:
 //! ```rust
 //! # #[macro_use] extern crate saphir_macro;
-//! # use crate::saphir::prelude::*;
-//! # use serde::Deserialize;
-//! #
-//! # fn main() {}
+//! # #[cfg(all(feature = "json", feature = "validate-requests"))]
+//! # mod example {
+//! # use saphir::prelude::*;
+//! # use serde_derive::Deserialize;
 //! #
 //! # enum MyError {
 //! #     Unknown
 //! # }
 //! # impl Responder for MyError {
-//! #    fn respond_with_builder(self,builder: Builder,ctx: &HttpContext) -> Builder {
-//! #        unimplemented!()
+//! #    fn respond_with_builder(self, builder: Builder, _ctx: &HttpContext) -> Builder {
+//! #        builder.status(500)
 //! #    }
 //! # }
 //! #
@@ -222,6 +796,8 @@
 //!     #[validator(exclude("req"))]
 //!     async fn my_handler(&self, req: Json<MyPayload>) -> Result<(), MyError> { /*...*/ Ok(()) }
 //! }
+//! # }
+//! # fn main() {}
 //! ```
 //!
 //! # Type Attributes (Struct & Enum)
@@ -229,6 +805,26 @@
 //!
 //! ## The `#[openapi(mime = <mime>)]` Attribute
 //! This attribute specify the OpenAPI mimetype for this type.
+//!
+//! ## The `#[openapi(example = <value>)]` Attribute
+//! This attribute gives an example of a struct, as a json document, or of one
+//! of its fields when placed on the field. Strings holding a json object or
+//! array are embedded as json, other values as they are.
+//! ```rust
+//! # #[macro_use] extern crate saphir_macro;
+//! # use serde_derive::Deserialize;
+//! #
+//! #[openapi(example = r#"{"name": "jo", "age": 32}"#)]
+//! #[derive(Deserialize)]
+//! struct User {
+//!     #[openapi(example = "jo")]
+//!     name: String,
+//!     #[openapi(example = 32)]
+//!     age: u8,
+//! }
+//! #
+//! # fn main() {}
+//! ```
 
 pub use futures::future::{BoxFuture, FutureExt};
 pub use saphir_macro::{controller, guard, middleware, openapi};
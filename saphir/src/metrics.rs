@@ -0,0 +1,405 @@
+//! Per-route request metrics.
+//!
+//! The [`MetricsMiddleware`] counts the requests handled by the stack, labeled
+//! by method, route template and status class, and records their latency in a
+//! histogram. Requests are labeled with the path their handler was registered
+//! with (e.g. `/users/{id}`) rather than the concrete path, so the number of
+//! series stays bounded. Requests which did not match any route share the
+//! `unmatched` label, and methods other than the standard ones share the
+//! `other` label.
+//!
+//! The collected values can be read with [`MetricsRegistry::snapshot`] to feed
+//! another metrics system, or served in the Prometheus text format with
+//! [`MetricsRegistry::handler`]:
+//!
+//! ```rust
+//! use saphir::{metrics::MetricsMiddleware, prelude::*};
+//!
+//! async fn user(_req: Request) -> (u16, &'static str) {
+//!     (200, "user")
+//! }
+//!
+//! let metrics = MetricsMiddleware::new();
+//! let registry = metrics.registry();
+//!
+//! let server = Server::builder()
+//!     .configure_middlewares(|m| m.apply(metrics, vec!["/**"], vec!["/metrics"]))
+//!     .configure_router(|r| r.route("/users/{id}", Method::GET, user).route("/metrics", Method::GET, registry.handler()))
+//!     .build();
+//! ```
+//!
+//! The middleware turns errors returned by the rest of the chain into their
+//! response so that their status can be recorded; it should therefore be the
+//! first middleware applied.
+
+use crate::{
+    error::SaphirError,
+    handler::Handler,
+    http_context::HttpContext,
+    middleware::{Middleware, MiddlewareChain},
+    request::Request,
+    response::Builder,
+};
+use futures::future::{ready, BoxFuture, FutureExt, Ready};
+use http::{Method, StatusCode};
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Arc, Mutex},
+};
+
+/// Default upper bounds of the latency histogram buckets, in seconds
+pub const DEFAULT_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Middleware recording the request count and latency of every route
+pub struct MetricsMiddleware {
+    registry: MetricsRegistry,
+}
+
+impl MetricsMiddleware {
+    /// Create a middleware using the [`DEFAULT_BUCKETS`] for its latency
+    /// histogram
+    pub fn new() -> Self {
+        Self::with_buckets(DEFAULT_BUCKETS.to_vec())
+    }
+
+    /// Create a middleware using custom latency histogram buckets, in seconds
+    pub fn with_buckets(mut buckets: Vec<f64>) -> Self {
+        buckets.retain(|b| b.is_finite());
+        buckets.sort_by(|a, b| a.total_cmp(b));
+        buckets.dedup();
+
+        MetricsMiddleware {
+            registry: MetricsRegistry {
+                inner: Arc::new(RegistryInner {
+                    buckets,
+                    values: Mutex::new(Values::default()),
+                }),
+            },
+        }
+    }
+
+    /// Returns a handle to the values recorded by this middleware
+    pub fn registry(&self) -> MetricsRegistry {
+        self.registry.clone()
+    }
+
+    async fn next_inner(&self, ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+        let start = ctx.started_at();
+        let method = ctx.state.request().map(|r| method_label(r.method())).unwrap_or_default().to_string();
+        let route = ctx.metadata.route_template.as_deref().unwrap_or(UNMATCHED_ROUTE).to_string();
+        let ctx = chain.next_with_response(ctx).await?;
+
+        let status = ctx.state.response().map(|r| r.status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        self.registry.record(method, route, status, start.elapsed().as_secs_f64());
+
+        Ok(ctx)
+    }
+}
+
+impl Default for MetricsMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Middleware for MetricsMiddleware {
+    fn next(&'static self, ctx: HttpContext, chain: &'static dyn MiddlewareChain) -> BoxFuture<'static, Result<HttpContext, SaphirError>> {
+        self.next_inner(ctx, chain).boxed()
+    }
+}
+
+/// Shared handle to the values recorded by a [`MetricsMiddleware`]
+#[derive(Clone)]
+pub struct MetricsRegistry {
+    inner: Arc<RegistryInner>,
+}
+
+struct RegistryInner {
+    buckets: Vec<f64>,
+    values: Mutex<Values>,
+}
+
+#[derive(Default)]
+struct Values {
+    requests: BTreeMap<(String, String, &'static str), u64>,
+    latencies: BTreeMap<(String, String), Histogram>,
+}
+
+struct Histogram {
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl MetricsRegistry {
+    fn record(&self, method: String, route: String, status: StatusCode, seconds: f64) {
+        let buckets = &self.inner.buckets;
+        let mut values = self.inner.values.lock().unwrap_or_else(|e| e.into_inner());
+
+        *values.requests.entry((method.clone(), route.clone(), status_class(status))).or_default() += 1;
+
+        let histogram = values.latencies.entry((method, route)).or_insert_with(|| Histogram {
+            counts: vec![0; buckets.len()],
+            sum: 0.0,
+            count: 0,
+        });
+        for (count, _) in histogram.counts.iter_mut().zip(buckets).filter(|(_, b)| seconds <= **b) {
+            *count += 1;
+        }
+        histogram.sum += seconds;
+        histogram.count += 1;
+    }
+
+    /// Returns a copy of every value recorded so far
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let buckets = &self.inner.buckets;
+        let values = self.inner.values.lock().unwrap_or_else(|e| e.into_inner());
+
+        MetricsSnapshot {
+            requests: values
+                .requests
+                .iter()
+                .map(|((method, route, status_class), count)| RequestCount {
+                    method: method.clone(),
+                    route: route.clone(),
+                    status_class,
+                    count: *count,
+                })
+                .collect(),
+            latencies: values
+                .latencies
+                .iter()
+                .map(|((method, route), h)| RouteLatency {
+                    method: method.clone(),
+                    route: route.clone(),
+                    buckets: buckets.iter().copied().zip(h.counts.iter().copied()).collect(),
+                    sum: h.sum,
+                    count: h.count,
+                })
+                .collect(),
+        }
+    }
+
+    /// Render every value recorded so far in the Prometheus text format
+    pub fn render_prometheus(&self) -> String {
+        self.snapshot().to_prometheus()
+    }
+
+    /// Returns a handler serving the recorded values in the Prometheus text
+    /// format
+    pub fn handler(&self) -> MetricsHandler {
+        MetricsHandler { registry: self.clone() }
+    }
+}
+
+/// Handler rendering a [`MetricsRegistry`] in the Prometheus text format
+pub struct MetricsHandler {
+    registry: MetricsRegistry,
+}
+
+impl<T> Handler<T> for MetricsHandler {
+    type Future = Ready<Builder>;
+    type Responder = Builder;
+
+    fn handle(&self, _req: Request<T>) -> Self::Future {
+        ready(
+            Builder::new()
+                .status(200)
+                .header(http::header::CONTENT_TYPE, "text/plain; version=0.0.4")
+                .body(self.registry.render_prometheus()),
+        )
+    }
+}
+
+/// Values recorded by a [`MetricsMiddleware`] at a given time
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricsSnapshot {
+    pub requests: Vec<RequestCount>,
+    pub latencies: Vec<RouteLatency>,
+}
+
+/// Number of requests handled for a method, route and status class
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestCount {
+    pub method: String,
+    pub route: String,
+    /// `1xx`, `2xx`, `3xx`, `4xx` or `5xx`
+    pub status_class: &'static str,
+    pub count: u64,
+}
+
+/// Latency histogram of a method and route
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteLatency {
+    pub method: String,
+    pub route: String,
+    /// Cumulative count of requests for every bucket upper bound, in seconds
+    pub buckets: Vec<(f64, u64)>,
+    /// Total time spent handling requests, in seconds
+    pub sum: f64,
+    pub count: u64,
+}
+
+impl MetricsSnapshot {
+    /// Render the snapshot in the Prometheus text format
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+
+        let _ = writeln!(out, "# HELP saphir_http_requests_total Total number of http requests handled.");
+        let _ = writeln!(out, "# TYPE saphir_http_requests_total counter");
+        for r in &self.requests {
+            let _ = writeln!(
+                out,
+                "saphir_http_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                escape_label(&r.method),
+                escape_label(&r.route),
+                r.status_class,
+                r.count
+            );
+        }
+
+        let _ = writeln!(out, "# HELP saphir_http_request_duration_seconds Latency of http requests.");
+        let _ = writeln!(out, "# TYPE saphir_http_request_duration_seconds histogram");
+        for l in &self.latencies {
+            let labels = format!("method=\"{}\",route=\"{}\"", escape_label(&l.method), escape_label(&l.route));
+            for (le, count) in &l.buckets {
+                let _ = writeln!(out, "saphir_http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}", labels, le, count);
+            }
+            let _ = writeln!(out, "saphir_http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}", labels, l.count);
+            let _ = writeln!(out, "saphir_http_request_duration_seconds_sum{{{}}} {}", labels, l.sum);
+            let _ = writeln!(out, "saphir_http_request_duration_seconds_count{{{}}} {}", labels, l.count);
+        }
+
+        out
+    }
+}
+
+fn status_class(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        _ => "5xx",
+    }
+}
+
+fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::HEAD => "HEAD",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::DELETE => "DELETE",
+        Method::CONNECT => "CONNECT",
+        Method::OPTIONS => "OPTIONS",
+        Method::TRACE => "TRACE",
+        Method::PATCH => "PATCH",
+        _ => "other",
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{server::Server, test::TestServer};
+
+    async fn user(req: Request) -> (u16, String) {
+        (200, req.captures().get("id").cloned().unwrap_or_default())
+    }
+
+    async fn fail(_req: Request) -> Result<(u16, &'static str), SaphirError> {
+        Err(SaphirError::Other("boom".to_string()))
+    }
+
+    #[test]
+    fn status_classes() {
+        assert_eq!(status_class(StatusCode::CONTINUE), "1xx");
+        assert_eq!(status_class(StatusCode::NO_CONTENT), "2xx");
+        assert_eq!(status_class(StatusCode::NOT_MODIFIED), "3xx");
+        assert_eq!(status_class(StatusCode::NOT_FOUND), "4xx");
+        assert_eq!(status_class(StatusCode::BAD_GATEWAY), "5xx");
+    }
+
+    #[tokio::test]
+    async fn records_route_templates() {
+        let metrics = MetricsMiddleware::with_buckets(vec![60.0, 0.0]);
+        let registry = metrics.registry();
+        let server = TestServer::new(
+            Server::builder()
+                .configure_middlewares(|m| m.apply(metrics, vec!["/**"], vec!["/metrics"]))
+                .configure_router(|r| {
+                    r.route("/users/{id}", Method::GET, user)
+                        .route("/fail", Method::GET, fail)
+                        .route("/metrics", Method::GET, registry.handler())
+                }),
+        )
+        .unwrap();
+        let client = server.client();
+
+        client.get("/users/1").send().await.unwrap();
+        client.get("/users/2").send().await.unwrap();
+        client.get("/nothing/here").send().await.unwrap();
+        assert_eq!(client.get("/fail").send().await.unwrap().status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let snapshot = registry.snapshot();
+        let count = |route: &str, class: &str| {
+            snapshot
+                .requests
+                .iter()
+                .find(|r| r.method == "GET" && r.route == route && r.status_class == class)
+                .map(|r| r.count)
+        };
+        assert_eq!(count("/users/{id}", "2xx"), Some(2));
+        assert_eq!(count("unmatched", "4xx"), Some(1));
+        assert_eq!(count("/fail", "5xx"), Some(1));
+        assert_eq!(snapshot.requests.len(), 3);
+
+        let users = snapshot.latencies.iter().find(|l| l.route == "/users/{id}").unwrap();
+        assert_eq!(users.count, 2);
+        assert_eq!(users.buckets.len(), 2);
+        assert_eq!(users.buckets[1], (60.0, 2));
+
+        let res = client.get("/metrics").send().await.unwrap();
+        assert_eq!(res.header(http::header::CONTENT_TYPE), Some("text/plain; version=0.0.4"));
+        let text = res.text().unwrap();
+        assert!(text.contains("saphir_http_requests_total{method=\"GET\",route=\"/users/{id}\",status=\"2xx\"} 2\n"));
+        assert!(text.contains("saphir_http_request_duration_seconds_bucket{method=\"GET\",route=\"/users/{id}\",le=\"+Inf\"} 2\n"));
+        assert!(!text.contains("route=\"/metrics\""));
+    }
+
+    #[tokio::test]
+    async fn extension_methods_share_a_label() {
+        let metrics = MetricsMiddleware::new();
+        let registry = metrics.registry();
+        let server = TestServer::new(
+            Server::builder()
+                .configure_middlewares(|m| m.apply(metrics, vec!["/**"], vec![]))
+                .configure_router(|r| r.route("/users/{id}", Method::GET, user)),
+        )
+        .unwrap();
+        let client = server.client();
+
+        for method in ["PURGE", "X-RANDOM-1", "X-RANDOM-2"] {
+            client.request(Method::from_bytes(method.as_bytes()).unwrap(), "/users/1").send().await.unwrap();
+        }
+        client.get("/users/1").send().await.unwrap();
+
+        let snapshot = registry.snapshot();
+        let mut methods: Vec<_> = snapshot.latencies.iter().map(|l| (l.method.as_str(), l.count)).collect();
+        methods.sort();
+        assert_eq!(methods, vec![("GET", 1), ("other", 3)]);
+    }
+
+    #[test]
+    fn labels_are_escaped() {
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -35,6 +35,108 @@
 //! }
 //! ```
 //!
+//! ## Post-processing the response
+//! After `chain.next(ctx)`, the response lives in `ctx.state` and can be
+//! modified through `ctx.state.response_mut()` before returning the context.
+//! An error returned by the rest of the chain, e.g. by another middleware,
+//! only becomes a response once it reaches the top of the stack, so
+//! `chain.next_with_response(ctx)` should be used instead to also
+//! post-process the response of such errors.
+//!
+//! ```rust
+//! # use saphir::{prelude::*, test::TestServer};
+//! # use saphir::http::HeaderValue;
+//! async fn served_by(ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+//!     let mut ctx = chain.next_with_response(ctx).await?;
+//!     if let Some(res) = ctx.state.response_mut() {
+//!         res.headers_mut().insert("x-served-by", HeaderValue::from_static("api-1"));
+//!     }
+//!     Ok(ctx)
+//! }
+//!
+//! async fn deny(ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+//!     match ctx.state.request().map(|r| r.uri().path()) {
+//!         Some("/denied") => Err(SaphirError::TooManyRequests),
+//!         _ => chain.next(ctx).await,
+//!     }
+//! }
+//!
+//! async fn hello(_req: Request) -> &'static str {
+//!     "hello"
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), SaphirError> {
+//! let client = TestServer::new(
+//!     Server::builder()
+//!         .configure_middlewares(|m| m.apply(deny, vec!["/"], None).apply(served_by, vec!["/"], None))
+//!         .configure_router(|r| r.route("/hello", Method::GET, hello)),
+//! )?
+//! .client();
+//!
+//! let res = client.get("/hello").send().await?;
+//! assert_eq!(res.header("x-served-by"), Some("api-1"));
+//! let res = client.get("/denied").send().await?;
+//! assert_eq!((res.status().as_u16(), res.header("x-served-by")), (429, Some("api-1")));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## Middlewares with state
+//! A middleware needing configuration is a struct implementing `Middleware`,
+//! most easily through the `#[middleware]` macro. Its fields are initialized
+//! at startup with a regular constructor and the middleware is then moved
+//! into the server stack by `apply`. The stack lives as long as the server,
+//! which is why `next` receives a `&'static self`; the fields themselves don't
+//! need to be `'static` nor leaked.
+//!
+//! ```rust
+//! # #[macro_use] extern crate saphir_macro;
+//! # use saphir::{prelude::*, test::TestServer};
+//! # use std::net::IpAddr;
+//! struct IpAllowlist {
+//!     allowed: Vec<IpAddr>,
+//! }
+//!
+//! #[middleware]
+//! impl IpAllowlist {
+//!     pub fn new(allowed: Vec<IpAddr>) -> Self {
+//!         IpAllowlist { allowed }
+//!     }
+//!
+//!     async fn next(&self, ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+//!         let ip = ctx.state.request().and_then(|r| r.peer_addr()).map(|addr| addr.ip());
+//!         match ip {
+//!             Some(ip) if self.allowed.contains(&ip) => chain.next(ctx).await,
+//!             _ => Err(SaphirError::responder(403)),
+//!         }
+//!     }
+//! }
+//!
+//! async fn hello(_req: Request) -> &'static str {
+//!     "hello"
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), SaphirError> {
+//! // e.g. read from the configuration
+//! let allowed = vec!["10.0.0.1".parse().unwrap()];
+//!
+//! let client = TestServer::new(
+//!     Server::builder()
+//!         .configure_middlewares(|m| m.apply(IpAllowlist::new(allowed), vec!["/"], None))
+//!         .configure_router(|r| r.route("/hello", Method::GET, hello)),
+//! )?
+//! .client();
+//!
+//! let res = client.get("/hello").peer_addr("10.0.0.1:4000".parse().unwrap()).send().await?;
+//! assert_eq!(res.status(), 200);
+//! let res = client.get("/hello").peer_addr("10.0.0.2:4000".parse().unwrap()).send().await?;
+//! assert_eq!(res.status(), 403);
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! *SAFETY NOTICE*
 //!
 //! Inside the middleware chain we need a little bit of unsafe code. This code
@@ -160,6 +262,21 @@ impl Rule {
 #[doc(hidden)]
 pub trait MiddlewareChain: Sync + Send {
     fn next(&self, ctx: HttpContext) -> BoxFuture<'static, Result<HttpContext, SaphirError>>;
+
+    /// Call the rest of the chain like `next`, but turn an error returned by it
+    /// into the response it produces, so that the response is available in
+    /// `ctx.state` whether it comes from a handler or from an error.
+    fn next_with_response(&self, ctx: HttpContext) -> BoxFuture<'static, Result<HttpContext, SaphirError>> {
+        let err_ctx = ctx.clone_with_empty_state();
+        let next = self.next(ctx);
+        async move {
+            match next.await {
+                Ok(ctx) => Ok(ctx),
+                Err(e) => e.into_context_response(err_ctx),
+            }
+        }
+        .boxed()
+    }
 }
 
 #[doc(hidden)]
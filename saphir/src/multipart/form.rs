@@ -0,0 +1,503 @@
+use super::{Multipart, MultipartError, SaveAllLimits};
+use crate::{
+    body::Bytes,
+    de::{deserialize_parsed, group, wrapper_impls, Repeated, Values},
+    error::SaphirError,
+    request::{FromRequest, Request},
+};
+use futures::future::BoxFuture;
+use mime::Mime;
+use serde::{
+    de::{
+        self,
+        value::{Error as DeError, MapDeserializer, SeqDeserializer, StrDeserializer, StringDeserializer},
+        DeserializeOwned, Deserializer, Error as _, IntoDeserializer, MapAccess, Visitor,
+    },
+    Deserialize,
+};
+use std::{fmt::Formatter, str::FromStr};
+
+/// Multipart body deserialized into `T`.
+///
+/// Each member of `T` is read from the fields of the same name: text fields
+/// are parsed into strings, numbers, booleans or unit enum variants, and file
+/// fields (fields sent with a filename) are loaded into a [`MultipartFile`],
+/// or written to a [`TempFile`](crate::body::TempFile) with the `temp-file`
+/// feature.
+///
+/// A field sent more than once is collected in order into a `Vec` member,
+/// and rejected for any other member. A missing field is rejected unless its
+/// member is an `Option` or has a `#[serde(default)]`; fields without a
+/// matching member are ignored. Bodies exceeding the default
+/// [`SaveAllLimits`] are rejected with a `413 Payload Too Large`, and any
+/// other invalid body with a `400 Bad Request`.
+///
+/// With the `validate-requests` feature, handlers of a `#[controller]` also
+/// validate `T` like they would a `Json<T>`.
+///
+/// ```rust
+/// use saphir::{multipart::{MultipartFile, MultipartForm}, prelude::*};
+/// use serde_derive::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Upload {
+///     title: String,
+///     tags: Vec<String>,
+///     draft: Option<bool>,
+///     document: MultipartFile,
+/// }
+///
+/// async fn upload(mut req: Request) -> Result<String, SaphirError> {
+///     let form = MultipartForm::<Upload>::from_request(&mut req).await?;
+///     Ok(format!("{} ({} bytes)", form.title, form.document.bytes.len()))
+/// }
+/// ```
+pub struct MultipartForm<T>(pub T);
+
+wrapper_impls!(MultipartForm);
+
+impl<T> FromRequest for MultipartForm<T>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    type Err = SaphirError;
+    type Fut = BoxFuture<'static, Result<Self, Self::Err>>;
+
+    fn from_request(req: &mut Request) -> Self::Fut {
+        let multipart = Multipart::from_request(req).into_inner();
+
+        Box::pin(async move {
+            let fields = multipart.map_err(SaphirError::responder)?.collect_fields(SaveAllLimits::default()).await;
+            let fields = fields.map_err(SaphirError::responder)?;
+
+            // Temp file members are written while deserializing
+            tokio::task::spawn_blocking(move || T::deserialize(MapDeserializer::new(fields.into_iter())))
+                .await
+                .map_err(|e| SaphirError::Other(format!("Multipart deserialization task failed: {}", e)))?
+                .map(MultipartForm)
+                .map_err(|e| SaphirError::responder(MultipartError::Fields(e)))
+        })
+    }
+}
+
+/// A file field of a [`MultipartForm`], loaded in memory
+#[derive(Clone, Debug)]
+pub struct MultipartFile {
+    /// Filename as sent by the client, unsanitized
+    pub filename: Option<String>,
+    /// Content type of the field
+    pub content_type: Mime,
+    /// Content of the file
+    pub bytes: Bytes,
+}
+
+const FILE_FIELDS: &[&str] = &["filename", "content_type", "bytes"];
+
+impl<'de> Deserialize<'de> for MultipartFile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FileVisitor;
+
+        impl<'de> Visitor<'de> for FileVisitor {
+            type Value = MultipartFile;
+
+            fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+                formatter.write_str("a multipart file")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let (mut filename, mut content_type, mut bytes) = (None, None, None);
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "filename" => filename = map.next_value::<Option<String>>()?,
+                        "content_type" => content_type = Some(map.next_value::<String>()?),
+                        "bytes" => bytes = Some(map.next_value::<ByteBuf>()?.0),
+                        _ => return Err(A::Error::unknown_field(&key, FILE_FIELDS)),
+                    }
+                }
+
+                Ok(MultipartFile {
+                    filename,
+                    content_type: content_type
+                        .map(|c_t| c_t.parse().map_err(A::Error::custom))
+                        .transpose()?
+                        .unwrap_or(mime::APPLICATION_OCTET_STREAM),
+                    bytes: Bytes::from(bytes.ok_or_else(|| A::Error::missing_field("bytes"))?),
+                })
+            }
+        }
+
+        deserializer.deserialize_struct("MultipartFile", FILE_FIELDS, FileVisitor)
+    }
+}
+
+#[cfg(feature = "temp-file")]
+impl<'de> Deserialize<'de> for crate::body::TempFile {
+    /// Write a [`MultipartForm`] file field to a temporary file. This blocks
+    /// the current thread, which `MultipartForm` accounts for.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let file = MultipartFile::deserialize(deserializer)?;
+        crate::body::TempFile::from_bytes_blocking(&file.bytes, Some(file.content_type)).map_err(D::Error::custom)
+    }
+}
+
+struct ByteBuf(Vec<u8>);
+
+impl<'de> Deserialize<'de> for ByteBuf {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ByteBufVisitor;
+
+        impl<'de> Visitor<'de> for ByteBufVisitor {
+            type Value = ByteBuf;
+
+            fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+                formatter.write_str("bytes")
+            }
+
+            fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+                Ok(ByteBuf(v.to_vec()))
+            }
+
+            fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+                Ok(ByteBuf(v))
+            }
+        }
+
+        deserializer.deserialize_byte_buf(ByteBufVisitor)
+    }
+}
+
+enum Part {
+    Text(String),
+    File(MultipartFile),
+}
+
+impl Multipart {
+    /// Read every field within `limits`, grouped by name in the order the
+    /// names first appear
+    async fn collect_fields(&self, limits: SaveAllLimits) -> Result<Vec<(String, Values<PartDeserializer>)>, MultipartError> {
+        let mut fields = Vec::new();
+        let mut field_count = 0;
+        let mut total_size = 0u64;
+
+        while let Some(field) = self.next_field().await? {
+            field_count += 1;
+            if limits.max_fields.filter(|max| field_count > *max).is_some() {
+                return Err(MultipartError::TooManyFields);
+            }
+
+            let name = field.name().to_string();
+            let filename = field.filename().map(str::to_string);
+            let content_type = field.content_type().clone();
+            let mut raw = field.raw.ok_or(MultipartError::AlreadyConsumed)?;
+
+            let mut content = Vec::new();
+            while let Some(bytes) = raw.chunk().await? {
+                total_size += bytes.len() as u64;
+                if limits.max_total_size.filter(|max| total_size > *max).is_some() {
+                    return Err(MultipartError::SizeLimitExceeded);
+                }
+                content.extend_from_slice(bytes.as_ref());
+            }
+
+            let part = match filename {
+                Some(filename) => Part::File(MultipartFile {
+                    filename: Some(filename),
+                    content_type,
+                    bytes: Bytes::from(content),
+                }),
+                None => Part::Text(String::from_utf8_lossy(&content).into_owned()),
+            };
+
+            fields.push((name.clone(), PartDeserializer { name, part }));
+        }
+
+        Ok(group(fields, Repeated::Reject))
+    }
+}
+
+/// A single part of a field
+struct PartDeserializer {
+    name: String,
+    part: Part,
+}
+
+impl PartDeserializer {
+    fn text(self) -> Result<String, DeError> {
+        match self.part {
+            Part::Text(text) => Ok(text),
+            Part::File(_) => Err(DeError::custom(format!("invalid file for field `{}`, expected a text field", self.name))),
+        }
+    }
+
+    fn parse<T: FromStr>(self) -> Result<T, DeError>
+    where
+        T::Err: std::fmt::Display,
+    {
+        let name = self.name.clone();
+        self.text()?
+            .trim()
+            .parse()
+            .map_err(|e| DeError::custom(format!("invalid value for field `{}`: {}", name, e)))
+    }
+}
+
+impl<'de> IntoDeserializer<'de, DeError> for PartDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for PartDeserializer {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.part {
+            Part::Text(text) => visitor.visit_string(text),
+            Part::File(file) => visitor.visit_map(FileAccess::new(file)),
+        }
+    }
+
+    deserialize_parsed!();
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.part {
+            Part::Text(text) => visitor.visit_string(text),
+            Part::File(file) => match String::from_utf8(Vec::from(file.bytes)) {
+                Ok(text) => visitor.visit_string(text),
+                Err(_) => Err(DeError::custom(format!("invalid utf-8 file for field `{}`", self.name))),
+            },
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.part {
+            Part::Text(text) => visitor.visit_byte_buf(text.into_bytes()),
+            Part::File(file) => visitor.visit_byte_buf(Vec::from(file.bytes)),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(SeqDeserializer::new(std::iter::once(self)))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, _len: usize, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error> {
+        match self.part {
+            Part::File(file) => visitor.visit_map(FileAccess::new(file)),
+            Part::Text(_) => Err(DeError::custom(format!("invalid text for field `{}`, expected a file field", self.name))),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error> {
+        let text: StringDeserializer<DeError> = self.text()?.into_deserializer();
+        visitor.visit_enum(text)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+}
+
+/// Map access over the `filename`, `content_type` and `bytes` of a file part
+struct FileAccess {
+    file: MultipartFile,
+    next: usize,
+}
+
+impl FileAccess {
+    fn new(file: MultipartFile) -> Self {
+        FileAccess { file, next: 0 }
+    }
+}
+
+impl<'de> MapAccess<'de> for FileAccess {
+    type Error = DeError;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error> {
+        match FILE_FIELDS.get(self.next) {
+            Some(key) => seed.deserialize(StrDeserializer::<DeError>::new(key)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Self::Error> {
+        self.next += 1;
+        match self.next {
+            1 => seed.deserialize(FileValue::Text(self.file.filename.take())),
+            2 => seed.deserialize(FileValue::Text(Some(self.file.content_type.to_string()))),
+            _ => seed.deserialize(FileValue::Bytes(std::mem::take(&mut self.file.bytes))),
+        }
+    }
+}
+
+enum FileValue {
+    Text(Option<String>),
+    Bytes(Bytes),
+}
+
+impl<'de> Deserializer<'de> for FileValue {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self {
+            FileValue::Text(Some(text)) => visitor.visit_string(text),
+            FileValue::Text(None) => visitor.visit_none(),
+            FileValue::Bytes(bytes) => visitor.visit_byte_buf(Vec::from(bytes)),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self {
+            FileValue::Text(None) => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::tests::multipart, *};
+    use serde_derive::Deserialize;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Visibility {
+        Public,
+        Private,
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct Upload {
+        title: String,
+        count: u32,
+        visibility: Visibility,
+        draft: Option<bool>,
+        #[serde(default)]
+        tags: Vec<String>,
+        document: MultipartFile,
+    }
+
+    async fn form<T: DeserializeOwned>(parts: &[(&str, Option<&str>, &str)]) -> Result<T, MultipartError> {
+        let fields = multipart(parts).collect_fields(SaveAllLimits::default()).await?;
+        T::deserialize(MapDeserializer::new(fields.into_iter())).map_err(MultipartError::Fields)
+    }
+
+    #[tokio::test]
+    async fn fields_are_deserialized() {
+        let upload: Upload = form(&[
+            ("title", None, "hello"),
+            ("tags", None, "a"),
+            ("count", None, "3"),
+            ("document", Some("doc.txt"), "content"),
+            ("visibility", None, "private"),
+            ("tags", None, "b"),
+            ("unknown", None, "ignored"),
+        ])
+        .await
+        .unwrap();
+
+        assert_eq!(upload.title, "hello");
+        assert_eq!(upload.count, 3);
+        assert_eq!(upload.visibility, Visibility::Private);
+        assert_eq!(upload.draft, None);
+        assert_eq!(upload.tags, vec!["a", "b"]);
+        assert_eq!(upload.document.filename.as_deref(), Some("doc.txt"));
+        assert_eq!(upload.document.content_type, mime::APPLICATION_OCTET_STREAM);
+        assert_eq!(upload.document.bytes.as_ref(), b"content");
+    }
+
+    #[tokio::test]
+    async fn invalid_fields_are_rejected() {
+        let error = |res: Result<Upload, MultipartError>| res.err().map(|e| e.to_string()).unwrap_or_default();
+        let document = ("document", Some("doc.txt"), "content");
+
+        let res = form(&[("title", None, "hello"), ("visibility", None, "public"), document]).await;
+        assert_eq!(error(res), "Fields: missing field `count`");
+
+        let res = form(&[
+            ("title", None, "a"),
+            ("title", None, "b"),
+            ("count", None, "1"),
+            ("visibility", None, "public"),
+            document,
+        ])
+        .await;
+        assert_eq!(error(res), "Fields: duplicate field `title`");
+
+        let res = form(&[("title", None, "a"), ("count", None, "many"), ("visibility", None, "public"), document]).await;
+        assert_eq!(error(res), "Fields: invalid value for field `count`: invalid digit found in string");
+
+        let res = form(&[
+            ("title", None, "a"),
+            ("count", None, "1"),
+            ("visibility", None, "public"),
+            ("document", None, "text"),
+        ])
+        .await;
+        assert_eq!(error(res), "Fields: invalid text for field `document`, expected a file field");
+    }
+
+    #[cfg(feature = "temp-file")]
+    #[tokio::test]
+    async fn files_are_written_to_temp_files() {
+        #[derive(Deserialize)]
+        struct Upload {
+            document: crate::body::TempFile,
+        }
+
+        let upload: Upload = form(&[("document", Some("doc.txt"), "content")]).await.unwrap();
+        let path = upload.document.path().to_path_buf();
+        assert_eq!(upload.document.size(), 7);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "content");
+
+        drop(upload);
+        assert!(!path.exists());
+    }
+}
@@ -1,5 +1,6 @@
 use crate::{
-    body::{Body, Bytes},
+    body::{raw::limited_chunks, Body, BodyInner, Bytes, REQUEST_BODY_BYTES_LIMIT},
+    error::SaphirError,
     http_context::HttpContext,
     request::{FromRequest, Request},
     responder::Responder,
@@ -10,14 +11,22 @@ use futures_util::stream::Stream;
 use mime::Mime;
 use multer::{Field as RawField, Multipart as RawMultipart};
 use std::{
+    collections::HashMap,
     fmt::{Debug, Formatter},
-    path::Path,
+    path::{Path, PathBuf},
     str::FromStr,
     sync::Arc,
 };
 use thiserror::Error;
 use tokio::sync::Mutex;
 
+#[cfg(feature = "form")]
+mod form;
+
+#[cfg(feature = "form")]
+#[cfg_attr(docsrs, doc(cfg(feature = "form")))]
+pub use form::{MultipartFile, MultipartForm};
+
 #[derive(Error, Debug)]
 pub enum MultipartError {
     #[error("Multer: {0}")]
@@ -33,6 +42,14 @@ pub enum MultipartError {
     MissingBoundary,
     #[error("Finished")]
     Finished,
+    /// Returned by [`Multipart::save_all`] when the body contains more fields
+    /// than allowed by the [`SaveAllLimits`]
+    #[error("Too many fields")]
+    TooManyFields,
+    /// Returned by [`Multipart::save_all`] when the total size of the fields
+    /// exceeds the [`SaveAllLimits`]
+    #[error("Size limit exceeded")]
+    SizeLimitExceeded,
     #[error("Hyper: {0}")]
     Hyper(hyper::Error),
     #[error("Io: {0}")]
@@ -45,11 +62,26 @@ pub enum MultipartError {
     #[cfg_attr(docsrs, doc(cfg(feature = "form")))]
     #[error("Form: {0}")]
     Form(serde_urlencoded::de::Error),
+    /// Returned by [`MultipartForm`] when the fields can't be deserialized
+    #[cfg(feature = "form")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "form")))]
+    #[error("Fields: {0}")]
+    Fields(serde::de::value::Error),
 }
 
 impl From<multer::Error> for MultipartError {
     fn from(e: multer::Error) -> Self {
-        Self::Multer(e)
+        match e {
+            // Errors of the body stream, e.g. its size limit, are kept as raised
+            multer::Error::StreamReadFailed(e) => match e.downcast::<MultipartError>() {
+                Ok(e) => *e,
+                Err(e) => match e.downcast::<multer::Error>() {
+                    Ok(e) => Self::from(*e),
+                    Err(e) => Self::Multer(multer::Error::StreamReadFailed(e)),
+                },
+            },
+            e => Self::Multer(e),
+        }
     }
 }
 
@@ -69,7 +101,10 @@ impl Responder for MultipartError {
         };
 
         debug!("{}Unable to parse multipart data: {:?}", op_id, &self);
-        builder.status(400)
+        match self {
+            MultipartError::SizeLimitExceeded => builder.status(413),
+            _ => builder.status(400),
+        }
     }
 }
 
@@ -279,9 +314,12 @@ impl FromRequest for Multipart {
             .map(|name| name.to_string())
             .ok_or(MultipartError::MissingBoundary);
 
-        let stream = req.body_mut().take().into_raw().map_err(MultipartError::Hyper);
+        let inner = req.body_mut().take().inner.unwrap_or_else(BodyInner::empty);
 
-        futures::future::ready(boundary.map(|boundary| Self::from_part(boundary, stream)))
+        // # SAFETY #
+        // The limit is only written once, before the server starts.
+        let limit = unsafe { REQUEST_BODY_BYTES_LIMIT };
+        futures::future::ready(boundary.map(|boundary| Self::from_chunks(boundary, inner, limit)))
     }
 }
 
@@ -298,6 +336,17 @@ impl Multipart {
         }
     }
 
+    /// Parse a body, failing with `SizeLimitExceeded` once it exceeds
+    /// `limit`. Chunked bodies have no content length to be checked before
+    /// the request is handled, so the limit is enforced while they are read.
+    fn from_chunks(boundary: String, inner: BodyInner, limit: Option<usize>) -> Self {
+        let stream = limited_chunks(inner, limit).map_err(|e| match e {
+            SaphirError::PayloadTooLarge => MultipartError::SizeLimitExceeded,
+            e => MultipartError::Io(std::io::Error::other(e)),
+        });
+        Self::from_part(boundary, Box::pin(stream))
+    }
+
     /// Yields the next [`MultipartField`] if available.
     ///
     /// Any previous `Field` returned by this method must be dropped before
@@ -310,3 +359,348 @@ impl Multipart {
         Ok(next_field.map(Field::from))
     }
 }
+
+/// Limits applied by [`Multipart::save_all`] while reading the fields
+#[derive(Clone, Debug)]
+pub struct SaveAllLimits {
+    max_total_size: Option<u64>,
+    max_fields: Option<usize>,
+}
+
+impl Default for SaveAllLimits {
+    /// Defaults to a maximum of 64 fields totaling 64 MiB
+    fn default() -> Self {
+        SaveAllLimits {
+            max_total_size: Some(64 * 1024 * 1024),
+            max_fields: Some(64),
+        }
+    }
+}
+
+impl SaveAllLimits {
+    /// Limits without any bound, should only be used with trusted clients
+    pub fn unlimited() -> Self {
+        SaveAllLimits {
+            max_total_size: None,
+            max_fields: None,
+        }
+    }
+
+    /// Maximum number of bytes read across all fields, files and text
+    #[inline]
+    pub fn max_total_size(mut self, max: Option<u64>) -> Self {
+        self.max_total_size = max;
+        self
+    }
+
+    /// Maximum number of fields, files and text
+    #[inline]
+    pub fn max_fields(mut self, max: Option<usize>) -> Self {
+        self.max_fields = max;
+        self
+    }
+}
+
+/// A file field written on disk by [`Multipart::save_all`]
+#[derive(Clone, Debug)]
+pub struct SavedFile {
+    /// Name of the form-data field
+    pub name: String,
+    /// Filename as sent by the client, unsanitized
+    pub original_filename: String,
+    /// Sanitized filename used to write the file
+    pub filename: String,
+    /// Content type of the field
+    pub content_type: Mime,
+    /// Number of bytes written
+    pub size: u64,
+    /// Path of the written file
+    pub path: PathBuf,
+}
+
+/// Result of [`Multipart::save_all`]
+#[derive(Clone, Debug, Default)]
+pub struct SavedMultipart {
+    /// File fields, in the order they were received
+    pub files: Vec<SavedFile>,
+    /// Text fields (fields without a filename), by field name
+    pub fields: HashMap<String, String>,
+}
+
+impl Multipart {
+    /// Save every file field of the multipart body into `dir` and collect the
+    /// text fields, using the default [`SaveAllLimits`].
+    ///
+    /// Filenames are sanitized so that a file can never be written outside
+    /// of `dir`, and suffixed with a counter rather than overwriting an
+    /// existing file.
+    pub async fn save_all<P: AsRef<Path>>(&self, dir: P) -> Result<SavedMultipart, MultipartError> {
+        self.save_all_with_limits(dir, SaveAllLimits::default()).await
+    }
+
+    /// Same as [`Multipart::save_all`], with custom limits.
+    ///
+    /// If a limit is exceeded, or if any error occurs, the files written so
+    /// far are removed.
+    pub async fn save_all_with_limits<P: AsRef<Path>>(&self, dir: P, limits: SaveAllLimits) -> Result<SavedMultipart, MultipartError> {
+        let mut saved = SavedMultipart::default();
+        let res = self.save_all_into(dir.as_ref(), &limits, &mut saved).await;
+
+        if res.is_err() {
+            for file in &saved.files {
+                let _ = tokio::fs::remove_file(&file.path).await;
+            }
+        }
+
+        res.map(|_| saved)
+    }
+
+    async fn save_all_into(&self, dir: &Path, limits: &SaveAllLimits, saved: &mut SavedMultipart) -> Result<(), MultipartError> {
+        use tokio::io::AsyncWriteExt;
+
+        let mut field_count = 0;
+        let mut total_size = 0u64;
+        let mut add_to_total = |len: usize| {
+            total_size += len as u64;
+            match limits.max_total_size {
+                Some(max) if total_size > max => Err(MultipartError::SizeLimitExceeded),
+                _ => Ok(()),
+            }
+        };
+
+        while let Some(field) = self.next_field().await? {
+            field_count += 1;
+            if limits.max_fields.filter(|max| field_count > *max).is_some() {
+                return Err(MultipartError::TooManyFields);
+            }
+
+            let name = field.name().to_string();
+            let content_type = field.content_type().clone();
+            let original_filename = field.filename().map(|f| f.to_string());
+            let mut raw = field.raw.ok_or(MultipartError::AlreadyConsumed)?;
+
+            match original_filename {
+                Some(original_filename) => {
+                    let (filename, path, mut file) = create_unique_file(dir, sanitize_filename(&original_filename)).await?;
+                    let mut size = 0u64;
+                    let write_res = async {
+                        while let Some(bytes) = raw.chunk().await? {
+                            add_to_total(bytes.len())?;
+                            file.write_all(bytes.as_ref()).await.map_err(MultipartError::Io)?;
+                            size += bytes.len() as u64;
+                        }
+                        file.flush().await.map_err(MultipartError::Io)
+                    }
+                    .await;
+
+                    if let Err(e) = write_res {
+                        drop(file);
+                        let _ = tokio::fs::remove_file(&path).await;
+                        return Err(e);
+                    }
+
+                    saved.files.push(SavedFile {
+                        name,
+                        original_filename,
+                        filename,
+                        content_type,
+                        size,
+                        path,
+                    });
+                }
+                None => {
+                    let mut text = Vec::new();
+                    while let Some(bytes) = raw.chunk().await? {
+                        add_to_total(bytes.len())?;
+                        text.extend_from_slice(bytes.as_ref());
+                    }
+                    saved.fields.insert(name, String::from_utf8_lossy(&text).into_owned());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Strip any directory component and any character that could be
+/// interpreted by the filesystem from a client provided filename.
+fn sanitize_filename(filename: &str) -> String {
+    let base = filename.rsplit(['/', '\\']).next().unwrap_or_default();
+    let sanitized: String = base
+        .chars()
+        .map(|c| match c {
+            c if c.is_control() => '_',
+            ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect();
+    let sanitized = sanitized.trim().trim_start_matches('.');
+
+    if sanitized.is_empty() {
+        "file".to_string()
+    } else {
+        sanitized.to_string()
+    }
+}
+
+/// Create a new file in `dir`, suffixing the filename with a counter rather
+/// than overwriting an existing file, whether it was saved from the same body
+/// or was already there.
+async fn create_unique_file(dir: &Path, filename: String) -> Result<(String, PathBuf, tokio::fs::File), MultipartError> {
+    let (stem, ext) = match filename.rsplit_once('.') {
+        Some((stem, ext)) => (stem.to_string(), Some(ext.to_string())),
+        None => (filename.clone(), None),
+    };
+    let candidates = std::iter::once(filename).chain((1..).map(|i| match &ext {
+        Some(ext) => format!("{}-{}.{}", stem, i, ext),
+        None => format!("{}-{}", stem, i),
+    }));
+
+    for candidate in candidates {
+        let path = dir.join(&candidate);
+        match tokio::fs::OpenOptions::new().write(true).create_new(true).open(&path).await {
+            Ok(file) => return Ok((candidate, path, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(MultipartError::Io(e)),
+        }
+    }
+    unreachable!("There is always an unused filename")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOUNDARY: &str = "SAPHIR_BOUNDARY";
+
+    pub(super) fn multipart(parts: &[(&str, Option<&str>, &str)]) -> Multipart {
+        Multipart::from_part(BOUNDARY.to_string(), futures::stream::iter(vec![Ok(Bytes::from(multipart_body(parts)))]))
+    }
+
+    fn multipart_body(parts: &[(&str, Option<&str>, &str)]) -> String {
+        let mut body = String::new();
+        for (name, filename, content) in parts {
+            body.push_str(&format!("--{}\r\n", BOUNDARY));
+            match filename {
+                Some(filename) => body.push_str(&format!(
+                    "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+                    name, filename
+                )),
+                None => body.push_str(&format!("Content-Disposition: form-data; name=\"{}\"\r\n\r\n", name)),
+            }
+            body.push_str(content);
+            body.push_str("\r\n");
+        }
+        body.push_str(&format!("--{}--\r\n", BOUNDARY));
+        body
+    }
+
+    /// Body sent with `Transfer-Encoding: chunked`, in chunks of `size` bytes
+    fn chunked(body: String, size: usize) -> BodyInner {
+        let chunks: Vec<_> = body
+            .into_bytes()
+            .chunks(size)
+            .map(|c| Ok::<_, std::io::Error>(Bytes::copy_from_slice(c)))
+            .collect();
+        BodyInner::from_raw(hyper::Body::wrap_stream(futures::stream::iter(chunks)))
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("saphir-multipart-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn sanitize_filename_prevents_traversal() {
+        assert_eq!(sanitize_filename("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize_filename("..\\..\\windows\\win.ini"), "win.ini");
+        assert_eq!(sanitize_filename("/abs/path.txt"), "path.txt");
+        assert_eq!(sanitize_filename(".."), "file");
+        assert_eq!(sanitize_filename(".hidden"), "hidden");
+        assert_eq!(sanitize_filename("a:b?.txt"), "a_b_.txt");
+        assert_eq!(sanitize_filename(""), "file");
+    }
+
+    #[tokio::test]
+    async fn save_all_writes_files_and_collects_fields() {
+        let dir = temp_dir("save-all");
+        let m = multipart(&[
+            ("title", None, "hello"),
+            ("upload", Some("../evil.txt"), "content"),
+            ("upload2", Some("evil.txt"), "other"),
+        ]);
+
+        let saved = m.save_all(&dir).await.unwrap();
+        assert_eq!(saved.fields.get("title").map(String::as_str), Some("hello"));
+        assert_eq!(saved.files.len(), 2);
+        assert_eq!(saved.files[0].name, "upload");
+        assert_eq!(saved.files[0].original_filename, "../evil.txt");
+        assert_eq!(saved.files[0].filename, "evil.txt");
+        assert_eq!(saved.files[0].size, 7);
+        assert_eq!(saved.files[0].path, dir.join("evil.txt"));
+        assert_eq!(saved.files[1].filename, "evil-1.txt");
+        assert_eq!(std::fs::read_to_string(&saved.files[0].path).unwrap(), "content");
+        assert_eq!(std::fs::read_to_string(&saved.files[1].path).unwrap(), "other");
+
+        // Files already in the directory are not overwritten
+        let m = multipart(&[("upload", Some("evil.txt"), "again")]);
+        let saved = m.save_all(&dir).await.unwrap();
+        assert_eq!(saved.files[0].filename, "evil-2.txt");
+        assert_eq!(std::fs::read_to_string(dir.join("evil.txt")).unwrap(), "content");
+        assert_eq!(std::fs::read_to_string(&saved.files[0].path).unwrap(), "again");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn save_all_enforces_limits() {
+        let dir = temp_dir("save-all-limits");
+
+        let m = multipart(&[("a", None, "1"), ("b", None, "2")]);
+        let res = m.save_all_with_limits(&dir, SaveAllLimits::unlimited().max_fields(Some(1))).await;
+        assert!(matches!(res, Err(MultipartError::TooManyFields)));
+
+        let m = multipart(&[("first", Some("first.bin"), "12345"), ("second", Some("second.bin"), "67890")]);
+        let res = m.save_all_with_limits(&dir, SaveAllLimits::unlimited().max_total_size(Some(8))).await;
+        assert!(matches!(res, Err(MultipartError::SizeLimitExceeded)));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn parses_chunked_bodies() {
+        let body = multipart_body(&[("title", None, "hello"), ("upload", Some("a.txt"), "some file content")]);
+
+        // Chunk boundaries fall everywhere, including inside the boundaries
+        for size in [1, 2, 7, 16] {
+            let m = Multipart::from_chunks(BOUNDARY.to_string(), chunked(body.clone(), size), None);
+            let title = m.next_field().await.unwrap().unwrap();
+            assert_eq!(title.name(), "title");
+            assert_eq!(title.to_text().await.unwrap(), "hello");
+            let upload = m.next_field().await.unwrap().unwrap();
+            assert_eq!(upload.filename(), Some("a.txt"));
+            assert_eq!(upload.to_text().await.unwrap(), "some file content");
+            assert!(m.next_field().await.unwrap().is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn chunked_bodies_are_limited() {
+        let body = multipart_body(&[("upload", Some("a.txt"), "some file content")]);
+
+        let m = Multipart::from_chunks(BOUNDARY.to_string(), chunked(body.clone(), 8), Some(body.len()));
+        assert!(m.next_field().await.unwrap().unwrap().to_text().await.is_ok());
+
+        let m = Multipart::from_chunks(BOUNDARY.to_string(), chunked(body.clone(), 8), Some(body.len() / 2));
+        let res = match m.next_field().await {
+            Ok(Some(field)) => field.to_text().await.map(|_| ()),
+            Ok(None) => Ok(()),
+            Err(e) => Err(e),
+        };
+        assert!(matches!(res, Err(MultipartError::SizeLimitExceeded)));
+    }
+}
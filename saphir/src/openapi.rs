@@ -0,0 +1,369 @@
+//! OpenAPI document assembled at runtime, without the build step of the CLI.
+//!
+//! Operations are described with an [`Operation`], whose request and response
+//! schemas are derived from types implementing
+//! [`JsonSchema`](schemars::JsonSchema), then attached to the routes with
+//! [`Builder::describe`](crate::router::Builder::describe) or to the endpoints
+//! of a controller with
+//! [`Controller::operations`](crate::controller::Controller::operations).
+//! [`Builder::openapi`](crate::router::Builder::openapi) serves the document
+//! assembled from every described operation.
+//!
+//! ```rust
+//! use saphir::{openapi::{Info, Operation}, prelude::*};
+//! use schemars::JsonSchema;
+//! # use serde_derive::{Deserialize, Serialize};
+//!
+//! #[derive(Deserialize, Serialize, JsonSchema)]
+//! struct User {
+//!     name: String,
+//! }
+//!
+//! async fn create_user(mut req: Request) -> Result<Json<User>, SaphirError> {
+//!     Ok(Json(req.body_mut().take_as::<Json<User>>().await?))
+//! }
+//!
+//! let server = Server::builder()
+//!     .configure_router(|r| {
+//!         r.route("/users", Method::POST, create_user)
+//!             .describe(
+//!                 "/users",
+//!                 Method::POST,
+//!                 Operation::new().summary("Create a user").request_body::<User>().response::<User>(200, "The created user"),
+//!             )
+//!             .openapi("/openapi.json", Info::new("Users", "1.0.0"))
+//!     })
+//!     .build();
+//! ```
+
+use http::Method;
+use schemars::{
+    gen::{SchemaGenerator, SchemaSettings},
+    schema::Schema,
+    JsonSchema,
+};
+use serde_json::{json, Map, Value};
+
+/// Version of the OpenAPI specification of the documents
+pub const OPENAPI_VERSION: &str = "3.0.3";
+
+type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+/// General information about the API
+#[derive(Clone, Debug)]
+pub struct Info {
+    title: String,
+    version: String,
+    description: Option<String>,
+}
+
+impl Info {
+    pub fn new(title: impl Into<String>, version: impl Into<String>) -> Self {
+        Info {
+            title: title.into(),
+            version: version.into(),
+            description: None,
+        }
+    }
+
+    /// Set the description of the API, which may use markdown
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+/// Content of a request or response body, described by the schema of a type
+#[derive(Clone)]
+struct Content {
+    content_type: String,
+    schema: SchemaFn,
+}
+
+#[derive(Clone)]
+struct Response {
+    status: u16,
+    description: String,
+    content: Option<Content>,
+}
+
+/// Description of an operation, i.e. a method on a path
+#[derive(Clone, Default)]
+pub struct Operation {
+    summary: Option<String>,
+    description: Option<String>,
+    tags: Vec<String>,
+    request_body: Option<Content>,
+    responses: Vec<Response>,
+}
+
+impl Operation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the short summary of the operation
+    pub fn summary(mut self, summary: impl Into<String>) -> Self {
+        self.summary = Some(summary.into());
+        self
+    }
+
+    /// Set the description of the operation, which may use markdown
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Add a tag grouping the operation with others
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Set the schema of the json request body
+    pub fn request_body<T: JsonSchema>(self) -> Self {
+        self.request_body_with_content_type::<T>("application/json")
+    }
+
+    /// Set the schema of the request body sent as `content_type`
+    pub fn request_body_with_content_type<T: JsonSchema>(mut self, content_type: impl Into<String>) -> Self {
+        self.request_body = Some(Content {
+            content_type: content_type.into(),
+            schema: SchemaGenerator::subschema_for::<T>,
+        });
+        self
+    }
+
+    /// Add a response of `status` with a json body
+    pub fn response<T: JsonSchema>(self, status: u16, description: impl Into<String>) -> Self {
+        self.response_with_content_type::<T>(status, description, "application/json")
+    }
+
+    /// Add a response of `status` with a body sent as `content_type`
+    pub fn response_with_content_type<T: JsonSchema>(mut self, status: u16, description: impl Into<String>, content_type: impl Into<String>) -> Self {
+        self.responses.push(Response {
+            status,
+            description: description.into(),
+            content: Some(Content {
+                content_type: content_type.into(),
+                schema: SchemaGenerator::subschema_for::<T>,
+            }),
+        });
+        self
+    }
+
+    /// Add a response of `status` without a body
+    pub fn empty_response(mut self, status: u16, description: impl Into<String>) -> Self {
+        self.responses.push(Response {
+            status,
+            description: description.into(),
+            content: None,
+        });
+        self
+    }
+
+    fn to_json(&self, path_params: &[&str], gen: &mut SchemaGenerator) -> Value {
+        let mut operation = Map::new();
+        if let Some(summary) = &self.summary {
+            operation.insert("summary".into(), summary.as_str().into());
+        }
+        if let Some(description) = &self.description {
+            operation.insert("description".into(), description.as_str().into());
+        }
+        if !self.tags.is_empty() {
+            operation.insert("tags".into(), self.tags.clone().into());
+        }
+        if !path_params.is_empty() {
+            let params = path_params
+                .iter()
+                .map(|name| json!({"name": name, "in": "path", "required": true, "schema": {"type": "string"}}))
+                .collect();
+            operation.insert("parameters".into(), Value::Array(params));
+        }
+        if let Some(content) = &self.request_body {
+            operation.insert("requestBody".into(), json!({"required": true, "content": content.to_json(gen)}));
+        }
+
+        let mut responses = Map::new();
+        for response in &self.responses {
+            let mut value = json!({ "description": response.description });
+            if let Some(content) = &response.content {
+                value["content"] = content.to_json(gen);
+            }
+            responses.insert(response.status.to_string(), value);
+        }
+        if responses.is_empty() {
+            responses.insert("default".into(), json!({"description": "Default response"}));
+        }
+        operation.insert("responses".into(), Value::Object(responses));
+
+        Value::Object(operation)
+    }
+}
+
+impl Content {
+    fn to_json(&self, gen: &mut SchemaGenerator) -> Value {
+        let schema = (self.schema)(gen);
+        json!({ &self.content_type: { "schema": schema } })
+    }
+}
+
+/// OpenAPI document of the described operations
+#[derive(Clone)]
+pub struct OpenApi {
+    info: Info,
+    operations: Vec<(String, Method, Operation)>,
+}
+
+impl OpenApi {
+    pub fn new(info: Info) -> Self {
+        OpenApi { info, operations: Vec::new() }
+    }
+
+    /// Add the operation of `method` on the `route` template, whose variable
+    /// segments, e.g. `<id>` or `{id}`, are documented as path parameters
+    pub fn operation(mut self, route: impl Into<String>, method: Method, operation: Operation) -> Self {
+        self.operations.push((route.into(), method, operation));
+        self
+    }
+
+    /// Render the document, the schemas of the named types being collected
+    /// in its `components`
+    pub fn to_json(&self) -> Value {
+        let mut gen = SchemaSettings::openapi3().into_generator();
+        let mut paths = Map::new();
+        for (route, method, operation) in &self.operations {
+            let (path, params) = openapi_path(route);
+            let item = paths.entry(path).or_insert_with(|| Value::Object(Map::new()));
+            item[method.as_str().to_ascii_lowercase()] = operation.to_json(&params, &mut gen);
+        }
+
+        let mut info = json!({ "title": self.info.title, "version": self.info.version });
+        if let Some(description) = &self.info.description {
+            info["description"] = description.as_str().into();
+        }
+
+        json!({
+            "openapi": OPENAPI_VERSION,
+            "info": info,
+            "paths": paths,
+            "components": { "schemas": gen.take_definitions() },
+        })
+    }
+}
+
+/// Path of a route template in the OpenAPI syntax, with the names of its
+/// variable segments
+fn openapi_path(route: &str) -> (String, Vec<&str>) {
+    let mut params = Vec::new();
+    let path = route
+        .split('/')
+        .map(|segment| {
+            let variable = segment
+                .strip_prefix(['<', '{'])
+                .and_then(|s| s.strip_suffix(['>', '}']))
+                .and_then(|s| s.split("#r").next())
+                .filter(|name| !name.is_empty());
+            match variable {
+                Some(name) => {
+                    params.push(name);
+                    format!("{{{}}}", name)
+                }
+                None => segment.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+    (path, params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{body::Json, prelude::*, test::TestServer};
+    use serde_derive::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Serialize, JsonSchema)]
+    struct User {
+        name: String,
+        age: Option<u8>,
+    }
+
+    #[test]
+    fn path_variables_are_parameters() {
+        assert_eq!(
+            openapi_path("/users/<id>/posts/{post#r(\\d+)}"),
+            ("/users/{id}/posts/{post}".to_string(), vec!["id", "post"])
+        );
+        assert_eq!(openapi_path("/"), ("/".to_string(), vec![]));
+    }
+
+    #[test]
+    fn schemas_are_components() {
+        let doc = OpenApi::new(Info::new("Users", "1.0.0").description("Users API"))
+            .operation("/users", Method::POST, Operation::new().request_body::<User>().response::<User>(201, "Created"))
+            .operation(
+                "/users/<id>",
+                Method::GET,
+                Operation::new().summary("Get a user").response::<User>(200, "The user"),
+            )
+            .operation("/users/<id>", Method::DELETE, Operation::new().empty_response(204, "Deleted"))
+            .to_json();
+
+        assert_eq!(doc["openapi"], OPENAPI_VERSION);
+        assert_eq!(doc["info"]["description"], "Users API");
+        assert_eq!(
+            doc["paths"]["/users"]["post"]["requestBody"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/User"
+        );
+        assert_eq!(doc["paths"]["/users/{id}"]["get"]["summary"], "Get a user");
+        assert_eq!(doc["paths"]["/users/{id}"]["get"]["parameters"][0]["name"], "id");
+        assert_eq!(doc["paths"]["/users/{id}"]["delete"]["responses"]["204"], json!({"description": "Deleted"}));
+        assert_eq!(doc["components"]["schemas"]["User"]["required"], json!(["name"]));
+    }
+
+    struct UsersController;
+
+    impl UsersController {
+        async fn get(&self, _req: Request) -> Json<User> {
+            Json(User { name: "jo".into(), age: None })
+        }
+    }
+
+    impl Controller for UsersController {
+        const BASE_PATH: &'static str = "/users";
+
+        fn handlers(&self) -> Vec<ControllerEndpoint<Self>> {
+            EndpointsBuilder::new().add(Method::GET, "/<id>", UsersController::get).build()
+        }
+
+        fn operations(&self) -> Vec<(Method, &'static str, Operation)> {
+            vec![(Method::GET, "/<id>", Operation::new().response::<User>(200, "The user"))]
+        }
+    }
+
+    #[tokio::test]
+    async fn document_is_served() {
+        async fn status(_req: Request) -> u16 {
+            200
+        }
+
+        let client = TestServer::new(Server::builder().configure_router(|r| {
+            r.openapi("/openapi.json", Info::new("Users", "1.0.0")).mount("/v1", |r| {
+                r.controller(UsersController)
+                    .route("/status", Method::GET, status)
+                    .describe("/status", Method::GET, Operation::new().empty_response(200, "Up"))
+            })
+        }))
+        .unwrap()
+        .client();
+
+        let res = client.get("/openapi.json").send().await.unwrap();
+        assert_eq!(res.status(), 200);
+        let doc: Value = serde_json::from_str(&res.text().unwrap()).unwrap();
+        assert_eq!(doc["info"]["title"], "Users");
+        assert!(doc["paths"]["/v1/users/{id}"]["get"]["responses"]["200"].is_object());
+        assert!(doc["paths"]["/v1/status"]["get"]["responses"]["200"].is_object());
+        assert!(doc["components"]["schemas"]["User"].is_object());
+    }
+}
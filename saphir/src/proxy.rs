@@ -0,0 +1,175 @@
+//! Reverse proxy helpers.
+//!
+//! [`upstream_request`] turns an incoming request into a request to an
+//! upstream server, and [`downstream_response`] turns the response of the
+//! upstream server into a response [`Builder`]. Both keep the body as a raw
+//! hyper body, so it is streamed through the server without being buffered,
+//! and strip the hop-by-hop headers, which only apply to a single connection.
+//!
+//! Sending the upstream request is left to the http client of the
+//! application, e.g. a `hyper::Client`:
+//!
+//! ```rust,ignore
+//! use saphir::{prelude::*, proxy};
+//!
+//! async fn forward(req: Request) -> Result<Builder, SaphirError> {
+//!     let upstream = proxy::upstream_request(req, &"http://backend:8080/api".parse()?)?;
+//!     let res = CLIENT.request(upstream).await?;
+//!     Ok(proxy::downstream_response(res))
+//! }
+//! ```
+//!
+//! The request body limit of the listener is not enforced on the forwarded
+//! bodies.
+
+use crate::{error::SaphirError, request::Request, response::Builder};
+use http::{
+    header::{self, HeaderName},
+    uri::PathAndQuery,
+    HeaderMap, HeaderValue, Uri, Version,
+};
+use hyper::Body as RawBody;
+
+/// Headers which only apply to a single connection, as listed by RFC 9110
+const HOP_BY_HOP_HEADERS: [HeaderName; 8] = [
+    header::CONNECTION,
+    HeaderName::from_static("keep-alive"),
+    header::PROXY_AUTHENTICATE,
+    header::PROXY_AUTHORIZATION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+/// Remove the hop-by-hop headers, including the ones named by the
+/// `Connection` header
+pub fn strip_hop_by_hop_headers(headers: &mut HeaderMap) {
+    let named: Vec<HeaderName> = headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|h| h.to_str().ok())
+        .flat_map(|h| h.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect();
+
+    for name in named.iter().chain(HOP_BY_HOP_HEADERS.iter()) {
+        headers.remove(name);
+    }
+}
+
+/// Build the request forwarding `req` to `upstream`.
+///
+/// The method, headers and body of `req` are kept, and its path and query are
+/// appended to the path of `upstream`, so that `/users?page=2` forwarded to
+/// `http://backend:8080/api` requests `http://backend:8080/api/users?page=2`.
+/// The `Host` header is kept as well, and set from the authority of HTTP/2
+/// requests, which are forwarded with the default version of the client.
+pub fn upstream_request(req: Request, upstream: &Uri) -> Result<hyper::Request<RawBody>, SaphirError> {
+    let req: http::Request<_> = req.into();
+    let (mut parts, body) = req.into_parts();
+
+    parts.version = Version::default();
+    if let (false, Some(authority)) = (parts.headers.contains_key(header::HOST), parts.uri.authority()) {
+        parts.headers.insert(header::HOST, HeaderValue::from_str(authority.as_str())?);
+    }
+
+    let prefix = upstream.path().trim_end_matches('/');
+    let path_and_query = parts.uri.path_and_query().map(PathAndQuery::as_str).unwrap_or("/");
+    let mut uri = Uri::builder().path_and_query(format!("{}{}", prefix, path_and_query));
+    if let Some(scheme) = upstream.scheme() {
+        uri = uri.scheme(scheme.clone());
+    }
+    if let Some(authority) = upstream.authority() {
+        uri = uri.authority(authority.clone());
+    }
+    parts.uri = uri.build()?;
+    strip_hop_by_hop_headers(&mut parts.headers);
+
+    Ok(hyper::Request::from_parts(parts, body.into_raw()))
+}
+
+/// Build the response relaying `res`, the response of an upstream server
+pub fn downstream_response(res: hyper::Response<RawBody>) -> Builder {
+    let (mut parts, body) = res.into_parts();
+    strip_hop_by_hop_headers(&mut parts.headers);
+
+    let mut builder = Builder::new().status(parts.status).body(body);
+    if let Some(headers) = builder.headers_mut() {
+        *headers = parts.headers;
+    }
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{body::Body, server::Server, test::TestServer};
+    use http::Method;
+    use hyper::body::HttpBody;
+
+    async fn echo(req: Request) -> Result<Builder, SaphirError> {
+        let mut upstream = upstream_request(req, &"http://backend:8080/api/".parse().unwrap())?;
+        assert_eq!(upstream.uri(), "http://backend:8080/api/echo?x=1");
+        assert!(upstream.body().size_hint().exact().is_none());
+
+        let mut res = hyper::Response::new(std::mem::take(upstream.body_mut()));
+        res.headers_mut().insert(header::CONNECTION, "close, x-hop".parse().unwrap());
+        res.headers_mut().insert("x-hop", "1".parse().unwrap());
+        res.headers_mut().extend(upstream.headers().clone());
+        Ok(downstream_response(res))
+    }
+
+    #[tokio::test]
+    async fn bodies_are_streamed_through() {
+        let client = TestServer::new(Server::builder().configure_router(|r| r.route("/echo", Method::POST, echo)))
+            .unwrap()
+            .client();
+
+        let chunks = futures::stream::iter(["hello ", "proxy"].map(Ok::<_, std::io::Error>));
+        let res = client
+            .post("/echo?x=1")
+            .header("x-custom", "kept")
+            .header(header::CONNECTION, "keep-alive")
+            .header("keep-alive", "timeout=5")
+            .body(RawBody::wrap_stream(chunks))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.header("x-custom"), Some("kept"));
+        assert_eq!(res.header("keep-alive"), None);
+        assert_eq!(res.header("x-hop"), None);
+        assert_eq!(res.text().unwrap(), "hello proxy");
+    }
+
+    #[test]
+    fn http2_requests_are_forwarded_as_http1() {
+        let raw = http::Request::get("https://saphir.rs/echo?x=1")
+            .version(Version::HTTP_2)
+            .body(Body::empty())
+            .unwrap();
+        let upstream = upstream_request(Request::new(raw, None), &"http://backend:8080".parse().unwrap()).unwrap();
+        assert_eq!(upstream.version(), Version::HTTP_11);
+        assert_eq!(upstream.uri(), "http://backend:8080/echo?x=1");
+        assert_eq!(upstream.headers()[header::HOST], "saphir.rs");
+
+        let raw = http::Request::get("/echo").header(header::HOST, "saphir.rs:8080").body(Body::empty()).unwrap();
+        let upstream = upstream_request(Request::new(raw, None), &"http://backend".parse().unwrap()).unwrap();
+        assert_eq!(upstream.headers()[header::HOST], "saphir.rs:8080");
+    }
+
+    #[test]
+    fn hop_by_hop_headers_are_stripped() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONNECTION, "Upgrade, X-Internal".parse().unwrap());
+        headers.insert(header::UPGRADE, "websocket".parse().unwrap());
+        headers.insert(header::TRANSFER_ENCODING, "chunked".parse().unwrap());
+        headers.insert("x-internal", "secret".parse().unwrap());
+        headers.insert(header::ACCEPT, "*/*".parse().unwrap());
+
+        strip_hop_by_hop_headers(&mut headers);
+        assert_eq!(headers.len(), 1);
+        assert!(headers.contains_key(header::ACCEPT));
+    }
+}
@@ -0,0 +1,240 @@
+//! Query string deserialization.
+//!
+//! Unlike a plain urlencoded deserialization, repeated keys are kept: the
+//! values of `?tag=a&tag=b` are collected in order into a `Vec` member, a
+//! single value becomes a one-element `Vec`, and bracketed keys like `tag[]`
+//! are read as `tag`. Any other member takes the last value sent for its key.
+//!
+//! Handlers of a `#[controller]` read query strings the same way, through a
+//! [`Query<T>`] parameter or `Vec<T>` query string parameters:
+//!
+//! ```rust
+//! # #[macro_use] extern crate saphir_macro;
+//! # use saphir::{prelude::*, test::TestServer};
+//! # use serde_derive::Deserialize;
+//! #
+//! #[derive(Deserialize)]
+//! struct Filters {
+//!     tag: Vec<String>,
+//! }
+//!
+//! struct SearchController {}
+//!
+//! #[controller(name = "search")]
+//! impl SearchController {
+//!     #[get("/ids")]
+//!     async fn ids(&self, id: Vec<u32>, sort: Option<Vec<String>>) -> String {
+//!         format!("{:?} {:?}", id, sort)
+//!     }
+//!
+//!     #[get("/tags")]
+//!     # #[validator(exclude("filters"))]
+//!     async fn tags(&self, filters: Query<Filters>) -> String {
+//!         filters.tag.join(",")
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), SaphirError> {
+//! let client = TestServer::new(Server::builder().configure_router(|r| r.controller(SearchController {})))?.client();
+//!
+//! assert_eq!(client.get("/search/ids?id=1&id=2").send().await?.text()?, "[1, 2] None");
+//! assert_eq!(client.get("/search/ids?sort[]=name").send().await?.text()?, "[] Some([\"name\"])");
+//! assert_eq!(client.get("/search/ids?id=x").send().await?.status(), 400);
+//! assert_eq!(client.get("/search/tags?tag=a&tag[]=b").send().await?.text()?, "a,b");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    de::{self, deserialize_parsed, wrapper_impls, Repeated, Values},
+    error::SaphirError,
+    request::{FromRequest, Request},
+};
+use serde::de::{
+    value::{Error as DeError, MapDeserializer, SeqDeserializer, StringDeserializer},
+    DeserializeOwned, Deserializer, Error as _, IntoDeserializer, Visitor,
+};
+use std::{collections::HashMap, str::FromStr};
+
+/// Query string of the request deserialized into `T`.
+///
+/// Members are parsed from the values of the keys of the same name, see the
+/// [module documentation](self) for repeated keys. A request without a query
+/// string is read as an empty one, so that `Option` and `#[serde(default)]`
+/// members can be omitted; an invalid query string is rejected with a
+/// `400 Bad Request`.
+///
+/// ```rust
+/// use saphir::prelude::*;
+/// use serde_derive::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Search {
+///     q: String,
+///     tag: Vec<String>,
+///     page: Option<u32>,
+/// }
+///
+/// async fn search(mut req: Request) -> Result<String, SaphirError> {
+///     let search = Query::<Search>::from_request(&mut req).await?;
+///     Ok(format!("{} in {}", search.q, search.tag.join(", ")))
+/// }
+/// ```
+pub struct Query<T>(pub T);
+
+wrapper_impls!(Query);
+
+impl<T> FromRequest for Query<T>
+where
+    T: DeserializeOwned,
+{
+    type Err = SaphirError;
+    type Fut = futures::future::Ready<Result<Self, Self::Err>>;
+
+    fn from_request(req: &mut Request) -> Self::Fut {
+        let query = from_str(req.uri().query().unwrap_or_default()).map(Query).map_err(SaphirError::SerdeUrlDe);
+        futures::future::ready(query)
+    }
+}
+
+/// Deserialize a query string into `T`, keeping repeated keys
+pub fn from_str<T: DeserializeOwned>(query_str: &str) -> Result<T, DeError> {
+    T::deserialize(MapDeserializer::new(group(query_str)?.into_iter()))
+}
+
+/// Read every value of each key of a query string, in order
+pub fn to_multimap(query_str: &str) -> Result<HashMap<String, Vec<String>>, DeError> {
+    Ok(group(query_str)?
+        .into_iter()
+        .map(|(name, values)| (name, values.into_values().into_iter().map(|value| value.value).collect()))
+        .collect())
+}
+
+/// Group the values of a query string by key, keys ordered by their first
+/// occurrence
+fn group(query_str: &str) -> Result<Vec<(String, Values<Value>)>, DeError> {
+    let values = serde_urlencoded::from_str::<Vec<(String, String)>>(query_str)?.into_iter().map(|(key, value)| {
+        let name = key.strip_suffix("[]").map(str::to_string).unwrap_or(key);
+        (name.clone(), Value { name, value })
+    });
+    Ok(de::group(values, Repeated::Last))
+}
+
+/// A single value of a key
+struct Value {
+    name: String,
+    value: String,
+}
+
+impl Value {
+    fn parse<T: FromStr>(self) -> Result<T, DeError>
+    where
+        T::Err: std::fmt::Display,
+    {
+        self.value
+            .parse()
+            .map_err(|e| DeError::custom(format!("invalid value for field `{}`: {}", self.name, e)))
+    }
+}
+
+impl<'de> IntoDeserializer<'de, DeError> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for Value {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_string(self.value)
+    }
+
+    deserialize_parsed!();
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(SeqDeserializer::new(std::iter::once(self)))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, _len: usize, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error> {
+        let value: StringDeserializer<DeError> = self.value.into_deserializer();
+        visitor.visit_enum(value)
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string bytes byte_buf map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_derive::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Search {
+        q: String,
+        tag: Vec<String>,
+        #[serde(default)]
+        ids: Vec<u32>,
+        page: Option<u32>,
+    }
+
+    #[test]
+    fn repeated_keys_are_collected() {
+        let search: Search = from_str("q=rust&tag=a&page=2&tag=b%20c&ids[]=1&ids[]=2").unwrap();
+        assert_eq!(
+            search,
+            Search {
+                q: "rust".to_string(),
+                tag: vec!["a".to_string(), "b c".to_string()],
+                ids: vec![1, 2],
+                page: Some(2),
+            }
+        );
+
+        let search: Search = from_str("tag=a&q=rust&q=saphir").unwrap();
+        assert_eq!(search.q, "saphir");
+        assert_eq!(search.tag, vec!["a".to_string()]);
+        assert!(search.ids.is_empty());
+        assert_eq!(search.page, None);
+
+        let multimap = to_multimap("tag[]=a&tag=b&q=").unwrap();
+        assert_eq!(multimap["tag"], vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(multimap["q"], vec![String::new()]);
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let err = from_str::<Search>("q=rust&tag=a&ids=1&ids=x").unwrap_err();
+        assert!(err.to_string().starts_with("invalid value for field `ids`"));
+        assert!(from_str::<Search>("q=rust").is_err());
+    }
+}
@@ -0,0 +1,402 @@
+//! Request rate limiting.
+//!
+//! The [`RateLimit`] guard throttles the requests of a handler to a quota of
+//! requests per window, for each client. Clients are identified by their peer
+//! address by default, or by a custom key function, e.g. reading an api key
+//! header. Once a client exhausted its quota, its requests are answered with
+//! `429 Too Many Requests` and a `Retry-After` header until enough time passed.
+//!
+//! Quotas are enforced with a token bucket: the `requests` tokens of a client
+//! are refilled continuously over the `window`, so bursts up to the quota are
+//! allowed while the sustained rate is bounded.
+//!
+//! ```rust
+//! use saphir::{
+//!     prelude::*,
+//!     rate_limit::{Quota, RateLimit},
+//! };
+//!
+//! async fn login(_req: Request) -> (u16, &'static str) {
+//!     (200, "welcome")
+//! }
+//!
+//! let server = Server::builder()
+//!     .configure_router(|r| r.route_with_guards("/login", Method::POST, login, |g| g.apply(RateLimit::new(Quota::per_minute(5)))))
+//!     .build();
+//! ```
+//!
+//! With the controller macro, the guard is applied with its quota as init
+//! expression:
+//!
+//! ```rust
+//! # #[macro_use] extern crate saphir_macro;
+//! # use saphir::{prelude::*, rate_limit::{Quota, RateLimit}};
+//! struct AuthController;
+//!
+//! #[controller(name = "auth")]
+//! impl AuthController {
+//!     #[post("/login")]
+//!     #[guard(RateLimit, init_expr = "Quota::per_minute(5)")]
+//!     async fn login(&self) -> (u16, &'static str) {
+//!         (200, "welcome")
+//!     }
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! The buckets are kept in memory by default, and are therefore per server
+//! instance. Another backend can be used by implementing [`RateLimitStore`].
+
+use crate::{guard::Guard, http_context::HttpContext, request::Request, responder::Responder, response::Builder};
+use futures::future::{BoxFuture, FutureExt};
+use http::StatusCode;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Default most keys kept by the in-memory store
+const DEFAULT_MAX_KEYS: usize = 100_000;
+
+/// Number of requests allowed per window
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Quota {
+    requests: u32,
+    window: Duration,
+}
+
+impl Quota {
+    /// # Panics
+    /// If `requests` or `window` is zero
+    pub fn new(requests: u32, window: Duration) -> Self {
+        assert!(requests > 0, "A rate limit quota needs to allow at least one request");
+        assert!(!window.is_zero(), "A rate limit quota needs a non-zero window");
+        Quota { requests, window }
+    }
+
+    pub fn per_second(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(1))
+    }
+
+    pub fn per_minute(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(60))
+    }
+
+    pub fn requests(&self) -> u32 {
+        self.requests
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Time needed to refill one token
+    fn refill_interval(&self) -> Duration {
+        self.window / self.requests
+    }
+}
+
+impl From<(u32, Duration)> for Quota {
+    fn from((requests, window): (u32, Duration)) -> Self {
+        Quota::new(requests, window)
+    }
+}
+
+/// Storage of the rate limit state of the clients.
+///
+/// Implementations take a token out of the bucket of a key, returning how long
+/// to wait before a token is available when the bucket is empty.
+pub trait RateLimitStore: Send + Sync {
+    fn acquire<'a>(&'a self, key: &'a str, quota: Quota) -> BoxFuture<'a, Result<(), Duration>>;
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// Sequence number of the last use of the bucket
+    used: u64,
+}
+
+impl Bucket {
+    fn refill(&mut self, quota: Quota, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        let refilled = elapsed / quota.refill_interval().as_secs_f64();
+        self.tokens = (self.tokens + refilled).min(quota.requests as f64);
+        self.updated = now;
+    }
+}
+
+#[derive(Default)]
+struct Buckets {
+    map: HashMap<String, Bucket>,
+    /// Keys in the order they were used, along with the sequence number of
+    /// the use, which tells apart the keys used again since
+    order: VecDeque<(u64, String)>,
+    uses: u64,
+}
+
+impl Buckets {
+    fn acquire(&mut self, key: &str, quota: Quota, now: Instant) -> Result<(), Duration> {
+        self.uses += 1;
+        self.order.push_back((self.uses, key.to_string()));
+        let bucket = self.map.entry(key.to_string()).or_insert_with(|| Bucket {
+            tokens: quota.requests as f64,
+            updated: now,
+            used: 0,
+        });
+        bucket.used = self.uses;
+        bucket.refill(quota, now);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(quota.refill_interval().mul_f64(1.0 - bucket.tokens))
+        }
+    }
+
+    /// Remove the least recently used buckets while they were refilled, a
+    /// full bucket holding no more information than a missing one, or the
+    /// store holds more than `max_keys`. Each use being queued once, this is
+    /// amortized constant time.
+    fn evict(&mut self, now: Instant, quota: Quota, max_keys: usize) {
+        while let Some((used, key)) = self.order.front() {
+            let bucket = self.map.get(key).filter(|bucket| bucket.used == *used);
+            let current = bucket.is_some();
+            let refilled = bucket.is_some_and(|bucket| now.saturating_duration_since(bucket.updated) >= quota.window);
+            if current && !refilled && self.map.len() <= max_keys {
+                break;
+            }
+            if let Some((_, key)) = self.order.pop_front() {
+                if current {
+                    self.map.remove(&key);
+                }
+            }
+        }
+
+        // Keys used over and over stay queued behind older ones, drop their
+        // previous uses once they make up most of the queue
+        if self.order.len() > 2 * self.map.len() + 16 {
+            let map = &self.map;
+            self.order.retain(|(used, key)| map.get(key).is_some_and(|bucket| bucket.used == *used));
+        }
+    }
+}
+
+/// Token buckets kept in the server memory.
+///
+/// The buckets of the clients idle for a whole window are dropped, and the
+/// store holds at most 100 000 keys by default, the least recently used
+/// being evicted first when it is full.
+pub struct InMemoryStore {
+    buckets: Mutex<Buckets>,
+    max_keys: usize,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        InMemoryStore {
+            buckets: Mutex::new(Buckets::default()),
+            max_keys: DEFAULT_MAX_KEYS,
+        }
+    }
+
+    /// Set the most keys kept
+    pub fn max_keys(mut self, max_keys: usize) -> Self {
+        self.max_keys = max_keys;
+        self
+    }
+
+    fn acquire_at(&self, key: &str, quota: Quota, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().expect("rate limit store lock poisoned");
+        let res = buckets.acquire(key, quota, now);
+        buckets.evict(now, quota, self.max_keys);
+        res
+    }
+}
+
+impl Default for InMemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RateLimitStore for InMemoryStore {
+    fn acquire<'a>(&'a self, key: &'a str, quota: Quota) -> BoxFuture<'a, Result<(), Duration>> {
+        futures::future::ready(self.acquire_at(key, quota, Instant::now())).boxed()
+    }
+}
+
+type KeyFn = Box<dyn Fn(&Request) -> Option<String> + Send + Sync>;
+
+/// Guard limiting the rate of requests of each client.
+///
+/// Requests for which no key can be found, e.g. injected requests without a
+/// peer address, are not limited.
+pub struct RateLimit {
+    quota: Quota,
+    store: Box<dyn RateLimitStore>,
+    key: KeyFn,
+}
+
+impl RateLimit {
+    /// Limit to `quota` requests per peer address, keeping the buckets in
+    /// memory
+    pub fn new<Q: Into<Quota>>(quota: Q) -> Self {
+        RateLimit {
+            quota: quota.into(),
+            store: Box::new(InMemoryStore::new()),
+            key: Box::new(|req| req.peer_addr().map(|addr| addr.ip().to_string())),
+        }
+    }
+
+    /// Identify the clients with a custom key, e.g. an api key header
+    pub fn key<F>(mut self, key: F) -> Self
+    where
+        F: Fn(&Request) -> Option<String> + Send + Sync + 'static,
+    {
+        self.key = Box::new(key);
+        self
+    }
+
+    /// Keep the rate limit state in another store
+    pub fn store<S: RateLimitStore + 'static>(mut self, store: S) -> Self {
+        self.store = Box::new(store);
+        self
+    }
+}
+
+impl Guard for RateLimit {
+    type Future = BoxFuture<'static, Result<Request, Self::Responder>>;
+    type Responder = TooManyRequests;
+
+    fn validate(&'static self, req: Request) -> Self::Future {
+        async move {
+            if let Some(key) = (self.key)(&req) {
+                self.store
+                    .acquire(&key, self.quota)
+                    .await
+                    .map_err(|retry_after| TooManyRequests { retry_after })?;
+            }
+            Ok(req)
+        }
+        .boxed()
+    }
+}
+
+/// Responder of the requests over their quota, `429 Too Many Requests` with a
+/// `Retry-After` header
+#[derive(Debug)]
+pub struct TooManyRequests {
+    retry_after: Duration,
+}
+
+impl TooManyRequests {
+    pub fn retry_after(&self) -> Duration {
+        self.retry_after
+    }
+}
+
+impl Responder for TooManyRequests {
+    fn respond_with_builder(self, builder: Builder, _ctx: &HttpContext) -> Builder {
+        // Retry-After is in whole seconds, round up so that the retry succeeds
+        let secs = self.retry_after.as_secs() + u64::from(self.retry_after.subsec_nanos() > 0);
+        builder.status(StatusCode::TOO_MANY_REQUESTS).header(http::header::RETRY_AFTER, secs.max(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{server::Server, test::TestServer};
+    use http::Method;
+
+    async fn login(_req: Request) -> (u16, &'static str) {
+        (200, "welcome")
+    }
+
+    #[test]
+    fn buckets_refill_over_the_window() {
+        let store = InMemoryStore::new();
+        let quota = Quota::new(2, Duration::from_secs(10));
+        let now = Instant::now();
+
+        assert!(store.acquire_at("a", quota, now).is_ok());
+        assert!(store.acquire_at("a", quota, now).is_ok());
+        assert_eq!(store.acquire_at("a", quota, now), Err(Duration::from_secs(5)));
+        assert!(store.acquire_at("b", quota, now).is_ok());
+
+        assert_eq!(
+            store.acquire_at("a", quota, now + Duration::from_millis(2500)),
+            Err(Duration::from_millis(2500))
+        );
+        assert!(store.acquire_at("a", quota, now + Duration::from_secs(5)).is_ok());
+        assert!(store.acquire_at("a", quota, now + Duration::from_secs(5)).is_err());
+    }
+
+    #[test]
+    fn refilled_buckets_are_pruned() {
+        let store = InMemoryStore::new();
+        let quota = Quota::per_second(1);
+        let now = Instant::now();
+
+        for i in 0..1000 {
+            store.acquire_at(&i.to_string(), quota, now).unwrap();
+        }
+        store.acquire_at("0", quota, now + Duration::from_millis(500)).unwrap_err();
+        store.acquire_at("new", quota, now + Duration::from_millis(1200)).unwrap();
+
+        let buckets = store.buckets.lock().unwrap();
+        assert_eq!(buckets.map.len(), 2);
+        assert!(buckets.map.contains_key("0"));
+    }
+
+    #[test]
+    fn least_recently_used_buckets_are_evicted() {
+        let store = InMemoryStore::new().max_keys(2);
+        let quota = Quota::per_minute(1);
+        let now = Instant::now();
+
+        store.acquire_at("a", quota, now).unwrap();
+        store.acquire_at("b", quota, now).unwrap();
+        for _ in 0..100 {
+            store.acquire_at("a", quota, now).unwrap_err();
+        }
+        store.acquire_at("c", quota, now).unwrap();
+
+        let buckets = store.buckets.lock().unwrap();
+        assert_eq!(buckets.map.len(), 2);
+        assert!(buckets.map.contains_key("a") && buckets.map.contains_key("c"));
+        assert!(buckets.order.len() <= 2 * 2 + 16);
+    }
+
+    #[tokio::test]
+    async fn rejects_requests_over_the_quota() {
+        let server = TestServer::new(Server::builder().configure_router(|r| {
+            r.route_with_guards("/login", Method::POST, login, |g| g.apply(RateLimit::new(Quota::per_minute(2))))
+                .route_with_guards("/key", Method::POST, login, |g| {
+                    g.apply(
+                        RateLimit::new((1, Duration::from_secs(60)))
+                            .key(|req| req.headers().get("X-Api-Key").and_then(|h| h.to_str().ok()).map(str::to_string)),
+                    )
+                })
+        }))
+        .unwrap();
+        let client = server.client();
+        let first = "10.0.0.1:4000".parse().unwrap();
+        let second = "10.0.0.2:4000".parse().unwrap();
+
+        assert_eq!(client.post("/login").peer_addr(first).send().await.unwrap().status(), 200);
+        assert_eq!(client.post("/login").peer_addr(first).send().await.unwrap().status(), 200);
+        let res = client.post("/login").peer_addr(first).send().await.unwrap();
+        assert_eq!(res.status(), 429);
+        assert_eq!(res.header(http::header::RETRY_AFTER), Some("30"));
+        assert_eq!(client.post("/login").peer_addr(second).send().await.unwrap().status(), 200);
+
+        assert_eq!(client.post("/key").header("X-Api-Key", "a").send().await.unwrap().status(), 200);
+        assert_eq!(client.post("/key").header("X-Api-Key", "a").send().await.unwrap().status(), 429);
+        assert_eq!(client.post("/key").header("X-Api-Key", "b").send().await.unwrap().status(), 200);
+        assert_eq!(client.post("/key").send().await.unwrap().status(), 200);
+    }
+}
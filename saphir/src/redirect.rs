@@ -1,10 +1,16 @@
-use crate::{body::TransmuteBody, http_context::HttpContext, responder::Responder, response::Builder as ResponseBuilder};
+use crate::{
+    body::TransmuteBody,
+    error::SaphirError,
+    http_context::HttpContext,
+    responder::Responder,
+    response::{location_header, Builder as ResponseBuilder},
+};
 use cookie::{Cookie, CookieJar};
-use http::{header::HeaderName, HeaderMap, HeaderValue, StatusCode, Uri};
+use http::{header::HeaderName, HeaderMap, HeaderValue, StatusCode};
 use hyper::body::Body as RawBody;
 use mime::Mime;
 use serde::Serialize;
-use std::{collections::HashMap, convert::TryInto, fmt::Debug};
+use std::{collections::HashMap, convert::TryFrom, fmt::Debug};
 
 #[derive(Debug)]
 pub enum BuilderError {
@@ -24,6 +30,15 @@ pub enum BuilderError {
     HeaderError(Box<http::Error>),
 }
 
+impl From<BuilderError> for SaphirError {
+    fn from(e: BuilderError) -> Self {
+        match e {
+            BuilderError::HeaderError(e) => SaphirError::from(*e),
+            e => SaphirError::InvalidRedirect(format!("{:?}", e)),
+        }
+    }
+}
+
 impl From<http::Error> for BuilderError {
     fn from(e: http::Error) -> Self {
         BuilderError::HeaderError(Box::new(e))
@@ -82,20 +97,21 @@ impl Builder {
     }
 
     #[inline]
-    pub fn header<E, K, V>(mut self, name: K, value: V) -> Self
+    pub fn header<K, V>(mut self, name: K, value: V) -> Self
     where
-        E: Into<http::Error>,
-        K: TryInto<HeaderName, Error = E>,
-        V: TryInto<HeaderValue, Error = E>,
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<http::Error>,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<http::Error>,
     {
-        let name = match name.try_into() {
+        let name = match HeaderName::try_from(name) {
             Ok(name) => Some(name),
             Err(e) => {
                 self.extra_headers_errors.push(e.into());
                 None
             }
         };
-        let value = match value.try_into() {
+        let value = match HeaderValue::try_from(value) {
             Ok(value) => Some(value),
             Err(e) => {
                 self.extra_headers_errors.push(e.into());
@@ -130,6 +146,10 @@ impl Builder {
     }
 
     pub fn build(mut self) -> Result<Redirect, BuilderError> {
+        if let Some(e) = self.extra_headers_errors.pop() {
+            return Err(e.into());
+        }
+
         match self.status {
             StatusCode::MOVED_PERMANENTLY | StatusCode::PERMANENT_REDIRECT | StatusCode::FOUND | StatusCode::SEE_OTHER | StatusCode::TEMPORARY_REDIRECT => {
                 self.assert_location(true)?;
@@ -187,7 +207,7 @@ impl Builder {
 
     fn format_location(&mut self) -> Result<Option<String>, BuilderError> {
         let mut url = match self.location.take() {
-            Some(url) => url.parse::<Uri>().map_err(|_| BuilderError::InvalidLocation)?.to_string(),
+            Some(url) => url,
             None => return Ok(None),
         };
 
@@ -201,6 +221,8 @@ impl Builder {
             url.push_str(fragment.as_str());
         }
 
+        location_header(&url).map_err(|_| BuilderError::InvalidLocation)?;
+
         Ok(Some(url))
     }
 
@@ -256,6 +278,37 @@ impl Builder {
     }
 }
 
+/// A redirection responder, built from one of its status constructors.
+///
+/// The builder can be returned directly by a handler, in which case an invalid
+/// redirect, e.g. with an invalid location or header, results in a
+/// `SaphirError` instead of an incomplete response.
+///
+/// The constructors of the redirections needing a location take it as
+/// argument; it can be replaced with [`Builder::location`].
+/// ```rust
+/// use saphir::{prelude::*, redirect::Builder as RedirectBuilder, test::TestServer};
+///
+/// async fn logout(_req: Request) -> RedirectBuilder {
+///     Redirect::see_other("/login").query_string("logged_out=true")
+/// }
+///
+/// async fn broken(_req: Request) -> RedirectBuilder {
+///     Redirect::found("/login").header("X-Reason", "new\nline")
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), SaphirError> {
+/// let client = TestServer::new(Server::builder().configure_router(|r| r.route("/logout", Method::GET, logout).route("/broken", Method::GET, broken)))?.client();
+///
+/// let res = client.get("/logout").send().await?;
+/// assert_eq!(res.status(), StatusCode::SEE_OTHER);
+/// assert_eq!(res.header(header::LOCATION), Some("/login?logged_out=true"));
+///
+/// assert_eq!(client.get("/broken").send().await?.status(), StatusCode::INTERNAL_SERVER_ERROR);
+/// # Ok(())
+/// # }
+/// ```
 pub struct Redirect {
     status: StatusCode,
     location: Option<String>,
@@ -292,41 +345,46 @@ impl Redirect {
     }
 
     #[inline]
-    pub fn moved_permanently() -> Builder {
+    pub fn moved_permanently(location: impl Into<String>) -> Builder {
         Builder {
             status: StatusCode::MOVED_PERMANENTLY,
+            location: Some(location.into()),
             ..Default::default()
         }
     }
 
     #[inline]
-    pub fn permanent_redirect() -> Builder {
+    pub fn permanent_redirect(location: impl Into<String>) -> Builder {
         Builder {
             status: StatusCode::PERMANENT_REDIRECT,
+            location: Some(location.into()),
             ..Default::default()
         }
     }
 
     #[inline]
-    pub fn found() -> Builder {
+    pub fn found(location: impl Into<String>) -> Builder {
         Builder {
             status: StatusCode::FOUND,
+            location: Some(location.into()),
             ..Default::default()
         }
     }
 
     #[inline]
-    pub fn see_other() -> Builder {
+    pub fn see_other(location: impl Into<String>) -> Builder {
         Builder {
             status: StatusCode::SEE_OTHER,
+            location: Some(location.into()),
             ..Default::default()
         }
     }
 
     #[inline]
-    pub fn temporary_redirect() -> Builder {
+    pub fn temporary_redirect(location: impl Into<String>) -> Builder {
         Builder {
             status: StatusCode::TEMPORARY_REDIRECT,
+            location: Some(location.into()),
             ..Default::default()
         }
     }
@@ -349,6 +407,17 @@ impl Redirect {
     }
 }
 
+/// A redirect builder can be returned directly by a handler, an invalid
+/// redirect resulting in a `SaphirError`
+impl Responder for Builder {
+    fn respond_with_builder(self, builder: ResponseBuilder, ctx: &HttpContext) -> ResponseBuilder {
+        match self.build() {
+            Ok(redirect) => redirect.respond_with_builder(builder, ctx),
+            Err(e) => SaphirError::from(e).respond_with_builder(builder, ctx),
+        }
+    }
+}
+
 impl Responder for Redirect {
     fn respond_with_builder(self, mut builder: ResponseBuilder, _ctx: &HttpContext) -> ResponseBuilder {
         builder = builder.status(self.status);
@@ -10,6 +10,7 @@ use hyper::body::Bytes;
 
 use crate::{
     body::{Body, FromBytes},
+    conditional::{EntityTag, Precondition},
     error::SaphirError,
 };
 
@@ -150,6 +151,15 @@ impl<T> Request<T> {
         &mut self.captures
     }
 
+    /// Fail with `412 Precondition Failed` unless the `If-Match` header of the
+    /// request matches `etag`, the entity tag of the current version of the
+    /// resource. See [`Precondition`](crate::conditional::Precondition) to
+    /// also evaluate `If-Unmodified-Since`.
+    #[inline]
+    pub fn check_precondition(&self, etag: &EntityTag) -> Result<(), SaphirError> {
+        Precondition::from_headers(self.headers()).check(etag, None)
+    }
+
     /// Convert a request of T in a request of U
     ///
     /// ```rust
@@ -290,6 +300,82 @@ impl<T: FromBytes + Unpin + 'static> Request<Body<T>> {
             operation_id,
         })
     }
+
+    /// Load the whole body in memory and return its bytes, keeping them as
+    /// the body so that the handler can still consume it, e.g. to log the
+    /// payload or verify its signature in a middleware.
+    ///
+    /// The body stays in memory until the request is dropped. As any loaded
+    /// body, it fails with `SaphirError::PayloadTooLarge` once it exceeds the
+    /// `request_body_max_bytes` limit of the listener.
+    ///
+    /// ```rust
+    /// # use saphir::{prelude::*, test::TestServer};
+    /// async fn log_body(mut ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+    ///     let body = ctx.state.request_unchecked_mut().body_bytes().await?;
+    ///     println!("received {} bytes", body.len());
+    ///     chain.next(ctx).await
+    /// }
+    ///
+    /// async fn echo(mut req: Request) -> Result<String, SaphirError> {
+    ///     req.body_mut().take_as::<String>().await
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), SaphirError> {
+    /// let client = TestServer::new(
+    ///     Server::builder()
+    ///         .configure_middlewares(|m| m.apply(log_body, vec!["/"], None))
+    ///         .configure_router(|r| r.route("/echo", Method::POST, echo)),
+    /// )?
+    /// .client();
+    ///
+    /// let res = client.post("/echo").body("hello").send().await?;
+    /// assert_eq!(res.text()?, "hello");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn body_bytes(&mut self) -> Result<Bytes, SaphirError> {
+        self.inner.body_mut().buffer().await
+    }
+
+    /// Replace the body of the request, returning the previous one. A body
+    /// built from [`Bytes`] is already in memory, and isn't checked against
+    /// the request body limit of the listener. The headers, such as
+    /// `Content-Length`, are left untouched.
+    ///
+    /// ```rust
+    /// # use saphir::{prelude::*, test::TestServer};
+    /// async fn unwrap_envelope(mut ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+    ///     let req = ctx.state.request_unchecked_mut();
+    ///     let body = req.body_bytes().await?;
+    ///     if let Some(payload) = body.strip_prefix(b"envelope:") {
+    ///         req.replace_body(Bytes::copy_from_slice(payload).into());
+    ///     }
+    ///     chain.next(ctx).await
+    /// }
+    ///
+    /// async fn echo(mut req: Request) -> Result<String, SaphirError> {
+    ///     req.body_mut().take_as::<String>().await
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), SaphirError> {
+    /// let client = TestServer::new(
+    ///     Server::builder()
+    ///         .configure_middlewares(|m| m.apply(unwrap_envelope, vec!["/"], None))
+    ///         .configure_router(|r| r.route("/echo", Method::POST, echo)),
+    /// )?
+    /// .client();
+    ///
+    /// let res = client.post("/echo").body("envelope:hello").send().await?;
+    /// assert_eq!(res.text()?, "hello");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn replace_body(&mut self, body: Body<T>) -> Body<T> {
+        std::mem::replace(self.inner.body_mut(), body)
+    }
 }
 
 impl<T, E> Request<Result<T, E>> {
@@ -369,9 +455,11 @@ mod json {
     use super::*;
 
     impl Request<Body<Bytes>> {
+        /// Deserialize the json body, see [`Json`] for why `T` must be
+        /// `Send + 'static`.
         pub async fn json<T>(&mut self) -> Result<T, SaphirError>
         where
-            T: for<'a> Deserialize<'a> + Unpin + 'static,
+            T: for<'a> Deserialize<'a> + Send + Unpin + 'static,
         {
             self.body_mut().take_as::<Json<T>>().await
         }
@@ -0,0 +1,239 @@
+//! Request correlation ids.
+//!
+//! The [`RequestIdMiddleware`] reads the id of a request from its
+//! `X-Request-Id` header, or generates one when the header is missing or
+//! invalid. The id is then made available to the handlers through the
+//! [`RequestId`] extractor and echoed on the response.
+//!
+//! With the `operation` feature, generated ids are the operation id of the
+//! request, so that logs of both can be correlated.
+//!
+//! ```rust
+//! use saphir::{prelude::*, request_id::RequestIdMiddleware};
+//!
+//! async fn hello(req: Request) -> (u16, String) {
+//!     let id = req.extensions().get::<RequestId>().map(|id| id.to_string()).unwrap_or_default();
+//!     (200, format!("request {}", id))
+//! }
+//!
+//! let server = Server::builder()
+//!     .configure_middlewares(|m| m.apply(RequestIdMiddleware::new(), vec!["/**"], None))
+//!     .configure_router(|r| r.route("/hello", Method::GET, hello))
+//!     .build();
+//! ```
+
+use crate::{
+    error::SaphirError,
+    extension::ExtError,
+    http_context::{HttpContext, State},
+    middleware::{Middleware, MiddlewareChain},
+    request::{FromRequest, Request},
+};
+use futures::future::{BoxFuture, FutureExt};
+use http::{header::HeaderName, HeaderValue};
+use std::{
+    fmt::{Display, Formatter},
+    ops::Deref,
+};
+
+/// Incoming ids longer than this are replaced by a generated one
+pub const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Correlation id of a request
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct RequestId(String);
+
+impl RequestId {
+    /// Generate a new random id
+    pub fn generate() -> Self {
+        RequestId(uuid::Uuid::new_v4().as_hyphenated().to_string())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+
+    fn from_header(value: &HeaderValue) -> Option<Self> {
+        value
+            .to_str()
+            .ok()
+            .map(str::trim)
+            .filter(|v| !v.is_empty() && v.len() <= MAX_REQUEST_ID_LEN)
+            .map(|v| RequestId(v.to_string()))
+    }
+}
+
+impl Deref for RequestId {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Display for RequestId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromRequest for RequestId {
+    type Err = ExtError;
+    type Fut = futures::future::Ready<Result<Self, Self::Err>>;
+
+    fn from_request(req: &mut Request) -> Self::Fut {
+        futures::future::ready(
+            req.extensions()
+                .get::<RequestId>()
+                .cloned()
+                .ok_or_else(|| ExtError::MissingExtension(std::any::type_name::<RequestId>())),
+        )
+    }
+}
+
+/// Middleware assigning a [`RequestId`] to every request
+pub struct RequestIdMiddleware {
+    header: HeaderName,
+    trust_incoming: bool,
+}
+
+impl RequestIdMiddleware {
+    /// Create a middleware using the `X-Request-Id` header
+    pub fn new() -> Self {
+        RequestIdMiddleware {
+            header: HeaderName::from_static("x-request-id"),
+            trust_incoming: true,
+        }
+    }
+
+    /// Set the header the id is read from and echoed on
+    ///
+    /// # Panics
+    /// Panics if `header` is not a valid header name
+    pub fn header_name(mut self, header: &str) -> Self {
+        self.header = HeaderName::from_bytes(header.as_bytes()).expect("invalid request id header name");
+        self
+    }
+
+    /// Whether the id sent by the client should be reused. When disabled, a
+    /// new id is always generated. Enabled by default.
+    pub fn trust_incoming(mut self, trust_incoming: bool) -> Self {
+        self.trust_incoming = trust_incoming;
+        self
+    }
+
+    fn generate(_ctx: &HttpContext) -> RequestId {
+        #[cfg(feature = "operation")]
+        {
+            RequestId(_ctx.operation_id.to_string())
+        }
+
+        #[cfg(not(feature = "operation"))]
+        {
+            RequestId::generate()
+        }
+    }
+
+    async fn next_inner(&self, mut ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+        let incoming = if self.trust_incoming {
+            ctx.state.request().and_then(|r| r.headers().get(&self.header)).and_then(RequestId::from_header)
+        } else {
+            None
+        };
+        let id = incoming.unwrap_or_else(|| Self::generate(&ctx));
+        let value = HeaderValue::from_str(id.as_str())?;
+
+        #[cfg(feature = "operation")]
+        debug!("[Operation id: {}] Request id: {}", ctx.operation_id, id);
+
+        if let State::Before(req) = &mut ctx.state {
+            req.extensions_mut().insert(id);
+        }
+
+        let mut ctx = chain.next_with_response(ctx).await?;
+
+        if let Some(res) = ctx.state.response_mut() {
+            res.headers_mut().insert(self.header.clone(), value);
+        }
+
+        Ok(ctx)
+    }
+}
+
+impl Default for RequestIdMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Middleware for RequestIdMiddleware {
+    fn next(&'static self, ctx: HttpContext, chain: &'static dyn MiddlewareChain) -> BoxFuture<'static, Result<HttpContext, SaphirError>> {
+        self.next_inner(ctx, chain).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{server::Server, test::TestServer};
+    use http::Method;
+
+    async fn echo(mut req: Request) -> (u16, String) {
+        let id = RequestId::from_request(&mut req).await.map(|id| id.into_inner()).unwrap_or_default();
+        (200, id)
+    }
+
+    fn client(middleware: RequestIdMiddleware) -> crate::test::TestClient {
+        TestServer::new(
+            Server::builder()
+                .configure_middlewares(|m| m.apply(middleware, vec!["/**"], None))
+                .configure_router(|r| r.route("/echo", Method::GET, echo)),
+        )
+        .unwrap()
+        .client()
+    }
+
+    #[tokio::test]
+    async fn propagates_incoming_id() {
+        let res = client(RequestIdMiddleware::new())
+            .get("/echo")
+            .header("X-Request-Id", "abc-123")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.header("X-Request-Id"), Some("abc-123"));
+        assert_eq!(res.text().unwrap(), "abc-123");
+    }
+
+    #[tokio::test]
+    async fn generates_missing_or_invalid_ids() {
+        let client = client(RequestIdMiddleware::new().header_name("X-Correlation-Id"));
+
+        let res = client.get("/echo").send().await.unwrap();
+        let id = res.header("X-Correlation-Id").unwrap().to_string();
+        assert_eq!(id.len(), 36);
+        assert_eq!(res.text().unwrap(), id);
+
+        let res = client.get("/echo").header("X-Correlation-Id", "a".repeat(200)).send().await.unwrap();
+        assert_eq!(res.header("X-Correlation-Id").map(str::len), Some(36));
+
+        let res = client.get("/nowhere").send().await.unwrap();
+        assert_eq!(res.status(), 404);
+        assert!(res.header("X-Correlation-Id").is_some());
+    }
+
+    #[tokio::test]
+    async fn ignores_incoming_id_when_untrusted() {
+        let res = client(RequestIdMiddleware::new().trust_incoming(false))
+            .get("/echo")
+            .header("X-Request-Id", "abc-123")
+            .send()
+            .await
+            .unwrap();
+        assert_ne!(res.header("X-Request-Id"), Some("abc-123"));
+    }
+}
@@ -1,6 +1,11 @@
 #![allow(clippy::let_and_return)]
 use crate::{http_context::HttpContext, response::Builder};
-use http::StatusCode;
+use futures::{
+    future::{BoxFuture, FutureExt},
+    Future, Stream, TryStreamExt,
+};
+use http::{header::HeaderName, HeaderMap, StatusCode};
+use hyper::body::Bytes;
 
 macro_rules! impl_status_responder {
     ( $( $x:ty ),+ ) => {
@@ -67,6 +72,14 @@ pub trait Responder {
     /// }
     /// ```
     fn respond_with_builder(self, builder: Builder, ctx: &HttpContext) -> Builder;
+
+    /// Box the responder, see [`BoxedResponder`]
+    fn into_boxed(self) -> BoxedResponder
+    where
+        Self: Sized + Send + 'static,
+    {
+        BoxedResponder::new(self)
+    }
 }
 
 impl<T> Responder for Vec<T>
@@ -99,16 +112,18 @@ impl Responder for StatusCode {
     }
 }
 
+/// An empty `204 No Content` response. A status set by another responder,
+/// e.g. `(201, ())`, or as the default status of the handler is kept.
 impl Responder for () {
     fn respond_with_builder(self, builder: Builder, _ctx: &HttpContext) -> Builder {
-        builder.status(200)
+        builder.implicit_status(StatusCode::NO_CONTENT)
     }
 }
 
 impl<T: Responder> Responder for Option<T> {
     fn respond_with_builder(self, builder: Builder, ctx: &HttpContext) -> Builder {
         if let Some(r) = self {
-            r.respond_with_builder(builder, ctx).status_if_not_set(200)
+            r.respond_with_builder(builder, ctx)
         } else {
             builder.status_if_not_set(404)
         }
@@ -118,12 +133,186 @@ impl<T: Responder> Responder for Option<T> {
 impl<T: Responder, E: Responder> Responder for Result<T, E> {
     fn respond_with_builder(self, builder: Builder, ctx: &HttpContext) -> Builder {
         match self {
-            Ok(r) => r.respond_with_builder(builder, ctx).status_if_not_set(200),
+            Ok(r) => r.respond_with_builder(builder, ctx),
             Err(r) => r.respond_with_builder(builder, ctx).status_if_not_set(500),
         }
     }
 }
 
+/// Responder overriding the default status of the response produced by
+/// another responder. The status is left untouched if the wrapped responder
+/// set one explicitly.
+///
+/// This is what the `status` parameter of the handler attributes, e.g.
+/// `#[post("/", status = 201)]`, generates.
+/// ```rust
+/// # use saphir::{prelude::*, responder::DefaultStatus};
+/// async fn create(_req: Request) -> DefaultStatus<String> {
+///     DefaultStatus::new("created".to_string(), 201)
+/// }
+/// ```
+#[derive(Debug)]
+pub struct DefaultStatus<R> {
+    responder: R,
+    status: u16,
+}
+
+impl<R> DefaultStatus<R> {
+    pub fn new(responder: R, status: u16) -> Self {
+        DefaultStatus { responder, status }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.responder
+    }
+}
+
+impl<R: Responder> Responder for DefaultStatus<R> {
+    fn respond_with_builder(self, builder: Builder, ctx: &HttpContext) -> Builder {
+        self.responder.respond_with_builder(builder, ctx).status_if_not_set(self.status)
+    }
+}
+
+/// Responder of any type, for handlers returning different responders from
+/// different branches.
+/// ```rust
+/// # #[macro_use] extern crate saphir_macro;
+/// # use saphir::{prelude::*, responder::BoxedResponder, test::TestServer};
+/// #
+/// struct UsersController {}
+///
+/// #[controller(name = "users")]
+/// impl UsersController {
+///     #[get("/<id>")]
+///     async fn get_user(&self, id: u32) -> BoxedResponder {
+///         match id {
+///             0 => StatusCode::FORBIDDEN.into_boxed(),
+///             1 => "richer".into_boxed(),
+///             _ => (404, format!("no user {}", id)).into_boxed(),
+///         }
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), SaphirError> {
+/// let client = TestServer::new(Server::builder().configure_router(|r| r.controller(UsersController {})))?.client();
+///
+/// assert_eq!(client.get("/users/0").send().await?.status(), 403);
+/// assert_eq!(client.get("/users/1").send().await?.text()?, "richer");
+/// let res = client.get("/users/2").send().await?;
+/// assert_eq!((res.status().as_u16(), res.text()?.as_str()), (404, "no user 2"));
+/// # Ok(())
+/// # }
+/// ```
+pub struct BoxedResponder(Box<dyn DynResponder + Send>);
+
+impl BoxedResponder {
+    pub fn new<R: Responder + Send + 'static>(responder: R) -> Self {
+        BoxedResponder(Box::new(Some(responder)))
+    }
+}
+
+impl Responder for BoxedResponder {
+    fn respond_with_builder(mut self, builder: Builder, ctx: &HttpContext) -> Builder {
+        self.0.dyn_respond(builder, ctx)
+    }
+}
+
+/// Responder streaming its body from any stream of bytes, with a chunked
+/// transfer encoding unless a content length is set.
+///
+/// The content type defaults to `application/octet-stream`.
+/// ```rust
+/// # use saphir::{prelude::*, responder::StreamResponder, test::TestServer};
+/// # use futures::stream;
+/// async fn export(_req: Request) -> StreamResponder<impl futures::Stream<Item = Result<Bytes, std::io::Error>> + Send> {
+///     let rows = (1..=3).map(|i| Ok(Bytes::from(format!("{},row {}\n", i, i))));
+///     StreamResponder::new(stream::iter(rows)).content_type("text/csv")
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), SaphirError> {
+/// let client = TestServer::new(Server::builder().configure_router(|r| r.route("/export", Method::GET, export)))?.client();
+///
+/// let res = client.get("/export").send().await?;
+/// assert_eq!(res.header(header::CONTENT_TYPE), Some("text/csv"));
+/// assert_eq!(res.text()?, "1,row 1\n2,row 2\n3,row 3\n");
+/// # Ok(())
+/// # }
+/// ```
+pub struct StreamResponder<S> {
+    stream: S,
+    content_type: Option<String>,
+    content_length: Option<u64>,
+    trailers: Option<(Vec<HeaderName>, BoxFuture<'static, HeaderMap>)>,
+}
+
+impl<S, E> StreamResponder<S>
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+    E: Into<Box<dyn std::error::Error + Send + Sync + 'static>> + 'static,
+{
+    pub fn new(stream: S) -> Self {
+        StreamResponder {
+            stream,
+            content_type: None,
+            content_length: None,
+            trailers: None,
+        }
+    }
+
+    pub fn content_type(mut self, content_type: &str) -> Self {
+        self.content_type = Some(content_type.to_string());
+        self
+    }
+
+    /// Set the length of the body when it is known in advance. The stream
+    /// needs to produce exactly this many bytes, the connection is otherwise
+    /// closed by the server.
+    pub fn content_length(mut self, len: u64) -> Self {
+        self.content_length = Some(len);
+        self
+    }
+
+    /// Send trailer headers once the stream ended and `trailers` resolved, see
+    /// [`Builder::trailers`]. They can't be combined with a content length.
+    pub fn trailers<F>(mut self, names: &[HeaderName], trailers: F) -> Self
+    where
+        F: Future<Output = HeaderMap> + Send + 'static,
+    {
+        self.trailers = Some((names.to_vec(), trailers.boxed()));
+        self
+    }
+}
+
+impl<S, E> Responder for StreamResponder<S>
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+    E: Into<Box<dyn std::error::Error + Send + Sync + 'static>> + 'static,
+{
+    fn respond_with_builder(self, builder: Builder, _ctx: &HttpContext) -> Builder {
+        let StreamResponder {
+            stream,
+            content_type,
+            content_length,
+            trailers,
+        } = self;
+
+        let body =
+            Box::new(stream.map_err(Into::into)) as Box<dyn Stream<Item = Result<Bytes, Box<dyn std::error::Error + Send + Sync + 'static>>> + Send + 'static>;
+        let mut builder = builder
+            .body(body)
+            .header(http::header::CONTENT_TYPE, content_type.as_deref().unwrap_or("application/octet-stream"));
+        if let Some(len) = content_length {
+            builder = builder.header(http::header::CONTENT_LENGTH, len);
+        }
+        if let Some((names, trailers)) = trailers {
+            builder = builder.trailers(&names, trailers);
+        }
+        builder
+    }
+}
+
 impl Responder for hyper::Error {
     fn respond_with_builder(self, builder: Builder, _ctx: &HttpContext) -> Builder {
         builder.status(500)
@@ -140,7 +329,11 @@ impl Responder for Builder {
 #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
 mod json {
     use super::*;
-    use crate::body::Json;
+    use crate::{
+        body::{Json, JsonLines},
+        error::SaphirError,
+    };
+    use futures::StreamExt;
     use serde::Serialize;
 
     impl<T: Serialize> Responder for Json<T> {
@@ -151,6 +344,34 @@ mod json {
             }
         }
     }
+
+    impl<S, T> Responder for JsonLines<S>
+    where
+        S: Stream<Item = T> + Send + 'static,
+        T: Serialize,
+    {
+        fn respond_with_builder(self, builder: Builder, ctx: &HttpContext) -> Builder {
+            let mut items = Box::pin(self.0);
+            let lines = async_stream::stream! {
+                while let Some(line) = items.next().await.map(|item| serde_json::to_vec(&item)) {
+                    match line {
+                        Ok(mut line) => {
+                            line.push(b'\n');
+                            yield Ok(Bytes::from(line));
+                        }
+                        Err(e) => {
+                            error!("Unable to serialize a json line, ending the response: {}", e);
+                            yield Err(SaphirError::from(e));
+                            break;
+                        }
+                    }
+                }
+            };
+            StreamResponder::new(Box::pin(lines))
+                .content_type("application/x-ndjson")
+                .respond_with_builder(builder, ctx)
+        }
+    }
 }
 
 #[cfg(feature = "form")]
@@ -170,6 +391,156 @@ mod form {
     }
 }
 
+#[cfg(feature = "msgpack")]
+#[cfg_attr(docsrs, doc(cfg(feature = "msgpack")))]
+mod msgpack {
+    use super::*;
+    use crate::body::MsgPack;
+    use serde::Serialize;
+
+    impl<T: Serialize> Responder for MsgPack<T> {
+        fn respond_with_builder(self, builder: Builder, _ctx: &HttpContext) -> Builder {
+            match builder.msgpack(&self.0) {
+                Ok(b) => b,
+                Err(e) => e.0.status(500).body("Unable to serialize msgpack data"),
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::{prelude::*, test::TestServer};
+
+        #[derive(serde_derive::Deserialize, serde_derive::Serialize)]
+        struct User {
+            name: String,
+        }
+
+        async fn echo(mut req: Request) -> Result<MsgPack<User>, SaphirError> {
+            let mut user = req.body_mut().take_as::<MsgPack<User>>().await?;
+            user.name.push_str(" (echoed)");
+            Ok(MsgPack(user))
+        }
+
+        #[tokio::test]
+        async fn responds_with_msgpack() {
+            let server = TestServer::new(Server::builder().configure_router(|r| r.route("/echo", Method::POST, echo))).unwrap();
+            let body = rmp_serde::to_vec_named(&User { name: "richer".to_string() }).unwrap();
+            let res = server.client().post("/echo").body(body).send().await.unwrap();
+            assert_eq!(res.header("content-type"), Some("application/msgpack"));
+            let user: User = rmp_serde::from_slice(res.body()).unwrap();
+            assert_eq!(user.name, "richer (echoed)");
+
+            let res = server.client().post("/echo").body("not msgpack").send().await.unwrap();
+            assert_eq!(res.status(), 400);
+        }
+    }
+}
+
+#[cfg(feature = "json5")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json5")))]
+mod json5 {
+    use super::*;
+    use crate::body::Json5;
+    use serde::Serialize;
+
+    impl<T: Serialize> Responder for Json5<T> {
+        fn respond_with_builder(self, builder: Builder, _ctx: &HttpContext) -> Builder {
+            match builder.json(&self.0) {
+                Ok(b) => b,
+                Err((b, _e)) => b.status(500).body("Unable to serialize json data"),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "cbor")]
+#[cfg_attr(docsrs, doc(cfg(feature = "cbor")))]
+mod cbor {
+    use super::*;
+    use crate::body::Cbor;
+    #[cfg(feature = "json")]
+    use crate::body::{Json, Negotiated};
+    use serde::Serialize;
+
+    impl<T: Serialize> Responder for Cbor<T> {
+        fn respond_with_builder(self, builder: Builder, _ctx: &HttpContext) -> Builder {
+            match builder.cbor(&self.0) {
+                Ok(b) => b,
+                Err(e) => e.0.status(500).body("Unable to serialize cbor data"),
+            }
+        }
+    }
+
+    #[cfg(feature = "json")]
+    impl<T: Serialize> Responder for Negotiated<T> {
+        fn respond_with_builder(self, builder: Builder, ctx: &HttpContext) -> Builder {
+            let builder = builder.vary(http::header::ACCEPT);
+            match self {
+                Negotiated::Json(t) => Json(t).respond_with_builder(builder, ctx),
+                Negotiated::Cbor(t) => Cbor(t).respond_with_builder(builder, ctx),
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::{prelude::*, test::TestServer};
+
+        #[derive(serde_derive::Deserialize, serde_derive::Serialize)]
+        struct User {
+            name: String,
+        }
+
+        async fn echo(mut req: Request) -> Result<Cbor<User>, SaphirError> {
+            let mut user = req.body_mut().take_as::<Cbor<User>>().await?;
+            user.name.push_str(" (echoed)");
+            Ok(Cbor(user))
+        }
+
+        #[cfg(feature = "json")]
+        async fn user(req: Request) -> Negotiated<User> {
+            Negotiated::new(&req, User { name: "richer".to_string() })
+        }
+
+        #[tokio::test]
+        async fn responds_with_cbor() {
+            let server = TestServer::new(Server::builder().configure_router(|r| r.route("/echo", Method::POST, echo))).unwrap();
+            let client = server.client();
+
+            let mut body = Vec::new();
+            ciborium::into_writer(&User { name: "richer".to_string() }, &mut body).unwrap();
+            let res = client.post("/echo").body(body).send().await.unwrap();
+            assert_eq!(res.header("content-type"), Some("application/cbor"));
+            let user: User = ciborium::from_reader(res.body().as_ref()).unwrap();
+            assert_eq!(user.name, "richer (echoed)");
+            assert_eq!(client.post("/echo").body("not cbor").send().await.unwrap().status(), 400);
+        }
+
+        #[cfg(feature = "json")]
+        #[tokio::test]
+        async fn negotiates_json_or_cbor() {
+            let server = TestServer::new(Server::builder().configure_router(|r| r.route("/user", Method::GET, user))).unwrap();
+            let client = server.client();
+
+            let res = client.get("/user").header("accept", "application/cbor").send().await.unwrap();
+            assert_eq!(res.header("content-type"), Some("application/cbor"));
+            assert_eq!(res.header("vary"), Some("accept"));
+            let user: User = ciborium::from_reader(res.body().as_ref()).unwrap();
+            assert_eq!(user.name, "richer");
+
+            let res = client
+                .get("/user")
+                .header("accept", "application/json, application/cbor;q=0.9")
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(res.header("content-type"), Some("application/json"));
+            assert_eq!(res.text().unwrap(), r#"{"name":"richer"}"#);
+        }
+    }
+}
+
 impl_status_responder!(u16, i16, u32, i32, u64, i64, usize, isize);
 impl_plain_body_responder!(String, &'static str);
 impl_body_responder!(Vec<u8>, &'static [u8], hyper::body::Bytes);
@@ -221,3 +592,49 @@ pub mod spanned {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{prelude::*, responder::DefaultStatus, test::TestServer};
+
+    async fn unit(_req: Request) {}
+
+    async fn created(_req: Request) -> (u16, ()) {
+        (201, ())
+    }
+
+    async fn accepted(_req: Request) -> DefaultStatus<()> {
+        DefaultStatus::new((), 202)
+    }
+
+    async fn failed(_req: Request) -> Result<(), ()> {
+        Err(())
+    }
+
+    async fn conflict(_req: Request) -> StatusCode {
+        StatusCode::CONFLICT
+    }
+
+    #[tokio::test]
+    async fn status_only_responses() {
+        let server = TestServer::new(Server::builder().configure_router(|r| {
+            r.route("/unit", Method::DELETE, unit)
+                .route("/created", Method::POST, created)
+                .route("/accepted", Method::POST, accepted)
+                .route("/failed", Method::POST, failed)
+                .route("/conflict", Method::PUT, conflict)
+        }))
+        .unwrap();
+        let client = server.client();
+
+        let res = client.delete("/unit").send().await.unwrap();
+        assert_eq!(res.status(), 204);
+        assert!(res.body().is_empty());
+        assert_eq!(client.post("/created").send().await.unwrap().status(), 201);
+        assert_eq!(client.post("/accepted").send().await.unwrap().status(), 202);
+        assert_eq!(client.post("/failed").send().await.unwrap().status(), 500);
+        let res = client.put("/conflict").send().await.unwrap();
+        assert_eq!(res.status(), 409);
+        assert!(res.body().is_empty());
+    }
+}
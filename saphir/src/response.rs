@@ -5,11 +5,14 @@ use std::{
 };
 
 use crate::cookie::{Cookie, CookieJar};
+use futures::future::{BoxFuture, FutureExt};
 use http::{header::HeaderName, response::Builder as RawBuilder, HeaderMap, HeaderValue, Response as RawResponse, StatusCode, Version};
-use hyper::body::Body as RawBody;
+use hyper::body::{Body as RawBody, HttpBody};
+use std::future::Future;
 
 use crate::{
     body::{Body, TransmuteBody},
+    conditional::EntityTag,
     error::SaphirError,
 };
 
@@ -120,6 +123,10 @@ pub struct Builder {
     body: Box<dyn TransmuteBody + Send>,
     #[doc(hidden)]
     status_set: bool,
+    #[doc(hidden)]
+    error: Option<SaphirError>,
+    #[doc(hidden)]
+    trailers: Option<BoxFuture<'static, HeaderMap>>,
     #[cfg(feature = "tracing-instrument")]
     #[doc(hidden)]
     span: Option<tracing::span::Span>,
@@ -143,6 +150,8 @@ impl Builder {
             cookies: None,
             body: Box::new(Option::<String>::None),
             status_set: false,
+            error: None,
+            trailers: None,
             #[cfg(feature = "tracing-instrument")]
             span: None,
         }
@@ -168,6 +177,16 @@ impl Builder {
         }
     }
 
+    /// Set the status of the response unless one was set, without preventing
+    /// a later default status from replacing it
+    #[inline]
+    pub(crate) fn implicit_status(mut self, status: StatusCode) -> Builder {
+        if !self.status_set {
+            self.inner = self.inner.status(status);
+        }
+        self
+    }
+
     /// Set the HTTP status for this response.
     ///
     /// This function will configure the HTTP status code of the `Response` that
@@ -193,6 +212,79 @@ impl Builder {
         self
     }
 
+    /// Set a redirection status and the `Location` header of the response.
+    ///
+    /// The location can be absolute or relative to the request uri. When the
+    /// status is not a redirection or the location is invalid, e.g. containing
+    /// spaces or non-ASCII characters which are not percent-encoded,
+    /// `Builder::build` returns a `SaphirError::InvalidRedirect`.
+    /// ```
+    /// # use saphir::prelude::*;
+    ///
+    /// let response = Builder::new()
+    ///     .redirect(StatusCode::SEE_OTHER, "../login?next=%2Fhome")
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    /// assert_eq!(response.headers()[header::LOCATION], "../login?next=%2Fhome");
+    ///
+    /// assert!(Builder::new().redirect(StatusCode::FOUND, "/a path").build().is_err());
+    /// assert!(Builder::new().redirect(StatusCode::OK, "/login").build().is_err());
+    /// ```
+    #[inline]
+    pub fn redirect<T>(self, status: T, location: &str) -> Builder
+    where
+        StatusCode: TryFrom<T>,
+        <StatusCode as TryFrom<T>>::Error: Into<http::Error>,
+    {
+        let status = match StatusCode::try_from(status) {
+            Ok(status) if status.is_redirection() => status,
+            Ok(status) => return self.fail(SaphirError::InvalidRedirect(format!("{} is not a redirection status", status))),
+            Err(e) => return self.fail(SaphirError::from(e.into())),
+        };
+
+        match location_header(location) {
+            Ok(location) => self.status::<StatusCode>(status).header(http::header::LOCATION, location),
+            Err(e) => self.fail(e),
+        }
+    }
+
+    /// Redirect to `location` with a `307 Temporary Redirect`, preserving the
+    /// request method and body.
+    /// ```
+    /// # use saphir::prelude::*;
+    ///
+    /// let response = Builder::new().temporary_redirect("/maintenance").build().unwrap();
+    ///
+    /// assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    /// ```
+    #[inline]
+    pub fn temporary_redirect(self, location: &str) -> Builder {
+        self.redirect(StatusCode::TEMPORARY_REDIRECT, location)
+    }
+
+    /// Redirect to `location` with a `308 Permanent Redirect`, preserving the
+    /// request method and body.
+    /// ```
+    /// # use saphir::prelude::*;
+    ///
+    /// let response = Builder::new().permanent_redirect("https://saphir.rs/docs").build().unwrap();
+    ///
+    /// assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    /// ```
+    #[inline]
+    pub fn permanent_redirect(self, location: &str) -> Builder {
+        self.redirect(StatusCode::PERMANENT_REDIRECT, location)
+    }
+
+    /// Keep the first error to be returned by `Builder::build`
+    #[inline]
+    fn fail(mut self, error: SaphirError) -> Builder {
+        self.error.get_or_insert(error);
+        self
+    }
+
     /// Set the HTTP version for this response.
     ///
     /// This function will configure the HTTP version of the `Response` that
@@ -243,6 +335,58 @@ impl Builder {
         self
     }
 
+    /// Set the `ETag` header of the response
+    #[inline]
+    pub fn etag(self, etag: &EntityTag) -> Builder {
+        self.header(http::header::ETAG, etag.get_tag())
+    }
+
+    /// Add `name` to the `Vary` header of the response, merged with the header
+    /// names it already lists
+    ///
+    /// ```
+    /// # use saphir::prelude::*;
+    /// let res = Builder::new()
+    ///     .header("Vary", "Accept")
+    ///     .vary(http::header::ACCEPT_ENCODING)
+    ///     .vary(http::header::ACCEPT)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(res.headers()["Vary"], "Accept, accept-encoding");
+    /// ```
+    pub fn vary(mut self, name: HeaderName) -> Builder {
+        if let Some(headers) = self.headers_mut() {
+            append_vary(headers, &name);
+        }
+        self
+    }
+
+    /// Set the `Content-Disposition` header so that the response is
+    /// downloaded as `filename` rather than displayed.
+    ///
+    /// Quotes, backslashes, path separators and control characters of the
+    /// name are replaced by `_`. Non-ASCII names are sent in the
+    /// `filename*=UTF-8''` form, along with an ASCII approximation for older
+    /// clients.
+    /// ```
+    /// # use saphir::prelude::*;
+    /// let res = Builder::new().attachment("résumé.pdf").build().unwrap();
+    /// assert_eq!(
+    ///     res.headers()["Content-Disposition"],
+    ///     "attachment; filename=\"r_sum_.pdf\"; filename*=UTF-8''r%C3%A9sum%C3%A9.pdf"
+    /// );
+    /// ```
+    pub fn attachment(self, filename: &str) -> Builder {
+        self.header(http::header::CONTENT_DISPOSITION, content_disposition("attachment", filename))
+    }
+
+    /// Set the `Content-Disposition` header so that the response is
+    /// displayed, with `filename` as the name to use when it is saved. The
+    /// name is encoded as for [`attachment`](Self::attachment).
+    pub fn inline(self, filename: &str) -> Builder {
+        self.header(http::header::CONTENT_DISPOSITION, content_disposition("inline", filename))
+    }
+
     /// Get header on this response builder.
     ///
     /// When builder has error returns None.
@@ -344,7 +488,7 @@ impl Builder {
         self
     }
 
-    #[cfg(any(feature = "form", feature = "json"))]
+    #[cfg(any(feature = "form", feature = "json", feature = "msgpack", feature = "cbor"))]
     #[inline]
     pub(crate) fn content_type_if_not_set(mut self, content_type: &str) -> Builder {
         if let Some(headers) = self.inner.headers_mut() {
@@ -357,6 +501,39 @@ impl Builder {
         self
     }
 
+    /// Send trailer headers after the body, once `trailers` resolves, e.g. a
+    /// checksum of the streamed data or a gRPC status.
+    ///
+    /// The `names` of the trailers are advertised in the `Trailer` header.
+    /// Trailers can only be sent with a chunked body, so building a response
+    /// which also has a `Content-Length` header fails. They are only sent on
+    /// HTTP/2 connections: HTTP/1 encoders do not write them, so the
+    /// `Trailer` header is removed from the responses to HTTP/1 requests, with
+    /// a warning.
+    ///
+    /// ```rust
+    /// # use saphir::prelude::*;
+    /// # use saphir::http::{HeaderMap, HeaderValue};
+    /// async fn export(_req: Request) -> Builder {
+    ///     let mut trailers = HeaderMap::new();
+    ///     trailers.insert("x-checksum", HeaderValue::from_static("d41d8cd9"));
+    ///     Builder::new()
+    ///         .body("data")
+    ///         .trailers(&[header::HeaderName::from_static("x-checksum")], async move { trailers })
+    /// }
+    /// ```
+    pub fn trailers<F>(mut self, names: &[HeaderName], trailers: F) -> Builder
+    where
+        F: Future<Output = HeaderMap> + Send + 'static,
+    {
+        let names = names.iter().map(HeaderName::as_str).collect::<Vec<_>>().join(", ");
+        if !names.is_empty() {
+            self = self.header(http::header::TRAILER, names);
+        }
+        self.trailers = Some(trailers.boxed());
+        self
+    }
+
     /// Finish the builder into Response<Body>
     #[inline]
     pub fn build(self) -> Result<Response<Body>, SaphirError> {
@@ -365,13 +542,37 @@ impl Builder {
             inner,
             cookies,
             mut body,
+            error,
+            trailers,
             span,
             ..
         } = self;
         #[cfg(not(feature = "tracing-instrument"))]
-        let Builder { inner, cookies, mut body, .. } = self;
-        let b = body.transmute();
-        let raw = inner.body(b)?;
+        let Builder {
+            inner,
+            cookies,
+            mut body,
+            error,
+            trailers,
+            ..
+        } = self;
+        if let Some(e) = error {
+            return Err(e);
+        }
+        let has_trailers = trailers.is_some();
+        let b = match trailers {
+            Some(_) if inner.headers_ref().is_some_and(|h| h.contains_key(http::header::CONTENT_LENGTH)) => {
+                return Err(SaphirError::Other(
+                    "Trailers can only be sent with a chunked body, without Content-Length".to_string(),
+                ));
+            }
+            Some(trailers) => with_trailers(body.transmute().into_raw(), trailers),
+            None => body.transmute(),
+        };
+        let mut raw = inner.body(b)?;
+        if has_trailers {
+            raw.extensions_mut().insert(WithTrailers);
+        }
 
         Ok(Response {
             inner: raw,
@@ -382,6 +583,109 @@ impl Builder {
     }
 }
 
+/// Marks the responses whose body is followed by trailers
+pub(crate) struct WithTrailers;
+
+/// Forward `body` to a chunked body, followed by the `trailers`
+fn with_trailers(mut body: RawBody, trailers: BoxFuture<'static, HeaderMap>) -> Body {
+    let (mut sender, chunked) = RawBody::channel();
+    tokio::spawn(async move {
+        while let Some(chunk) = body.data().await {
+            let sent = match chunk {
+                Ok(chunk) => sender.send_data(chunk).await.is_ok(),
+                Err(_) => false,
+            };
+            if !sent {
+                return sender.abort();
+            }
+        }
+        let _ = sender.send_trailers(trailers.await).await;
+    });
+    Body::from_raw(chunked)
+}
+
+/// Add `name` to the `Vary` header of `headers`, unless it is already listed,
+/// case insensitively, or the header is `*`. Multiple `Vary` headers are
+/// merged into one without duplicates.
+pub(crate) fn append_vary(headers: &mut HeaderMap, name: &HeaderName) {
+    let mut names: Vec<String> = Vec::new();
+    for listed in headers
+        .get_all(http::header::VARY)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+    {
+        let listed = listed.trim();
+        if !listed.is_empty() && !names.iter().any(|n| n.eq_ignore_ascii_case(listed)) {
+            names.push(listed.to_string());
+        }
+    }
+
+    if names.iter().any(|n| n == "*") {
+        names = vec!["*".to_string()];
+    } else if !names.iter().any(|n| n.eq_ignore_ascii_case(name.as_str())) {
+        names.push(name.as_str().to_string());
+    }
+
+    if let Ok(value) = HeaderValue::from_str(&names.join(", ")) {
+        headers.insert(http::header::VARY, value);
+    }
+}
+
+/// `Content-Disposition` header value of the `disposition` type for
+/// `filename`, encoded as described by RFC 6266
+pub(crate) fn content_disposition(disposition: &str, filename: &str) -> String {
+    let sanitized: String = filename
+        .chars()
+        .map(|c| if c.is_control() || matches!(c, '"' | '\\' | '/') { '_' } else { c })
+        .collect();
+
+    if sanitized.is_ascii() {
+        return format!("{}; filename=\"{}\"", disposition, sanitized);
+    }
+
+    let fallback: String = sanitized.chars().map(|c| if c.is_ascii() { c } else { '_' }).collect();
+    let mut encoded = String::with_capacity(sanitized.len() * 3);
+    for b in sanitized.bytes() {
+        // attr-char of RFC 5987
+        if b.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
+        }
+    }
+    format!("{}; filename=\"{}\"; filename*=UTF-8''{}", disposition, fallback, encoded)
+}
+
+/// Validate a `Location` header value.
+///
+/// Relative references are allowed, but every character outside of the
+/// visible ASCII range needs to be percent-encoded, and absolute uris need to
+/// be valid.
+pub(crate) fn location_header(location: &str) -> Result<HeaderValue, SaphirError> {
+    let invalid = |reason: &str| SaphirError::InvalidRedirect(format!("Invalid location `{}`: {}", location, reason));
+
+    if location.is_empty() {
+        return Err(invalid("empty location"));
+    }
+
+    if !location.bytes().all(|b| b.is_ascii_graphic()) {
+        return Err(invalid("whitespace, control and non-ASCII characters need to be percent-encoded"));
+    }
+
+    let has_scheme = location
+        .split_once(':')
+        .filter(|(scheme, _)| {
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic()) && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        })
+        .is_some();
+    if has_scheme {
+        location.parse::<http::Uri>().map_err(|e| invalid(&e.to_string()))?;
+    }
+
+    HeaderValue::from_str(location).map_err(|e| invalid(&e.to_string()))
+}
+
 impl Default for Builder {
     fn default() -> Self {
         Self::new()
@@ -422,12 +726,56 @@ mod form {
     }
 }
 
+#[cfg(feature = "msgpack")]
+#[cfg_attr(docsrs, doc(cfg(feature = "msgpack")))]
+mod msgpack {
+    use serde::Serialize;
+
+    use super::*;
+
+    impl Builder {
+        /// Serialize `t` as the msgpack body. On failure, the builder is
+        /// returned along with the error, boxed as the builder is large.
+        pub fn msgpack<T: Serialize>(self, t: &T) -> Result<Builder, Box<(Builder, SaphirError)>> {
+            match rmp_serde::to_vec_named(t) {
+                Ok(v) => Ok(self.content_type_if_not_set("application/msgpack").body(v)),
+                Err(e) => Err(Box::new((self, e.into()))),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "cbor")]
+#[cfg_attr(docsrs, doc(cfg(feature = "cbor")))]
+mod cbor {
+    use serde::Serialize;
+
+    use super::*;
+
+    impl Builder {
+        /// Serialize `t` as the cbor body. On failure, the builder is returned
+        /// along with the error, boxed as the builder is large.
+        pub fn cbor<T: Serialize>(self, t: &T) -> Result<Builder, Box<(Builder, SaphirError)>> {
+            let mut v = Vec::new();
+            match ciborium::into_writer(t, &mut v) {
+                Ok(()) => Ok(self.content_type_if_not_set("application/cbor").body(v)),
+                Err(e) => Err(Box::new((self, e.into()))),
+            }
+        }
+    }
+}
+
 #[cfg(feature = "file")]
 #[cfg_attr(docsrs, doc(cfg(feature = "file")))]
 mod file {
     use super::*;
-    use crate::{file::FileStream, prelude::Bytes};
+    use crate::{
+        file::{cache::FileCache, middleware::respond_file, Compression, File, FileStream},
+        prelude::Bytes,
+        request::Request,
+    };
     use futures::Stream;
+    use std::{io, path::Path};
 
     impl Builder {
         pub fn file<F: Into<FileStream>>(self, file: F) -> Builder {
@@ -436,5 +784,136 @@ mod file {
                     dyn Stream<Item = Result<Bytes, Box<dyn std::error::Error + Send + Sync + 'static>>> + Send + 'static,
                 >)
         }
+
+        /// Stream the file at `path` as the body, with its mime type, its
+        /// length, and the `ETag` and `Last-Modified` headers used by the
+        /// `ConditionalGetMiddleware`.
+        ///
+        /// A missing file is an error answered with `404 Not Found`.
+        /// ```rust
+        /// # use saphir::prelude::*;
+        /// async fn readme(_req: Request) -> Result<Builder, SaphirError> {
+        ///     Builder::new().file_path("examples/files_to_serve/index.html").await
+        /// }
+        /// ```
+        pub async fn file_path<P: AsRef<Path>>(self, path: P) -> Result<Builder, SaphirError> {
+            let path = path.as_ref();
+            let file = File::open(&path.to_string_lossy()).await.map_err(|e| open_error(e, path))?;
+            Ok(file.into_builder(self))
+        }
+
+        /// Like [`file_path`](Self::file_path), answering the conditional and
+        /// range requests of `req` too, as the `FileMiddleware` does: failed
+        /// preconditions with `412 Precondition Failed`, up to date clients
+        /// with `304 Not Modified`, and ranges with `206 Partial Content`.
+        ///
+        /// The file is served uncompressed.
+        /// ```rust
+        /// # use saphir::{prelude::*, test::TestServer};
+        /// async fn readme(req: Request) -> Result<Builder, SaphirError> {
+        ///     Builder::new().file_path_for(&req, "examples/files_to_serve/index.html").await
+        /// }
+        ///
+        /// # #[tokio::main]
+        /// # async fn main() -> Result<(), SaphirError> {
+        /// let client = TestServer::new(Server::builder().configure_router(|r| r.route("/readme", Method::GET, readme)))?.client();
+        ///
+        /// let res = client.get("/readme").header(header::RANGE, "bytes=0-1").send().await?;
+        /// assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        /// assert_eq!(res.text()?, "<!");
+        ///
+        /// let etag = res.header(header::ETAG).unwrap().to_string();
+        /// let res = client.get("/readme").header(header::IF_NONE_MATCH, etag).send().await?;
+        /// assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        /// # Ok(())
+        /// # }
+        /// ```
+        pub async fn file_path_for<P: AsRef<Path>>(self, req: &Request, path: P) -> Result<Builder, SaphirError> {
+            let path = path.as_ref();
+            if !path.is_file() {
+                return Err(open_error(io::ErrorKind::NotFound.into(), path));
+            }
+
+            // A cache without capacity, so that the file is streamed
+            respond_file(req, path, Compression::Raw, None, &mut FileCache::new(0, 0), self).await
+        }
+    }
+
+    fn open_error(e: io::Error, path: &Path) -> SaphirError {
+        match e.kind() {
+            io::ErrorKind::NotFound => SaphirError::Handler(
+                StatusCode::NOT_FOUND,
+                Box::new(io::Error::new(io::ErrorKind::NotFound, format!("file not found: {}", path.display()))),
+            ),
+            _ => SaphirError::Io(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn trailers_follow_the_body() {
+        let names = [HeaderName::from_static("x-checksum")];
+        let mut res = Builder::new()
+            .body("data")
+            .trailers(&names, async {
+                let mut trailers = HeaderMap::new();
+                trailers.insert("x-checksum", HeaderValue::from_static("abc"));
+                trailers
+            })
+            .build()
+            .unwrap();
+        assert_eq!(res.headers().get(http::header::TRAILER).unwrap(), "x-checksum");
+        assert_eq!(res.body().size_hint().exact(), None);
+
+        let body = res.body_mut();
+        assert_eq!(body.data().await.unwrap().unwrap(), "data");
+        assert!(body.data().await.is_none());
+        let trailers = body.trailers().await.unwrap().unwrap();
+        assert_eq!(trailers.get("x-checksum").unwrap(), "abc");
+
+        let res = Builder::new()
+            .header(http::header::CONTENT_LENGTH, 4)
+            .body("data")
+            .trailers(&names, async { HeaderMap::new() })
+            .build();
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn content_disposition_is_encoded() {
+        assert_eq!(content_disposition("attachment", "report.pdf"), "attachment; filename=\"report.pdf\"");
+        assert_eq!(
+            content_disposition("inline", "my \"best\"\r\nreport/..\\x.pdf"),
+            "inline; filename=\"my _best___report_.._x.pdf\""
+        );
+        assert_eq!(
+            content_disposition("attachment", "報告 1.pdf"),
+            "attachment; filename=\"__ 1.pdf\"; filename*=UTF-8''%E5%A0%B1%E5%91%8A%201.pdf"
+        );
+
+        let res = Builder::new().inline("a b.txt").build().unwrap();
+        assert_eq!(res.headers()[http::header::CONTENT_DISPOSITION], "inline; filename=\"a b.txt\"");
+    }
+
+    #[test]
+    fn vary_values_are_merged_without_duplicates() {
+        let mut headers = HeaderMap::new();
+        append_vary(&mut headers, &http::header::ACCEPT);
+        assert_eq!(headers[http::header::VARY], "accept");
+
+        headers.append(http::header::VARY, HeaderValue::from_static("Accept-Language, Accept"));
+        headers.append(http::header::VARY, HeaderValue::from_static("origin,accept-language"));
+        append_vary(&mut headers, &http::header::ACCEPT_ENCODING);
+        append_vary(&mut headers, &http::header::ACCEPT_LANGUAGE);
+        assert_eq!(headers.get_all(http::header::VARY).iter().count(), 1);
+        assert_eq!(headers[http::header::VARY], "accept, Accept-Language, origin, accept-encoding");
+
+        headers.insert(http::header::VARY, HeaderValue::from_static("*"));
+        append_vary(&mut headers, &http::header::ACCEPT);
+        assert_eq!(headers[http::header::VARY], "*");
     }
 }
@@ -12,6 +12,7 @@
 use crate::{
     body::Body,
     controller::{Controller, DynControllerHandler},
+    cookie::Cookies,
     error::SaphirError,
     guard::{Builder as GuardBuilder, GuardChain, GuardChainEnd},
     handler::DynHandler,
@@ -22,11 +23,28 @@ use crate::{
 };
 use futures::{future::BoxFuture, FutureExt};
 use http::Method;
-use std::{collections::HashMap, sync::Arc};
+use std::{cmp::Ordering, collections::HashMap, sync::Arc};
+
+#[cfg(feature = "openapi-runtime")]
+use crate::{
+    body::Json,
+    openapi::{Info, OpenApi, Operation},
+};
+
+type BoxedHandler = Box<dyn DynHandler<Body> + Send + Sync>;
 
 /// Builder type for the router
 pub struct Builder<Chain: RouterChain + Send + Unpin + 'static + Sync> {
     resolver: HashMap<String, EndpointResolver>,
+    duplicates: Vec<String>,
+    prefix: String,
+    fallback: Option<BoxedHandler>,
+    method_not_allowed: Option<BoxedHandler>,
+    trailing_slash: TrailingSlash,
+    #[cfg(feature = "openapi-runtime")]
+    operations: Vec<(String, Method, Operation)>,
+    #[cfg(feature = "openapi-runtime")]
+    openapi: Option<(String, Info)>,
     chain: Chain,
 }
 
@@ -34,13 +52,24 @@ impl Default for Builder<RouterChainEnd> {
     fn default() -> Self {
         Self {
             resolver: Default::default(),
+            duplicates: Default::default(),
+            prefix: Default::default(),
+            fallback: None,
+            method_not_allowed: None,
+            trailing_slash: TrailingSlash::default(),
+            #[cfg(feature = "openapi-runtime")]
+            operations: Vec::new(),
+            #[cfg(feature = "openapi-runtime")]
+            openapi: None,
             chain: RouterChainEnd { handlers: Default::default() },
         }
     }
 }
 
 impl<Controllers: 'static + RouterChain + Unpin + Send + Sync> Builder<Controllers> {
-    /// Add a simple request handle to a given path
+    /// Add a simple request handle to a given path. The handler can be an
+    /// `async fn` or a closure capturing some state, see
+    /// [`Handler`](crate::handler::Handler)
     ///
     /// ```rust
     /// # use saphir::router::Builder as RBuilder;
@@ -59,15 +88,7 @@ impl<Controllers: 'static + RouterChain + Unpin + Send + Sync> Builder<Controlle
     where
         H: 'static + DynHandler<Body> + Send + Sync,
     {
-        let endpoint_id = if let Some(er) = self.resolver.get_mut(route) {
-            er.add_method(method.clone());
-            er.id()
-        } else {
-            let er = EndpointResolver::new(route, method.clone()).expect("Unable to construct endpoint resolver");
-            let er_id = er.id();
-            self.resolver.insert(route.to_string(), er);
-            er_id
-        };
+        let endpoint_id = self.endpoint(route, method.clone(), None);
 
         self.chain
             .add_handler(endpoint_id, method, Box::new(handler), crate::guard::Builder::default().build());
@@ -104,15 +125,7 @@ impl<Controllers: 'static + RouterChain + Unpin + Send + Sync> Builder<Controlle
         F: FnOnce(GuardBuilder<GuardChainEnd>) -> GuardBuilder<Chain>,
         Chain: GuardChain + 'static,
     {
-        let endpoint_id = if let Some(er) = self.resolver.get_mut(route) {
-            er.add_method(method.clone());
-            er.id()
-        } else {
-            let er = EndpointResolver::new(route, method.clone()).expect("Unable to construct endpoint resolver");
-            let er_id = er.id();
-            self.resolver.insert(route.to_string(), er);
-            er_id
-        };
+        let endpoint_id = self.endpoint(route, method.clone(), None);
 
         self.chain
             .add_handler(endpoint_id, method, Box::new(handler), guards(GuardBuilder::default()).build());
@@ -139,28 +152,36 @@ impl<Controllers: 'static + RouterChain + Unpin + Send + Sync> Builder<Controlle
     /// // ...
     /// ```
     pub fn controller<C: Controller + Send + Unpin + Sync>(mut self, controller: C) -> Builder<RouterChainLink<C, Controllers>> {
+        #[cfg(feature = "openapi-runtime")]
+        for (method, subroute, operation) in controller.operations() {
+            self = self.describe(&join_path(C::BASE_PATH, subroute), method, operation);
+        }
+
         let mut handlers = HashMap::new();
         for (name, method, subroute, handler, guard_chain) in controller.handlers() {
-            let route = format!("{}{}", C::BASE_PATH, subroute);
-            let meta = name.map(|name| HandlerMetadata {
+            let route = join_path(C::BASE_PATH, subroute);
+            let meta = HandlerMetadata {
                 route_id: Default::default(),
-                name: Some(name),
-            });
-            let endpoint_id = if let Some(er) = self.resolver.get_mut(&route) {
-                er.add_method_with_metadata(method.clone(), meta);
-                er.id()
-            } else {
-                let er = EndpointResolver::new_with_metadata(&route, method.clone(), meta).expect("Unable to construct endpoint resolver");
-                let er_id = er.id();
-                self.resolver.insert(route, er);
-                er_id
+                name,
+                controller: C::NAME,
+                route_template: None,
             };
+            let endpoint_id = self.endpoint(&route, method.clone(), Some(meta));
 
             handlers.insert((endpoint_id, method), (handler, guard_chain));
         }
 
         Builder {
             resolver: self.resolver,
+            duplicates: self.duplicates,
+            prefix: self.prefix,
+            fallback: self.fallback,
+            method_not_allowed: self.method_not_allowed,
+            trailing_slash: self.trailing_slash,
+            #[cfg(feature = "openapi-runtime")]
+            operations: self.operations,
+            #[cfg(feature = "openapi-runtime")]
+            openapi: self.openapi,
             chain: RouterChainLink {
                 controller,
                 handlers,
@@ -169,15 +190,231 @@ impl<Controllers: 'static + RouterChain + Unpin + Send + Sync> Builder<Controlle
         }
     }
 
+    /// Handle the requests whose path matches no route, instead of answering
+    /// `404 Not Found` without a body.
+    ///
+    /// The response of the fallback handler has the `404` status unless it
+    /// sets another one.
+    ///
+    /// ```rust
+    /// # use saphir::prelude::*;
+    /// async fn not_found(req: Request) -> String {
+    ///     format!("<h1>Nothing to see at {}</h1>", req.uri().path())
+    /// }
+    ///
+    /// let server = Server::builder()
+    ///     .configure_router(|r| r.fallback(not_found))
+    ///     .build();
+    /// ```
+    pub fn fallback<H>(mut self, handler: H) -> Self
+    where
+        H: 'static + DynHandler<Body> + Send + Sync,
+    {
+        self.fallback = Some(Box::new(handler));
+        self
+    }
+
+    /// Handle the requests whose path matches a route registered for other
+    /// methods, instead of answering `405 Method Not Allowed` without a body.
+    ///
+    /// The response of the handler has the `405` status and the `Allow`
+    /// header listing the methods of the path, unless it sets them.
+    pub fn method_not_allowed<H>(mut self, handler: H) -> Self
+    where
+        H: 'static + DynHandler<Body> + Send + Sync,
+    {
+        self.method_not_allowed = Some(Box::new(handler));
+        self
+    }
+
+    /// Set how the requests whose path only differs from a route by a trailing
+    /// slash are handled, [`TrailingSlash::Merge`] by default.
+    ///
+    /// ```rust
+    /// # use saphir::{prelude::*, router::TrailingSlash, test::TestServer};
+    /// async fn users(_req: Request) -> &'static str { "[]" }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), SaphirError> {
+    /// let client = TestServer::new(Server::builder().configure_router(|r| r.trailing_slash(TrailingSlash::Redirect).route("/users", Method::GET, users)))?.client();
+    ///
+    /// let res = client.get("/users/?page=2").send().await?;
+    /// assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+    /// assert_eq!(res.header("location"), Some("/users?page=2"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn trailing_slash(mut self, trailing_slash: TrailingSlash) -> Self {
+        self.trailing_slash = trailing_slash;
+        self
+    }
+
+    /// Describe the operation of `method` on `route` in the OpenAPI document
+    /// served by [`openapi`](Self::openapi), see [`crate::openapi`]
+    #[cfg(feature = "openapi-runtime")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "openapi-runtime")))]
+    pub fn describe(mut self, route: &str, method: Method, operation: Operation) -> Self {
+        let route = if self.prefix.is_empty() {
+            route.to_string()
+        } else {
+            join_path(&self.prefix, route)
+        };
+        self.operations.push((route, method, operation));
+        self
+    }
+
+    /// Serve the OpenAPI document of the described operations as json on
+    /// `GET path`, see [`crate::openapi`]
+    #[cfg(feature = "openapi-runtime")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "openapi-runtime")))]
+    pub fn openapi(mut self, path: &str, info: Info) -> Self {
+        let path = if self.prefix.is_empty() {
+            path.to_string()
+        } else {
+            join_path(&self.prefix, path)
+        };
+        self.openapi = Some((path, info));
+        self
+    }
+
+    /// Register the route serving the OpenAPI document, once every operation
+    /// is described
+    #[cfg(feature = "openapi-runtime")]
+    fn serve_openapi(mut self) -> Self {
+        if let Some((path, info)) = self.openapi.take() {
+            let document = std::mem::take(&mut self.operations)
+                .into_iter()
+                .fold(OpenApi::new(info), |doc, (route, method, operation)| doc.operation(route, method, operation))
+                .to_json();
+            self = self.route(&path, Method::GET, move |_req: Request| {
+                let document = document.clone();
+                async move { Json(document) }
+            });
+        }
+        self
+    }
+
+    /// Register the routes and controllers added by `f` under `prefix`.
+    ///
+    /// Groups of routes can then be declared in functions and mounted under
+    /// different prefixes, and mounts can be nested.
+    ///
+    /// ```rust
+    /// # use saphir::router::{Builder, RouterChain};
+    /// # use saphir::prelude::*;
+    /// async fn list_users(_req: Request) -> &'static str { "[]" }
+    /// async fn get_user(_req: Request) -> &'static str { "{}" }
+    ///
+    /// fn users<C: RouterChain + Unpin + Send + Sync>(r: Builder<C>) -> Builder<C> {
+    ///     r.route("/users", Method::GET, list_users).route("/users/{id}", Method::GET, get_user)
+    /// }
+    ///
+    /// // Serves GET /v1/users, /v1/users/{id}, /v2/users and /v2/users/{id}
+    /// let server = Server::builder()
+    ///     .configure_router(|r| r.mount("/v1", users).mount("/v2", users))
+    ///     .build();
+    /// ```
+    pub fn mount<F, NewChain>(mut self, prefix: &str, f: F) -> Builder<NewChain>
+    where
+        F: FnOnce(Self) -> Builder<NewChain>,
+        NewChain: RouterChain + Unpin + Send + Sync,
+    {
+        let outer = self.prefix.clone();
+        self.prefix = format!("{}/{}", outer, prefix.trim_matches('/')).trim_end_matches('/').to_string();
+        let mut builder = f(self);
+        builder.prefix = outer;
+        builder
+    }
+
+    /// Register `method` on the endpoint of `route`, returning the endpoint id
+    fn endpoint(&mut self, route: &str, method: Method, meta: Option<HandlerMetadata>) -> u64 {
+        let prefixed;
+        let route = if self.prefix.is_empty() {
+            route
+        } else {
+            prefixed = join_path(&self.prefix, route);
+            &prefixed
+        };
+        if let Some(er) = self.resolver.get_mut(route) {
+            if er.methods().is_some_and(|methods| methods.contains(&&method)) {
+                self.duplicates.push(format!("{} {} is registered more than once", method, route));
+            }
+            er.add_method_with_metadata(method, meta);
+            er.id()
+        } else {
+            let er = EndpointResolver::new_with_metadata(route, method, meta).expect("Unable to construct endpoint resolver");
+            let er_id = er.id();
+            self.resolver.insert(route.to_string(), er);
+            er_id
+        }
+    }
+
+    /// # Panics
+    /// If a method and path are registered more than once, or if a method of
+    /// an endpoint can't be reliably reached because another endpoint
+    /// matching all of its paths has the same method and priority, or is
+    /// resolved first.
     pub(crate) fn build(self) -> Router {
-        let Builder { resolver, chain: controllers } = self;
+        #[cfg(feature = "openapi-runtime")]
+        let builder = self.serve_openapi();
+        #[cfg(not(feature = "openapi-runtime"))]
+        let builder = self;
+        let Builder {
+            resolver,
+            mut duplicates,
+            fallback,
+            method_not_allowed,
+            trailing_slash,
+            chain: controllers,
+            ..
+        } = builder;
 
         let mut resolvers: Vec<_> = resolver.into_values().collect();
         resolvers.sort_unstable();
 
+        for (i, resolver) in resolvers.iter().enumerate() {
+            for other in &resolvers[(i + 1)..] {
+                if trailing_slash == TrailingSlash::Strict && resolver.trailing_slash_differs(other) {
+                    continue;
+                }
+
+                if resolver.cmp(other) == Ordering::Equal {
+                    // Endpoints of the same priority are resolved in an undefined order
+                    let methods = if resolver.path_covers(other) {
+                        resolver.shadowed_methods(other)
+                    } else {
+                        other.shadowed_methods(resolver)
+                    };
+                    let mut templates = [resolver.template(), other.template()];
+                    templates.sort_unstable();
+                    duplicates.extend(
+                        methods
+                            .into_iter()
+                            .map(|m| format!("{} {} and {} {} overlap with the same priority", m, templates[0], m, templates[1])),
+                    );
+                } else {
+                    duplicates.extend(resolver.shadowed_methods(other).into_iter().map(|m| {
+                        format!(
+                            "{} {} is unreachable, {} {} matches all of its requests",
+                            m,
+                            other.template(),
+                            m,
+                            resolver.template()
+                        )
+                    }));
+                }
+            }
+        }
+        if !duplicates.is_empty() {
+            panic!("Conflicting routes registered in the router:\n  - {}", duplicates.join("\n  - "));
+        }
+
         Router {
             inner: Arc::new(RouterInner {
                 resolvers,
+                fallback,
+                method_not_allowed,
+                trailing_slash,
                 chain: Box::new(controllers),
             }),
         }
@@ -186,6 +423,9 @@ impl<Controllers: 'static + RouterChain + Unpin + Send + Sync> Builder<Controlle
 
 struct RouterInner {
     resolvers: Vec<EndpointResolver>,
+    fallback: Option<BoxedHandler>,
+    method_not_allowed: Option<BoxedHandler>,
+    trailing_slash: TrailingSlash,
     chain: Box<dyn RouterChain + Send + Unpin + Sync>,
 }
 
@@ -203,15 +443,22 @@ impl Router {
     pub fn resolve(&self, req: &mut Request<Body>) -> Result<u64, u16> {
         let mut method_not_allowed = false;
         for endpoint_resolver in &self.inner.resolvers {
-            match endpoint_resolver.resolve(req) {
+            match endpoint_resolver.resolve_with(req, self.inner.trailing_slash) {
                 EndpointResolverResult::InvalidPath => continue,
                 EndpointResolverResult::MethodNotAllowed => method_not_allowed = true,
                 EndpointResolverResult::Match(_) => return Ok(endpoint_resolver.id()),
+                // An empty segment would make the location a protocol-relative url, e.g. `//host`
+                EndpointResolverResult::TrailingSlashRedirect if req.uri().path().contains("//") => continue,
+                EndpointResolverResult::TrailingSlashRedirect => return Err(308),
             }
         }
 
         if method_not_allowed {
-            Err(405)
+            if req.method() == Method::OPTIONS {
+                Err(204)
+            } else {
+                Err(405)
+            }
         } else {
             Err(404)
         }
@@ -221,22 +468,46 @@ impl Router {
         let mut method_not_allowed = false;
 
         for endpoint_resolver in &self.inner.resolvers {
-            match endpoint_resolver.resolve(req) {
+            match endpoint_resolver.resolve_with(req, self.inner.trailing_slash) {
                 EndpointResolverResult::InvalidPath => continue,
                 EndpointResolverResult::MethodNotAllowed => method_not_allowed = true,
                 EndpointResolverResult::Match(meta) => return meta.clone(),
+                EndpointResolverResult::TrailingSlashRedirect if req.uri().path().contains("//") => continue,
+                EndpointResolverResult::TrailingSlashRedirect => return HandlerMetadata::trailing_slash_redirect(),
             }
         }
 
         if method_not_allowed {
-            HandlerMetadata::not_allowed()
+            if req.method() == Method::OPTIONS {
+                HandlerMetadata::options()
+            } else {
+                HandlerMetadata::not_allowed()
+            }
         } else {
             HandlerMetadata::not_found()
         }
     }
 
+    /// Returns every method registered for the endpoints matching `path`,
+    /// as advertised by the `Allow` header.
+    ///
+    /// `OPTIONS` is always part of the allowed methods, since it is answered
+    /// automatically when no handler is registered for it.
+    pub fn allowed_methods(&self, path: &str) -> Vec<Method> {
+        let mut methods = vec![Method::OPTIONS];
+        for endpoint_resolver in self.inner.resolvers.iter().filter(|r| r.match_path(path)) {
+            if let Some(resolver_methods) = endpoint_resolver.methods() {
+                methods.extend(resolver_methods.into_iter().cloned());
+            }
+        }
+
+        methods.sort_unstable_by(|a, b| a.as_str().cmp(b.as_str()));
+        methods.dedup();
+        methods
+    }
+
     pub async fn dispatch(&self, mut ctx: HttpContext) -> Result<HttpContext, SaphirError> {
-        let req = ctx.state.take_request().ok_or(SaphirError::RequestMovedBeforeHandler)?;
+        let mut req = ctx.state.take_request().ok_or(SaphirError::RequestMovedBeforeHandler)?;
         // # SAFETY #
         // The router is initialized in static memory when calling run on Server.
         let static_self = unsafe { std::mem::transmute::<&'_ Self, &'static Self>(self) };
@@ -244,14 +515,40 @@ impl Router {
         let route_id = match ctx.metadata.route_id {
             RouteId::Id(id) => id,
             RouteId::Error(e) => {
-                return e.respond_with_builder(b, &ctx).build().map(|r| {
+                let b = if e == 405 || (e == 204 && req.method() == Method::OPTIONS) {
+                    let allow = self.allowed_methods(req.uri().path()).iter().map(|m| m.as_str()).collect::<Vec<_>>().join(", ");
+                    b.header(http::header::ALLOW, allow)
+                } else if e == 308 {
+                    b.permanent_redirect(&toggle_trailing_slash(req.uri()))
+                } else {
+                    b
+                };
+                let handler = match e {
+                    404 => self.inner.fallback.as_ref(),
+                    405 => self.inner.method_not_allowed.as_ref(),
+                    _ => None,
+                };
+                let b = match handler {
+                    Some(handler) => {
+                        let cookies = Cookies::default();
+                        req.extensions_mut().insert(cookies.clone());
+                        cookies.write_back(handler.dyn_handle(req).await.dyn_respond(b, &ctx)).status_if_not_set(e)
+                    }
+                    None => e.respond_with_builder(b, &ctx),
+                };
+                return b.build().map(|r| {
                     ctx.state = State::After(Box::new(r));
                     ctx
                 });
             }
         };
+        if let Some(route) = ctx.matched_route() {
+            req.extensions_mut().insert(route);
+        }
+        let cookies = Cookies::default();
+        req.extensions_mut().insert(cookies.clone());
         let res = if let Some(responder) = static_self.inner.chain.dispatch(route_id, req) {
-            responder.await.dyn_respond(b, &ctx)
+            cookies.write_back(responder.await.dyn_respond(b, &ctx))
         } else {
             404.respond_with_builder(b, &ctx)
         }
@@ -264,6 +561,51 @@ impl Router {
     }
 }
 
+/// Handling of the requests whose path only differs from a route by a
+/// trailing slash, e.g. `/users/` for the `/users` route.
+///
+/// The route template is the canonical path: `/users/` is the canonical path
+/// of a route registered as `/users/`. The root path `/` and the routes ending
+/// with a `**` wildcard, which captures the trailing slash, are always
+/// matched.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TrailingSlash {
+    /// Only the canonical path matches the route, the other one is answered
+    /// with `404 Not Found`
+    Strict,
+    /// Both paths match the route
+    #[default]
+    Merge,
+    /// The other path is redirected to the canonical one with a `308
+    /// Permanent Redirect`, keeping the query string
+    Redirect,
+}
+
+/// Path of `route` under `base`, without a trailing slash when `route` is the
+/// root of `base`, e.g. the `#[get("/")]` handler of a controller
+fn join_path(base: &str, route: &str) -> String {
+    if route == "/" && !base.is_empty() {
+        base.to_string()
+    } else {
+        format!("{}{}", base, route)
+    }
+}
+
+/// Location of `uri` with its trailing slash added or removed
+fn toggle_trailing_slash(uri: &http::Uri) -> String {
+    let path = uri.path();
+    let mut location = if let Some(path) = path.strip_suffix('/') {
+        path.to_string()
+    } else {
+        format!("{}/", path)
+    };
+    if let Some(query) = uri.query() {
+        location.push('?');
+        location.push_str(query);
+    }
+    location
+}
+
 #[doc(hidden)]
 pub trait RouterChain {
     fn dispatch(&'static self, resolver_id: u64, req: Request<Body>) -> Option<BoxFuture<'static, Box<dyn DynResponder + Send>>>;
@@ -333,3 +675,293 @@ impl<C: Sync + Send, Rest: RouterChain + Sync + Send> RouterChain for RouterChai
         self.rest.add_handler(endpoint_id, method, handler, guards);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn handler(_: Request<Body>) -> u16 {
+        200
+    }
+
+    fn request(method: Method, path: &str) -> Request<Body> {
+        Request::new(http::Request::builder().method(method).uri(path).body(Body::empty()).unwrap(), None)
+    }
+
+    #[test]
+    fn allowed_methods_of_matching_endpoints() {
+        let router = Router::builder()
+            .route("/users", Method::GET, handler)
+            .route("/users", Method::POST, handler)
+            .route("/users/<id>", Method::DELETE, handler)
+            .route("/users/me", Method::PUT, handler)
+            .build();
+
+        assert_eq!(router.allowed_methods("/users"), vec![Method::GET, Method::OPTIONS, Method::POST]);
+        assert_eq!(router.allowed_methods("/users/me"), vec![Method::DELETE, Method::OPTIONS, Method::PUT]);
+        assert_eq!(router.allowed_methods("/unknown"), vec![Method::OPTIONS]);
+    }
+
+    #[test]
+    fn options_without_handler_is_answered() {
+        let router = Router::builder()
+            .route("/users", Method::GET, handler)
+            .route("/explicit", Method::OPTIONS, handler)
+            .build();
+
+        assert_eq!(router.resolve_metadata(&mut request(Method::OPTIONS, "/users")).route_id, RouteId::Error(204));
+        assert_eq!(router.resolve_metadata(&mut request(Method::POST, "/users")).route_id, RouteId::Error(405));
+        assert_eq!(router.resolve_metadata(&mut request(Method::OPTIONS, "/unknown")).route_id, RouteId::Error(404));
+        assert!(matches!(
+            router.resolve_metadata(&mut request(Method::OPTIONS, "/explicit")).route_id,
+            RouteId::Id(_)
+        ));
+    }
+
+    #[test]
+    fn mounted_routes_are_prefixed() {
+        fn users<C: RouterChain + Unpin + Send + Sync>(r: Builder<C>) -> Builder<C> {
+            r.route("/users", Method::GET, handler).route("/users/<id>", Method::DELETE, handler)
+        }
+
+        let router = Router::builder()
+            .mount("/v1/", users)
+            .mount("v2", |r| users(r).mount("/admin", users).route("/status", Method::GET, handler))
+            .route("/status", Method::GET, handler)
+            .build();
+
+        for path in ["/v1/users", "/v2/users", "/v2/admin/users", "/v2/status", "/status"] {
+            assert!(router.resolve(&mut request(Method::GET, path)).is_ok(), "{}", path);
+        }
+        assert!(router.resolve(&mut request(Method::DELETE, "/v2/admin/users/1")).is_ok());
+        assert_eq!(router.resolve(&mut request(Method::GET, "/users")), Err(404));
+        assert_eq!(router.resolve(&mut request(Method::GET, "/v1/status")), Err(404));
+        assert_eq!(router.resolve(&mut request(Method::GET, "/v2/admin/status")), Err(404));
+    }
+
+    #[tokio::test]
+    async fn unmatched_requests_reach_the_fallbacks() {
+        use crate::{server::Server, test::TestServer};
+
+        async fn not_found(req: Request) -> String {
+            format!("no {}", req.uri().path())
+        }
+
+        async fn not_allowed(req: Request) -> String {
+            format!("no {}", req.method())
+        }
+
+        let client = TestServer::new(
+            Server::builder().configure_router(|r| r.route("/users", Method::GET, handler).fallback(not_found).method_not_allowed(not_allowed)),
+        )
+        .unwrap()
+        .client();
+
+        let res = client.get("/unknown").send().await.unwrap();
+        assert_eq!(res.status(), 404);
+        assert_eq!(res.text().unwrap(), "no /unknown");
+
+        let res = client.post("/users").send().await.unwrap();
+        assert_eq!(res.status(), 405);
+        assert_eq!(res.header(http::header::ALLOW), Some("GET, OPTIONS"));
+        assert_eq!(res.text().unwrap(), "no POST");
+
+        let res = client.request(Method::OPTIONS, "/users").send().await.unwrap();
+        assert_eq!(res.status(), 204);
+        assert_eq!(client.get("/users").send().await.unwrap().status(), 200);
+    }
+
+    #[test]
+    #[should_panic(expected = "GET /users is registered more than once")]
+    fn duplicate_routes_are_rejected() {
+        Router::builder()
+            .route("/users", Method::GET, handler)
+            .route("/users", Method::POST, handler)
+            .route("/users", Method::GET, handler)
+            .build();
+    }
+
+    #[test]
+    #[should_panic(expected = "DELETE /users/<id> and DELETE /users/<name> overlap with the same priority")]
+    fn equivalent_routes_are_rejected() {
+        Router::builder()
+            .route("/users/<id>", Method::DELETE, handler)
+            .route("/users/<name>", Method::DELETE, handler)
+            .build();
+    }
+
+    #[test]
+    #[should_panic(expected = "GET /files/<name> and GET /files/img-* overlap with the same priority")]
+    fn overlapping_routes_are_rejected() {
+        Router::builder()
+            .route("/files/<name>", Method::GET, handler)
+            .route("/files/img-*", Method::GET, handler)
+            .build();
+    }
+
+    #[test]
+    fn partially_shadowed_routes_are_allowed() {
+        Router::builder()
+            .route("/users/<id>", Method::GET, handler)
+            .route("/users/<name>", Method::POST, handler)
+            .route("/users/me", Method::GET, handler)
+            .route("/files/**", Method::GET, handler)
+            .route("/files/**/raw", Method::GET, handler)
+            .build();
+    }
+
+    #[tokio::test]
+    async fn matched_route_is_available_to_guards_and_handlers() {
+        use crate::{http_context::MatchedRoute, request::FromRequest, server::Server, test::TestServer};
+
+        async fn route(mut req: Request<Body>) -> String {
+            MatchedRoute::from_request(&mut req).await.unwrap().to_string()
+        }
+
+        async fn guard(req: Request<Body>) -> Result<Request<Body>, u16> {
+            match req.extensions().get::<MatchedRoute>() {
+                Some(route) if route.as_str() == "/guarded/<id>" => Ok(req),
+                _ => Err(500),
+            }
+        }
+
+        let server = TestServer::new(Server::builder().configure_router(|r| {
+            r.route("/users/<id>", Method::GET, route)
+                .route_with_guards("/guarded/<id>", Method::GET, route, |g| g.apply(guard))
+        }))
+        .unwrap();
+        let client = server.client();
+
+        assert_eq!(client.get("/users/42").send().await.unwrap().text().unwrap(), "/users/<id>");
+        assert_eq!(client.get("/guarded/42").send().await.unwrap().text().unwrap(), "/guarded/<id>");
+    }
+
+    fn trailing_slash_router(trailing_slash: TrailingSlash) -> Router {
+        Router::builder()
+            .trailing_slash(trailing_slash)
+            .route("/", Method::GET, handler)
+            .route("/users", Method::GET, handler)
+            .route("/users/<id>/posts/", Method::GET, handler)
+            .route("/files/**", Method::GET, handler)
+            .route("/assets/**/raw", Method::GET, handler)
+            .build()
+    }
+
+    #[test]
+    fn trailing_slashes_are_merged_by_default() {
+        let router = trailing_slash_router(TrailingSlash::default());
+
+        for path in [
+            "/",
+            "/users",
+            "/users/",
+            "/users/1/posts",
+            "/users/1/posts/",
+            "/files/a/b",
+            "/files/a/",
+            "/assets/a/raw",
+            "/assets/a/raw/",
+        ] {
+            assert!(router.resolve(&mut request(Method::GET, path)).is_ok(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn trailing_slashes_are_strict() {
+        let router = trailing_slash_router(TrailingSlash::Strict);
+
+        for path in ["/", "/users", "/users/1/posts/", "/files/a/b", "/files/a/", "/assets/a/raw"] {
+            assert!(router.resolve(&mut request(Method::GET, path)).is_ok(), "{}", path);
+        }
+        for path in ["/users/", "/users/1/posts", "/assets/a/raw/"] {
+            assert_eq!(router.resolve(&mut request(Method::GET, path)), Err(404), "{}", path);
+        }
+    }
+
+    #[test]
+    fn strict_trailing_slashes_tell_routes_apart() {
+        let router = Router::builder()
+            .trailing_slash(TrailingSlash::Strict)
+            .route("/users", Method::GET, handler)
+            .route("/users/", Method::GET, handler)
+            .build();
+
+        let without = router.resolve(&mut request(Method::GET, "/users")).unwrap();
+        let with = router.resolve(&mut request(Method::GET, "/users/")).unwrap();
+        assert_ne!(without, with);
+    }
+
+    #[test]
+    fn trailing_slashes_are_redirected() {
+        let router = trailing_slash_router(TrailingSlash::Redirect);
+
+        for path in ["/", "/users", "/users/1/posts/", "/files/a/b", "/files/a/", "/assets/a/raw"] {
+            assert!(router.resolve(&mut request(Method::GET, path)).is_ok(), "{}", path);
+        }
+        for path in ["/users/", "/users/1/posts", "/assets/a/raw/"] {
+            assert_eq!(router.resolve(&mut request(Method::GET, path)), Err(308), "{}", path);
+            assert_eq!(
+                router.resolve_metadata(&mut request(Method::GET, path)).route_id,
+                RouteId::Error(308),
+                "{}",
+                path
+            );
+        }
+        assert_eq!(router.resolve(&mut request(Method::GET, "/unknown/")), Err(404));
+    }
+
+    #[tokio::test]
+    async fn trailing_slash_redirects_point_to_the_route() {
+        use crate::{server::Server, test::TestServer};
+
+        let client = TestServer::new(Server::builder().configure_router(|r| {
+            r.trailing_slash(TrailingSlash::Redirect)
+                .route("/users", Method::GET, handler)
+                .route("/users/<id>/posts/", Method::GET, handler)
+        }))
+        .unwrap()
+        .client();
+
+        let res = client.get("/users/?page=2").send().await.unwrap();
+        assert_eq!(res.status(), 308);
+        assert_eq!(res.header(http::header::LOCATION), Some("/users?page=2"));
+
+        let res = client.get("/users/1/posts").send().await.unwrap();
+        assert_eq!(res.status(), 308);
+        assert_eq!(res.header(http::header::LOCATION), Some("/users/1/posts/"));
+    }
+
+    #[tokio::test]
+    async fn empty_segments_are_not_redirected() {
+        use crate::{server::Server, test::TestServer};
+
+        let client = TestServer::new(Server::builder().configure_router(|r| {
+            r.trailing_slash(TrailingSlash::Redirect)
+                .route("/<org>/<repo>", Method::GET, handler)
+                .route("/<org>/<repo>/issues/", Method::GET, handler)
+        }))
+        .unwrap()
+        .client();
+
+        for path in ["//evil.com/", "//evil.com/issues", "/saphir//issues"] {
+            let res = client.get(path).send().await.unwrap();
+            assert_eq!(res.status(), 404, "{}", path);
+            assert_eq!(res.header(http::header::LOCATION), None, "{}", path);
+        }
+
+        let res = client.get("/richerarc/saphir/").send().await.unwrap();
+        assert_eq!(res.status(), 308);
+        assert_eq!(res.header(http::header::LOCATION), Some("/richerarc/saphir"));
+    }
+
+    #[test]
+    fn mounted_root_routes_have_no_trailing_slash() {
+        let router = Router::builder()
+            .trailing_slash(TrailingSlash::Strict)
+            .mount("/v1", |r| r.route("/", Method::GET, handler))
+            .build();
+
+        assert!(router.resolve(&mut request(Method::GET, "/v1")).is_ok());
+        assert_eq!(router.resolve(&mut request(Method::GET, "/v1/")), Err(404));
+    }
+}
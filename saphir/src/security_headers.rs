@@ -0,0 +1,239 @@
+//! Security headers for every response.
+//!
+//! The [`SecurityHeadersMiddleware`] adds the headers hardening browsers
+//! against common attacks to the responses missing them. A header set by a
+//! handler, or by a middleware running before this one, e.g. a deliberately
+//! relaxed `Content-Security-Policy`, is left untouched, so it can be applied
+//! alongside the other middlewares in any order.
+//!
+//! The defaults are:
+//! - `Content-Security-Policy: default-src 'self'; object-src 'none';
+//!   frame-ancestors 'none'; base-uri 'self'`
+//! - `Referrer-Policy: strict-origin-when-cross-origin`
+//! - `Permissions-Policy: camera=(), microphone=(), geolocation=()`
+//! - `X-Content-Type-Options: nosniff`
+//! - `X-Frame-Options: DENY`
+//! - `Strict-Transport-Security: max-age=31536000; includeSubDomains`, on TLS
+//!   connections only
+//!
+//! Each of them can be customized or disabled, by passing `None`:
+//!
+//! ```rust
+//! use saphir::{prelude::*, security_headers::SecurityHeadersMiddleware};
+//!
+//! let security_headers = SecurityHeadersMiddleware::new()
+//!     .content_security_policy("default-src 'self'; img-src *")
+//!     .frame_options("SAMEORIGIN")
+//!     .permissions_policy(None);
+//!
+//! let server = Server::builder()
+//!     .configure_middlewares(|m| m.apply(security_headers, vec!["/**"], None))
+//!     .build();
+//! ```
+
+use crate::{
+    connection::ConnectionInfo,
+    error::SaphirError,
+    http_context::HttpContext,
+    middleware::{Middleware, MiddlewareChain},
+};
+use futures::future::{BoxFuture, FutureExt};
+use http::{
+    header::{self, HeaderName},
+    HeaderValue,
+};
+
+pub const DEFAULT_CONTENT_SECURITY_POLICY: &str = "default-src 'self'; object-src 'none'; frame-ancestors 'none'; base-uri 'self'";
+pub const DEFAULT_REFERRER_POLICY: &str = "strict-origin-when-cross-origin";
+pub const DEFAULT_PERMISSIONS_POLICY: &str = "camera=(), microphone=(), geolocation=()";
+pub const DEFAULT_FRAME_OPTIONS: &str = "DENY";
+pub const DEFAULT_STRICT_TRANSPORT_SECURITY: &str = "max-age=31536000; includeSubDomains";
+
+/// Middleware adding security headers to the responses missing them
+pub struct SecurityHeadersMiddleware {
+    content_security_policy: Option<HeaderValue>,
+    referrer_policy: Option<HeaderValue>,
+    permissions_policy: Option<HeaderValue>,
+    content_type_options: bool,
+    frame_options: Option<HeaderValue>,
+    strict_transport_security: Option<HeaderValue>,
+}
+
+fn header_value(value: Option<&str>) -> Option<HeaderValue> {
+    value.map(|v| HeaderValue::from_str(v).expect("invalid security header value"))
+}
+
+impl SecurityHeadersMiddleware {
+    /// Create a middleware sending the recommended headers
+    pub fn new() -> Self {
+        SecurityHeadersMiddleware {
+            content_security_policy: Some(HeaderValue::from_static(DEFAULT_CONTENT_SECURITY_POLICY)),
+            referrer_policy: Some(HeaderValue::from_static(DEFAULT_REFERRER_POLICY)),
+            permissions_policy: Some(HeaderValue::from_static(DEFAULT_PERMISSIONS_POLICY)),
+            content_type_options: true,
+            frame_options: Some(HeaderValue::from_static(DEFAULT_FRAME_OPTIONS)),
+            strict_transport_security: Some(HeaderValue::from_static(DEFAULT_STRICT_TRANSPORT_SECURITY)),
+        }
+    }
+
+    /// Set the `Content-Security-Policy` header, `None` to disable it
+    ///
+    /// # Panics
+    /// Panics if `policy` is not a valid header value
+    pub fn content_security_policy<'a, P: Into<Option<&'a str>>>(mut self, policy: P) -> Self {
+        self.content_security_policy = header_value(policy.into());
+        self
+    }
+
+    /// Set the `Referrer-Policy` header, `None` to disable it
+    ///
+    /// # Panics
+    /// Panics if `policy` is not a valid header value
+    pub fn referrer_policy<'a, P: Into<Option<&'a str>>>(mut self, policy: P) -> Self {
+        self.referrer_policy = header_value(policy.into());
+        self
+    }
+
+    /// Set the `Permissions-Policy` header, `None` to disable it
+    ///
+    /// # Panics
+    /// Panics if `policy` is not a valid header value
+    pub fn permissions_policy<'a, P: Into<Option<&'a str>>>(mut self, policy: P) -> Self {
+        self.permissions_policy = header_value(policy.into());
+        self
+    }
+
+    /// Whether to send `X-Content-Type-Options: nosniff`. Enabled by default.
+    pub fn content_type_options(mut self, nosniff: bool) -> Self {
+        self.content_type_options = nosniff;
+        self
+    }
+
+    /// Set the `X-Frame-Options` header, `None` to disable it
+    ///
+    /// # Panics
+    /// Panics if `options` is not a valid header value
+    pub fn frame_options<'a, O: Into<Option<&'a str>>>(mut self, options: O) -> Self {
+        self.frame_options = header_value(options.into());
+        self
+    }
+
+    /// Set the `Strict-Transport-Security` header sent on TLS connections,
+    /// `None` to disable it
+    ///
+    /// # Panics
+    /// Panics if `policy` is not a valid header value
+    pub fn strict_transport_security<'a, P: Into<Option<&'a str>>>(mut self, policy: P) -> Self {
+        self.strict_transport_security = header_value(policy.into());
+        self
+    }
+
+    fn headers(&self, is_tls: bool) -> impl Iterator<Item = (HeaderName, &HeaderValue)> {
+        [
+            (header::CONTENT_SECURITY_POLICY, self.content_security_policy.as_ref()),
+            (header::REFERRER_POLICY, self.referrer_policy.as_ref()),
+            (HeaderName::from_static("permissions-policy"), self.permissions_policy.as_ref()),
+            (header::X_FRAME_OPTIONS, self.frame_options.as_ref()),
+            (header::STRICT_TRANSPORT_SECURITY, self.strict_transport_security.as_ref().filter(|_| is_tls)),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| (name, value)))
+    }
+
+    async fn next_inner(&self, ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+        let is_tls = ctx
+            .state
+            .request()
+            .and_then(|req| req.extensions().get::<ConnectionInfo>())
+            .is_some_and(ConnectionInfo::is_tls);
+
+        let mut ctx = chain.next_with_response(ctx).await?;
+
+        if let Some(res) = ctx.state.response_mut() {
+            let headers = res.headers_mut();
+            for (name, value) in self.headers(is_tls) {
+                headers.entry(name).or_insert_with(|| value.clone());
+            }
+            if self.content_type_options {
+                headers
+                    .entry(header::X_CONTENT_TYPE_OPTIONS)
+                    .or_insert_with(|| HeaderValue::from_static("nosniff"));
+            }
+        }
+
+        Ok(ctx)
+    }
+}
+
+impl Default for SecurityHeadersMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Middleware for SecurityHeadersMiddleware {
+    fn next(&'static self, ctx: HttpContext, chain: &'static dyn MiddlewareChain) -> BoxFuture<'static, Result<HttpContext, SaphirError>> {
+        self.next_inner(ctx, chain).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{request::Request, response::Builder, server::Server, test::TestServer};
+    use http::Method;
+
+    async fn page(_req: Request) -> Builder {
+        Builder::new().header(header::CONTENT_SECURITY_POLICY, "default-src *").body("page")
+    }
+
+    async fn hello(_req: Request) -> &'static str {
+        "hello"
+    }
+
+    fn client(middleware: SecurityHeadersMiddleware) -> crate::test::TestClient {
+        TestServer::new(
+            Server::builder()
+                .configure_middlewares(|m| m.apply(middleware, vec!["/**"], None))
+                .configure_router(|r| r.route("/hello", Method::GET, hello).route("/page", Method::GET, page)),
+        )
+        .unwrap()
+        .client()
+    }
+
+    #[tokio::test]
+    async fn missing_headers_are_added() {
+        let client = client(SecurityHeadersMiddleware::new());
+
+        let res = client.get("/hello").send().await.unwrap();
+        assert_eq!(res.header(header::CONTENT_SECURITY_POLICY), Some(DEFAULT_CONTENT_SECURITY_POLICY));
+        assert_eq!(res.header(header::REFERRER_POLICY), Some(DEFAULT_REFERRER_POLICY));
+        assert_eq!(res.header("permissions-policy"), Some(DEFAULT_PERMISSIONS_POLICY));
+        assert_eq!(res.header(header::X_CONTENT_TYPE_OPTIONS), Some("nosniff"));
+        assert_eq!(res.header(header::X_FRAME_OPTIONS), Some(DEFAULT_FRAME_OPTIONS));
+        assert_eq!(res.header(header::STRICT_TRANSPORT_SECURITY), None);
+
+        let res = client.get("/page").send().await.unwrap();
+        assert_eq!(res.header(header::CONTENT_SECURITY_POLICY), Some("default-src *"));
+
+        let res = client.get("/missing").send().await.unwrap();
+        assert_eq!(res.status(), 404);
+        assert_eq!(res.header(header::X_CONTENT_TYPE_OPTIONS), Some("nosniff"));
+    }
+
+    #[tokio::test]
+    async fn headers_can_be_customized() {
+        let client = client(
+            SecurityHeadersMiddleware::new()
+                .content_security_policy("default-src 'none'")
+                .frame_options(None)
+                .content_type_options(false),
+        );
+
+        let res = client.get("/hello").send().await.unwrap();
+        assert_eq!(res.header(header::CONTENT_SECURITY_POLICY), Some("default-src 'none'"));
+        assert_eq!(res.header(header::X_FRAME_OPTIONS), None);
+        assert_eq!(res.header(header::X_CONTENT_TYPE_OPTIONS), None);
+        assert_eq!(res.header(header::REFERRER_POLICY), Some(DEFAULT_REFERRER_POLICY));
+    }
+}
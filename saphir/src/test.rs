@@ -0,0 +1,406 @@
+//! Utilities to test a saphir stack without binding a socket.
+//!
+//! Unlike [`inject_raw`](crate::server::inject_raw), the stack of a
+//! [`TestServer`] is not written into the global static memory, so any number
+//! of test servers can live side by side and tests can run in parallel.
+//!
+//! ```rust
+//! use saphir::{prelude::*, test::TestServer};
+//!
+//! async fn hello(_req: Request) -> (u16, &'static str) {
+//!     (200, "Hello")
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), SaphirError> {
+//! let server = TestServer::new(Server::builder().configure_router(|r| r.route("/hello", Method::GET, hello)))?;
+//! let client = server.client();
+//!
+//! let res = client.get("/hello").header("Accept", "text/plain").send().await?;
+//! assert_eq!(res.status(), StatusCode::OK);
+//! assert_eq!(res.text()?, "Hello");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! *SAFETY NOTICE*
+//!
+//! Middlewares and handlers rely on the stack having a static lifetime, the
+//! tasks they spawn can still use it once a request is answered. Each
+//! `TestServer` therefore leaks its stack, which is never reclaimed, even once
+//! the server and its clients are dropped. This is fine for tests but means
+//! that it should never be used to serve real traffic, and that test suites
+//! building many servers should rather share a single one between their tests:
+//!
+//! ```rust
+//! use saphir::{prelude::*, test::TestServer};
+//! use std::sync::OnceLock;
+//!
+//! async fn hello(_req: Request) -> (u16, &'static str) {
+//!     (200, "Hello")
+//! }
+//!
+//! fn server() -> &'static TestServer {
+//!     static SERVER: OnceLock<TestServer> = OnceLock::new();
+//!     SERVER.get_or_init(|| {
+//!         let builder = Server::builder().configure_router(|r| r.route("/hello", Method::GET, hello));
+//!         TestServer::new(builder).expect("the server should build")
+//!     })
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), SaphirError> {
+//! assert_eq!(server().client().get("/hello").send().await?.status(), StatusCode::OK);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    body::{Body, Bytes},
+    error::SaphirError,
+    middleware::MiddlewareChain,
+    request::Request,
+    router::RouterChain,
+    server::{Builder, Stack},
+};
+use http::{header::HeaderName, HeaderMap, HeaderValue, Method, StatusCode, Uri, Version};
+use hyper::body::Body as RawBody;
+use std::{convert::TryFrom, net::SocketAddr};
+
+/// A saphir stack which can receive requests from a [`TestClient`]
+pub struct TestServer {
+    stack: &'static Stack,
+    server_value: HeaderValue,
+    timeout_ms: Option<u64>,
+}
+
+impl TestServer {
+    /// Build the stack of a server builder, which is leaked, see the
+    /// [module documentation](self).
+    ///
+    /// Only the `server_name`, `request_timeout`, `default_headers` and
+    /// `temp_dir` options of the listener are used.
+    pub fn new<Controllers, Middlewares>(builder: Builder<Controllers, Middlewares>) -> Result<Self, SaphirError>
+    where
+        Controllers: 'static + RouterChain + Unpin + Send + Sync,
+        Middlewares: 'static + MiddlewareChain + Unpin + Send + Sync,
+    {
+        let (stack, server_value, timeout_ms) = builder.build_local_stack()?;
+
+        Ok(TestServer {
+            stack: Box::leak(Box::new(stack)),
+            server_value,
+            timeout_ms,
+        })
+    }
+
+    /// Create a client sending its requests to this server
+    pub fn client(&self) -> TestClient {
+        TestClient {
+            stack: self.stack,
+            server_value: self.server_value.clone(),
+            timeout_ms: self.timeout_ms,
+        }
+    }
+}
+
+/// Client sending requests directly to a [`TestServer`]
+#[derive(Clone)]
+pub struct TestClient {
+    stack: &'static Stack,
+    server_value: HeaderValue,
+    timeout_ms: Option<u64>,
+}
+
+impl TestClient {
+    /// Send an already built request through the stack of the server,
+    /// keeping the captures and extensions set on it, and load the whole
+    /// response
+    pub async fn send_request(&self, req: Request<Body>) -> Result<TestResponse, SaphirError> {
+        let res = self.stack.invoke_local(req, self.timeout_ms, &self.server_value).await?;
+
+        let (parts, body) = res.into_parts();
+        let body = hyper::body::to_bytes(body).await?;
+
+        Ok(TestResponse {
+            status: parts.status,
+            version: parts.version,
+            headers: parts.headers,
+            body,
+        })
+    }
+
+    /// Start building a request with the given method and uri
+    pub fn request<U>(&self, method: Method, uri: U) -> TestRequestBuilder
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        TestRequestBuilder {
+            client: self.clone(),
+            inner: http::Request::builder().method(method).uri(uri),
+            body: Ok(RawBody::empty()),
+            peer_addr: None,
+        }
+    }
+
+    /// Start building a `GET` request
+    pub fn get<U>(&self, uri: U) -> TestRequestBuilder
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        self.request(Method::GET, uri)
+    }
+
+    /// Start building a `POST` request
+    pub fn post<U>(&self, uri: U) -> TestRequestBuilder
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        self.request(Method::POST, uri)
+    }
+
+    /// Start building a `PUT` request
+    pub fn put<U>(&self, uri: U) -> TestRequestBuilder
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        self.request(Method::PUT, uri)
+    }
+
+    /// Start building a `PATCH` request
+    pub fn patch<U>(&self, uri: U) -> TestRequestBuilder
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        self.request(Method::PATCH, uri)
+    }
+
+    /// Start building a `DELETE` request
+    pub fn delete<U>(&self, uri: U) -> TestRequestBuilder
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        self.request(Method::DELETE, uri)
+    }
+
+    /// Start building a `HEAD` request
+    pub fn head<U>(&self, uri: U) -> TestRequestBuilder
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        self.request(Method::HEAD, uri)
+    }
+
+    /// Start building an `OPTIONS` request
+    pub fn options<U>(&self, uri: U) -> TestRequestBuilder
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        self.request(Method::OPTIONS, uri)
+    }
+}
+
+/// Builder for a request sent by a [`TestClient`]
+pub struct TestRequestBuilder {
+    client: TestClient,
+    inner: http::request::Builder,
+    body: Result<RawBody, SaphirError>,
+    peer_addr: Option<SocketAddr>,
+}
+
+impl TestRequestBuilder {
+    /// Append a header to the request
+    pub fn header<K, V>(mut self, key: K, value: V) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<http::Error>,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<http::Error>,
+    {
+        self.inner = self.inner.header(key, value);
+        self
+    }
+
+    /// Set the http version of the request
+    pub fn version(mut self, version: Version) -> Self {
+        self.inner = self.inner.version(version);
+        self
+    }
+
+    /// Set the peer address seen by the handlers
+    pub fn peer_addr(mut self, peer_addr: SocketAddr) -> Self {
+        self.peer_addr = Some(peer_addr);
+        self
+    }
+
+    /// Set the body of the request
+    pub fn body<B: Into<RawBody>>(mut self, body: B) -> Self {
+        self.body = Ok(body.into());
+        self
+    }
+
+    /// Set the body of the request as json, along with the `Content-Type`
+    /// header
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn json<T: serde::Serialize>(mut self, t: &T) -> Self {
+        self.body = serde_json::to_vec(t).map(RawBody::from).map_err(SaphirError::SerdeJson);
+        self.header(http::header::CONTENT_TYPE, "application/json")
+    }
+
+    /// Set the body of the request as an urlencoded form, along with the
+    /// `Content-Type` header
+    #[cfg(feature = "form")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "form")))]
+    pub fn form<T: serde::Serialize>(mut self, t: &T) -> Self {
+        self.body = serde_urlencoded::to_string(t).map(RawBody::from).map_err(SaphirError::SerdeUrlSer);
+        self.header(http::header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+    }
+
+    /// Send the request through the stack of the server and load the whole
+    /// response
+    pub async fn send(self) -> Result<TestResponse, SaphirError> {
+        let TestRequestBuilder {
+            client,
+            inner,
+            body,
+            peer_addr,
+        } = self;
+        let req = inner.body(Body::from_raw(body?))?;
+        client.send_request(Request::new(req, peer_addr)).await
+    }
+}
+
+/// A response fully loaded in memory
+#[derive(Debug)]
+pub struct TestResponse {
+    status: StatusCode,
+    version: Version,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl TestResponse {
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Returns the first value of a header, if it is valid visible ASCII
+    pub fn header<K: http::header::AsHeaderName>(&self, key: K) -> Option<&str> {
+        self.headers.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// Returns the body as an utf-8 string
+    pub fn text(&self) -> Result<String, SaphirError> {
+        String::from_utf8(self.body.to_vec()).map_err(|e| SaphirError::Other(e.to_string()))
+    }
+
+    /// Deserialize the body from json
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn json<T: for<'a> serde::Deserialize<'a>>(&self) -> Result<T, SaphirError> {
+        serde_json::from_slice(&self.body).map_err(SaphirError::SerdeJson)
+    }
+
+    /// Deserialize the body from an urlencoded form
+    #[cfg(feature = "form")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "form")))]
+    pub fn form<T: for<'a> serde::Deserialize<'a>>(&self) -> Result<T, SaphirError> {
+        serde_urlencoded::from_bytes(&self.body).map_err(SaphirError::SerdeUrlDe)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{http_context::HttpContext, server::Server};
+    use futures::future::BoxFuture;
+
+    async fn echo(mut req: Request) -> (u16, String) {
+        let body = req.body_mut().take_as::<String>().await.unwrap_or_default();
+        (200, format!("{} {}", req.captures().get("name").cloned().unwrap_or_default(), body))
+    }
+
+    fn tag(ctx: HttpContext, chain: &'static dyn MiddlewareChain) -> BoxFuture<'static, Result<HttpContext, SaphirError>> {
+        Box::pin(async move {
+            let mut ctx = chain.next(ctx).await?;
+            if let Some(res) = ctx.state.response_mut() {
+                res.headers_mut().insert("X-Tag", HeaderValue::from_static("tagged"));
+            }
+            Ok(ctx)
+        })
+    }
+
+    fn server(name: &str) -> TestServer {
+        TestServer::new(
+            Server::builder()
+                .configure_listener(|l| l.server_name(name))
+                .configure_middlewares(|m| m.apply(tag, vec!["/**"], None))
+                .configure_router(|r| r.route("/echo/{name}", Method::POST, echo)),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn request_goes_through_the_stack() {
+        let client = server("first").client();
+        let res = client.post("/echo/saphir").body("hello").send().await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.header(http::header::SERVER), Some("first"));
+        assert_eq!(res.header("X-Tag"), Some("tagged"));
+        assert_eq!(res.text().unwrap(), "saphir hello");
+
+        let res = client.get("/echo/saphir").send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn built_requests_keep_their_extensions() {
+        #[derive(Clone)]
+        struct Tenant(&'static str);
+
+        async fn tenant(req: Request) -> String {
+            req.extensions().get::<Tenant>().map(|t| t.0.to_string()).unwrap_or_default()
+        }
+
+        let client = TestServer::new(Server::builder().configure_router(|r| r.route("/tenant", Method::GET, tenant)))
+            .unwrap()
+            .client();
+        let mut req = Request::new(http::Request::get("/tenant").body(Body::empty()).unwrap(), None);
+        req.extensions_mut().insert(Tenant("acme"));
+
+        let res = client.send_request(req).await.unwrap();
+        assert_eq!(res.text().unwrap(), "acme");
+    }
+
+    #[tokio::test]
+    async fn servers_are_independent() {
+        let first = server("first").client();
+        let second = server("second").client();
+
+        let (a, b) = futures::join!(first.post("/echo/a").send(), second.post("/echo/b").send());
+        assert_eq!(a.unwrap().header(http::header::SERVER), Some("first"));
+        assert_eq!(b.unwrap().header(http::header::SERVER), Some("second"));
+    }
+}
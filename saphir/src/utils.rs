@@ -3,6 +3,7 @@ use crate::{
     error::SaphirError,
     http_context::{HandlerMetadata, RouteId},
     request::Request,
+    router::TrailingSlash,
 };
 use http::Method;
 use regex::Regex;
@@ -12,7 +13,7 @@ use std::{
     fmt::Write,
     iter::FromIterator,
     str::FromStr,
-    sync::atomic::AtomicU64,
+    sync::{atomic::AtomicU64, Arc},
 };
 
 // TODO: Add possibility to match any route like /page/<path..>/view
@@ -28,6 +29,9 @@ pub enum EndpointResolverResult<'a> {
     InvalidPath,
     MethodNotAllowed,
     Match(&'a HandlerMetadata),
+    /// The path only matches without or with a trailing slash, and should be
+    /// redirected
+    TrailingSlashRedirect,
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -40,6 +44,8 @@ pub enum EndpointResolverMethods {
 pub struct EndpointResolver {
     id: u64,
     path_matcher: UriPathMatcher,
+    template: Arc<str>,
+    trailing_slash: bool,
     methods: EndpointResolverMethods,
 }
 
@@ -64,9 +70,12 @@ impl PartialEq for EndpointResolver {
 impl EndpointResolver {
     pub fn new(path_str: &str, method: Method) -> Result<EndpointResolver, SaphirError> {
         let id = ENDPOINT_ID.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        let template: Arc<str> = Arc::from(path_str);
         let meta = HandlerMetadata {
             route_id: RouteId::new(id),
             name: None,
+            controller: None,
+            route_template: Some(template.clone()),
         };
         let methods = if method.is_any() {
             EndpointResolverMethods::Any(meta)
@@ -78,6 +87,8 @@ impl EndpointResolver {
 
         Ok(EndpointResolver {
             path_matcher: UriPathMatcher::new(path_str).map_err(SaphirError::Other)?,
+            template,
+            trailing_slash: has_trailing_slash(path_str),
             methods,
             id,
         })
@@ -85,8 +96,10 @@ impl EndpointResolver {
 
     pub fn new_with_metadata<I: Into<Option<HandlerMetadata>>>(path_str: &str, method: Method, meta: I) -> Result<EndpointResolver, SaphirError> {
         let id = ENDPOINT_ID.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        let template: Arc<str> = Arc::from(path_str);
         let mut meta = meta.into().unwrap_or_default();
         meta.route_id = RouteId::new(id);
+        meta.route_template = Some(template.clone());
         let methods = if method.is_any() {
             EndpointResolverMethods::Any(meta)
         } else {
@@ -97,6 +110,8 @@ impl EndpointResolver {
 
         Ok(EndpointResolver {
             path_matcher: UriPathMatcher::new(path_str).map_err(SaphirError::Other)?,
+            template,
+            trailing_slash: has_trailing_slash(path_str),
             methods,
             id,
         })
@@ -111,6 +126,8 @@ impl EndpointResolver {
                 let meta = HandlerMetadata {
                     route_id: RouteId::new(self.id),
                     name: None,
+                    controller: None,
+                    route_template: Some(self.template.clone()),
                 };
                 inner.insert(m, meta);
             }
@@ -126,15 +143,31 @@ impl EndpointResolver {
                 }
                 let mut meta = meta.into().unwrap_or_default();
                 meta.route_id = RouteId::new(self.id);
+                meta.route_template = Some(self.template.clone());
                 inner.insert(m, meta);
             }
             EndpointResolverMethods::Any(_) => panic!("Adding a specific endpoint method but an Handler already defines ANY method, This is fatal"),
         }
     }
 
-    pub fn resolve(&self, req: &mut Request<Body>) -> EndpointResolverResult {
+    pub fn resolve(&self, req: &mut Request<Body>) -> EndpointResolverResult<'_> {
+        self.resolve_with(req, TrailingSlash::Merge)
+    }
+
+    /// Resolve `req`, handling a trailing slash which differs from the
+    /// template according to `trailing_slash`
+    pub fn resolve_with(&self, req: &mut Request<Body>, trailing_slash: TrailingSlash) -> EndpointResolverResult<'_> {
         let path = req.uri().path().to_string();
+        let differs = has_trailing_slash(&path) != self.trailing_slash && !self.path_matcher.is_open_ended();
+        if differs && trailing_slash == TrailingSlash::Strict {
+            return EndpointResolverResult::InvalidPath;
+        }
+
         if self.path_matcher.match_all_and_capture(path, req.captures_mut()) {
+            if differs && trailing_slash == TrailingSlash::Redirect {
+                return EndpointResolverResult::TrailingSlashRedirect;
+            }
+
             match &self.methods {
                 EndpointResolverMethods::Specific(methods) => {
                     if let Some(meta) = methods.get(req.method()) {
@@ -153,6 +186,58 @@ impl EndpointResolver {
     pub fn id(&self) -> u64 {
         self.id
     }
+
+    /// Returns the methods registered for this endpoint, or `None` if it
+    /// accepts any method
+    pub fn methods(&self) -> Option<Vec<&Method>> {
+        match &self.methods {
+            EndpointResolverMethods::Specific(methods) => Some(methods.keys().collect()),
+            EndpointResolverMethods::Any(_) => None,
+        }
+    }
+
+    /// Check if the path matches this endpoint, regardless of the method
+    pub fn match_path(&self, path: &str) -> bool {
+        self.path_matcher.match_all_and_capture(path.to_string(), &mut HashMap::new())
+    }
+
+    /// Path template this endpoint was registered with
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    /// Check if a trailing slash is all that tells the paths of this endpoint
+    /// and of `other` apart, e.g. `/users` and `/users/`
+    pub fn trailing_slash_differs(&self, other: &EndpointResolver) -> bool {
+        self.trailing_slash != other.trailing_slash && !self.path_matcher.is_open_ended() && !other.path_matcher.is_open_ended()
+    }
+
+    /// Check if this endpoint matches all the paths of `other`
+    pub fn path_covers(&self, other: &EndpointResolver) -> bool {
+        self.path_matcher.covers(&other.path_matcher)
+    }
+
+    /// Returns the methods of `other` which this endpoint would answer first,
+    /// when it is resolved before `other` and matches all of its paths
+    pub fn shadowed_methods(&self, other: &EndpointResolver) -> Vec<Method> {
+        if !self.path_covers(other) {
+            return Vec::new();
+        }
+
+        match (&self.methods, &other.methods) {
+            (EndpointResolverMethods::Any(_), EndpointResolverMethods::Any(_)) => vec![Method::any()],
+            (EndpointResolverMethods::Any(_), EndpointResolverMethods::Specific(methods)) => methods.keys().cloned().collect(),
+            (EndpointResolverMethods::Specific(methods), EndpointResolverMethods::Any(_)) => methods.keys().cloned().collect(),
+            (EndpointResolverMethods::Specific(methods), EndpointResolverMethods::Specific(others)) => {
+                methods.keys().filter(|m| others.contains_key(*m)).cloned().collect()
+            }
+        }
+    }
+}
+
+/// Whether `path` ends with a slash, the root path `/` aside
+fn has_trailing_slash(path: &str) -> bool {
+    path.len() > 1 && path.ends_with('/')
 }
 
 #[derive(Debug, Eq)]
@@ -307,6 +392,12 @@ impl UriPathMatcher {
         Ok(inner)
     }
 
+    /// Whether the matcher ends with a wildcard, which also captures a
+    /// trailing slash
+    pub fn is_open_ended(&self) -> bool {
+        matches!(self, UriPathMatcher::Wildcard { end, .. } if end.is_empty())
+    }
+
     pub fn match_non_exhaustive(&self, path: &str) -> bool {
         let mut path_split = path.trim_start_matches('/').split('/').collect();
 
@@ -345,6 +436,36 @@ impl UriPathMatcher {
         true
     }
 
+    /// Check if every path matched by `other` is also matched by this matcher
+    pub fn covers(&self, other: &UriPathMatcher) -> bool {
+        match (self, other) {
+            (UriPathMatcher::Simple { inner }, UriPathMatcher::Simple { inner: other }) => {
+                inner.len() == other.len() && Self::covers_all(inner.iter(), other.iter())
+            }
+            (UriPathMatcher::Wildcard { start, end, .. }, UriPathMatcher::Simple { inner: other }) => {
+                other.len() >= start.len() + end.len() && Self::covers_all(start.iter(), other.iter()) && Self::covers_all(end.iter().rev(), other.iter().rev())
+            }
+            (
+                UriPathMatcher::Wildcard { start, end, .. },
+                UriPathMatcher::Wildcard {
+                    start: other_start,
+                    end: other_end,
+                    ..
+                },
+            ) => {
+                other_start.len() >= start.len()
+                    && other_end.len() >= end.len()
+                    && Self::covers_all(start.iter(), other_start.iter())
+                    && Self::covers_all(end.iter().rev(), other_end.iter().rev())
+            }
+            (UriPathMatcher::Simple { .. }, UriPathMatcher::Wildcard { .. }) => false,
+        }
+    }
+
+    fn covers_all<'a>(segments: impl Iterator<Item = &'a UriPathSegmentMatcher>, others: impl Iterator<Item = &'a UriPathSegmentMatcher>) -> bool {
+        segments.zip(others).all(|(s, o)| s.covers(o))
+    }
+
     pub fn match_all_and_capture(&self, path: String, captures: &mut HashMap<String, String>) -> bool {
         let mut path_segments = path.split('/').collect::<VecDeque<_>>();
         path_segments.pop_front();
@@ -509,6 +630,28 @@ impl UriPathSegmentMatcher {
         }
     }
 
+    /// Check if every segment matched by `other` is also matched by this
+    /// matcher
+    pub fn covers(&self, other: &UriPathSegmentMatcher) -> bool {
+        match (self, other) {
+            (UriPathSegmentMatcher::Variable { .. }, _) => true,
+            (UriPathSegmentMatcher::Wildcard { prefix: None, suffix: None }, _) => true,
+            (_, UriPathSegmentMatcher::Static { segment }) => self.matches(segment),
+            (UriPathSegmentMatcher::Custom { segment, .. }, UriPathSegmentMatcher::Custom { segment: other, .. }) => segment.as_str() == other.as_str(),
+            (
+                UriPathSegmentMatcher::Wildcard { prefix, suffix },
+                UriPathSegmentMatcher::Wildcard {
+                    prefix: other_prefix,
+                    suffix: other_suffix,
+                },
+            ) => {
+                prefix.as_ref().is_none_or(|p| other_prefix.as_ref().is_some_and(|o| o.starts_with(p.as_str())))
+                    && suffix.as_ref().is_none_or(|s| other_suffix.as_ref().is_some_and(|o| o.ends_with(s.as_str())))
+            }
+            _ => false,
+        }
+    }
+
     #[inline]
     pub fn name(&self) -> Option<&str> {
         match self {
@@ -559,7 +702,15 @@ pub fn read_query_string_to_type<T>(query_str: &str) -> Result<T, serde_urlencod
 where
     T: for<'a> serde::Deserialize<'a>,
 {
-    serde_urlencoded::from_str::<T>(query_str)
+    crate::query::from_str::<T>(query_str)
+}
+
+/// Read every value of each key of a query string, unlike
+/// [`read_query_string_to_hashmap`] which only keeps the last one
+#[cfg(feature = "form")]
+#[cfg_attr(docsrs, doc(cfg(feature = "form")))]
+pub fn read_query_string_to_multimap(query_str: &str) -> Result<HashMap<String, Vec<String>>, serde_urlencoded::de::Error> {
+    crate::query::to_multimap(query_str)
 }
 
 #[cfg(test)]
@@ -0,0 +1,163 @@
+use crate::openapi::generate::crate_syn_browser::{content_hash, Browser};
+use quote::ToTokens;
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use syn::{visit_mut::VisitMut, Block, File as SynFile, ImplItemMethod, ItemFn, TraitItemMethod};
+
+/// Sources of the last generation, stored under the `target` directory. The
+/// documentation is not generated again as long as none of the sources, nor
+/// the generated file, changed since.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct GenCache {
+    files: Vec<(PathBuf, u64)>,
+    output: PathBuf,
+    output_hash: u64,
+}
+
+impl GenCache {
+    /// Path of the cache of a generation, named after its arguments
+    pub(crate) fn path(browser: &Browser, args_key: &str) -> PathBuf {
+        let key = content_hash(format!("{}{}", env!("CARGO_PKG_VERSION"), args_key).as_bytes());
+        browser
+            .crate_metadata
+            .target_directory
+            .as_std_path()
+            .join("saphir-cli")
+            .join(format!("openapi-{:016x}.json", key))
+    }
+
+    /// Number of unchanged sources, if the previous generation of `output`
+    /// is still up to date
+    pub(crate) fn up_to_date(path: &Path, output: &Path) -> Option<usize> {
+        let cache: GenCache = serde_json::from_slice(&fs::read(path).ok()?).ok()?;
+        if cache.output != output || !unchanged(&cache.output, cache.output_hash) {
+            return None;
+        }
+        cache.files.iter().all(|(file, hash)| unchanged(file, *hash)).then_some(cache.files.len())
+    }
+
+    /// Remember the sources of `output`. Failing to do so only means the next
+    /// run will not be able to skip the generation.
+    pub(crate) fn store(path: &Path, files: Vec<(PathBuf, u64)>, output: &Path) {
+        let output_hash = match fs::read(output) {
+            Ok(content) => content_hash(&content),
+            Err(_) => return,
+        };
+        let cache = GenCache {
+            files,
+            output: output.to_path_buf(),
+            output_hash,
+        };
+        if let (Some(dir), Ok(content)) = (path.parent(), serde_json::to_vec(&cache)) {
+            let _ = fs::create_dir_all(dir).and_then(|_| fs::write(path, content));
+        }
+    }
+}
+
+/// Items of the source files parsed by the previous runs, stored under the
+/// `target` directory, so that the unchanged files are not parsed again.
+///
+/// The items are kept without the bodies of their functions, which the
+/// generation never reads, making them much faster to parse than the source.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct ParseCache {
+    files: HashMap<PathBuf, ParsedFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ParsedFile {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+    items: String,
+}
+
+impl ParseCache {
+    /// Path of the parse cache of a crate
+    pub(crate) fn path(browser: &Browser) -> PathBuf {
+        browser
+            .crate_metadata
+            .target_directory
+            .as_std_path()
+            .join("saphir-cli")
+            .join(format!("parse-{}.json", env!("CARGO_PKG_VERSION")))
+    }
+
+    pub(crate) fn load(path: &Path) -> Self {
+        fs::read(path)
+            .ok()
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Remember the items of the `files` parsed by this run. Failing to do so
+    /// only means the next run will parse them again.
+    pub(crate) fn store(mut self, path: &Path, files: &[(PathBuf, u64)]) {
+        self.files.retain(|file, _| files.iter().any(|(read, _)| read == file));
+        if let (Some(dir), Ok(content)) = (path.parent(), serde_json::to_vec(&self)) {
+            let _ = fs::create_dir_all(dir).and_then(|_| fs::write(path, content));
+        }
+    }
+
+    /// Cached items of `file`, if its modification time and length did not
+    /// change since it was parsed
+    pub(crate) fn by_modification(&self, file: &Path, modified: Option<SystemTime>, len: u64) -> Option<(u64, &str)> {
+        self.files
+            .get(file)
+            .filter(|parsed| parsed.modified.is_some() && parsed.modified == modified && parsed.len == len)
+            .map(|parsed| (parsed.hash, parsed.items.as_str()))
+    }
+
+    /// Cached items of `file`, if its content did not change since it was
+    /// parsed
+    pub(crate) fn by_hash(&self, file: &Path, hash: u64) -> Option<&str> {
+        self.files.get(file).filter(|parsed| parsed.hash == hash).map(|parsed| parsed.items.as_str())
+    }
+
+    pub(crate) fn insert(&mut self, file: PathBuf, modified: Option<SystemTime>, len: u64, hash: u64, parsed: &SynFile) {
+        let mut items = parsed.clone();
+        StripBodies.visit_file_mut(&mut items);
+        let items = items.into_token_stream().to_string();
+        self.files.insert(file, ParsedFile { modified, len, hash, items });
+    }
+
+    /// Update the modification time of a file whose content did not change
+    pub(crate) fn touch(&mut self, file: &Path, modified: Option<SystemTime>, len: u64) {
+        if let Some(parsed) = self.files.get_mut(file) {
+            parsed.modified = modified;
+            parsed.len = len;
+        }
+    }
+}
+
+/// Replace the bodies of the functions with empty blocks
+struct StripBodies;
+
+impl VisitMut for StripBodies {
+    fn visit_item_fn_mut(&mut self, item: &mut ItemFn) {
+        *item.block = empty_block();
+    }
+
+    fn visit_impl_item_method_mut(&mut self, item: &mut ImplItemMethod) {
+        item.block = empty_block();
+    }
+
+    fn visit_trait_item_method_mut(&mut self, item: &mut TraitItemMethod) {
+        if let Some(block) = &mut item.default {
+            *block = empty_block();
+        }
+    }
+}
+
+fn empty_block() -> Block {
+    syn::parse_quote!({})
+}
+
+fn unchanged(file: &Path, hash: u64) -> bool {
+    fs::read(file).map(|content| content_hash(&content) == hash).unwrap_or(false)
+}
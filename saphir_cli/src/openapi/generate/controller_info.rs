@@ -11,6 +11,11 @@ pub(crate) struct ControllerInfo {
     pub(crate) name: String,
     pub(crate) version: Option<String>,
     pub(crate) prefix: Option<String>,
+    /// Name of the tag grouping the handlers, instead of the controller name
+    pub(crate) tag: Option<String>,
+    pub(crate) tag_description: Option<String>,
+    /// Whether the controller is left out of the documentation
+    pub(crate) hidden: bool,
     pub(crate) handlers: Vec<HandlerInfo>,
 }
 
@@ -44,8 +49,14 @@ impl Gen {
                             let mut name = name.to_string();
                             let mut prefix = None;
                             let mut version = None;
+                            let mut tag = None;
+                            let mut tag_description = None;
+                            let mut hidden = false;
                             if let Ok(Meta::List(meta)) = attr.parse_meta() {
                                 for nested in meta.nested {
+                                    if let NestedMeta::Meta(Meta::Path(p)) = &nested {
+                                        hidden |= p.is_ident("openapi_hidden");
+                                    }
                                     if let NestedMeta::Meta(Meta::NameValue(nv)) = nested {
                                         if let Some(p) = nv.path.segments.first() {
                                             let value = match nv.lit {
@@ -57,6 +68,8 @@ impl Gen {
                                                 "name" => name = value,
                                                 "prefix" => prefix = Some(value),
                                                 "version" => version = Some(value),
+                                                "openapi_tag" => tag = Some(value),
+                                                "openapi_tag_description" => tag_description = Some(value),
                                                 _ => {}
                                             }
                                         }
@@ -69,6 +82,9 @@ impl Gen {
                                 name,
                                 prefix,
                                 version,
+                                tag,
+                                tag_description,
+                                hidden,
                                 handlers: Vec::new(),
                             };
                             let mut handlers = im
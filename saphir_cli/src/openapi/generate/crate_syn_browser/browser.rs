@@ -1,11 +1,22 @@
-use super::{Error, Package};
+use super::{content_hash, Error, Package};
+use crate::openapi::generate::cache::ParseCache;
 use lazycell::LazyCell;
-use std::{fmt::Debug, path::PathBuf};
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Debug,
+    fs,
+    path::{Path, PathBuf},
+};
+use syn::File as SynFile;
+use Error::*;
 
 #[derive(Debug)]
 pub struct Browser<'b> {
     pub(crate) crate_metadata: cargo_metadata::Metadata,
     packages: LazyCell<Vec<Package<'b>>>,
+    files_read: RefCell<Vec<(PathBuf, u64)>>,
+    parse_cache: RefCell<ParseCache>,
+    cache_hits: Cell<usize>,
 }
 
 impl<'b> Browser<'b> {
@@ -15,12 +26,15 @@ impl<'b> Browser<'b> {
         let browser = Self {
             crate_metadata,
             packages: LazyCell::new(),
+            files_read: RefCell::default(),
+            parse_cache: RefCell::default(),
+            cache_hits: Cell::new(0),
         };
 
         Ok(browser)
     }
 
-    pub fn package_by_name(&self, name: &str) -> Option<&'b Package> {
+    pub fn package_by_name(&self, name: &str) -> Option<&'b Package<'_>> {
         self.packages().iter().find(|p| p.meta.name.as_str() == name)
     }
 
@@ -36,7 +50,61 @@ impl<'b> Browser<'b> {
         }
     }
 
-    pub fn packages(&'b self) -> &'b Vec<Package> {
+    /// Parse the source file at `path`, reusing the items cached by a
+    /// previous run if the file did not change since
+    pub(crate) fn parse_file(&self, path: &Path) -> Result<SynFile, Error> {
+        let io_error = |e| FileIo(Box::new(path.to_path_buf()), Box::new(e));
+        let metadata = fs::metadata(path).map_err(io_error)?;
+        let (modified, len) = (metadata.modified().ok(), metadata.len());
+
+        let cached = self
+            .parse_cache
+            .borrow()
+            .by_modification(path, modified, len)
+            .and_then(|(hash, items)| Some((hash, syn::parse_file(items).ok()?)));
+        if let Some((hash, file)) = cached {
+            self.cache_hits.set(self.cache_hits.get() + 1);
+            self.files_read.borrow_mut().push((path.to_path_buf(), hash));
+            return Ok(file);
+        }
+
+        let content = fs::read_to_string(path).map_err(io_error)?;
+        let hash = content_hash(content.as_bytes());
+        self.files_read.borrow_mut().push((path.to_path_buf(), hash));
+
+        let cached = self.parse_cache.borrow().by_hash(path, hash).and_then(|items| syn::parse_file(items).ok());
+        if let Some(file) = cached {
+            self.cache_hits.set(self.cache_hits.get() + 1);
+            self.parse_cache.borrow_mut().touch(path, modified, len);
+            return Ok(file);
+        }
+
+        let file = syn::parse_file(content.as_str()).map_err(|e| FileParse(Box::new(path.to_path_buf()), Box::new(e)))?;
+        self.parse_cache.borrow_mut().insert(path.to_path_buf(), modified, len, hash, &file);
+        Ok(file)
+    }
+
+    /// Reuse the items parsed by a previous run
+    pub(crate) fn use_parse_cache(&self, cache: ParseCache) {
+        *self.parse_cache.borrow_mut() = cache;
+    }
+
+    /// Items of the files parsed so far, to be reused by the next run
+    pub(crate) fn take_parse_cache(&self) -> ParseCache {
+        self.parse_cache.take()
+    }
+
+    /// Number of files whose items were read from the parse cache
+    pub fn cache_hits(&self) -> usize {
+        self.cache_hits.get()
+    }
+
+    /// Source files read so far, with the hash of their content
+    pub fn files_read(&self) -> Vec<(PathBuf, u64)> {
+        self.files_read.borrow().clone()
+    }
+
+    pub fn packages(&'b self) -> &'b Vec<Package<'b>> {
         self.init_packages();
         self.packages.borrow().expect("Should have been initialized by the previous statement")
     }
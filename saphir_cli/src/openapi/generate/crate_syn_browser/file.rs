@@ -1,14 +1,12 @@
 use super::{Error, Target};
 use std::{
     fmt::Debug,
-    fs::File as FsFile,
-    io::Read,
     path::{Path, PathBuf},
 };
 use syn::File as SynFile;
-use Error::*;
 
 #[derive(Debug)]
+#[allow(dead_code)]
 pub struct File<'b> {
     pub target: &'b Target<'b>,
     pub file: SynFile,
@@ -18,11 +16,7 @@ pub struct File<'b> {
 
 impl<'b> File<'b> {
     pub fn new(target: &'b Target<'b>, dir: &Path, path: String) -> Result<File<'b>, Error> {
-        let mut f = FsFile::open(dir).map_err(|e| FileIo(Box::new(dir.to_path_buf()), Box::new(e)))?;
-        let mut buffer = String::new();
-        f.read_to_string(&mut buffer).map_err(|e| FileIo(Box::new(dir.to_path_buf()), Box::new(e)))?;
-
-        let file = syn::parse_file(buffer.as_str()).map_err(|e| FileParse(Box::new(dir.to_path_buf()), Box::new(e)))?;
+        let file = target.package.browser.parse_file(dir)?;
 
         let file = Self {
             target,
@@ -26,6 +26,7 @@ impl<'b> Item<'b> {
 }
 
 #[derive(Debug)]
+#[allow(dead_code)]
 pub enum ItemKind<'b> {
     Use(Use<'b>),
     Struct(Struct<'b>),
@@ -35,18 +36,21 @@ pub enum ItemKind<'b> {
 }
 
 #[derive(Debug)]
+#[allow(dead_code)]
 pub struct Use<'b> {
     pub item: &'b Item<'b>,
     pub syn: &'b SynItemUse,
 }
 
 #[derive(Debug)]
+#[allow(dead_code)]
 pub struct Enum<'b> {
     pub item: &'b Item<'b>,
     pub syn: &'b SynItemEnum,
 }
 
 #[derive(Debug)]
+#[allow(dead_code)]
 pub struct Struct<'b> {
     pub item: &'b Item<'b>,
     pub syn: &'b SynItemStruct,
@@ -66,6 +70,7 @@ impl<'b> Impl<'b> {
 }
 
 #[derive(Debug)]
+#[allow(dead_code)]
 pub struct ImplItem<'b> {
     pub im: &'b Impl<'b>,
     pub syn: &'b SynImplItem,
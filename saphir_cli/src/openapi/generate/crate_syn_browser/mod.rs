@@ -19,6 +19,14 @@ pub use self::{
     target::Target,
 };
 
+/// FNV-1a hash of the content of a file, telling whether it changed between
+/// runs. Unlike the std hashers, it is stable across builds of the CLI.
+pub fn content_hash(content: &[u8]) -> u64 {
+    content
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
 #[derive(Debug)]
 pub enum Error {
     CargoToml(Box<cargo_metadata::Error>),
@@ -95,4 +103,6 @@ pub struct ExpandedUse {
     pub name: String,
     pub alias: String,
     pub is_pub: bool,
+    /// `use path::*;`, where `path` is the globbed module and `name` is `*`
+    pub is_glob: bool,
 }
@@ -41,7 +41,7 @@ impl<'b> UseScope<'b> for Module<'b> {
             return Ok(Some(item));
         }
 
-        for u in self.uses() {
+        for u in self.uses().iter().filter(|u| !u.is_glob) {
             if u.alias.as_str() == name {
                 if let Some(module) = self.target().module_by_use_path(u.path.as_str())? {
                     return module.find_type_definition(u.name.as_str());
@@ -49,6 +49,16 @@ impl<'b> UseScope<'b> for Module<'b> {
             }
         }
 
+        // Explicit imports take precedence over glob imports
+        let mut visited = vec![self.path.clone()];
+        for u in self.uses().iter().filter(|u| u.is_glob) {
+            if let Some(module) = self.target().module_by_use_path(u.path.as_str())? {
+                if let Some(item) = module.find_glob_definition(name, &mut visited)? {
+                    return Ok(Some(item));
+                }
+            }
+        }
+
         let split: Vec<&str> = name.split("::").collect();
         let split_len = split.len();
         if split_len > 1 {
@@ -183,7 +193,7 @@ impl<'b> Module<'b> {
         self.name.as_str()
     }
 
-    fn items(&'b self) -> &Vec<Item<'b>> {
+    fn items(&'b self) -> &'b Vec<Item<'b>> {
         if !self.items.filled() {
             let items = match self.kind.borrow().expect("Kind should always be initialized") {
                 ModuleKind::Crate(m) => &m.file.file.items,
@@ -212,7 +222,7 @@ impl<'b> Module<'b> {
         &self.uses
     }
 
-    pub fn modules(&'b self) -> Result<&'b Vec<Module>, Error> {
+    pub fn modules(&'b self) -> Result<&'b Vec<Module<'b>>, Error> {
         let cell = &self.modules;
         if !cell.filled() {
             let mut syn_modules = Vec::new();
@@ -270,13 +280,20 @@ impl<'b> Module<'b> {
                     alias: name.clone(),
                     name,
                     is_pub,
+                    is_glob: false,
                 }]
             }
             UseTree::Rename(n) => {
                 let name = n.ident.to_string();
                 let alias = n.rename.to_string();
                 let path = prefix.unwrap_or_else(|| self.path().to_string());
-                vec![ExpandedUse { path, name, alias, is_pub }]
+                vec![ExpandedUse {
+                    path,
+                    name,
+                    alias,
+                    is_pub,
+                    is_glob: false,
+                }]
             }
             UseTree::Group(g) => g.items.iter().flat_map(|u| self.expand_use_tree(u, prefix.clone(), is_pub)).collect(),
             UseTree::Path(p) => {
@@ -300,14 +317,58 @@ impl<'b> Module<'b> {
                 self.expand_use_tree(p.tree.as_ref(), Some(prefix), is_pub)
             }
             UseTree::Glob(_) => {
+                // Globbed modules are only browsed when looking up a name, since they might glob
+                // this module in return
                 let path = prefix.expect("Glob pattern should have a path prefix");
-                if let Ok(Some(module)) = self.target().module_by_use_path(path.as_str()) {
-                    module.pub_uses().iter().cloned().cloned().collect()
-                } else {
-                    vec![]
+                vec![ExpandedUse {
+                    path,
+                    name: "*".to_string(),
+                    alias: "*".to_string(),
+                    is_pub,
+                    is_glob: true,
+                }]
+            }
+        }
+    }
+
+    /// Find a type definition brought into scope by globbing this module,
+    /// i.e. among its public items and public re-exports. `visited` holds the
+    /// paths of the modules already browsed, so that cyclic globs terminate.
+    fn find_glob_definition(&'b self, name: &str, visited: &mut Vec<String>) -> Result<Option<&'b Item<'b>>, Error> {
+        if visited.contains(&self.path) {
+            return Ok(None);
+        }
+        visited.push(self.path.clone());
+
+        if let Some(item) = self.find_type_definition_inline(name)? {
+            let is_pub = match &item.item {
+                SynItem::Enum(e) => !matches!(e.vis, Visibility::Inherited),
+                SynItem::Struct(s) => !matches!(s.vis, Visibility::Inherited),
+                _ => false,
+            };
+            if is_pub {
+                return Ok(Some(item));
+            }
+        }
+
+        let pub_uses = self.pub_uses();
+        for u in pub_uses.iter().filter(|u| !u.is_glob) {
+            if u.alias.as_str() == name {
+                if let Some(module) = self.target().module_by_use_path(u.path.as_str())? {
+                    return module.find_type_definition(u.name.as_str());
+                }
+            }
+        }
+
+        for u in pub_uses.iter().filter(|u| u.is_glob) {
+            if let Some(module) = self.target().module_by_use_path(u.path.as_str())? {
+                if let Some(item) = module.find_glob_definition(name, visited)? {
+                    return Ok(Some(item));
                 }
             }
         }
+
+        Ok(None)
     }
 
     pub fn all_items(&'b self) -> Result<Vec<&'b Item<'b>>, Error> {
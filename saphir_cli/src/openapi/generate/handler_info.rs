@@ -1,20 +1,36 @@
 use crate::openapi::{
-    generate::{crate_syn_browser::Method, response_info::ResponseInfo, route_info::RouteInfo, type_info::TypeInfo, BodyParamInfo, Gen, RouteParametersInfo},
+    generate::{
+        crate_syn_browser::Method,
+        response_info::ResponseInfo,
+        route_info::RouteInfo,
+        security_info::SecurityInfo,
+        type_info::TypeInfo,
+        utils::{find_macro_attribute_flag, get_doc_comment},
+        BodyParamInfo, Gen, RouteParametersInfo, SchemaGranularity,
+    },
     schema::{OpenApiMimeType, OpenApiParameter, OpenApiParameterLocation, OpenApiSchema, OpenApiType},
 };
 use syn::{FnArg, GenericArgument, ImplItemMethod, Pat, PathArguments, Type};
 
 #[derive(Clone, Debug, Default)]
 pub(crate) struct HandlerInfo {
+    pub(crate) summary: Option<String>,
+    pub(crate) description: Option<String>,
     pub(crate) use_cookies: bool,
     pub(crate) parameters: Vec<OpenApiParameter>,
     pub(crate) body_info: Option<BodyParamInfo>,
+    pub(crate) query_info: Option<BodyParamInfo>,
     pub(crate) routes: Vec<RouteInfo>,
     pub(crate) responses: Vec<ResponseInfo>,
+    pub(crate) security: Vec<SecurityInfo>,
 }
 
 impl Gen {
     pub(crate) fn extract_handler_info<'b>(&mut self, controller_path: &str, method: &'b Method<'b>) -> Option<HandlerInfo> {
+        if find_macro_attribute_flag(&method.syn.attrs, "openapi", "hidden") {
+            return None;
+        }
+
         let mut consume_cookies: bool = self.handler_has_cookies(method.syn);
 
         let routes = self.extract_routes_info_from_method_macro(method, controller_path);
@@ -29,13 +45,27 @@ impl Gen {
         }
 
         let responses = self.extract_response_info(method);
+        let security = self.extract_security_info(method);
+
+        // The first line of the doc comment is the summary, the following ones are the description
+        let (summary, description) = match get_doc_comment(&method.syn.attrs) {
+            Some(doc) => match doc.split_once('\n') {
+                Some((summary, description)) => (Some(summary.trim().to_string()), Some(description.trim().to_string()).filter(|d| !d.is_empty())),
+                None => (Some(doc), None),
+            },
+            None => (None, None),
+        };
 
         Some(HandlerInfo {
+            summary,
+            description,
             use_cookies: consume_cookies,
             parameters: parameters_info.parameters.clone(),
             body_info: parameters_info.body_info,
+            query_info: parameters_info.query_info,
             routes,
             responses,
+            security,
         })
     }
 
@@ -54,10 +84,11 @@ impl Gen {
     ///       implement a ParameterInfo struct with typing for param, fill
     ///       HandlerInfo with this, separate the discovery of BodyInfo and
     ///       cookies usage from parameters.
-    fn parse_handler_parameters<'b>(&self, method: &'b Method<'b>, uri_params: &[String]) -> RouteParametersInfo {
+    fn parse_handler_parameters<'b>(&mut self, method: &'b Method<'b>, uri_params: &[String]) -> RouteParametersInfo {
         let mut parameters = Vec::new();
         let mut has_cookies_param = false;
         let mut body_type = None;
+        let mut query_type = None;
         for param in method.syn.sig.inputs.iter().filter_map(|i| match i {
             FnArg::Typed(p) => Some(p),
             _ => None,
@@ -71,7 +102,24 @@ impl Gen {
                 Type::Path(p) => {
                     if let Some(s1) = p.path.segments.last() {
                         let mut param_type = s1.ident.to_string();
-                        if param_type.as_str() == "Ext" {
+                        if matches!(
+                            param_type.as_str(),
+                            "Ext"
+                                | "ConnectionInfo"
+                                | "RequestId"
+                                | "RawBody"
+                                | "TempFile"
+                                | "JsonLines"
+                                | "MatchedRoute"
+                                | "RequestStart"
+                                | "Lang"
+                                | "Cookies"
+                                | "Precondition"
+                        ) {
+                            continue;
+                        }
+                        if param_type.as_str() == "Query" {
+                            query_type = Some(s1);
                             continue;
                         }
                         if param_type.as_str() == "CookieJar" {
@@ -88,29 +136,64 @@ impl Gen {
                             }
                             continue;
                         }
-                        if param_type.as_str() == "Json" || param_type.as_str() == "Form" {
+                        if param_type.as_str() == "Json"
+                            || param_type.as_str() == "Json5"
+                            || param_type.as_str() == "Form"
+                            || param_type.as_str() == "MsgPack"
+                            || param_type.as_str() == "Cbor"
+                            || param_type.as_str() == "AnyBody"
+                            || param_type.as_str() == "MultipartForm"
+                        {
                             body_type = Some(s1);
                             continue;
                         }
                         let optional = param_type.as_str() == "Option";
+                        let mut segment = s1;
+                        let mut value_type = param.ty.as_ref();
                         if optional {
                             param_type = "String".to_string();
                             if let PathArguments::AngleBracketed(ab) = &s1.arguments {
-                                if let Some(GenericArgument::Type(Type::Path(p))) = ab.args.first() {
+                                if let Some(GenericArgument::Type(t @ Type::Path(p))) = ab.args.first() {
+                                    value_type = t;
                                     if let Some(i) = p.path.get_ident() {
                                         param_type = i.to_string();
+                                    } else if let Some(vec) = p.path.segments.last().filter(|s| s.ident == "Vec") {
+                                        param_type = "Vec".to_string();
+                                        segment = vec;
                                     }
                                 }
                             }
                         }
 
-                        let api_type = OpenApiType::from_rust_type_str(param_type.as_str());
-                        (api_type.unwrap_or_else(OpenApiType::string), optional)
+                        if param_type.as_str() == "Vec" {
+                            // Repeated query keys, e.g. `?tag=a&tag=b`
+                            let item_type = match &segment.arguments {
+                                PathArguments::AngleBracketed(ab) => match ab.args.first() {
+                                    Some(GenericArgument::Type(Type::Path(p))) => {
+                                        p.path.get_ident().and_then(|i| OpenApiType::from_rust_type_str(i.to_string().as_str()))
+                                    }
+                                    _ => None,
+                                },
+                                _ => None,
+                            };
+                            let api_type = OpenApiType::Array {
+                                items: Box::new(OpenApiSchema::Inline(item_type.unwrap_or_else(OpenApiType::string))),
+                                min_items: None,
+                                max_items: None,
+                            };
+                            (OpenApiSchema::Inline(api_type), optional)
+                        } else {
+                            let schema = match OpenApiType::from_rust_type_str(param_type.as_str()) {
+                                Some(api_type) => OpenApiSchema::Inline(api_type),
+                                None => self.get_parameter_schema_from_type(method, value_type),
+                            };
+                            (schema, optional)
+                        }
                     } else {
-                        (OpenApiType::string(), false)
+                        (OpenApiSchema::Inline(OpenApiType::string()), false)
                     }
                 }
-                _ => (OpenApiType::string(), false),
+                _ => (OpenApiSchema::Inline(OpenApiType::string()), false),
             };
 
             let location = if uri_params.contains(&param_name) {
@@ -123,25 +206,43 @@ impl Gen {
                 required: !optional,
                 nullable: optional,
                 location,
-                schema: OpenApiSchema::Inline(param_type),
+                schema: param_type,
                 ..Default::default()
             })
         }
 
+        // Captures read from the request rather than the handler's arguments
+        for uri_param in uri_params {
+            if !parameters.iter().any(|p| &p.name == uri_param) {
+                parameters.push(OpenApiParameter {
+                    name: uri_param.clone(),
+                    required: true,
+                    location: OpenApiParameterLocation::Path,
+                    schema: OpenApiSchema::Inline(OpenApiType::string()),
+                    ..Default::default()
+                })
+            }
+        }
+
         let mut body_info: Option<BodyParamInfo> = None;
         if let Some(body) = body_type {
             let body_type = body.ident.to_string();
-            let openapi_type = match body_type.as_str() {
-                "Json" => OpenApiMimeType::Json,
-                "Form" => OpenApiMimeType::Form,
-                _ => OpenApiMimeType::Any,
+            let openapi_types = match body_type.as_str() {
+                "Json" => vec![OpenApiMimeType::Json],
+                "Json5" => vec![OpenApiMimeType::Other("application/json5".to_string())],
+                "Form" => vec![OpenApiMimeType::Form],
+                "MsgPack" => vec![OpenApiMimeType::MsgPack],
+                "Cbor" => vec![OpenApiMimeType::Cbor],
+                "AnyBody" => vec![OpenApiMimeType::Json, OpenApiMimeType::Form],
+                "MultipartForm" => vec![OpenApiMimeType::Other("multipart/form-data".to_string())],
+                _ => vec![OpenApiMimeType::Any],
             };
             match body_type.as_str() {
-                "Json" | "Form" => {
+                "Json" | "Json5" | "Form" | "MsgPack" | "Cbor" | "AnyBody" | "MultipartForm" => {
                     if let PathArguments::AngleBracketed(ag) = &body.arguments {
                         if let Some(GenericArgument::Type(t)) = ag.args.first() {
                             if let Some(type_info) = TypeInfo::new(method.impl_item.im.item.scope, t) {
-                                body_info = Some(BodyParamInfo { openapi_type, type_info });
+                                body_info = Some(BodyParamInfo { openapi_types, type_info });
                             }
                         }
                     }
@@ -150,10 +251,36 @@ impl Gen {
             };
         }
 
+        // The fields of a `Query<T>` are documented as query parameters, like
+        // the body of a GET handler
+        let query_info = query_type
+            .and_then(|query| match &query.arguments {
+                PathArguments::AngleBracketed(ag) => match ag.args.first() {
+                    Some(GenericArgument::Type(t)) => TypeInfo::new(method.impl_item.im.item.scope, t),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .map(|type_info| BodyParamInfo {
+                openapi_types: Vec::new(),
+                type_info,
+            });
+
         RouteParametersInfo {
             parameters,
             has_cookies_param,
             body_info,
+            query_info,
         }
     }
+
+    /// Schema of a parameter whose type is defined in the crate, e.g. an enum
+    /// of the accepted values, or a string if it cannot be resolved
+    fn get_parameter_schema_from_type<'b>(&mut self, method: &'b Method<'b>, ty: &Type) -> OpenApiSchema {
+        let scope = method.impl_item.im.item.scope;
+        let as_ref = self.args.schema_granularity != SchemaGranularity::None;
+        TypeInfo::new(scope, ty)
+            .and_then(|type_info| self.get_open_api_schema_from_type_info(scope, &type_info, as_ref))
+            .unwrap_or_else(|| OpenApiSchema::Inline(OpenApiType::string()))
+    }
 }
@@ -1,15 +1,16 @@
 use crate::{
     openapi::{
         generate::{
+            cache::{GenCache, ParseCache},
             controller_info::ControllerInfo,
-            crate_syn_browser::{Browser, Item, ItemKind, Module, UseScope},
+            crate_syn_browser::{content_hash, Browser, Item, ItemKind, Module, UseScope},
             response_info::AnonymousType,
             type_info::TypeInfo,
-            utils::{find_macro_attribute_flag, find_macro_attribute_named_value, get_serde_field},
+            utils::{example_from_lit, find_macro_attribute_flag, find_macro_attribute_named_value, get_doc_comment, get_serde_field},
         },
         schema::{
-            OpenApi, OpenApiContent, OpenApiMimeType, OpenApiObjectType, OpenApiParameter, OpenApiParameterLocation, OpenApiPath, OpenApiPathMethod,
-            OpenApiRequestBody, OpenApiResponse, OpenApiSchema, OpenApiTag, OpenApiType,
+            OpenApi, OpenApiContent, OpenApiDiscriminator, OpenApiMimeType, OpenApiObjectType, OpenApiParameter, OpenApiParameterLocation, OpenApiPath,
+            OpenApiPathMethod, OpenApiRequestBody, OpenApiResponse, OpenApiSchema, OpenApiTag, OpenApiType,
         },
     },
     Command, CommandResult,
@@ -19,23 +20,26 @@ use http::StatusCode;
 use serde_derive::Deserialize;
 use std::{
     cell::RefCell,
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{btree_map::Entry, BTreeMap, HashMap, HashSet},
     fmt::{Display, Formatter},
     fs::File as FsFile,
     io::Read,
-    path::PathBuf,
+    path::{Path, PathBuf},
     str::FromStr,
     time::Instant,
 };
-use syn::{Fields, Item as SynItem, ItemEnum, ItemStruct, Signature};
+use syn::{Attribute, Fields, Item as SynItem, ItemEnum, ItemStruct, Lit, Signature};
 
+mod cache;
 mod controller_info;
 mod crate_syn_browser;
 mod handler_info;
 mod response_info;
 mod route_info;
+mod security_info;
 mod type_info;
 mod utils;
+mod watch;
 
 #[derive(Debug, Eq, PartialEq, Clone, ValueEnum, Default)]
 enum Case {
@@ -81,6 +85,29 @@ pub enum SchemaGranularity {
     All,
 }
 
+#[derive(Debug, Eq, PartialEq, Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Yaml,
+    Json,
+}
+
+impl OutputFormat {
+    fn from_extension(ext: &str) -> Option<Self> {
+        match ext {
+            "yaml" | "yml" => Some(OutputFormat::Yaml),
+            "json" => Some(OutputFormat::Json),
+            _ => None,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Yaml => "yaml",
+            OutputFormat::Json => "json",
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum SchemaGranularityError {
     UnknownValue,
@@ -121,7 +148,7 @@ You can see help with the --help flag.",
 /// Generate OpenAPI v3 from a Saphir application.
 ///
 /// See: https://github.com/OAI/OpenAPI-Specification/blob/master/versions/3.0.2.md
-#[derive(Args, Debug, Default)]
+#[derive(Args, Clone, Debug, Default)]
 pub(crate) struct GenArgs {
     /// (Optional) Limit doc generation to the URIs under this scope.
     ///
@@ -131,7 +158,8 @@ pub(crate) struct GenArgs {
     /// - GET  /about
     /// - GET  /api/v1/user
     /// - POST /api/v1/user
-    /// , the generated doc would contain only the `/api/v2/user` endpoints.
+    ///
+    /// the generated doc would contain only the `/api/v2/user` endpoints.
     #[arg(short = 's', long = "scope", default_value = "/", verbatim_doc_comment)]
     scope: String,
 
@@ -158,10 +186,19 @@ pub(crate) struct GenArgs {
     package_name: Option<String>,
 
     /// (Optional) Resulting output path. Either the path to the resulting yaml
-    /// file, or a dir, which would then contain a openapi.yaml
+    /// or json file, or a dir, which would then contain a openapi.yaml
     #[arg(default_value = ".")]
     output_file: PathBuf,
 
+    /// (Optional) Format of the output, when it is a dir or a path without
+    /// extension. Otherwise, the format is deduced from the extension.
+    ///
+    /// Available values:
+    /// - yaml (default)
+    /// - json
+    #[arg(value_enum, short = 'f', long = "format", verbatim_doc_comment)]
+    format: Option<OutputFormat>,
+
     /// (Optional) Casing of the operation names.
     /// Accepted case names matches serde's :
     ///
@@ -175,6 +212,16 @@ pub(crate) struct GenArgs {
     /// - SCREAMING-KEBAB-CASE
     #[arg(short = 'c', long = "operation-name-case", default_value = "camelCase", value_enum, verbatim_doc_comment)]
     operation_name_case: Case,
+
+    /// (Optional) Generate the documentation even if none of the sources
+    /// changed since the last run.
+    #[arg(long = "no-cache")]
+    no_cache: bool,
+
+    /// (Optional) Keep running, and generate the documentation again each
+    /// time a source file of the project changes.
+    #[arg(short = 'w', long = "watch")]
+    pub(crate) watch: bool,
 }
 
 pub(crate) struct Gen {
@@ -201,15 +248,43 @@ impl Command for Gen {
 
     fn run<'b>(mut self) -> CommandResult {
         let now = Instant::now();
-        self.read_project_cargo_toml()?;
+        let cargo_toml_hash = self.read_project_cargo_toml()?;
         let browser = Browser::new(self.args.project_path.clone()).map_err(|e| format!("{}", e))?;
         let browser = unsafe { &*(&browser as *const Browser) }; // FIXME: Definitely find a better way to handle the lifetime issue here
+        let (output, format) = self.output_path()?;
+        let cache = GenCache::path(browser, format!("{:?}", self.args).as_str());
+        if !self.args.no_cache {
+            if let Some(files) = GenCache::up_to_date(&cache, &output) {
+                println!(
+                    "`{}` is up to date, {} cached files unchanged, in {}ms",
+                    output.to_str().unwrap_or_default(),
+                    files,
+                    now.elapsed().as_millis()
+                );
+                return Ok(());
+            }
+        }
+        let parse_cache = ParseCache::path(browser);
+        if !self.args.no_cache {
+            browser.use_parse_cache(ParseCache::load(&parse_cache));
+        }
         let entrypoint = self.get_crate_entrypoint(self.args.package_name.as_ref(), browser)?;
         let controllers = self.load_controllers(entrypoint)?;
         self.fill_openapi_with_controllers(entrypoint, controllers);
         self.doc.sort_and_dedup_tags();
-        let file = self.write_doc_file()?;
-        println!("Succesfully created `{}` in {}ms", file, now.elapsed().as_millis());
+        self.write_doc_file(&output, format)?;
+        let mut files = browser.files_read();
+        files.push((self.args.project_path.join("Cargo.toml"), cargo_toml_hash));
+        browser.take_parse_cache().store(&parse_cache, &files);
+        let parsed = files.len() - 1;
+        GenCache::store(&cache, files, &output);
+        println!(
+            "Succesfully created `{}` in {}ms, {} of {} files read from the parse cache",
+            output.to_str().unwrap_or_default(),
+            now.elapsed().as_millis(),
+            browser.cache_hits(),
+            parsed
+        );
         Ok(())
     }
 }
@@ -232,25 +307,37 @@ by using the --package flag."
         Ok(entrypoint)
     }
 
-    fn write_doc_file(&self) -> Result<String, String> {
+    fn output_path(&self) -> Result<(PathBuf, OutputFormat), String> {
         let mut path = self.args.output_file.clone();
+        let mut format = self.args.format.unwrap_or(OutputFormat::Yaml);
         if path.is_dir() {
-            path = path.join("openapi.yaml");
+            path = path.join("openapi").with_extension(format.extension());
         }
         match path.extension() {
-            None => path = path.with_extension("yaml"),
-            Some(ext) => {
-                if ext.to_str() != Some("yaml") && ext.to_str() != Some("yml") {
-                    return Err("output must be a yaml file.".to_string());
+            None => path = path.with_extension(format.extension()),
+            Some(ext) => match ext.to_str().and_then(OutputFormat::from_extension) {
+                Some(ext_format) if self.args.format.filter(|f| *f != ext_format).is_some() => {
+                    return Err(format!("output extension does not match the {} format.", format.extension()));
                 }
-            }
+                Some(ext_format) => format = ext_format,
+                None => return Err("output must be a yaml or json file.".to_string()),
+            },
         }
-        let f = FsFile::create(&path).map_err(|_| format!("Unable to create file `{:?}`", &path))?;
-        serde_yaml::to_writer(f, &self.doc).map_err(|_| format!("Unable to write to `{:?}`", path))?;
-        Ok(path.to_str().unwrap_or_default().to_string())
+        Ok((path, format))
     }
 
-    fn read_project_cargo_toml(&mut self) -> CommandResult {
+    fn write_doc_file(&self, path: &Path, format: OutputFormat) -> CommandResult {
+        let f = FsFile::create(path).map_err(|_| format!("Unable to create file `{:?}`", path))?;
+        match format {
+            OutputFormat::Yaml => serde_yaml::to_writer(f, &self.doc).map_err(|_| format!("Unable to write to `{:?}`", path))?,
+            OutputFormat::Json => serde_json::to_writer_pretty(f, &self.doc).map_err(|_| format!("Unable to write to `{:?}`", path))?,
+        }
+        Ok(())
+    }
+
+    /// Read the title and version of the API from the manifest, returning the
+    /// hash of its content
+    fn read_project_cargo_toml(&mut self) -> Result<u64, String> {
         #[derive(Deserialize)]
         struct Cargo {
             pub package: Package,
@@ -270,7 +357,7 @@ by using the --package flag."
         self.doc.info.title = cargo.package.name;
         self.doc.info.version = cargo.package.version;
 
-        Ok(())
+        Ok(content_hash(buffer.as_bytes()))
     }
 
     fn load_controllers<'b>(&mut self, entrypoint: &'b Module<'b>) -> Result<Vec<ControllerInfo>, String> {
@@ -287,24 +374,29 @@ by using the --package flag."
     }
 
     fn fill_openapi_with_controllers<'b>(&mut self, entrypoint: &'b Module<'b>, controllers: Vec<ControllerInfo>) {
-        for controller in controllers {
+        for controller in controllers.into_iter().filter(|c| !c.hidden) {
             let controller_name = controller.controller_name.as_str();
             let controller_model_name = controller.name.as_str();
+            let tag_name = controller.tag.as_deref().unwrap_or(controller_model_name);
             let mut cur_controller_schema = 0;
             for mut handler in controller.handlers {
                 for route in handler.routes {
                     let path = route.uri;
                     let method = route.method;
-                    let description = if handler.use_cookies {
-                        Some("NOTE: This request consume cookies.".to_string())
-                    } else {
-                        None
+                    let description = match (&handler.description, handler.use_cookies) {
+                        (Some(d), true) => Some(format!("{}\n\nNOTE: This request consume cookies.", d)),
+                        (Some(d), false) => Some(d.clone()),
+                        (None, true) => Some("NOTE: This request consume cookies.".to_string()),
+                        (None, false) => None,
                     };
 
                     let mut tags = Vec::new();
                     tags.push(OpenApiTag {
-                        name: controller.name.clone(),
-                        description: Some(format!("Endpoints under the {} controller (`{}`).", controller_model_name, controller_name)),
+                        name: tag_name.to_string(),
+                        description: controller
+                            .tag_description
+                            .clone()
+                            .or_else(|| Some(format!("Endpoints under the {} controller (`{}`).", controller_model_name, controller_name))),
                     });
                     if let Some(version) = &controller.version {
                         tags.push(OpenApiTag {
@@ -312,7 +404,7 @@ by using the --package flag."
                             description: Some(format!("Endpoints under the v{} controllers.", version)),
                         });
                         tags.push(OpenApiTag {
-                            name: format!("{}-v{}", controller_model_name, version),
+                            name: format!("{}-v{}", tag_name, version),
                             description: Some(format!(
                                 "Endpoints under the {} controller v{} (`{}`).",
                                 controller_model_name, version, controller_name
@@ -322,6 +414,7 @@ by using the --package flag."
 
                     let mut data = OpenApiPath {
                         parameters: handler.parameters.clone(),
+                        summary: handler.summary.clone().unwrap_or_default(),
                         description: description.clone(),
                         operation_id: route.operation_id.clone(),
                         operation_name: route.operation_name.clone(),
@@ -330,6 +423,30 @@ by using the --package flag."
                     };
                     self.doc.tags.extend(tags);
 
+                    if !handler.security.is_empty() {
+                        // Every guard must pass, so all of their schemes are required together
+                        let mut requirement = BTreeMap::new();
+                        for security in &handler.security {
+                            match self.doc.components.security_schemes.entry(security.name.clone()) {
+                                Entry::Vacant(entry) => {
+                                    entry.insert(security.scheme.clone());
+                                }
+                                Entry::Occupied(entry) if entry.get() != &security.scheme => println!(
+                                    "The security scheme `{}` of {}, protecting {}, conflicts with another scheme of the same name which is kept instead, use a distinct `security_name`",
+                                    security.name, security.guard_name, path
+                                ),
+                                Entry::Occupied(_) => {}
+                            }
+                            requirement.insert(security.name.clone(), Vec::new());
+                        }
+                        data.security.push(requirement);
+                    }
+
+                    if let Some(query_info) = handler.query_info.as_ref() {
+                        let parameters = self.get_open_api_parameters_from_body_info(entrypoint, query_info);
+                        data.parameters.extend(parameters);
+                    }
+
                     if let Some(body_info) = handler.body_info.as_mut() {
                         if method == OpenApiPathMethod::Get {
                             let parameters = self.get_open_api_parameters_from_body_info(entrypoint, body_info);
@@ -374,6 +491,7 @@ by using the --package flag."
                                             OpenApiType::Object {
                                                 object: OpenApiObjectType::Dictionary {
                                                     properties: Default::default(),
+                                                    required: Default::default(),
                                                     additional_properties: Box::new(OpenApiSchema::Inline(raw_type)),
                                                 },
                                             }
@@ -384,7 +502,13 @@ by using the --package flag."
                                 })
                             })
                         {
-                            content.insert(response.mime.clone(), OpenApiContent { schema });
+                            content.insert(
+                                response.mime.clone(),
+                                OpenApiContent {
+                                    schema,
+                                    example: response.example.clone(),
+                                },
+                            );
                         }
                         let status = StatusCode::from_u16(response.code);
                         let description = response
@@ -426,31 +550,14 @@ by using the --package flag."
                 OpenApiSchema::Inline(t) => {
                     ty = t.clone();
                 }
-                OpenApiSchema::Ref { .. } => return OpenApiSchema::Inline(ty),
+                _ => return OpenApiSchema::Inline(ty),
             }
         }
 
         let schema = if ty.is_primitive(&self.args.schema_granularity) {
             OpenApiSchema::Inline(ty)
         } else {
-            let ref_name = if let Some(map) = self.generated_schema_names.get_mut(name) {
-                if let Some(ref_name) = map.get(&full_path.map(|s| s.to_string())) {
-                    ref_name.clone()
-                } else {
-                    let name = format!("{}_{}", name, map.len() + 1);
-                    map.insert(full_path.map(|s| s.to_string()), name.clone());
-                    name
-                }
-            } else {
-                let mut map = HashMap::new();
-                map.insert(full_path.map(|s| s.to_string()), name.to_string());
-                self.generated_schema_names.insert(name.to_string(), map);
-                name.to_string()
-            };
-            self.doc.components.schemas.insert(ref_name.clone(), OpenApiSchema::Inline(ty));
-            OpenApiSchema::Ref {
-                type_ref: format!("#/components/schemas/{}", ref_name.as_str()),
-            }
+            self.get_schema_ref_from_schema(name, full_path, OpenApiSchema::Inline(ty))
         };
         if is_array {
             OpenApiSchema::Inline(OpenApiType::Array {
@@ -463,6 +570,28 @@ by using the --package flag."
         }
     }
 
+    /// Register a named schema in the components and reference it
+    fn get_schema_ref_from_schema(&mut self, name: &str, full_path: Option<&str>, schema: OpenApiSchema) -> OpenApiSchema {
+        let ref_name = if let Some(map) = self.generated_schema_names.get_mut(name) {
+            if let Some(ref_name) = map.get(&full_path.map(|s| s.to_string())) {
+                ref_name.clone()
+            } else {
+                let name = format!("{}_{}", name, map.len() + 1);
+                map.insert(full_path.map(|s| s.to_string()), name.clone());
+                name
+            }
+        } else {
+            let mut map = HashMap::new();
+            map.insert(full_path.map(|s| s.to_string()), name.to_string());
+            self.generated_schema_names.insert(name.to_string(), map);
+            name.to_string()
+        };
+        self.doc.components.schemas.insert(ref_name.clone(), schema);
+        OpenApiSchema::Ref {
+            type_ref: format!("#/components/schemas/{}", ref_name.as_str()),
+        }
+    }
+
     fn get_open_api_body_param<'b>(&mut self, entrypoint: &'b Module<'b>, body_info: &mut BodyParamInfo) -> OpenApiRequestBody {
         let schema = if body_info.type_info.is_type_deserializable {
             let ty = &mut body_info.type_info;
@@ -474,7 +603,15 @@ by using the --package flag."
             OpenApiSchema::Inline(OpenApiType::anonymous_input_object())
         };
         let mut content: BTreeMap<OpenApiMimeType, OpenApiContent> = BTreeMap::new();
-        content.insert(body_info.openapi_type.clone(), OpenApiContent { schema });
+        for openapi_type in &body_info.openapi_types {
+            content.insert(
+                openapi_type.clone(),
+                OpenApiContent {
+                    schema: schema.clone(),
+                    example: None,
+                },
+            );
+        }
         OpenApiRequestBody {
             description: body_info.type_info.name.clone(),
             required: !body_info.type_info.is_optional,
@@ -492,33 +629,29 @@ by using the --package flag."
             None
         } {
             let t = match schema {
-                OpenApiSchema::Inline(t) => t,
-                _ => unreachable!(),
+                OpenApiSchema::Inline(t) | OpenApiSchema::ExampleInline { schema: t, .. } => t,
+                _ => return parameters,
             };
 
             if let OpenApiType::Object {
-                object:
-                    OpenApiObjectType::Object {
-                        properties,
-                        required,
-                        additional_properties: false,
-                    },
+                object: OpenApiObjectType::Object { properties, required, .. },
             } = t
             {
                 for (name, schema) in properties {
+                    let (schema, description) = schema.split_description();
                     let schema = if as_ref {
-                        let schema = *schema;
                         match schema {
                             OpenApiSchema::Inline(t) => self.get_schema_ref(body_info.type_info.name.as_str(), body_info.type_info.type_path.as_deref(), t),
                             r => r,
                         }
                     } else {
-                        *schema
+                        schema
                     };
 
                     parameters.push(OpenApiParameter {
                         name: name.clone(),
                         location: OpenApiParameterLocation::Query,
+                        description,
                         required: required.contains(&name),
                         schema,
                         ..Default::default()
@@ -549,6 +682,7 @@ by using the --package flag."
             Some(OpenApiSchema::Inline(OpenApiType::Object {
                 object: OpenApiObjectType::Dictionary {
                     properties: Default::default(),
+                    required: Default::default(),
                     additional_properties: Box::new(schema),
                 },
             }))
@@ -558,34 +692,95 @@ by using the --package flag."
     }
 
     fn get_open_api_type_from_struct<'b>(&mut self, name: &str, item: &'b Item<'b>, s: &ItemStruct, as_ref: bool) -> OpenApiSchema {
+        let object = self.get_open_api_type_from_fields(item.scope, &s.fields, &s.attrs, as_ref);
+        let path = item.scope.path();
+        match find_macro_attribute_named_value(&s.attrs, "openapi", "example").and_then(|lit| example_from_lit(&lit)) {
+            Some(example) => {
+                let schema = OpenApiSchema::Inline(object).with_example(example);
+                if as_ref {
+                    self.get_schema_ref_from_schema(name, Some(path), schema)
+                } else {
+                    schema
+                }
+            }
+            None => self.get_schema(name, Some(path), object, as_ref),
+        }
+    }
+
+    /// Object type of named fields. Fields marked with `#[serde(flatten)]` are
+    /// merged into the object, or become its `additionalProperties` when they
+    /// are dictionaries. Unknown properties are allowed, as serde ignores them,
+    /// unless the container denies them with `#[serde(deny_unknown_fields)]`.
+    fn get_open_api_type_from_fields<'b>(&mut self, scope: &'b dyn UseScope<'b>, fields: &Fields, container_attrs: &[Attribute], as_ref: bool) -> OpenApiType {
         let mut properties = BTreeMap::new();
         let mut required = Vec::new();
-        for field in &s.fields {
-            if let Some(field_name) = field.ident.as_ref().and_then(|i| get_serde_field(i.to_string(), &field.attrs, &s.attrs)) {
-                if let Some(field_type_info) = TypeInfo::new(item.scope, &field.ty) {
-                    let field_as_ref = self.args.schema_granularity == SchemaGranularity::All && as_ref;
-                    let field_schema = self
-                        .get_open_api_schema_from_type_info(item.scope, &field_type_info, field_as_ref)
-                        .unwrap_or_else(|| {
-                            let type_name = field_type_info.rename.as_ref().unwrap_or(&field_type_info.name);
-                            let raw_type = OpenApiType::from_rust_type_str(type_name.as_str()).unwrap_or_else(OpenApiType::string);
-                            if field_type_info.is_array {
-                                OpenApiSchema::Inline(OpenApiType::Array {
-                                    items: Box::new(OpenApiSchema::Inline(raw_type)),
-                                    min_items: field_type_info.min_array_len,
-                                    max_items: field_type_info.max_array_len,
-                                })
-                            } else if field_type_info.is_dictionary {
-                                OpenApiSchema::Inline(OpenApiType::Object {
-                                    object: OpenApiObjectType::Dictionary {
-                                        properties: Default::default(),
-                                        additional_properties: Box::new(OpenApiSchema::Inline(raw_type)),
-                                    },
-                                })
-                            } else {
-                                OpenApiSchema::Inline(raw_type)
-                            }
-                        });
+        let mut additional_properties = None;
+        let field_as_ref = self.args.schema_granularity == SchemaGranularity::All && as_ref;
+        for field in fields {
+            if find_macro_attribute_flag(&field.attrs, "serde", "flatten") {
+                let field_type_info = match TypeInfo::new(scope, &field.ty) {
+                    Some(t) => t,
+                    None => {
+                        println!("Unsupported type : {:?}", &field.ty);
+                        continue;
+                    }
+                };
+                let flattened = if field_type_info.is_dictionary {
+                    self.get_open_api_schema_or_raw(scope, &field_type_info, field_as_ref)
+                } else {
+                    match self.get_open_api_schema_from_type_info(scope, &field_type_info, false) {
+                        Some(OpenApiSchema::ExampleInline { schema, .. }) => OpenApiSchema::Inline(schema),
+                        Some(schema) => schema,
+                        None => {
+                            println!("Unsupported flattened type : {}", field_type_info.name);
+                            continue;
+                        }
+                    }
+                };
+                match flattened {
+                    OpenApiSchema::Inline(OpenApiType::Object {
+                        object: OpenApiObjectType::Object {
+                            properties: p, required: r, ..
+                        },
+                    }) => {
+                        properties.extend(p);
+                        if !field_type_info.is_optional {
+                            required.extend(r);
+                        }
+                    }
+                    OpenApiSchema::Inline(OpenApiType::Object {
+                        object:
+                            OpenApiObjectType::Dictionary {
+                                properties: p,
+                                required: r,
+                                additional_properties: a,
+                            },
+                    }) => {
+                        properties.extend(p);
+                        if !field_type_info.is_optional {
+                            required.extend(r);
+                        }
+                        additional_properties = Some(a);
+                    }
+                    OpenApiSchema::Inline(OpenApiType::Object {
+                        object: OpenApiObjectType::AnonymousInputObject { .. },
+                    }) => {}
+                    _ => println!("Unsupported flattened type : {}", field_type_info.name),
+                }
+                continue;
+            }
+
+            if let Some(field_name) = field.ident.as_ref().and_then(|i| get_serde_field(i.to_string(), &field.attrs, container_attrs)) {
+                if let Some(field_type_info) = TypeInfo::new(scope, &field.ty) {
+                    let field_schema = self.get_open_api_schema_or_raw(scope, &field_type_info, field_as_ref);
+                    let field_schema = match get_doc_comment(&field.attrs) {
+                        Some(description) => field_schema.with_description(description),
+                        None => field_schema,
+                    };
+                    let field_schema = match find_macro_attribute_named_value(&field.attrs, "openapi", "example").and_then(|lit| example_from_lit(&lit)) {
+                        Some(example) => field_schema.with_example(example),
+                        None => field_schema,
+                    };
                     if !field_type_info.is_optional
                         && !find_macro_attribute_flag(&field.attrs, "serde", "default")
                         && find_macro_attribute_named_value(&field.attrs, "serde", "default").is_none()
@@ -599,32 +794,228 @@ by using the --package flag."
             }
         }
 
-        let path = item.scope.path();
-        let object = if !properties.is_empty() {
-            OpenApiType::object(properties, required)
-        } else {
-            OpenApiType::anonymous_input_object()
-        };
-        self.get_schema(name, Some(path), object, as_ref)
+        match additional_properties {
+            Some(additional_properties) => OpenApiType::Object {
+                object: OpenApiObjectType::Dictionary {
+                    properties,
+                    required,
+                    additional_properties,
+                },
+            },
+            None if !properties.is_empty() => OpenApiType::Object {
+                object: OpenApiObjectType::Object {
+                    properties,
+                    required,
+                    additional_properties: find_macro_attribute_flag(container_attrs, "serde", "deny_unknown_fields").then_some(false),
+                },
+            },
+            None => OpenApiType::anonymous_input_object(),
+        }
+    }
+
+    /// Schema of a type, falling back to the raw rust type when its
+    /// definition cannot be found
+    fn get_open_api_schema_or_raw<'b>(&mut self, scope: &'b dyn UseScope<'b>, type_info: &TypeInfo, as_ref: bool) -> OpenApiSchema {
+        self.get_open_api_schema_from_type_info(scope, type_info, as_ref).unwrap_or_else(|| {
+            let type_name = type_info.rename.as_ref().unwrap_or(&type_info.name);
+            let raw_type = OpenApiType::from_rust_type_str(type_name.as_str()).unwrap_or_else(OpenApiType::string);
+            if type_info.is_array {
+                OpenApiSchema::Inline(OpenApiType::Array {
+                    items: Box::new(OpenApiSchema::Inline(raw_type)),
+                    min_items: type_info.min_array_len,
+                    max_items: type_info.max_array_len,
+                })
+            } else if type_info.is_dictionary {
+                OpenApiSchema::Inline(OpenApiType::Object {
+                    object: OpenApiObjectType::Dictionary {
+                        properties: Default::default(),
+                        required: Default::default(),
+                        additional_properties: Box::new(OpenApiSchema::Inline(raw_type)),
+                    },
+                })
+            } else {
+                OpenApiSchema::Inline(raw_type)
+            }
+        })
     }
 
     fn get_open_api_type_from_enum<'b>(&mut self, name: &str, item: &Item<'b>, e: &'b ItemEnum, as_ref: bool) -> OpenApiSchema {
-        let ty = if e.variants.iter().all(|v| v.fields == Fields::Unit) {
+        let path = item.scope.path();
+        if e.variants.iter().all(|v| v.fields == Fields::Unit) {
             let mut values: Vec<String> = Vec::new();
             for variant in &e.variants {
                 if let Some(name) = get_serde_field(variant.ident.to_string(), &variant.attrs, &e.attrs) {
                     values.push(name);
                 }
             }
-            OpenApiType::enums(values)
-        } else {
-            // TODO: properly support tuple and struct enum variants.
-            //       this will require the item param
-            OpenApiType::anonymous_input_object()
+            return self.get_schema(name, Some(path), OpenApiType::enums(values), as_ref);
+        }
+
+        if let Some(tag) = find_macro_attribute_named_value(&e.attrs, "serde", "tag") {
+            return match tag {
+                Lit::Str(tag) if find_macro_attribute_named_value(&e.attrs, "serde", "content").is_none() => {
+                    self.get_open_api_type_from_internally_tagged_enum(name, item, e, &tag.value(), as_ref)
+                }
+                // TODO: support adjacently tagged enums
+                _ => self.get_schema(name, Some(path), OpenApiType::anonymous_input_object(), as_ref),
+            };
+        }
+
+        // Externally tagged variants are objects with a single property, named
+        // after the variant. Untagged variants are their content only.
+        let untagged = find_macro_attribute_flag(&e.attrs, "serde", "untagged");
+        let field_as_ref = self.args.schema_granularity == SchemaGranularity::All && as_ref;
+        let mut unit_values = Vec::new();
+        let mut one_of = Vec::new();
+        for variant in &e.variants {
+            let variant_name = match get_serde_field(variant.ident.to_string(), &variant.attrs, &e.attrs) {
+                Some(variant_name) => variant_name,
+                None => continue,
+            };
+            let content = match &variant.fields {
+                Fields::Unit => {
+                    if !untagged {
+                        unit_values.push(variant_name);
+                    }
+                    continue;
+                }
+                Fields::Named(_) => OpenApiSchema::Inline(self.get_open_api_type_from_fields(item.scope, &variant.fields, &variant.attrs, as_ref)),
+                Fields::Unnamed(fields) => {
+                    // OpenAPI 3.0 can't type the items by position, so the items
+                    // of a tuple are any of the distinct types of its fields
+                    let mut items = Vec::new();
+                    for field in &fields.unnamed {
+                        match TypeInfo::new(item.scope, &field.ty) {
+                            Some(type_info) => {
+                                let schema = self.get_open_api_schema_or_raw(item.scope, &type_info, field_as_ref);
+                                if !items.contains(&schema) {
+                                    items.push(schema);
+                                }
+                            }
+                            None => println!("Unsupported type : {:?}", &field.ty),
+                        }
+                    }
+                    let len = fields.unnamed.len() as u32;
+                    if len == 1 && items.len() == 1 {
+                        items.remove(0)
+                    } else {
+                        OpenApiSchema::Inline(OpenApiType::Array {
+                            items: Box::new(if items.len() == 1 {
+                                items.remove(0)
+                            } else {
+                                OpenApiSchema::AnyOf {
+                                    any_of: items,
+                                    description: None,
+                                }
+                            }),
+                            min_items: Some(len),
+                            max_items: Some(len),
+                        })
+                    }
+                }
+            };
+            let content = match get_doc_comment(&variant.attrs) {
+                Some(description) => content.with_description(description),
+                None => content,
+            };
+            one_of.push(if untagged {
+                content
+            } else {
+                let mut properties = BTreeMap::new();
+                properties.insert(variant_name.clone(), Box::new(content));
+                OpenApiSchema::Inline(OpenApiType::object(properties, vec![variant_name]))
+            });
+        }
+        if !unit_values.is_empty() {
+            one_of.insert(0, OpenApiSchema::Inline(OpenApiType::enums(unit_values)));
+        }
+
+        let schema = OpenApiSchema::OneOf {
+            one_of,
+            discriminator: None,
+            description: None,
         };
+        if as_ref {
+            self.get_schema_ref_from_schema(name, Some(path), schema)
+        } else {
+            schema
+        }
+    }
 
+    /// Internally tagged variants are objects holding the `tag` property along
+    /// with their content. Each variant is a component schema, so the
+    /// discriminator can map the tag values to them.
+    fn get_open_api_type_from_internally_tagged_enum<'b>(&mut self, name: &str, item: &Item<'b>, e: &'b ItemEnum, tag: &str, as_ref: bool) -> OpenApiSchema {
         let path = item.scope.path();
-        self.get_schema(name, Some(path), ty, as_ref)
+        let deny_unknown_fields = find_macro_attribute_flag(&e.attrs, "serde", "deny_unknown_fields");
+        let mut one_of = Vec::new();
+        let mut mapping = BTreeMap::new();
+        for variant in &e.variants {
+            let variant_name = match get_serde_field(variant.ident.to_string(), &variant.attrs, &e.attrs) {
+                Some(variant_name) => variant_name,
+                None => continue,
+            };
+            let mut variant_type = match &variant.fields {
+                Fields::Unit => OpenApiType::object(BTreeMap::new(), Vec::new()),
+                Fields::Named(_) => self.get_open_api_type_from_fields(item.scope, &variant.fields, &variant.attrs, as_ref),
+                Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                    let content = TypeInfo::new(item.scope, &fields.unnamed[0].ty).and_then(|t| self.get_open_api_schema_from_type_info(item.scope, &t, false));
+                    match content {
+                        Some(OpenApiSchema::Inline(ty))
+                        | Some(OpenApiSchema::DescribedInline { schema: ty, .. })
+                        | Some(OpenApiSchema::ExampleInline { schema: ty, .. }) => ty,
+                        _ => OpenApiType::anonymous_input_object(),
+                    }
+                }
+                _ => {
+                    println!("Unsupported internally tagged variant : {}::{}", name, variant.ident);
+                    continue;
+                }
+            };
+            match &mut variant_type {
+                OpenApiType::Object {
+                    object:
+                        OpenApiObjectType::Object {
+                            properties,
+                            required,
+                            additional_properties,
+                        },
+                } => {
+                    properties.insert(tag.to_string(), Box::new(OpenApiSchema::Inline(OpenApiType::enums(vec![variant_name.clone()]))));
+                    required.insert(0, tag.to_string());
+                    *additional_properties = deny_unknown_fields.then_some(false);
+                }
+                OpenApiType::Object { .. } => {}
+                _ => {
+                    println!("Unsupported internally tagged variant : {}::{}", name, variant.ident);
+                    continue;
+                }
+            }
+
+            let schema = match get_doc_comment(&variant.attrs) {
+                Some(description) => OpenApiSchema::Inline(variant_type).with_description(description),
+                None => OpenApiSchema::Inline(variant_type),
+            };
+            let variant_ref = self.get_schema_ref_from_schema(&format!("{}{}", name, variant.ident), Some(path), schema);
+            if let OpenApiSchema::Ref { type_ref } = &variant_ref {
+                mapping.insert(variant_name, type_ref.clone());
+            }
+            one_of.push(variant_ref);
+        }
+
+        let schema = OpenApiSchema::OneOf {
+            one_of,
+            discriminator: Some(OpenApiDiscriminator {
+                property_name: tag.to_string(),
+                mapping,
+            }),
+            description: None,
+        };
+        if as_ref {
+            self.get_schema_ref_from_schema(name, Some(path), schema)
+        } else {
+            schema
+        }
     }
 
     fn handler_operation_id_from_sig(&self, sig: &Signature) -> String {
@@ -749,7 +1140,7 @@ fn char_is_quote(c: char) -> bool {
 
 #[derive(Clone, Debug)]
 pub(crate) struct BodyParamInfo {
-    openapi_type: OpenApiMimeType,
+    openapi_types: Vec<OpenApiMimeType>,
     type_info: TypeInfo,
 }
 
@@ -758,4 +1149,67 @@ pub(crate) struct RouteParametersInfo {
     parameters: Vec<OpenApiParameter>,
     has_cookies_param: bool,
     body_info: Option<BodyParamInfo>,
+    query_info: Option<BodyParamInfo>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const QUERY_HANDLER: &str = r#"
+use saphir::prelude::*;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct Search {
+    term: String,
+    page: Option<u32>,
+}
+
+struct SearchController;
+
+#[controller(name = "search")]
+impl SearchController {
+    #[get("/")]
+    async fn search(&self, query: Query<Search>) -> String {
+        query.into_inner().term
+    }
+}
+
+fn main() {}
+"#;
+
+    #[test]
+    fn query_fields_are_documented_as_query_parameters() {
+        let project = std::env::temp_dir().join(format!("saphir-cli-query-{}", std::process::id()));
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::write(
+            project.join("Cargo.toml"),
+            "[package]\nname = \"query\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+        )
+        .unwrap();
+        fs::write(project.join("src/main.rs"), QUERY_HANDLER).unwrap();
+
+        let output = project.join("openapi.yaml");
+        let args = GenArgs {
+            scope: "/".to_string(),
+            project_path: project.clone(),
+            output_file: output.clone(),
+            no_cache: true,
+            ..Default::default()
+        };
+        let result = Gen::new(args).run();
+        let doc = fs::read_to_string(&output);
+        fs::remove_dir_all(&project).unwrap();
+        result.unwrap();
+
+        let doc: serde_yaml::Value = serde_yaml::from_str(&doc.unwrap()).unwrap();
+        let parameters = doc["paths"]["/search"]["get"]["parameters"].as_sequence().unwrap();
+        let parameters: Vec<_> = parameters
+            .iter()
+            .map(|p| (p["name"].as_str().unwrap(), p["in"].as_str().unwrap(), p["required"].as_bool().unwrap()))
+            .collect();
+        assert_eq!(parameters, vec![("page", "query", false), ("term", "query", true)]);
+    }
 }
@@ -1,7 +1,8 @@
 use crate::openapi::{
-    generate::{crate_syn_browser::Method, type_info::TypeInfo, Gen},
+    generate::{crate_syn_browser::Method, type_info::TypeInfo, utils::example_from_lit, Gen},
     schema::{OpenApiMimeType, OpenApiSchema},
 };
+use serde_json::Value;
 use syn::{GenericArgument, Lit, Meta, MetaList, NestedMeta, Path, PathArguments, ReturnType, Type};
 
 #[derive(Clone, Debug, Default)]
@@ -10,6 +11,15 @@ pub(crate) struct ResponseInfo {
     pub(crate) type_info: Option<TypeInfo>,
     pub(crate) mime: OpenApiMimeType,
     pub(crate) anonymous_type: Option<AnonymousType>,
+    pub(crate) example: Option<Value>,
+}
+
+/// Response of the handlers returning `()`
+fn no_content() -> ResponseInfo {
+    ResponseInfo {
+        code: 204,
+        ..Default::default()
+    }
 }
 
 #[derive(Clone, Debug, Default)]
@@ -40,9 +50,17 @@ impl Gen {
             vec.extend(self.response_info_from_openapi_meta(method, meta));
         }
 
+        let status = self.handler_status_from_method_macro(method);
         if vec.is_empty() {
-            if let ReturnType::Type(_tokens, t) = &method.syn.sig.output {
-                vec = self.response_info_from_type(method, t);
+            vec = match &method.syn.sig.output {
+                ReturnType::Type(_tokens, t) => self.response_info_from_type(method, t),
+                ReturnType::Default => vec![(None, no_content())],
+            };
+            if let Some(status) = status {
+                for (code, response) in vec.iter_mut().filter(|(_, r)| r.code == 200 || r.code == 204) {
+                    *code = Some(status);
+                    response.code = status;
+                }
             }
         }
 
@@ -54,7 +72,7 @@ impl Gen {
             vec.push((
                 None,
                 ResponseInfo {
-                    code: 200,
+                    code: status.unwrap_or(200),
                     mime: OpenApiMimeType::Any,
                     ..Default::default()
                 },
@@ -77,6 +95,7 @@ impl Gen {
                             let mut types: Vec<String> = Vec::new();
                             let mut mime: Option<String> = None;
                             let mut name: Option<String> = None;
+                            let mut example: Option<Value> = None;
                             if nl.nested.is_empty() {
                                 continue;
                             }
@@ -111,6 +130,7 @@ impl Gen {
                                                 name = Some(s.value());
                                             }
                                         }
+                                        Some("example") => example = example_from_lit(&nv.lit),
                                         _ => {}
                                     }
                                 }
@@ -146,6 +166,7 @@ impl Gen {
                                                 mime: mime.clone().unwrap_or(OpenApiMimeType::Any),
                                                 type_info: None,
                                                 anonymous_type: Some(anonymous_type),
+                                                example: example.clone(),
                                             },
                                         ));
                                         continue;
@@ -160,6 +181,7 @@ impl Gen {
                                             ResponseInfo {
                                                 code,
                                                 mime: mime.clone().unwrap_or(OpenApiMimeType::Any),
+                                                example: example.clone(),
                                                 ..Default::default()
                                             },
                                         ));
@@ -172,6 +194,7 @@ impl Gen {
                                     if let Some(m) = &mime {
                                         r.mime = m.clone();
                                     }
+                                    r.example.clone_from(&example);
                                     (Some(code), r)
                                 }));
                             }
@@ -302,6 +325,9 @@ impl Gen {
             Type::Path(tp) => {
                 return self.response_info_from_type_path(method, &tp.path);
             }
+            Type::Tuple(tt) if tt.elems.is_empty() => {
+                return vec![(None, no_content())];
+            }
             Type::Tuple(_tt) => {
                 // TODO: Tuple with with StatusCode or u16 mean a status
                 //       code is specified for the associated return type.
@@ -361,7 +387,8 @@ impl Gen {
                         }
                     }
                 }
-                "Json" => {
+                // Negotiated responses are documented with their default format
+                "Json" | "Json5" | "Negotiated" => {
                     let mut result = self.extract_arguments(method, &last.arguments);
                     if result.len() == 1 {
                         for (_, mut success_response) in result.remove(0) {
@@ -379,6 +406,24 @@ impl Gen {
                         }
                     }
                 }
+                "MsgPack" => {
+                    let mut result = self.extract_arguments(method, &last.arguments);
+                    if result.len() == 1 {
+                        for (_, mut success_response) in result.remove(0) {
+                            success_response.mime = OpenApiMimeType::MsgPack;
+                            vec.push((None, success_response));
+                        }
+                    }
+                }
+                "Cbor" => {
+                    let mut result = self.extract_arguments(method, &last.arguments);
+                    if result.len() == 1 {
+                        for (_, mut success_response) in result.remove(0) {
+                            success_response.mime = OpenApiMimeType::Cbor;
+                            vec.push((None, success_response));
+                        }
+                    }
+                }
                 // TODO: Find a way to handle this. This is a temp workaround for Lucid
                 "JsonContent" | "NoCache" => {
                     let mut result = self.extract_arguments(method, &last.arguments);
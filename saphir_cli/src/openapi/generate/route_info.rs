@@ -21,8 +21,8 @@ impl Gen {
             .syn
             .attrs
             .iter()
-            .filter_map(|attr| self.handler_method_from_attr(attr).zip(Some(attr)))
-            .filter_map(|(method, attr)| self.handler_path_from_attr(attr).map(|(path, uri_params)| (method, path, uri_params)))
+            .filter_map(|attr| self.handler_path_from_attr(attr).map(|path| (self.handler_methods_from_attr(attr), path)))
+            .flat_map(|(methods, (path, uri_params))| methods.into_iter().map(move |method| (method, path.clone(), uri_params.clone())))
             .collect();
 
         let multi = routes.len() > 1;
@@ -70,9 +70,33 @@ impl Gen {
         .to_case((&self.args.operation_name_case).into())
     }
 
-    fn handler_method_from_attr(&self, attr: &Attribute) -> Option<OpenApiPathMethod> {
-        let ident = attr.path.get_ident()?;
-        OpenApiPathMethod::from_str(ident.to_string().as_str())
+    /// Methods of a saphir route macro, e.g. `#[get(..)]`, or the methods
+    /// listed by `#[route(.., methods(..))]`. Custom methods have no OpenAPI
+    /// operation and are left out.
+    fn handler_methods_from_attr(&self, attr: &Attribute) -> Vec<OpenApiPathMethod> {
+        let ident = match attr.path.get_ident() {
+            Some(ident) => ident.to_string(),
+            None => return Vec::new(),
+        };
+        if ident != "route" {
+            return OpenApiPathMethod::from_str(ident.as_str()).into_iter().collect();
+        }
+        let meta = match attr.parse_meta() {
+            Ok(Meta::List(meta)) => meta,
+            _ => return Vec::new(),
+        };
+        meta.nested
+            .into_iter()
+            .filter_map(|nested| match nested {
+                NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("methods") => Some(list.nested),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|nested| match nested {
+                NestedMeta::Lit(Lit::Str(m)) => OpenApiPathMethod::from_str(m.value().to_lowercase().as_str()),
+                _ => None,
+            })
+            .collect()
     }
 
     fn handler_path_from_attr(&self, attr: &Attribute) -> Option<(String, Vec<String>)> {
@@ -103,4 +127,26 @@ impl Gen {
         }
         None
     }
+
+    /// Status set by the `status` parameter of a saphir route macro, which
+    /// replaces the default success code of the handler.
+    pub(crate) fn handler_status_from_method_macro(&self, method: &Method) -> Option<u16> {
+        method
+            .syn
+            .attrs
+            .iter()
+            .filter(|attr| !self.handler_methods_from_attr(attr).is_empty())
+            .filter_map(|attr| match attr.parse_meta() {
+                Ok(Meta::List(meta)) => Some(meta),
+                _ => None,
+            })
+            .flat_map(|meta| meta.nested.into_iter())
+            .find_map(|nested| match nested {
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("status") => match nv.lit {
+                    Lit::Int(i) => i.base10_parse().ok(),
+                    _ => None,
+                },
+                _ => None,
+            })
+    }
 }
@@ -0,0 +1,98 @@
+use crate::openapi::{
+    generate::{
+        crate_syn_browser::Method,
+        utils::{find_macro_attribute_named_value, get_controller_guard_type_paths, get_guard_type_paths},
+        Gen,
+    },
+    schema::{OpenApiParameterLocation, OpenApiSecurityScheme},
+};
+use syn::{Attribute, Item as SynItem, Lit};
+
+/// Security scheme of a guard annotated with `#[openapi(security = "...")]`
+#[derive(Clone, Debug)]
+pub(crate) struct SecurityInfo {
+    pub(crate) guard_name: String,
+    pub(crate) name: String,
+    pub(crate) scheme: OpenApiSecurityScheme,
+}
+
+impl SecurityInfo {
+    /// Read the security scheme described by the `openapi` attribute of a
+    /// guard. The scheme is named after the guard type unless
+    /// `security_name` is specified.
+    ///
+    /// Supported schemes are :
+    /// - `bearer` : http bearer authentication, with an optional
+    ///   `bearer_format`
+    /// - `basic` : http basic authentication
+    /// - `api_key` : key sent in the header, cookie or query parameter named
+    ///   `key_name`, according to `location` (defaults to `header`)
+    pub(crate) fn from_attributes(guard_name: &str, attrs: &[Attribute]) -> Option<Self> {
+        let str_value = |name: &str| match find_macro_attribute_named_value(attrs, "openapi", name) {
+            Some(Lit::Str(s)) => Some(s.value()),
+            _ => None,
+        };
+
+        let security = str_value("security")?;
+        let scheme = match security.as_str() {
+            "bearer" => OpenApiSecurityScheme::Http {
+                scheme: "bearer".to_string(),
+                bearer_format: str_value("bearer_format"),
+            },
+            "basic" => OpenApiSecurityScheme::Http {
+                scheme: "basic".to_string(),
+                bearer_format: None,
+            },
+            "api_key" => {
+                let location = match str_value("location").as_deref() {
+                    None | Some("header") => OpenApiParameterLocation::Header,
+                    Some("cookie") => OpenApiParameterLocation::Cookie,
+                    Some("query") => OpenApiParameterLocation::Query,
+                    Some(l) => {
+                        println!("{} has an unsupported api key location `{}`, expected header, cookie or query", guard_name, l);
+                        return None;
+                    }
+                };
+                let name = match str_value("key_name") {
+                    Some(name) => name,
+                    None => {
+                        println!("{} is an api_key security scheme but does not specify its `key_name`", guard_name);
+                        return None;
+                    }
+                };
+                OpenApiSecurityScheme::ApiKey { name, location }
+            }
+            s => {
+                println!("{} has an unsupported security scheme `{}`, expected bearer, basic or api_key", guard_name, s);
+                return None;
+            }
+        };
+
+        Some(SecurityInfo {
+            guard_name: guard_name.to_string(),
+            name: str_value("security_name").unwrap_or_else(|| guard_name.to_string()),
+            scheme,
+        })
+    }
+}
+
+impl Gen {
+    /// Security schemes of the guards protecting a handler, including the
+    /// guards of its controller
+    pub(crate) fn extract_security_info<'b>(&self, method: &'b Method<'b>) -> Vec<SecurityInfo> {
+        let scope = method.impl_item.im.item.scope;
+        get_controller_guard_type_paths(&method.impl_item.im.syn.attrs)
+            .iter()
+            .chain(get_guard_type_paths(&method.syn.attrs).iter())
+            .filter_map(|path| {
+                let item = scope.find_type_definition(path.as_str()).ok().flatten()?;
+                let (name, attrs) = match item.item {
+                    SynItem::Struct(s) => (s.ident.to_string(), &s.attrs),
+                    SynItem::Enum(e) => (e.ident.to_string(), &e.attrs),
+                    _ => return None,
+                };
+                SecurityInfo::from_attributes(name.as_str(), attrs)
+            })
+            .collect()
+    }
+}
@@ -1,5 +1,6 @@
 use convert_case::{Case, Casing};
-use syn::{Attribute, Lit, Meta, NestedMeta};
+use serde_json::Value;
+use syn::{Attribute, Lit, Meta, MetaList, MetaNameValue, NestedMeta};
 
 pub(crate) fn case_from_serde_rename_str(case_name: &str) -> Option<Case> {
     match case_name {
@@ -29,6 +30,26 @@ pub(crate) fn get_serde_field(mut field_name: String, field_attributes: &[Attrib
     Some(field_name)
 }
 
+/// Value of an `example = ...` openapi attribute. Strings holding a json
+/// object or array are embedded as such.
+pub(crate) fn example_from_lit(lit: &Lit) -> Option<Value> {
+    match lit {
+        Lit::Str(s) => {
+            let s = s.value();
+            if s.trim_start().starts_with(['{', '[']) {
+                if let Ok(value) = serde_json::from_str(&s) {
+                    return Some(value);
+                }
+            }
+            Some(Value::String(s))
+        }
+        Lit::Int(i) => i.base10_parse::<i64>().ok().map(Value::from),
+        Lit::Float(f) => f.base10_parse::<f64>().ok().map(Value::from),
+        Lit::Bool(b) => Some(Value::Bool(b.value)),
+        _ => None,
+    }
+}
+
 pub(crate) fn find_macro_attribute_flag(attrs: &[Attribute], macro_name: &str, value_name: &str) -> bool {
     for attr in attrs
         .iter()
@@ -57,10 +78,8 @@ pub(crate) fn find_macro_attribute_flag_from_meta(meta: &Meta, value_name: &str)
                 }
             }
         }
-        Meta::Path(p) => {
-            if p.get_ident().map(|i| i.to_string()).filter(|s| s == value_name).is_some() {
-                return true;
-            }
+        Meta::Path(p) if p.get_ident().map(|i| i.to_string()).filter(|s| s == value_name).is_some() => {
+            return true;
         }
         _ => {}
     }
@@ -96,12 +115,69 @@ pub(crate) fn find_macro_attribute_value_from_meta(meta: &Meta, value_name: &str
                 }
             }
         }
-        Meta::NameValue(nv) => {
-            if nv.path.get_ident().map(|i| i.to_string()).filter(|s| s == value_name).is_some() {
-                return Some(nv.lit.clone());
-            }
+        Meta::NameValue(nv) if nv.path.get_ident().map(|i| i.to_string()).filter(|s| s == value_name).is_some() => {
+            return Some(nv.lit.clone());
         }
         _ => {}
     }
     None
 }
+
+/// Type paths of the guards listed in `#[guard(...)]` attributes
+pub(crate) fn get_guard_type_paths(attrs: &[Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|a| a.path.get_ident().filter(|i| i.to_string().as_str() == "guard").is_some())
+        .filter_map(|a| match a.parse_meta() {
+            Ok(Meta::List(l)) => get_guard_type_path(&l),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Type paths of the guards listed in the `#[controller(guard(...))]`
+/// attribute, which apply to every handler of the controller
+pub(crate) fn get_controller_guard_type_paths(attrs: &[Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|a| a.path.get_ident().filter(|i| i.to_string().as_str() == "controller").is_some())
+        .filter_map(|a| match a.parse_meta() {
+            Ok(Meta::List(l)) => Some(l),
+            _ => None,
+        })
+        .flat_map(|l| l.nested.into_iter())
+        .filter_map(|n| match n {
+            NestedMeta::Meta(Meta::List(l)) if l.path.is_ident("guard") => get_guard_type_path(&l),
+            _ => None,
+        })
+        .collect()
+}
+
+fn get_guard_type_path(guard: &MetaList) -> Option<String> {
+    guard.nested.iter().find_map(|n| match n {
+        NestedMeta::Meta(Meta::Path(p)) => Some(p.segments.iter().map(|s| s.ident.to_string()).collect::<Vec<_>>().join("::")),
+        _ => None,
+    })
+}
+
+/// Content of the `///` doc comments of an item, without the leading space of
+/// each line. Empty doc comments are ignored.
+pub(crate) fn get_doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|a| a.path.get_ident().filter(|i| i.to_string().as_str() == "doc").is_some())
+        .filter_map(|a| match a.parse_meta() {
+            Ok(Meta::NameValue(MetaNameValue { lit: Lit::Str(s), .. })) => {
+                let line = s.value();
+                Some(line.strip_prefix(' ').map(str::to_string).unwrap_or(line))
+            }
+            _ => None,
+        })
+        .collect();
+    let doc = lines.join("\n").trim().to_string();
+    if doc.is_empty() {
+        None
+    } else {
+        Some(doc)
+    }
+}
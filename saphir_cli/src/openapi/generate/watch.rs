@@ -0,0 +1,74 @@
+use crate::{
+    openapi::generate::{Gen, GenArgs},
+    Command, CommandResult,
+};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::Path,
+    sync::mpsc::{channel, Receiver, RecvTimeoutError},
+    time::Duration,
+};
+
+/// Delay without changes after which a burst of saves is considered done
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+impl Gen {
+    /// Generate the documentation again each time a source file of the
+    /// project changes. Errors are printed, the watcher running until the
+    /// process is stopped.
+    pub(crate) fn watch(args: GenArgs) -> CommandResult {
+        let project_path = args
+            .project_path
+            .canonicalize()
+            .map_err(|_| format!("Unable to find the project root `{:?}`", args.project_path))?;
+        let target_dir = project_path.join("target");
+        let (tx, rx) = channel();
+        let mut watcher = notify::recommended_watcher(tx).map_err(|e| format!("Unable to watch the project : {}", e))?;
+        watcher
+            .watch(&project_path, RecursiveMode::Recursive)
+            .map_err(|e| format!("Unable to watch `{:?}` : {}", project_path, e))?;
+
+        loop {
+            let gen = Gen::new(args.clone());
+            // Panics are reported by the panic hook
+            if let Ok(Err(e)) = catch_unwind(AssertUnwindSafe(|| gen.run())) {
+                eprintln!("{}", e);
+            }
+            println!("Watching `{}` for changes...", project_path.to_str().unwrap_or_default());
+            wait_for_change(&rx, &target_dir)?;
+        }
+    }
+}
+
+/// Wait for a change of a source file, then for the following ones until
+/// none happened for the `DEBOUNCE` delay
+fn wait_for_change(rx: &Receiver<notify::Result<Event>>, target_dir: &Path) -> CommandResult {
+    let is_source_change = |event: notify::Result<Event>| match event {
+        Ok(event) => {
+            matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_))
+                && event
+                    .paths
+                    .iter()
+                    .any(|path| !path.starts_with(target_dir) && (path.extension().filter(|ext| *ext == "rs").is_some() || path.ends_with("Cargo.toml")))
+        }
+        Err(e) => {
+            eprintln!("Unable to watch the project : {}", e);
+            false
+        }
+    };
+
+    loop {
+        let event = rx.recv().map_err(|_| "The project is no longer watched".to_string())?;
+        if is_source_change(event) {
+            break;
+        }
+    }
+    loop {
+        match rx.recv_timeout(DEBOUNCE) {
+            Ok(_) => {}
+            Err(RecvTimeoutError::Timeout) => return Ok(()),
+            Err(RecvTimeoutError::Disconnected) => return Err("The project is no longer watched".to_string()),
+        }
+    }
+}
@@ -32,6 +32,7 @@ impl Command for Openapi {
 
     fn run<'b>(self) -> CommandResult {
         match self.args.cmd {
+            OpenapiCommand::Gen(args) if args.watch => Gen::watch(args)?,
             OpenapiCommand::Gen(args) => {
                 let gen = Gen::new(args);
                 gen.run()?;
@@ -17,12 +17,16 @@ pub enum OpenApiParameterLocation {
     #[default]
     Path,
     Query,
+    Header,
+    Cookie,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Default)]
 pub enum OpenApiMimeType {
     Json,
     Form,
+    MsgPack,
+    Cbor,
     #[default]
     Any,
     Other(String),
@@ -33,6 +37,8 @@ impl From<String> for OpenApiMimeType {
         match s.as_str() {
             "json" | "application/json" => OpenApiMimeType::Json,
             "form" | "application/x-www-form-urlencoded" => OpenApiMimeType::Form,
+            "msgpack" | "application/msgpack" => OpenApiMimeType::MsgPack,
+            "cbor" | "application/cbor" => OpenApiMimeType::Cbor,
             "any" | "*/*" => OpenApiMimeType::Any,
             _ => OpenApiMimeType::Other(s),
         }
@@ -47,6 +53,8 @@ impl ImplSerialize for OpenApiMimeType {
         serializer.serialize_str(match self {
             OpenApiMimeType::Json => "application/json",
             OpenApiMimeType::Form => "application/x-www-form-urlencoded",
+            OpenApiMimeType::MsgPack => "application/msgpack",
+            OpenApiMimeType::Cbor => "application/cbor",
             OpenApiMimeType::Any => "*/*",
             OpenApiMimeType::Other(s) => s.as_str(),
         })
@@ -69,6 +77,8 @@ impl<'de> Visitor<'de> for OpenApiMimeTypeVisitor {
         Ok(match value {
             "application/json" => OpenApiMimeType::Json,
             "application/x-www-form-urlencoded" => OpenApiMimeType::Form,
+            "application/msgpack" => OpenApiMimeType::MsgPack,
+            "application/cbor" => OpenApiMimeType::Cbor,
             "*/*" => OpenApiMimeType::Any,
             s => OpenApiMimeType::Other(s.to_owned()),
         })
@@ -81,6 +91,8 @@ impl<'de> Visitor<'de> for OpenApiMimeTypeVisitor {
         Ok(match value.as_str() {
             "application/json" => OpenApiMimeType::Json,
             "application/x-www-form-urlencoded" => OpenApiMimeType::Form,
+            "application/msgpack" => OpenApiMimeType::MsgPack,
+            "application/cbor" => OpenApiMimeType::Cbor,
             "*/*" => OpenApiMimeType::Any,
             _ => OpenApiMimeType::Other(value),
         })
@@ -143,12 +155,14 @@ pub enum OpenApiObjectType {
         properties: BTreeMap<String, Box<OpenApiSchema>>,
         #[serde(skip_serializing_if = "Vec::is_empty")]
         required: Vec<String>,
-        #[serde(rename = "additionalProperties", default)]
-        additional_properties: bool,
+        #[serde(rename = "additionalProperties", skip_serializing_if = "Option::is_none", default)]
+        additional_properties: Option<bool>,
     },
     Dictionary {
         #[serde(skip_serializing_if = "BTreeMap::is_empty")]
         properties: BTreeMap<String, Box<OpenApiSchema>>,
+        #[serde(skip_serializing_if = "Vec::is_empty", default)]
+        required: Vec<String>,
         #[serde(rename = "additionalProperties")]
         additional_properties: Box<OpenApiSchema>,
     },
@@ -234,7 +248,7 @@ impl OpenApiType {
             object: OpenApiObjectType::Object {
                 properties,
                 required,
-                additional_properties: false,
+                additional_properties: Some(false),
             },
         }
     }
@@ -362,6 +376,9 @@ pub struct OpenApiPath {
     #[serde(rename = "x-codegen-request-body-name", skip_serializing_if = "Option::is_none")]
     pub(crate) x_codegen_request_body_name: Option<String>,
     pub(crate) responses: BTreeMap<String, OpenApiResponse>,
+    // Key: security scheme name / scopes
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) security: Vec<BTreeMap<String, Vec<String>>>,
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -387,6 +404,18 @@ pub struct OpenApiRequestBody {
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct OpenApiContent {
     pub(crate) schema: OpenApiSchema,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) example: Option<serde_json::Value>,
+}
+
+/// Property telling which of the `oneOf` schemas a value matches, with the
+/// schema of each of its values
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct OpenApiDiscriminator {
+    #[serde(rename = "propertyName")]
+    pub(crate) property_name: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub(crate) mapping: BTreeMap<String, String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
@@ -397,12 +426,138 @@ pub enum OpenApiSchema {
         type_ref: String,
     },
     Inline(OpenApiType),
+    /// Serialized as `allOf: [{ $ref }]`, since `$ref` can't have siblings
+    DescribedRef {
+        #[serde(rename = "allOf", with = "all_of_ref")]
+        type_ref: String,
+        description: String,
+    },
+    DescribedInline {
+        description: String,
+        #[serde(flatten)]
+        schema: OpenApiType,
+    },
+    ExampleInline {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        example: serde_json::Value,
+        #[serde(flatten)]
+        schema: OpenApiType,
+    },
+    OneOf {
+        #[serde(rename = "oneOf")]
+        one_of: Vec<OpenApiSchema>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        discriminator: Option<OpenApiDiscriminator>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+    },
+    AnyOf {
+        #[serde(rename = "anyOf")]
+        any_of: Vec<OpenApiSchema>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+    },
 }
+mod all_of_ref {
+    use super::{de::Error, Deserialize, Deserializer, ImplDeserialize, ImplSerialize, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Ref {
+        #[serde(rename = "$ref")]
+        type_ref: String,
+    }
+
+    pub fn serialize<S: Serializer>(type_ref: &str, serializer: S) -> Result<S::Ok, S::Error> {
+        [Ref {
+            type_ref: type_ref.to_string(),
+        }]
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+        match <Vec<Ref>>::deserialize(deserializer)?.as_mut_slice() {
+            [r] => Ok(std::mem::take(&mut r.type_ref)),
+            _ => Err(D::Error::custom("expected a single schema reference")),
+        }
+    }
+}
+
 impl Default for OpenApiSchema {
     fn default() -> Self {
         OpenApiSchema::Inline(OpenApiType::default())
     }
 }
+impl OpenApiSchema {
+    pub fn with_description(self, description: String) -> Self {
+        match self {
+            OpenApiSchema::Ref { type_ref } | OpenApiSchema::DescribedRef { type_ref, .. } => OpenApiSchema::DescribedRef { type_ref, description },
+            OpenApiSchema::Inline(schema) | OpenApiSchema::DescribedInline { schema, .. } => OpenApiSchema::DescribedInline { description, schema },
+            OpenApiSchema::ExampleInline { example, schema, .. } => OpenApiSchema::ExampleInline {
+                description: Some(description),
+                example,
+                schema,
+            },
+            OpenApiSchema::OneOf { one_of, discriminator, .. } => OpenApiSchema::OneOf {
+                one_of,
+                discriminator,
+                description: Some(description),
+            },
+            OpenApiSchema::AnyOf { any_of, .. } => OpenApiSchema::AnyOf {
+                any_of,
+                description: Some(description),
+            },
+        }
+    }
+
+    pub fn split_description(self) -> (Self, Option<String>) {
+        match self {
+            OpenApiSchema::DescribedRef { type_ref, description } => (OpenApiSchema::Ref { type_ref }, Some(description)),
+            OpenApiSchema::DescribedInline { description, schema } => (OpenApiSchema::Inline(schema), Some(description)),
+            OpenApiSchema::ExampleInline { description, example, schema } => (
+                OpenApiSchema::ExampleInline {
+                    description: None,
+                    example,
+                    schema,
+                },
+                description,
+            ),
+            OpenApiSchema::OneOf {
+                one_of,
+                discriminator,
+                description,
+            } => (
+                OpenApiSchema::OneOf {
+                    one_of,
+                    discriminator,
+                    description: None,
+                },
+                description,
+            ),
+            OpenApiSchema::AnyOf { any_of, description } => (OpenApiSchema::AnyOf { any_of, description: None }, description),
+            s => (s, None),
+        }
+    }
+
+    /// Attach an example to an inline schema. The examples of referenced
+    /// schemas belong to their component, as references cannot have siblings.
+    pub fn with_example(self, example: serde_json::Value) -> Self {
+        match self {
+            OpenApiSchema::Inline(schema) => OpenApiSchema::ExampleInline {
+                description: None,
+                example,
+                schema,
+            },
+            OpenApiSchema::DescribedInline { description, schema } => OpenApiSchema::ExampleInline {
+                description: Some(description),
+                example,
+                schema,
+            },
+            OpenApiSchema::ExampleInline { description, schema, .. } => OpenApiSchema::ExampleInline { description, example, schema },
+            s => s,
+        }
+    }
+}
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct OpenApiResponse {
@@ -411,13 +566,33 @@ pub struct OpenApiResponse {
     pub(crate) content: BTreeMap<OpenApiMimeType, OpenApiContent>,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(tag = "type")]
+pub enum OpenApiSecurityScheme {
+    #[serde(rename = "http")]
+    Http {
+        scheme: String,
+        #[serde(rename = "bearerFormat", skip_serializing_if = "Option::is_none")]
+        bearer_format: Option<String>,
+    },
+    #[serde(rename = "apiKey")]
+    ApiKey {
+        name: String,
+        #[serde(rename = "in")]
+        location: OpenApiParameterLocation,
+    },
+}
+
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct OpenApiComponents {
     #[serde(skip_serializing_if = "BTreeMap::is_empty")]
     pub(crate) schemas: BTreeMap<String, OpenApiSchema>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) security_schemes: BTreeMap<String, OpenApiSecurityScheme>,
 }
 impl OpenApiComponents {
     pub fn is_empty(&self) -> bool {
-        self.schemas.is_empty()
+        self.schemas.is_empty() && self.security_schemes.is_empty()
     }
 }
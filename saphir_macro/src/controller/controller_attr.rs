@@ -1,4 +1,4 @@
-use crate::controller::handler::{HandlerAttrs, HandlerRepr};
+use crate::controller::handler::{GuardDef, HandlerAttrs, HandlerRepr};
 use proc_macro2::{Ident, TokenStream};
 use syn::{AttributeArgs, Error, ItemImpl, Lit, Meta, MetaNameValue, NestedMeta, Result};
 
@@ -10,6 +10,7 @@ pub struct ControllerAttr {
     pub name: String,
     pub version: Option<u16>,
     pub prefix: Option<String>,
+    pub guards: Vec<GuardDef>,
 }
 
 impl ControllerAttr {
@@ -17,14 +18,20 @@ impl ControllerAttr {
         let mut name = None;
         let mut version = None;
         let mut prefix = None;
+        let mut guards = Vec::new();
 
         let ident = crate::utils::parse_item_impl_ident(input)?;
 
         for m in args.into_iter().filter_map(|a| if let NestedMeta::Meta(m) = a { Some(m) } else { None }) {
             match m {
+                // Read by saphir-cli when generating the OpenAPI documentation
+                Meta::Path(p) if p.is_ident("openapi_hidden") => {}
                 Meta::Path(p) => {
                     return Err(Error::new_spanned(p, "Unexpected Attribute on controller impl"));
                 }
+                Meta::List(l) if l.path.is_ident("guard") => {
+                    guards.push(GuardDef::new(&l)?);
+                }
                 Meta::List(l) => {
                     return Err(Error::new_spanned(l, "Unexpected Attribute on controller impl"));
                 }
@@ -48,6 +55,8 @@ impl ControllerAttr {
                     (Some("prefix"), Lit::Str(p)) => {
                         prefix = Some(p.value().trim_matches('/').to_string());
                     }
+                    // Read by saphir-cli when generating the OpenAPI documentation
+                    (Some("openapi_tag" | "openapi_tag_description"), Lit::Str(_)) => {}
                     _ => {
                         return Err(Error::new_spanned(path, "Unexpected Param in controller macro"));
                     }
@@ -62,6 +71,7 @@ impl ControllerAttr {
             name,
             version,
             prefix,
+            guards,
         })
     }
 }
@@ -98,8 +108,10 @@ fn gen_controller_base_path_const(attr: &ControllerAttr) -> TokenStream {
 
     path.push_str(attr.name.as_str());
 
+    let name = attr.name.as_str();
     let e = quote! {
         const BASE_PATH: &'static str = #path;
+        const NAME: Option<&'static str> = Some(#name);
     };
 
     e
@@ -113,6 +125,10 @@ fn gen_controller_handlers_fn(attr: &ControllerAttr, handlers: &[HandlerRepr]) -
         let HandlerAttrs { methods_paths, guards, .. } = &handler.attrs;
         let handler_ident = &handler.original_method.sig.ident;
 
+        // Controller guards run before the handler's own guards. The last guard
+        // applied to the chain is the first one to run, hence the reverse order.
+        let guards: Vec<&GuardDef> = attr.guards.iter().chain(guards.iter()).rev().collect();
+
         for (method, path) in methods_paths {
             let method = method.as_str();
             let handler_name = handler_ident.to_string();
@@ -124,7 +140,7 @@ fn gen_controller_handlers_fn(attr: &ControllerAttr, handlers: &[HandlerRepr]) -
             } else {
                 let mut guard_stream = TokenStream::new();
 
-                for guard_def in guards {
+                for guard_def in &guards {
                     (quote! {
                         .apply(#guard_def)
                     })
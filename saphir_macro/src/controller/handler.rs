@@ -3,21 +3,27 @@ use proc_macro2::{Ident, TokenStream};
 use quote::{quote_spanned, ToTokens};
 use std::str::FromStr;
 use syn::{
-    spanned::Spanned, Attribute, Error, Expr, FnArg, GenericArgument, ImplItem, ImplItemMethod, ItemImpl, Lit, Meta, MetaNameValue, NestedMeta, Pat, PatIdent,
-    PatType, Path, PathArguments, PathSegment, Result, ReturnType, Type, TypePath,
+    spanned::Spanned, Attribute, Error, Expr, FnArg, GenericArgument, ImplItem, ImplItemMethod, ItemImpl, Lit, Meta, MetaList, MetaNameValue, NestedMeta, Pat,
+    PatIdent, PatType, Path, PathArguments, PathSegment, Result, ReturnType, Type, TypePath,
 };
 
 #[derive(Clone, Debug)]
 pub enum MapAfterLoad {
     Json,
+    Json5,
     Form,
+    MsgPack,
+    Cbor,
 }
 
 impl MapAfterLoad {
     pub fn new(i: &Ident) -> Option<Self> {
         match i.to_string().as_str() {
             "Json" => Some(MapAfterLoad::Json),
+            "Json5" => Some(MapAfterLoad::Json5),
             "Form" => Some(MapAfterLoad::Form),
+            "MsgPack" => Some(MapAfterLoad::MsgPack),
+            "Cbor" => Some(MapAfterLoad::Cbor),
             _ => None,
         }
     }
@@ -28,11 +34,17 @@ pub enum ArgsReprType {
     SelfType,
     Request,
     Json,
+    Json5,
     Form,
+    MsgPack,
+    Cbor,
+    AnyBody,
     Multipart,
-    Params { is_query_param: bool, is_string: bool },
+    Params { is_query_param: bool, is_string: bool, is_vec: bool },
     Cookie,
+    Extractor,
     Ext,
+    Extension,
     Extensions,
     Option(Box<ArgsReprType>),
 }
@@ -44,7 +56,14 @@ impl ArgsReprType {
             "Request" => Ok(ArgsReprType::Request),
             "CookieJar" => Ok(ArgsReprType::Cookie),
             "Json" => Ok(ArgsReprType::Json),
+            "Json5" => Ok(ArgsReprType::Json5),
             "Form" => Ok(ArgsReprType::Form),
+            "MsgPack" => Ok(ArgsReprType::MsgPack),
+            "Cbor" => Ok(ArgsReprType::Cbor),
+            "AnyBody" | "MultipartForm" | "Query" => Ok(ArgsReprType::AnyBody),
+            "ConnectionInfo" | "RequestId" | "RawBody" | "TempFile" | "JsonLines" | "MatchedRoute" | "RequestStart" | "Lang" | "Cookies" | "Precondition" => {
+                Ok(ArgsReprType::Extractor)
+            }
             "Multipart" => Ok(ArgsReprType::Multipart),
             "Ext" => Ok(ArgsReprType::Ext),
             "Extensions" => Ok(ArgsReprType::Extensions),
@@ -62,9 +81,11 @@ impl ArgsReprType {
                 }
                 Err(Error::new_spanned(p, "Invalid option type"))
             }
+            _ if attrs.extensions.iter().any(|e| e == name) => Ok(ArgsReprType::Extension),
             _params => Ok(ArgsReprType::Params {
                 is_query_param: !attrs.methods_paths.iter().any(|(_, path)| path.contains(&format!("<{}>", name))),
                 is_string: typ_ident_str.eq("String"),
+                is_vec: typ_ident_str.eq("Vec"),
             }),
         }
     }
@@ -79,6 +100,9 @@ pub struct ArgsRepr {
     pub validated: bool,
     #[cfg(feature = "validate-requests")]
     pub is_vec: bool,
+    /// Whether the validated payload is an `Option`, e.g. `Json<Option<T>>`
+    #[cfg(feature = "validate-requests")]
+    pub is_option: bool,
 }
 
 impl ArgsRepr {
@@ -101,6 +125,8 @@ impl ArgsRepr {
                     validated: false,
                     #[cfg(feature = "validate-requests")]
                     is_vec: false,
+                    #[cfg(feature = "validate-requests")]
+                    is_option: false,
                 })
             }
             FnArg::Typed(t) => match t.pat.as_ref() {
@@ -130,7 +156,10 @@ impl ArgsRepr {
             {
                 let validated = !attrs.validator_exclusions.contains(&name);
                 let mut is_vec = false;
+                let mut is_option = false;
 
+                // `Option<Json<T>>` is validated like `Json<T>`, when present
+                let p = if p.ident == "Option" { first_type_segment(p).unwrap_or(p) } else { p };
                 let typ_ident_str = p.ident.to_string();
                 let mut validated_type = None;
 
@@ -139,7 +168,15 @@ impl ArgsRepr {
                         if let PathArguments::AngleBracketed(a) = &p.arguments {
                             let a = a.args.first().ok_or_else(|| Error::new_spanned(a, "Json types need an type argument"))?;
                             if let GenericArgument::Type(Type::Path(t)) = a {
-                                validated_type = t.path.segments.first().map(|p2| p2.ident.to_string());
+                                validated_type = t.path.segments.first();
+                            }
+                        }
+                    }
+                    "Json5" => {
+                        if let PathArguments::AngleBracketed(a) = &p.arguments {
+                            let a = a.args.first().ok_or_else(|| Error::new_spanned(a, "Json5 types need an type argument"))?;
+                            if let GenericArgument::Type(Type::Path(t)) = a {
+                                validated_type = t.path.segments.first();
                             }
                         }
                     }
@@ -147,14 +184,43 @@ impl ArgsRepr {
                         if let PathArguments::AngleBracketed(a) = &p.arguments {
                             let a = a.args.first().ok_or_else(|| Error::new_spanned(a, "Form types need an type argument"))?;
                             if let GenericArgument::Type(Type::Path(t)) = a {
-                                validated_type = t.path.segments.first().map(|p2| p2.ident.to_string());
+                                validated_type = t.path.segments.first();
+                            }
+                        }
+                    }
+                    "MsgPack" => {
+                        if let PathArguments::AngleBracketed(a) = &p.arguments {
+                            let a = a.args.first().ok_or_else(|| Error::new_spanned(a, "MsgPack types need an type argument"))?;
+                            if let GenericArgument::Type(Type::Path(t)) = a {
+                                validated_type = t.path.segments.first();
+                            }
+                        }
+                    }
+                    "Cbor" => {
+                        if let PathArguments::AngleBracketed(a) = &p.arguments {
+                            let a = a.args.first().ok_or_else(|| Error::new_spanned(a, "Cbor types need an type argument"))?;
+                            if let GenericArgument::Type(Type::Path(t)) = a {
+                                validated_type = t.path.segments.first();
+                            }
+                        }
+                    }
+                    "AnyBody" | "MultipartForm" | "Query" => {
+                        if let PathArguments::AngleBracketed(a) = &p.arguments {
+                            let a = a.args.first().ok_or_else(|| Error::new_spanned(a, "Body types need an type argument"))?;
+                            if let GenericArgument::Type(Type::Path(t)) = a {
+                                validated_type = t.path.segments.first();
                             }
                         }
                     }
                     _ => (),
                 };
 
-                if let Some("Vec") = validated_type.as_deref() {
+                if let Some(option) = validated_type.filter(|t| t.ident == "Option") {
+                    is_option = true;
+                    validated_type = first_type_segment(option);
+                }
+
+                if validated_type.is_some_and(|t| t.ident == "Vec") {
                     is_vec = true;
                 }
 
@@ -164,6 +230,7 @@ impl ArgsRepr {
                     a_type,
                     validated,
                     is_vec,
+                    is_option,
                 });
             }
 
@@ -186,6 +253,18 @@ impl ArgsRepr {
     }
 }
 
+/// First segment of the first type argument of `p`, e.g. `Vec` for `Json<Vec<T>>`
+#[cfg(feature = "validate-requests")]
+fn first_type_segment(p: &PathSegment) -> Option<&PathSegment> {
+    if let PathArguments::AngleBracketed(a) = &p.arguments {
+        if let Some(GenericArgument::Type(Type::Path(t))) = a.args.first() {
+            return t.path.segments.first();
+        }
+    }
+
+    None
+}
+
 #[derive(Clone, Debug)]
 pub struct HandlerWrapperOpt {
     pub sync_handler: bool,
@@ -197,6 +276,7 @@ pub struct HandlerWrapperOpt {
     pub map_multipart: bool,
     pub take_body_as: Option<TypePath>,
     pub map_after_load: Option<MapAfterLoad>,
+    pub status: Option<u16>,
     pub fn_arguments: Vec<ArgsRepr>,
 }
 
@@ -218,6 +298,13 @@ impl HandlerWrapperOpt {
 
         let fn_arguments = m.sig.inputs.iter().map(|fn_a| ArgsRepr::new(attrs, fn_a)).collect::<Result<Vec<ArgsRepr>>>()?;
 
+        if let Some(unknown) = attrs.extensions.iter().find(|e| !fn_arguments.iter().any(|a| &a.name == *e)) {
+            return Err(Error::new_spanned(
+                &m.sig,
+                format!("`{}` is listed in the ext attribute but is not a parameter of this handler", unknown),
+            ));
+        }
+
         fn_arguments.iter().for_each(|a_repr| match &a_repr.a_type {
             ArgsReprType::Cookie => parse_cookies = true,
             ArgsReprType::Params { is_query_param: true, .. } => parse_query = true,
@@ -301,6 +388,7 @@ impl HandlerWrapperOpt {
             map_multipart,
             take_body_as,
             map_after_load,
+            status: attrs.status,
             fn_arguments,
         })
     }
@@ -312,11 +400,12 @@ impl HandlerWrapperOpt {
             || self.take_body_as.is_some()
             || self.parse_query
             || self.parse_cookies
+            || self.status.is_some()
             || self.fn_arguments.len() > 2
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct GuardDef {
     pub guard_type: Path,
     pub init_data: Option<Lit>,
@@ -324,6 +413,63 @@ pub struct GuardDef {
     pub init_fn: Option<Path>,
 }
 
+impl GuardDef {
+    /// Parse a `guard(...)` attribute
+    pub fn new(attribute: &MetaList) -> Result<Self> {
+        let mut guard_type_path = None;
+        let mut init_fn = None;
+        let mut init_data = None;
+        let mut init_expr = None;
+
+        for guard_meta in attribute.nested.iter() {
+            if let NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit, .. })) = guard_meta {
+                let path = path
+                    .segments
+                    .first()
+                    .ok_or_else(|| Error::new_spanned(path, "Missing parameters in guard attributes"))?;
+                match path.ident.to_string().as_str() {
+                    "init_fn" => {
+                        if let Lit::Str(lit_str) = lit {
+                            init_fn = Some(
+                                syn::parse_str::<Path>(lit_str.value().as_str()).map_err(|_e| Error::new_spanned(path, "Expected path to a guard function"))?,
+                            );
+                        } else {
+                            return Err(Error::new_spanned(lit, "Expected path to a guard function"));
+                        }
+                    }
+
+                    "init_data" => {
+                        init_data = Some(lit.clone());
+                    }
+
+                    "init_expr" => {
+                        if let Lit::Str(lit_str) = lit {
+                            init_expr = Some(
+                                syn::parse_str::<Expr>(lit_str.value().as_str()).map_err(|_e| Error::new_spanned(path, "Expected a valid rust expression"))?,
+                            );
+                        } else {
+                            return Err(Error::new_spanned(lit, "Expected a string of a valid rust expression"));
+                        }
+                    }
+
+                    _ => {
+                        return Err(Error::new_spanned(path, "Unauthorized param in guard macro"));
+                    }
+                }
+            } else if let NestedMeta::Meta(Meta::Path(p)) = guard_meta {
+                guard_type_path = Some(p.clone());
+            }
+        }
+
+        Ok(GuardDef {
+            guard_type: guard_type_path.ok_or_else(|| Error::new_spanned(attribute, "Missing guard"))?,
+            init_data,
+            init_fn,
+            init_expr,
+        })
+    }
+}
+
 impl ToTokens for GuardDef {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         let span = tokens.span();
@@ -360,6 +506,8 @@ pub struct HandlerAttrs {
     pub methods_paths: Vec<(Method, String)>,
     pub guards: Vec<GuardDef>,
     pub cookie: bool,
+    pub extensions: Vec<String>,
+    pub status: Option<u16>,
     #[cfg(feature = "validate-requests")]
     pub validator_exclusions: Vec<String>,
 }
@@ -376,10 +524,9 @@ impl HandlerRepr {
     pub fn new(mut m: ImplItemMethod) -> Result<Self> {
         let attrs = HandlerAttrs::new(std::mem::take(&mut m.attrs), &m)?;
         let wrapper_options = HandlerWrapperOpt::new(&attrs, &m)?;
-        let return_type = if let ReturnType::Type(_0, typ) = &m.sig.output {
-            typ.clone()
-        } else {
-            return Err(Error::new_spanned(m.sig, "Invalid handler return type"));
+        let return_type = match &m.sig.output {
+            ReturnType::Type(_, typ) => typ.clone(),
+            ReturnType::Default => Box::new(syn::parse_quote!(())),
         };
         Ok(HandlerRepr {
             attrs,
@@ -396,6 +543,8 @@ impl HandlerAttrs {
             methods_paths: Vec::with_capacity(capacity),
             guards: Vec::with_capacity(capacity),
             cookie: false,
+            extensions: Vec::new(),
+            status: None,
             #[cfg(feature = "validate-requests")]
             validator_exclusions: Vec::new(),
         }
@@ -418,61 +567,21 @@ impl HandlerAttrs {
             match meta {
                 Meta::List(attribute) => {
                     if ident == "guard" {
-                        let mut guard_type_path = None;
-                        let mut init_fn = None;
-                        let mut init_data = None;
-                        let mut init_expr = None;
-
-                        for guard_meta in attribute.nested {
-                            if let NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit, .. })) = guard_meta {
-                                let path = path
-                                    .segments
-                                    .first()
-                                    .ok_or_else(|| Error::new_spanned(&path, "Missing parameters in guard attributes"))?;
-                                match path.ident.to_string().as_str() {
-                                    "init_fn" => {
-                                        if let Lit::Str(lit_str) = lit {
-                                            init_fn = Some(
-                                                syn::parse_str::<Path>(lit_str.value().as_str())
-                                                    .map_err(|_e| Error::new_spanned(path, "Expected path to a guard function"))?,
-                                            );
-                                        } else {
-                                            return Err(Error::new_spanned(lit, "Expected path to a guard function"));
-                                        }
-                                    }
-
-                                    "init_data" => {
-                                        init_data = Some(lit);
-                                    }
-
-                                    "init_expr" => {
-                                        if let Lit::Str(lit_str) = lit {
-                                            init_expr = Some(
-                                                syn::parse_str::<Expr>(lit_str.value().as_str())
-                                                    .map_err(|_e| Error::new_spanned(path, "Expected a valid rust expression"))?,
-                                            );
-                                        } else {
-                                            return Err(Error::new_spanned(lit, "Expected a string of a valid rust expression"));
-                                        }
-                                    }
+                        handler.guards.push(GuardDef::new(&attribute)?);
+                    } else if ident == "ext" {
+                        if attribute.nested.is_empty() {
+                            return Err(Error::new_spanned(ident, "ext attribute cannot be empty"));
+                        }
 
-                                    _ => {
-                                        return Err(Error::new_spanned(path, "Unauthorized param in guard macro"));
-                                    }
+                        for ext_meta in attribute.nested {
+                            match ext_meta {
+                                NestedMeta::Meta(Meta::Path(p)) if p.get_ident().is_some() => {
+                                    handler.extensions.push(p.get_ident().expect("checked above").to_string());
                                 }
-                            } else if let NestedMeta::Meta(Meta::Path(p)) = guard_meta {
-                                guard_type_path = Some(p);
+                                NestedMeta::Lit(Lit::Str(name)) => handler.extensions.push(name.value()),
+                                _ => return Err(Error::new_spanned(ext_meta, "Expected a list of parameter names")),
                             }
                         }
-
-                        let guard = GuardDef {
-                            guard_type: guard_type_path.ok_or_else(|| Error::new_spanned(ident, "Missing guard"))?,
-                            init_data,
-                            init_fn,
-                            init_expr,
-                        };
-
-                        handler.guards.push(guard);
                     } else if ident == "openapi" {
                         if attribute.nested.is_empty() {
                             return Err(Error::new_spanned(ident, "openapi attribute cannot be empty"));
@@ -487,6 +596,7 @@ impl HandlerAttrs {
                                             let mut nb_type = 0;
                                             let mut nb_mime = 0;
                                             let mut nb_name = 0;
+                                            let mut nb_example = 0;
                                             if openapi_attribute.nested.is_empty() {
                                                 return Err(Error::new_spanned(openapi_attribute, "openapi return attribute cannot be empty"));
                                             }
@@ -535,6 +645,12 @@ impl HandlerAttrs {
                                                                             return Err(Error::new_spanned(m, "Expected a string"));
                                                                         }
                                                                     }
+                                                                    Some("example") => {
+                                                                        if !matches!(&nv.lit, Lit::Str(_) | Lit::Int(_) | Lit::Float(_) | Lit::Bool(_)) {
+                                                                            return Err(Error::new_spanned(m, "Expected a string, number or boolean example"));
+                                                                        }
+                                                                        nb_example += 1;
+                                                                    }
                                                                     _ => return Err(Error::new_spanned(&nv.path, "Invalid openapi return attribute")),
                                                                 }
                                                             }
@@ -564,6 +680,10 @@ impl HandlerAttrs {
                                                 return Err(Error::new_spanned(openapi_attribute, "Cannot specify the name twice"));
                                             }
 
+                                            if nb_example > 1 {
+                                                return Err(Error::new_spanned(openapi_attribute, "Cannot specify the example twice"));
+                                            }
+
                                             if nb_code > 1 && nb_type > 1 {
                                                 return Err(Error::new_spanned(
                                                     openapi_attribute,
@@ -665,6 +785,8 @@ impl HandlerAttrs {
                                         _ => return Err(Error::new_spanned(openapi_attribute, "Invalid openapi attribute")),
                                     }
                                 }
+                                // Read by saphir-cli, which leaves the handler out of the document
+                                NestedMeta::Meta(Meta::Path(p)) if p.is_ident("hidden") => {}
                                 _ => return Err(Error::new_spanned(openapi_attributes, "Invalid openapi attribute")),
                             }
                         }
@@ -708,19 +830,61 @@ impl HandlerAttrs {
                                 }
                             }
                         }
+                    } else if ident == "route" {
+                        let path = handler_path(&attribute)?;
+                        let mut methods = Vec::new();
+                        for route_meta in attribute.nested.iter().skip(1) {
+                            match route_meta {
+                                NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("methods") => {
+                                    for m in &list.nested {
+                                        match m {
+                                            NestedMeta::Lit(Lit::Str(s)) => {
+                                                let value = s.value();
+                                                if value.bytes().any(|b| b.is_ascii_lowercase()) {
+                                                    return Err(Error::new_spanned(
+                                                        s,
+                                                        format!("HTTP methods are case sensitive, help: use `{}`", value.to_ascii_uppercase()),
+                                                    ));
+                                                }
+                                                let method = Method::from_str(value.as_str()).map_err(|_e| Error::new_spanned(s, "Invalid HTTP method"))?;
+                                                if methods.contains(&method) {
+                                                    return Err(Error::new_spanned(s, "Duplicate method"));
+                                                }
+                                                methods.push(method);
+                                            }
+                                            _ => return Err(Error::new_spanned(m, "Expected a quoted HTTP method")),
+                                        }
+                                    }
+                                }
+                                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("status") => handler.set_status(nv)?,
+                                _ => {
+                                    return Err(Error::new_spanned(
+                                        route_meta,
+                                        "Unrecognized parameter, expected `methods(\"<METHOD>\", ..)` or `status = <code>`",
+                                    ))
+                                }
+                            }
+                        }
+
+                        if methods.is_empty() {
+                            return Err(Error::new_spanned(
+                                attribute,
+                                "Missing methods for route, help: add something like `methods(\"GET\", \"POST\")`",
+                            ));
+                        }
+
+                        handler.methods_paths.extend(methods.into_iter().map(|m| (m, path.clone())));
                     } else {
                         let method =
                             Method::from_str(ident.to_string().to_uppercase().as_str()).map_err(|_e| Error::new_spanned(ident, "Invalid HTTP method"))?;
+                        let path = handler_path(&attribute)?;
+                        handler.methods_paths.push((method, path));
 
-                        if let Some(NestedMeta::Lit(Lit::Str(str))) = attribute.nested.first() {
-                            let path = str.value();
-                            if !path.starts_with('/') {
-                                return Err(Error::new_spanned(str, "Path must start with '/'"));
+                        for method_meta in attribute.nested.iter().skip(1) {
+                            match method_meta {
+                                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("status") => handler.set_status(nv)?,
+                                _ => return Err(Error::new_spanned(method_meta, "Unrecognized parameter, expected `status = <code>`")),
                             }
-
-                            handler.methods_paths.push((method, path));
-                        } else {
-                            return Err(Error::new_spanned(attribute, "Missing path for method"));
                         }
                     }
                 }
@@ -744,6 +908,34 @@ impl HandlerAttrs {
 
         Ok(handler)
     }
+
+    fn set_status(&mut self, nv: &MetaNameValue) -> Result<()> {
+        let status: u16 = match &nv.lit {
+            Lit::Int(i) => i.base10_parse().map_err(|_| Error::new_spanned(i, "Invalid status code"))?,
+            _ => return Err(Error::new_spanned(&nv.lit, "Expected a status code")),
+        };
+        if !(100..600).contains(&status) {
+            return Err(Error::new_spanned(&nv.lit, "Invalid status code"));
+        }
+        if self.status.is_some() {
+            return Err(Error::new_spanned(nv, "Cannot specify `status` twice"));
+        }
+        self.status = Some(status);
+        Ok(())
+    }
+}
+
+/// Path given as first parameter of a route attribute
+fn handler_path(attribute: &MetaList) -> Result<String> {
+    if let Some(NestedMeta::Lit(Lit::Str(str))) = attribute.nested.first() {
+        let path = str.value();
+        if !path.starts_with('/') {
+            return Err(Error::new_spanned(str, "Path must start with '/'"));
+        }
+        Ok(path)
+    } else {
+        Err(Error::new_spanned(attribute, "Missing path for method"))
+    }
 }
 
 pub fn parse_handlers(input: ItemImpl) -> Result<Vec<HandlerRepr>> {
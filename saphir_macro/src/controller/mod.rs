@@ -88,7 +88,12 @@ fn gen_wrapper_handler(handler_tokens: &mut TokenStream, handler: HandlerRepr) -
     for arg in opts.fn_arguments.into_iter() {
         arg.gen_parameter(&mut body_stream, &mut call_params_ident)?;
     }
-    let inner_call = gen_call_to_inner(inner_method_ident, call_params_ident, async_call);
+    let mut inner_call = gen_call_to_inner(inner_method_ident, call_params_ident, async_call);
+    let mut return_type = return_type.into_token_stream();
+    if let Some(status) = opts.status {
+        return_type = quote! {saphir::responder::DefaultStatus<#return_type>};
+        inner_call = quote! {saphir::responder::DefaultStatus::new(#inner_call, #status)};
+    }
 
     #[cfg(feature = "tracing-instrument")]
     let t = quote_spanned! {route_span=>
@@ -141,7 +146,7 @@ fn gen_cookie_load(stream: &mut TokenStream, opts: &HandlerWrapperOpt) {
 fn gen_query_load(stream: &mut TokenStream, opts: &HandlerWrapperOpt) {
     if opts.parse_query {
         (quote! {
-        let mut query = req.uri().query().map(saphir::utils::read_query_string_to_hashmap).transpose()?.unwrap_or_default();
+        let mut query = req.uri().query().map(saphir::utils::read_query_string_to_multimap).transpose()?.unwrap_or_default();
         })
         .to_tokens(stream);
     }
@@ -159,9 +164,18 @@ fn gen_map_after_load(stream: &mut TokenStream, opts: &HandlerWrapperOpt) {
             MapAfterLoad::Json => {
                 quote! {.map(|b| Json(b))}
             }
+            MapAfterLoad::Json5 => {
+                quote! {.map(|b| Json5(b))}
+            }
             MapAfterLoad::Form => {
                 quote! {.map(|b| Form(b))}
             }
+            MapAfterLoad::MsgPack => {
+                quote! {.map(|b| MsgPack(b))}
+            }
+            MapAfterLoad::Cbor => {
+                quote! {.map(|b| Cbor(b))}
+            }
         })
         .to_tokens(stream)
     }
@@ -232,7 +246,7 @@ impl ArgsRepr {
                 ArgsReprType::SelfType | ArgsReprType::Request | ArgsReprType::Cookie => {
                     return Err(Error::new(
                         self.typ.as_ref().map(|t| t.span()).unwrap_or_else(Span::call_site),
-                        "Optional parameters are only allowed for quey params, route params, or body param (Json or Form)",
+                        "Optional parameters are only allowed for quey params, route params, or body param (Json, Json5, Form, MsgPack or Cbor)",
                     ));
                 }
                 ArgsReprType::Option(_) => {
@@ -262,9 +276,15 @@ impl ArgsRepr {
         let ident = Ident::new(self.name.as_str(), Span::call_site());
         match &self.a_type {
             ArgsReprType::Json => self.gen_json_param(stream, optional),
+            ArgsReprType::Json5 => self.gen_json5_param(stream, optional),
             ArgsReprType::Form => self.gen_form_param(stream, optional),
+            ArgsReprType::MsgPack => self.gen_msgpack_param(stream, optional),
+            ArgsReprType::Cbor => self.gen_cbor_param(stream, optional),
+            ArgsReprType::AnyBody => self.gen_any_body_param(stream, optional),
+            ArgsReprType::Extractor => self.gen_extractor_param(stream, optional),
             ArgsReprType::Cookie => self.gen_cookie_param(stream),
             ArgsReprType::Ext => self.gen_ext_param(stream, optional),
+            ArgsReprType::Extension => self.gen_extension_param(stream, optional),
             ArgsReprType::Extensions => self.gen_extensions_param(stream),
             ArgsReprType::Params { is_query_param, .. } => {
                 if *is_query_param {
@@ -352,46 +372,137 @@ impl ArgsRepr {
         self.gen_validate_block(stream, &id, optional);
     }
 
+    fn gen_json5_param(&self, stream: &mut TokenStream, optional: bool) {
+        let id = Ident::new(self.name.as_str(), Span::call_site());
+        let typ = self.typ.as_ref().expect("This should not happens");
+
+        (quote! {
+            let #id = req.body_mut().take_as::<#typ>().await.map(|x| Json5(x))
+        })
+        .to_tokens(stream);
+
+        if optional {
+            (quote! {.ok();}).to_tokens(stream);
+        } else {
+            (quote! {?;}).to_tokens(stream);
+        }
+
+        #[cfg(feature = "validate-requests")]
+        self.gen_validate_block(stream, &id, optional);
+    }
+
+    fn gen_msgpack_param(&self, stream: &mut TokenStream, optional: bool) {
+        let id = Ident::new(self.name.as_str(), Span::call_site());
+        let typ = self.typ.as_ref().expect("This should not happens");
+
+        (quote! {
+            let #id = req.body_mut().take_as::<#typ>().await.map(|x| MsgPack(x))
+        })
+        .to_tokens(stream);
+
+        if optional {
+            (quote! {.ok();}).to_tokens(stream);
+        } else {
+            (quote! {?;}).to_tokens(stream);
+        }
+
+        #[cfg(feature = "validate-requests")]
+        self.gen_validate_block(stream, &id, optional);
+    }
+
+    fn gen_cbor_param(&self, stream: &mut TokenStream, optional: bool) {
+        let id = Ident::new(self.name.as_str(), Span::call_site());
+        let typ = self.typ.as_ref().expect("This should not happens");
+
+        (quote! {
+            let #id = req.body_mut().take_as::<#typ>().await.map(|x| Cbor(x))
+        })
+        .to_tokens(stream);
+
+        if optional {
+            (quote! {.ok();}).to_tokens(stream);
+        } else {
+            (quote! {?;}).to_tokens(stream);
+        }
+
+        #[cfg(feature = "validate-requests")]
+        self.gen_validate_block(stream, &id, optional);
+    }
+
+    fn gen_extractor_param(&self, stream: &mut TokenStream, optional: bool) {
+        let id = Ident::new(self.name.as_str(), Span::call_site());
+        let typ = self.typ.as_ref().expect("This should not happens");
+
+        let err_handling = if optional {
+            quote! {ok()}
+        } else {
+            quote! {map_err(|e| SaphirError::responder(e))?}
+        };
+
+        (quote! {
+
+            let #id = <#typ as FromRequest>::from_request(&mut req).await.#err_handling;
+        })
+        .to_tokens(stream);
+    }
+
+    fn gen_any_body_param(&self, stream: &mut TokenStream, optional: bool) {
+        let id = Ident::new(self.name.as_str(), Span::call_site());
+        let typ = self.typ.as_ref().expect("This should not happens");
+
+        (quote! {
+
+            let #id = <#typ as FromRequest>::from_request(&mut req).await
+        })
+        .to_tokens(stream);
+
+        if optional {
+            (quote! {.ok();}).to_tokens(stream);
+        } else {
+            (quote! {?;}).to_tokens(stream);
+        }
+
+        #[cfg(feature = "validate-requests")]
+        self.gen_validate_block(stream, &id, optional);
+    }
+
     #[cfg(feature = "validate-requests")]
-    #[allow(clippy::collapsible_else_if)]
     fn gen_validate_block(&self, stream: &mut TokenStream, id: &Ident, optional: bool) {
         if self.validated {
+            // `t` is a reference to the payload, narrowed down to every value to validate
+            let mut check = quote! {
+                t.validate().map_err(|e| saphir::error::SaphirError::ValidationErrors(e))?;
+            };
+            if self.is_vec {
+                check = quote! {
+                    for t in t.iter() {
+                        #check
+                    }
+                };
+            }
+            if self.is_option {
+                check = quote! {
+                    if let Some(t) = &**t {
+                        #check
+                    }
+                };
+            }
+
             if optional {
-                if self.is_vec {
-                    (quote! {
-                    if let Some(param) = &#id {
-                        use ::validator::Validate;
-                        for t in param.iter() {
-                            t.validate().map_err(|e| saphir::error::SaphirError::ValidationErrors(e))?;
-                        }
-                    }})
-                    .to_tokens(stream);
-                } else {
-                    (quote! {
-                    if let Some(param) = &#id {
-                        use ::validator::Validate;
-                        param.validate().map_err(|e| saphir::error::SaphirError::ValidationErrors(e))?;
-                    }})
-                    .to_tokens(stream);
-                }
+                (quote! {
+                if let Some(t) = &#id {
+                    use ::validator::Validate;
+                    #check
+                }})
+                .to_tokens(stream);
             } else {
-                if self.is_vec {
-                    (quote! {
-                    {
-                        use ::validator::Validate;
-                        for t in #id.iter() {
-                            t.validate().map_err(|e| saphir::error::SaphirError::ValidationErrors(e))?;
-                        }
-                    }})
-                    .to_tokens(stream);
-                } else {
-                    (quote! {
-                    {
-                        use ::validator::Validate;
-                        #id.validate().map_err(|e| saphir::error::SaphirError::ValidationErrors(e))?;
-                    }})
-                    .to_tokens(stream);
-                }
+                (quote! {
+                {
+                    use ::validator::Validate;
+                    let t = &#id;
+                    #check
+                }})
+                .to_tokens(stream);
             }
         }
     }
@@ -422,11 +533,28 @@ impl ArgsRepr {
         .to_tokens(stream);
     }
 
+    fn gen_extension_param(&self, stream: &mut TokenStream, optional: bool) {
+        let id = Ident::new(self.name.as_str(), Span::call_site());
+        let typ = self.typ.as_ref().expect("This should not happens");
+
+        let err_handling = if optional {
+            quote! {ok()}
+        } else {
+            quote! {map_err(|e| SaphirError::responder(e))?}
+        };
+
+        (quote! {
+
+             let #id = Ext::<#typ>::from_request(&mut req).await.map(Ext::into_inner).#err_handling;
+        })
+        .to_tokens(stream);
+    }
+
     fn gen_extensions_param(&self, stream: &mut TokenStream) {
         let id = Ident::new(self.name.as_str(), Span::call_site());
         (quote! {
 
-            let #id = Extensions::from_req(&mut req).await?;
+            let #id = Extensions::from_request(&mut req).await.map_err(|e| SaphirError::responder(e))?;
         })
         .to_tokens(stream);
     }
@@ -448,9 +576,23 @@ impl ArgsRepr {
     fn gen_query_param(&self, stream: &mut TokenStream, optional: bool) {
         let name = self.name.as_str();
         let id = Ident::new(self.name.as_str(), Span::call_site());
+        if let ArgsReprType::Params { is_vec: true, .. } = self.a_type {
+            let collect = quote! {
+                .map(|values| values.into_iter().map(|p| p.parse()).collect::<Result<Vec<_>, _>>())
+                .transpose()
+                .map_err(|_| SaphirError::InvalidParameter(#name.to_string(), false))?
+            };
+            if optional {
+                (quote! {let #id = query.remove(#name)#collect;}).to_tokens(stream);
+            } else {
+                (quote! {let #id = query.remove(#name)#collect.unwrap_or_default();}).to_tokens(stream);
+            }
+            return;
+        }
+
         (quote! {
 
-            let #id = query.remove(#name)
+            let #id = query.remove(#name).and_then(|mut values| values.pop())
         })
         .to_tokens(stream);
 
@@ -460,8 +602,7 @@ impl ArgsRepr {
     }
 
     fn gen_param_str_parsing(&self, stream: &mut TokenStream, name: &str, optional: bool) {
-        if !self.is_string() && self.typ.is_some() {
-            let typ = self.typ.as_ref().unwrap();
+        if let (false, Some(typ)) = (self.is_string(), self.typ.as_ref()) {
             (quote! {.map(|p| p.parse::<#typ>()).transpose().map_err(|_| SaphirError::InvalidParameter(#name.to_string(), false))?}).to_tokens(stream);
         }
 
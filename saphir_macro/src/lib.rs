@@ -64,6 +64,38 @@ pub fn controller(args: TokenStream1, input: TokenStream1) -> TokenStream1 {
     TokenStream1::from(expanded)
 }
 
+/// Implement `Middleware` for a type from its `async fn next(&self, ctx:
+/// HttpContext, chain: &dyn MiddlewareChain)` method.
+///
+/// The other items of the impl block are kept as is, so the middleware can
+/// carry fields built at startup, e.g. from the configuration, through a
+/// regular constructor. The middleware is moved into the server stack when
+/// applied and lives as long as the server, so `self` can be borrowed in
+/// `next` without any `'static` data. Generic middlewares are supported as
+/// long as their parameters are `Send + Sync + 'static`.
+///
+/// ##Example
+///
+/// ```ignore
+/// use saphir::prelude::*;
+/// use saphir_macro::middleware;
+///
+/// struct ApiKeyMiddleware {
+///     key: String,
+/// }
+///
+/// #[middleware]
+/// impl ApiKeyMiddleware {
+///     pub fn new(key: String) -> Self {
+///         ApiKeyMiddleware { key }
+///     }
+///
+///     async fn next(&self, ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+///         // ....
+///         chain.next(ctx).await
+///     }
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn middleware(_args: TokenStream1, input: TokenStream1) -> TokenStream1 {
     let input = parse_macro_input!(input as ItemImpl);
@@ -91,6 +123,20 @@ pub fn guard(_args: TokenStream1, input: TokenStream1) -> TokenStream1 {
 /// - json (application/json)
 /// - form (application/x-www-form-urlencoded)
 /// - any  (*/*)
+///
+/// `example` gives an example of a struct, either a json document or a plain
+/// value, and can also be placed on its fields : `#[openapi(example = "jo")]`.
+///
+/// On a guard, `security` describes the authentication it enforces, so that
+/// every handler protected by the guard documents the matching security
+/// requirement : `#[openapi(security = "bearer")]`. Supported schemes are:
+/// - bearer : http bearer authentication, with an optional `bearer_format`
+/// - basic : http basic authentication
+/// - api_key : key sent in the header, cookie or query parameter named
+///   `key_name`, according to `location` (`header` by default)
+///
+/// The scheme is named after the guard type, unless `security_name` is
+/// specified.
 #[proc_macro_attribute]
 pub fn openapi(args: TokenStream1, input: TokenStream1) -> TokenStream1 {
     let args = parse_macro_input!(args as AttributeArgs);
@@ -16,6 +16,8 @@ pub fn expand_middleware(mut mid_impl: ItemImpl) -> Result<TokenStream> {
 
     let mod_ident = Ident::new(&format!("SAPHIR_GEN_MIDDLEWARE_{}", &middleware_name), Span::call_site());
     let fn_ident = fn_def.fn_ident;
+    let self_ty = &mid_impl.self_ty;
+    let (impl_generics, _, where_clause) = mid_impl.generics.split_for_impl();
 
     Ok(quote! {
         #mid_impl
@@ -24,7 +26,7 @@ pub fn expand_middleware(mut mid_impl: ItemImpl) -> Result<TokenStream> {
             use super::*;
             use saphir::prelude::*;
 
-            impl Middleware for #middleware_ident {
+            impl #impl_generics Middleware for #self_ty #where_clause {
                 fn next(&'static self, ctx: HttpContext, chain: &'static dyn MiddlewareChain) -> BoxFuture<'static, Result<HttpContext, SaphirError>> {
                     self.#fn_ident(ctx, chain).boxed()
                 }
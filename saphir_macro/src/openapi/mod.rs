@@ -1,23 +1,35 @@
 use proc_macro2::TokenStream;
 use quote::ToTokens;
-use syn::{AttributeArgs, Error, Item, Lit, Meta, NestedMeta, Result};
+use syn::{AttributeArgs, Error, Fields, Item, Lit, Meta, NestedMeta, Result};
 
 const MISSING_ATTRIBUTE: &str = "openapi macro require at least one of the following attributes :
 - mime
-- name";
+- name
+- security
+- example";
 
-pub fn validate_openapi(args: AttributeArgs, input: Item) -> Result<TokenStream> {
-    match &input {
-        Item::Struct(_) | Item::Enum(_) => {
-            if args.is_empty() {
-                panic!("{}", MISSING_ATTRIBUTE);
-            }
-        }
+const SECURITY_ATTRIBUTES: [&str; 4] = ["security_name", "bearer_format", "location", "key_name"];
+
+pub fn validate_openapi(args: AttributeArgs, mut input: Item) -> Result<TokenStream> {
+    let has_field_examples = match &mut input {
+        Item::Struct(s) => strip_field_examples(&mut s.fields)?,
+        Item::Enum(_) => false,
         _ => panic!("openapi attribute can only be placed on Struct and Enum"),
+    };
+    if args.is_empty() && !has_field_examples {
+        panic!("{}", MISSING_ATTRIBUTE);
     }
     let mut mime: Option<String> = None;
     let mut name: Option<String> = None;
+    let mut security: Option<String> = None;
+    let mut security_attrs: Vec<(String, String)> = Vec::new();
+    let mut example = false;
     for arg in args.into_iter() {
+        if let NestedMeta::Meta(Meta::Path(p)) = &arg {
+            if p.is_ident("hidden") {
+                return Err(Error::new_spanned(p, "`hidden` can only be placed on a controller handler"));
+            }
+        }
         if let NestedMeta::Meta(Meta::NameValue(nv)) = arg {
             match nv.path.get_ident().map(|i| i.to_string()).as_deref() {
                 Some("mime") => {
@@ -38,14 +50,100 @@ pub fn validate_openapi(args: AttributeArgs, input: Item) -> Result<TokenStream>
                         _ => None,
                     }
                 }
+                Some("security") => {
+                    if security.is_some() {
+                        return Err(Error::new_spanned(nv, "Cannot specify `security` twice"));
+                    }
+                    security = match &nv.lit {
+                        Lit::Str(s) if matches!(s.value().as_str(), "bearer" | "basic" | "api_key") => Some(s.value()),
+                        _ => {
+                            return Err(Error::new_spanned(
+                                nv,
+                                "Expected one of the following security schemes : bearer, basic, api_key",
+                            ))
+                        }
+                    }
+                }
+                Some("example") => {
+                    if example {
+                        return Err(Error::new_spanned(nv, "Cannot specify `example` twice"));
+                    }
+                    if !matches!(input, Item::Struct(_)) {
+                        return Err(Error::new_spanned(nv, "`example` can only be placed on a struct"));
+                    }
+                    validate_example(&nv.lit)?;
+                    example = true;
+                }
+                Some(attr) if SECURITY_ATTRIBUTES.contains(&attr) => {
+                    if security_attrs.iter().any(|(a, _)| a == attr) {
+                        return Err(Error::new_spanned(&nv, format!("Cannot specify `{}` twice", attr)));
+                    }
+                    let value = match &nv.lit {
+                        Lit::Str(s) => s.value(),
+                        _ => return Err(Error::new_spanned(&nv, format!("Expected a string for `{}`", attr))),
+                    };
+                    if attr == "location" && !matches!(value.as_str(), "header" | "cookie" | "query") {
+                        return Err(Error::new_spanned(&nv, "Expected one of the following locations : header, cookie, query"));
+                    }
+                    security_attrs.push((attr.to_string(), value));
+                }
                 _ => return Err(Error::new_spanned(nv, "Unrecognized parameter")),
             }
         }
     }
 
-    if mime.is_none() && name.is_none() {
+    match security.as_deref() {
+        None if !security_attrs.is_empty() => {
+            return Err(Error::new_spanned(
+                &input,
+                format!("`{}` can only be specified along with `security`", security_attrs[0].0),
+            ));
+        }
+        Some("api_key") if !security_attrs.iter().any(|(a, _)| a == "key_name") => {
+            return Err(Error::new_spanned(&input, "The `api_key` security scheme requires a `key_name`"));
+        }
+        _ => {}
+    }
+
+    if mime.is_none() && name.is_none() && security.is_none() && !example && !has_field_examples {
         panic!("{}", MISSING_ATTRIBUTE);
     }
 
     Ok(input.to_token_stream())
 }
+
+/// Validate and remove the `#[openapi(example = ...)]` attributes of the
+/// fields, which are read by saphir-cli
+fn strip_field_examples(fields: &mut Fields) -> Result<bool> {
+    let mut found = false;
+    for field in fields.iter_mut() {
+        let mut attrs = Vec::with_capacity(field.attrs.len());
+        for attr in field.attrs.drain(..) {
+            if !attr.path.is_ident("openapi") {
+                attrs.push(attr);
+                continue;
+            }
+            match attr.parse_meta()? {
+                Meta::List(list) if !list.nested.is_empty() => {
+                    for nested in &list.nested {
+                        match nested {
+                            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("example") => validate_example(&nv.lit)?,
+                            _ => return Err(Error::new_spanned(nested, "Expected `example = <value>`")),
+                        }
+                    }
+                }
+                meta => return Err(Error::new_spanned(meta, "Expected `openapi(example = <value>)`")),
+            }
+            found = true;
+        }
+        field.attrs = attrs;
+    }
+    Ok(found)
+}
+
+fn validate_example(lit: &Lit) -> Result<()> {
+    match lit {
+        Lit::Str(_) | Lit::Int(_) | Lit::Float(_) | Lit::Bool(_) => Ok(()),
+        _ => Err(Error::new_spanned(lit, "Expected a string, number or boolean example")),
+    }
+}